@@ -0,0 +1,81 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <stdbool.h>
+#include <string.h>
+#include <stdint.h>
+
+uint8_t tan_float_to_u8(float x) {
+	if (x != x) {
+		return 0;
+	}
+	if (x >= 256.0f) {
+		return 255;
+	}
+	if (x <= 0.0f) {
+		return 0;
+	}
+	return (uint8_t)x;
+}
+
+int i;
+int x;
+uint64_t huge;
+uint32_t mid;
+float g;
+
+int abs(int x);
+int putchar(int c);
+const char* classify(int n);
+uint8_t clampByte(float x);
+
+const char* classify(int n) {
+	if (n < 0) {
+		return "negative";
+	} else if (n == 0) {
+		return "zero";
+	} else {
+		return "positive";
+	}
+	return "unreachable";
+}
+
+uint8_t clampByte(float x) {
+	return tan_float_to_u8(x);
+}
+
+int main() {
+	i = 0;
+	while (i < 3) {
+		int j = 0;
+		while (true) {
+			j += 1;
+			if (j == 2) {
+				i += 1;
+				goto outer_0_continue;
+			}
+			if (i == 2) {
+				goto outer_0_break;
+			}
+			printf("%d, %d\n", i, j);
+		}
+		outer_0_continue:;
+	}
+	outer_0_break:;
+	printf("%d\n", i);
+	x = 5;
+	{
+		int x_shadowed = x * 2;
+		int x = x_shadowed;
+		printf("%d\n", x);
+	}
+	({ __auto_type arg_0 = x; __auto_type arg_1 = abs(0 - 7); __auto_type arg_2 = classify(0 - 3); __auto_type arg_3 = classify(0); printf("%d, %d, %s, %s\n", arg_0, arg_1, arg_2, arg_3); });
+	putchar(65);
+	putchar(10);
+	({ __auto_type arg_0 = clampByte((float)(300)); __auto_type arg_1 = clampByte((float)(0) - 5); __auto_type arg_2 = (uint8_t)((0 - 1)); __auto_type arg_3 = (int16_t)(70000); printf("%d, %d, %d, %d\n", arg_0, arg_1, arg_2, arg_3); });
+	huge = 18446744073709551615ULL;
+	mid = 4000000000ULL;
+	printf("%llu, %u, %llu\n", (unsigned long long)(huge), mid, (unsigned long long)(huge / 2));
+	g = (float)(1) / 3;
+	printf("%s, %s, %s, %s, %d\n", (g < 1) ? "true" : "false", (g == g) ? "true" : "false", ((bool)(g)) ? "true" : "false", ((bool)(0)) ? "true" : "false", (int)(true) + 1);
+	return 0;
+}
@@ -0,0 +1,118 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <stdbool.h>
+#include <string.h>
+#include <stdint.h>
+
+uint8_t tan_float_to_u8(float x) {
+	if (x != x) {
+		return 0;
+	}
+	if (x >= 256.0f) {
+		return 255;
+	}
+	if (x <= 0.0f) {
+		return 0;
+	}
+	return (uint8_t)x;
+}
+
+int i;
+int x;
+uint64_t huge;
+uint32_t mid;
+float g;
+
+int abs(int);
+int putchar(int);
+const char* classify(int n);
+uint8_t clampByte(float x_0);
+
+const char* classify(int n) {
+	bool t_1;
+	bool t_2;
+	t_1 = n < 0;
+	if (!t_1) goto block_2;
+	return "negative";
+block_2:
+	t_2 = n == 0;
+	if (!t_2) goto block_4;
+	return "zero";
+block_4:
+	return "positive";
+}
+
+uint8_t clampByte(float x_0) {
+	uint8_t t_1;
+	t_1 = tan_float_to_u8(x_0);
+	return t_1;
+}
+
+int main() {
+	int t_0;
+	bool t_1;
+	int j;
+	bool t_3;
+	int t_4;
+	bool t_5;
+	int t_6;
+	int t_7;
+	int t_8;
+	const char* t_9;
+	const char* t_10;
+	uint64_t t_11;
+	uint32_t t_12;
+	uint64_t t_13;
+	float t_14;
+	bool t_15;
+	bool t_16;
+	bool t_17;
+	uint8_t t_18;
+	uint8_t t_19;
+	i = 0;
+block_1:
+	t_0 = i;
+	t_1 = t_0 < 3;
+	if (!t_1) goto block_7;
+	j = 0;
+	t_5 = t_0 == 2;
+block_3:
+	j = j + 1;
+	t_3 = j == 2;
+	if (!t_3) goto block_5;
+	t_4 = t_0 + 1;
+	i = t_4;
+	goto block_1;
+block_5:
+	if (t_5) goto block_7;
+	printf("%d, %d\n", t_0, j);
+	goto block_3;
+block_7:
+	printf("%d\n", t_0);
+	x = 5;
+	t_6 = x;
+	t_7 = t_6 * 2;
+	printf("%d\n", t_7);
+	t_8 = abs(-7);
+	t_9 = "negative";
+	t_10 = "zero";
+	printf("%d, %d, %s, %s\n", t_6, t_8, t_9, t_10);
+	putchar(65);
+	putchar(10);
+	t_18 = tan_float_to_u8(300.0f);
+	t_19 = tan_float_to_u8(-5.0f);
+	printf("%d, %d, %d, %d\n", t_18, t_19, 255, 4464);
+	huge = 18446744073709551615ULL;
+	mid = 4000000000ULL;
+	t_11 = huge;
+	t_12 = mid;
+	t_13 = t_11 / 2;
+	printf("%llu, %u, %llu\n", (unsigned long long)(t_11), t_12, (unsigned long long)(t_13));
+	g = 0.33333334f;
+	t_14 = g;
+	t_15 = t_14 < 1.0f;
+	t_16 = t_14 == t_14;
+	t_17 = (bool)t_14;
+	printf("%s, %s, %s, %s, %d\n", (t_15) ? "true" : "false", (t_16) ? "true" : "false", (t_17) ? "true" : "false", (false) ? "true" : "false", 2);
+	return 0;
+}
@@ -0,0 +1,63 @@
+export const scale = 3;
+
+export const greeting = "hello\tworld";
+
+let limit = 10;
+
+export function fib(n) {
+  if (n < 2) {
+    return n;
+  }
+  return (fib((n - 1 | 0)) + fib((n - 2 | 0)) | 0);
+}
+
+export function sum8(a, b, c, d, e, f, g, h) {
+  return (((((((a - b | 0) + c | 0) - d | 0) + e | 0) - f | 0) + Math.imul(g, 1000) | 0) + h | 0);
+}
+
+export function classify(n) {
+  if (n < 0) {
+    return "negative";
+  } else if (n === 0) {
+    return "zero";
+  }
+  return "positive";
+}
+
+let i = 0;
+let total = 0;
+outer: while (i < limit) {
+  i = (i + 1 | 0);
+  if ((i % 2 | 0) === 0) {
+    continue;
+  }
+  let j = 0;
+  while (true) {
+    j = (j + 1 | 0);
+    if (j > 2) {
+      break;
+    }
+    if (Math.imul(i, j) > 12) {
+      break outer;
+    }
+    total = (total + Math.imul(Math.imul(i, j), scale) | 0);
+  }
+}
+console.log(`${i}, ${total}, ${fib(15)}`);
+console.log(`${sum8(1, 2, 3, 4, 5, 6, (0 - 7 << 24 >> 24), 65535)}, ${classify((0 - 3 | 0))}, ${classify(0)}, ${classify(8)}`);
+let b = i === 7 && total > 0 || false;
+console.log(`${b}, ${!b}, ${greeting}, ${greeting === "x"}, ${greeting !== "x"}`);
+let small = 250;
+small = (small + 10 & 255);
+let tiny = 127;
+tiny = (tiny + 1 << 24 >> 24);
+let mid = 4000000000;
+let big = 3000000000n;
+let huge = 18446744073709551615n;
+console.log(`${small}, ${tiny}, ${mid}, ${BigInt.asIntN(64, big * 2n)}, ${huge}, ${BigInt.asUintN(64, huge / 3n)}, ${(((0 - 1 | 0)) & 255)}, ${4464}`);
+let m = (b ? (() => {
+  let t = 4;
+  return Math.imul(t, 2);
+})() : 2);
+let neg = (0 - 17 | 0);
+console.log(`${m}, ${(~m | 0)}, ${m ^ 3}, ${m & 6}, ${m | 1}, ${(Math.trunc(neg / 5) | 0)}, ${(neg % 5 | 0)}, ${(neg >>> 0)}, ${true}`);
@@ -0,0 +1,88 @@
+function tan_float_string(value) {
+  if (Number.isNaN(value)) {
+    return "NaN";
+  }
+  if (!Number.isFinite(value)) {
+    return value < 0 ? "-inf" : "inf";
+  }
+  let scientific = value.toExponential();
+  for (let precision = 0; precision < 9; precision++) {
+    scientific = value.toExponential(precision);
+    if (Math.fround(Number(scientific)) === value) {
+      break;
+    }
+  }
+
+  const [mantissa, exponent] = scientific.replace(/^-/, "").split("e");
+  const digits = mantissa.replace(".", "").replace(/0+$/, "") || "0";
+  const point = Number(exponent) + 1;
+  const sign = value < 0 || Object.is(value, -0) ? "-" : "";
+  if (point <= 0) {
+    return `${sign}0.${"0".repeat(-point)}${digits}`;
+  }
+  if (point >= digits.length) {
+    return `${sign}${digits}${"0".repeat(point - digits.length)}`;
+  }
+  return `${sign}${digits.slice(0, point)}.${digits.slice(point)}`;
+}
+
+function tan_float_to_int(value, min, max) {
+  if (Number.isNaN(value)) {
+    return 0;
+  }
+  return Math.trunc(Math.min(Math.max(value, min), max));
+}
+
+function tan_float_to_bigint(value, min, max) {
+  if (Number.isNaN(value)) {
+    return 0n;
+  }
+  const truncated = Math.trunc(value);
+  if (truncated <= Number(min)) {
+    return min;
+  }
+  if (truncated >= Number(max)) {
+    return max;
+  }
+  return BigInt(truncated);
+}
+
+export const scale = 3;
+
+let limit = 10;
+
+export function fib(n) {
+  if (n < 2) {
+    return n;
+  }
+  return (fib((n - 1 | 0)) + fib((n - 2 | 0)) | 0);
+}
+
+export function half(x) {
+  return Math.fround(x / 2);
+}
+
+let i = 0;
+let total = 0;
+while (i < limit) {
+  i = (i + 1 | 0);
+  if ((i % 2 | 0) === 0) {
+    continue;
+  }
+  total = (total + Math.imul(i, scale) | 0);
+}
+console.log(total);
+console.log(fib(10));
+let f = Math.fround(79 / 10);
+console.log(`${tan_float_string(half(f))}, ${tan_float_to_int(f, -2147483648, 2147483647)}, ${f > 7}`);
+let b = i === 10 && total > 0 || false;
+console.log(`${b}, ${!b}`);
+let small = 250;
+small = (small + 10 & 255);
+console.log(small);
+let big = 3000000000n;
+console.log(`${BigInt.asIntN(64, big * 2n)}`);
+let s = "hi \"there\"";
+console.log(`${s}, ${s === "x"}`);
+let m = (b ? 1 : 2);
+console.log(`${m}, ${(~m | 0)}, ${m ^ 3}`);
@@ -0,0 +1,65 @@
+export class Point {
+  constructor(x, y) {
+    this.x = x;
+    this.y = y;
+  }
+
+  copy() {
+    return new Point(this.x, this.y);
+  }
+
+  toString() {
+    return `Point { x: ${this.x}, y: ${this.y} }`;
+  }
+}
+
+export const Shape = Object.freeze({
+  Dot: "Shape::Dot",
+  Square: "Shape::Square",
+  Circle: "Shape::Circle",
+});
+
+export function area(shape, size) {
+  return (shape === Shape.Dot ? 0 : shape === Shape.Square ? Math.imul(size, size) : (() => {
+    let r = (Math.trunc(size / 2) | 0);
+    return Math.imul(Math.imul(3, r), r);
+  })());
+}
+
+export function moved(p, by) {
+  p.x = (p.x + by | 0);
+  return p.copy();
+}
+
+export function counter(start) {
+  return ((start) => () => (start + 1 | 0))(start);
+}
+
+let origin = new Point(0, 0);
+let p = new Point(1, 2);
+let q = moved(p.copy(), 7);
+console.log(`${origin}, ${p}, ${q}`);
+let shape = Shape.Circle;
+console.log(`${shape}, ${area(shape, 7)}, ${area(Shape.Square, 3)}`);
+let step = 3;
+let add = (x) => (x + step | 0);
+step = 100;
+console.log(`${add(1)}, ${counter(41)()}`);
+let sizes = { small: 1, large: 10 };
+sizes.small = (sizes.small + (Math.trunc(sizes["large"] / 3) | 0) | 0);
+console.log(sizes.small);
+let signal_total = 1;
+let signal_doubled = Math.imul(signal_total, 2);
+update_total(5);
+console.log(signal_doubled);
+let x = 10;
+{
+  let x_1 = Math.imul(x, 2);
+  console.log(x_1);
+}
+console.log(`${x}, ${(Math.trunc(((0 - 7 | 0)) / 2) | 0)}, ${(7 % 3 | 0)}`);
+
+function update_total(value) {
+  signal_total = value;
+  signal_doubled = Math.imul(signal_total, 2);
+}
@@ -0,0 +1,148 @@
+@.str.0 = private unnamed_addr constant [9 x i8] c"negative\00"
+@.str.1 = private unnamed_addr constant [5 x i8] c"zero\00"
+@.str.2 = private unnamed_addr constant [9 x i8] c"positive\00"
+@.str.3 = private unnamed_addr constant [12 x i8] c"unreachable\00"
+@.str.4 = private unnamed_addr constant [8 x i8] c"%d, %d\0A\00"
+@.str.5 = private unnamed_addr constant [4 x i8] c"%d\0A\00"
+@.str.6 = private unnamed_addr constant [16 x i8] c"%d, %d, %s, %s\0A\00"
+@.str.7 = private unnamed_addr constant [16 x i8] c"%d, %d, %d, %d\0A\00"
+@.str.8 = private unnamed_addr constant [16 x i8] c"%llu, %u, %llu\0A\00"
+@.str.9 = private unnamed_addr constant [5 x i8] c"true\00"
+@.str.10 = private unnamed_addr constant [6 x i8] c"false\00"
+@.str.11 = private unnamed_addr constant [20 x i8] c"%s, %s, %s, %s, %d\0A\00"
+
+@i = global i32 0
+@x = global i32 0
+@huge = global i64 0
+@mid = global i32 0
+@g = global float 0x0000000000000000
+
+declare i32 @abs(i32)
+declare i32 @putchar(i32)
+declare i8 @llvm.fptoui.sat.i8.f32(float)
+declare i32 @printf(ptr, ...)
+
+define ptr @classify(i32 %n) {
+entry:
+  %n.addr = alloca i32
+  store i32 %n, ptr %n.addr
+  %0 = load i32, ptr %n.addr
+  %1 = icmp slt i32 %0, 0
+  br i1 %1, label %if.then.0, label %if.else.2
+if.then.0:
+  ret ptr @.str.0
+if.else.2:
+  %2 = load i32, ptr %n.addr
+  %3 = icmp eq i32 %2, 0
+  br i1 %3, label %if.then.3, label %if.else.5
+if.then.3:
+  ret ptr @.str.1
+if.else.5:
+  ret ptr @.str.2
+if.end.4:
+  br label %if.end.1
+if.end.1:
+  ret ptr @.str.3
+}
+
+define i8 @clampByte(float %x) {
+entry:
+  %x.addr = alloca float
+  store float %x, ptr %x.addr
+  %0 = load float, ptr %x.addr
+  %1 = call i8 @llvm.fptoui.sat.i8.f32(float %0)
+  ret i8 %1
+}
+
+define i32 @main() {
+entry:
+  %j.addr = alloca i32
+  %x.addr = alloca i32
+  store i32 0, ptr @i
+  br label %while.cond.0
+while.cond.0:
+  %0 = load i32, ptr @i
+  %1 = icmp slt i32 %0, 3
+  br i1 %1, label %while.body.1, label %while.end.2
+while.body.1:
+  store i32 0, ptr %j.addr
+  br label %while.cond.3
+while.cond.3:
+  br i1 true, label %while.body.4, label %while.end.5
+while.body.4:
+  %2 = load i32, ptr %j.addr
+  %3 = add i32 %2, 1
+  store i32 %3, ptr %j.addr
+  %4 = load i32, ptr %j.addr
+  %5 = icmp eq i32 %4, 2
+  br i1 %5, label %if.then.6, label %if.end.7
+if.then.6:
+  %6 = load i32, ptr @i
+  %7 = add i32 %6, 1
+  store i32 %7, ptr @i
+  br label %while.cond.0
+if.end.7:
+  %8 = load i32, ptr @i
+  %9 = icmp eq i32 %8, 2
+  br i1 %9, label %if.then.8, label %if.end.9
+if.then.8:
+  br label %while.end.2
+if.end.9:
+  %10 = load i32, ptr @i
+  %11 = load i32, ptr %j.addr
+  %12 = call i32 (ptr, ...) @printf(ptr @.str.4, i32 %10, i32 %11)
+  br label %while.cond.3
+while.end.5:
+  br label %while.cond.0
+while.end.2:
+  %13 = load i32, ptr @i
+  %14 = call i32 (ptr, ...) @printf(ptr @.str.5, i32 %13)
+  store i32 5, ptr @x
+  %15 = load i32, ptr @x
+  %16 = mul i32 %15, 2
+  store i32 %16, ptr %x.addr
+  %17 = load i32, ptr %x.addr
+  %18 = call i32 (ptr, ...) @printf(ptr @.str.5, i32 %17)
+  %19 = load i32, ptr @x
+  %20 = sub i32 0, 7
+  %21 = call i32 @abs(i32 %20)
+  %22 = sub i32 0, 3
+  %23 = call ptr @classify(i32 %22)
+  %24 = call ptr @classify(i32 0)
+  %25 = call i32 (ptr, ...) @printf(ptr @.str.6, i32 %19, i32 %21, ptr %23, ptr %24)
+  %26 = call i32 @putchar(i32 65)
+  %27 = call i32 @putchar(i32 10)
+  %28 = call i8 @clampByte(float 0x4072C00000000000)
+  %29 = zext i8 %28 to i32
+  %30 = fsub float 0x0000000000000000, 0x4014000000000000
+  %31 = call i8 @clampByte(float %30)
+  %32 = zext i8 %31 to i32
+  %33 = sub i32 0, 1
+  %34 = trunc i32 %33 to i8
+  %35 = zext i8 %34 to i32
+  %36 = call i32 (ptr, ...) @printf(ptr @.str.7, i32 %29, i32 %32, i32 %35, i32 4464)
+  store i64 18446744073709551615, ptr @huge
+  store i32 4000000000, ptr @mid
+  %37 = load i64, ptr @huge
+  %38 = load i32, ptr @mid
+  %39 = load i64, ptr @huge
+  %40 = udiv i64 %39, 2
+  %41 = call i32 (ptr, ...) @printf(ptr @.str.8, i64 %37, i32 %38, i64 %40)
+  %42 = fdiv float 0x3FF0000000000000, 0x4008000000000000
+  store float %42, ptr @g
+  %43 = load float, ptr @g
+  %44 = fcmp olt float %43, 0x3FF0000000000000
+  %45 = select i1 %44, ptr @.str.9, ptr @.str.10
+  %46 = load float, ptr @g
+  %47 = load float, ptr @g
+  %48 = fcmp oeq float %46, %47
+  %49 = select i1 %48, ptr @.str.9, ptr @.str.10
+  %50 = load float, ptr @g
+  %51 = fcmp une float %50, 0x0000000000000000
+  %52 = select i1 %51, ptr @.str.9, ptr @.str.10
+  %53 = select i1 false, ptr @.str.9, ptr @.str.10
+  %54 = zext i1 true to i32
+  %55 = add i32 %54, 1
+  %56 = call i32 (ptr, ...) @printf(ptr @.str.11, ptr %45, ptr %49, ptr %52, ptr %53, i32 %55)
+  ret i32 0
+}
@@ -0,0 +1,221 @@
+@.str.0 = private unnamed_addr constant [1 x i8] c"\00"
+@.str.1 = private unnamed_addr constant [4 x i8] c"%d\0A\00"
+@.str.2 = private unnamed_addr constant [5 x i8] c"true\00"
+@.str.3 = private unnamed_addr constant [6 x i8] c"false\00"
+@.str.4 = private unnamed_addr constant [12 x i8] c"%s, %d, %s\0A\00"
+@.str.5 = private unnamed_addr constant [8 x i8] c"%s, %s\0A\00"
+@.str.6 = private unnamed_addr constant [6 x i8] c"%lld\0A\00"
+@.str.7 = private unnamed_addr constant [11 x i8] c"hi \22there\22\00"
+@.str.8 = private unnamed_addr constant [2 x i8] c"x\00"
+@.str.9 = private unnamed_addr constant [12 x i8] c"%d, %d, %d\0A\00"
+
+@limit = global i32 0
+@scale = constant i32 3
+@i = global i32 0
+@total = global i32 0
+@f = global float 0x0000000000000000
+@b = global i1 false
+@small = global i8 0
+@big = global i64 0
+@s = global ptr @.str.0
+@m = global i32 0
+
+declare i32 @printf(ptr, ...)
+declare i32 @llvm.fptosi.sat.i32.f32(float)
+declare i32 @strcmp(ptr, ptr)
+declare i32 @snprintf(ptr, i64, ptr, ...)
+declare float @strtof(ptr, ptr)
+declare ptr @strchr(ptr, i32)
+declare i32 @atoi(ptr)
+declare float @llvm.fabs.f32(float)
+
+define i32 @fib(i32 %n) {
+entry:
+  %n.addr = alloca i32
+  store i32 %n, ptr %n.addr
+  %0 = load i32, ptr %n.addr
+  %1 = icmp slt i32 %0, 2
+  br i1 %1, label %if.then.0, label %if.end.1
+if.then.0:
+  %2 = load i32, ptr %n.addr
+  ret i32 %2
+if.end.1:
+  %3 = load i32, ptr %n.addr
+  %4 = sub i32 %3, 1
+  %5 = call i32 @fib(i32 %4)
+  %6 = load i32, ptr %n.addr
+  %7 = sub i32 %6, 2
+  %8 = call i32 @fib(i32 %7)
+  %9 = add i32 %5, %8
+  ret i32 %9
+}
+
+define float @half(float %x) {
+entry:
+  %x.addr = alloca float
+  store float %x, ptr %x.addr
+  %0 = load float, ptr %x.addr
+  %1 = fdiv float %0, 0x4000000000000000
+  ret float %1
+}
+
+define i32 @main() {
+entry:
+  %float.text0 = alloca [64 x i8]
+  store i32 10, ptr @limit
+  store i32 0, ptr @i
+  store i32 0, ptr @total
+  br label %while.cond.0
+while.cond.0:
+  %0 = load i32, ptr @i
+  %1 = load i32, ptr @limit
+  %2 = icmp slt i32 %0, %1
+  br i1 %2, label %while.body.1, label %while.end.2
+while.body.1:
+  %3 = load i32, ptr @i
+  %4 = add i32 %3, 1
+  store i32 %4, ptr @i
+  %5 = load i32, ptr @i
+  %6 = srem i32 %5, 2
+  %7 = icmp eq i32 %6, 0
+  br i1 %7, label %if.then.3, label %if.end.4
+if.then.3:
+  br label %while.cond.0
+if.end.4:
+  %8 = load i32, ptr @total
+  %9 = load i32, ptr @i
+  %10 = load i32, ptr @scale
+  %11 = mul i32 %9, %10
+  %12 = add i32 %8, %11
+  store i32 %12, ptr @total
+  br label %while.cond.0
+while.end.2:
+  %13 = load i32, ptr @total
+  %14 = call i32 (ptr, ...) @printf(ptr @.str.1, i32 %13)
+  %15 = call i32 @fib(i32 10)
+  %16 = call i32 (ptr, ...) @printf(ptr @.str.1, i32 %15)
+  %17 = fdiv float 0x4053C00000000000, 0x4024000000000000
+  store float %17, ptr @f
+  %18 = load float, ptr @f
+  %19 = call float @half(float %18)
+  call void @tan_float_string(ptr %float.text0, float %19)
+  %20 = load float, ptr @f
+  %21 = call i32 @llvm.fptosi.sat.i32.f32(float %20)
+  %22 = load float, ptr @f
+  %23 = fcmp ogt float %22, 0x401C000000000000
+  %24 = select i1 %23, ptr @.str.2, ptr @.str.3
+  %25 = call i32 (ptr, ...) @printf(ptr @.str.4, ptr %float.text0, i32 %21, ptr %24)
+  %26 = load i32, ptr @i
+  %27 = icmp eq i32 %26, 10
+  br i1 %27, label %and.rhs.7, label %and.end.8
+and.rhs.7:
+  %28 = load i32, ptr @total
+  %29 = icmp sgt i32 %28, 0
+  br label %and.end.8
+and.end.8:
+  %30 = phi i1 [ false, %while.end.2 ], [ %29, %and.rhs.7 ]
+  br i1 %30, label %or.end.6, label %or.rhs.5
+or.rhs.5:
+  br label %or.end.6
+or.end.6:
+  %31 = phi i1 [ true, %and.end.8 ], [ false, %or.rhs.5 ]
+  store i1 %31, ptr @b
+  %32 = load i1, ptr @b
+  %33 = select i1 %32, ptr @.str.2, ptr @.str.3
+  %34 = load i1, ptr @b
+  %35 = xor i1 %34, true
+  %36 = select i1 %35, ptr @.str.2, ptr @.str.3
+  %37 = call i32 (ptr, ...) @printf(ptr @.str.5, ptr %33, ptr %36)
+  store i8 250, ptr @small
+  %38 = load i8, ptr @small
+  %39 = add i8 %38, 10
+  store i8 %39, ptr @small
+  %40 = load i8, ptr @small
+  %41 = zext i8 %40 to i32
+  %42 = call i32 (ptr, ...) @printf(ptr @.str.1, i32 %41)
+  store i64 3000000000, ptr @big
+  %43 = load i64, ptr @big
+  %44 = mul i64 %43, 2
+  %45 = call i32 (ptr, ...) @printf(ptr @.str.6, i64 %44)
+  store ptr @.str.7, ptr @s
+  %46 = load ptr, ptr @s
+  %47 = load ptr, ptr @s
+  %48 = call i32 @strcmp(ptr %47, ptr @.str.8)
+  %49 = icmp eq i32 %48, 0
+  %50 = select i1 %49, ptr @.str.2, ptr @.str.3
+  %51 = call i32 (ptr, ...) @printf(ptr @.str.5, ptr %46, ptr %50)
+  %52 = load i1, ptr @b
+  br i1 %52, label %if.then.9, label %if.else.10
+if.then.9:
+  br label %if.end.11
+if.else.10:
+  br label %if.end.11
+if.end.11:
+  %53 = phi i32 [ 1, %if.then.9 ], [ 2, %if.else.10 ]
+  store i32 %53, ptr @m
+  %54 = load i32, ptr @m
+  %55 = load i32, ptr @m
+  %56 = xor i32 %55, -1
+  %57 = load i32, ptr @m
+  %58 = xor i32 %57, 3
+  %59 = call i32 (ptr, ...) @printf(ptr @.str.9, i32 %54, i32 %56, i32 %58)
+  ret i32 0
+}
+
+@.float.nan = private unnamed_addr constant [4 x i8] c"NaN\00"
+@.float.inf = private unnamed_addr constant [4 x i8] c"inf\00"
+@.float.minus.inf = private unnamed_addr constant [5 x i8] c"-inf\00"
+@.float.scientific = private unnamed_addr constant [5 x i8] c"%.*e\00"
+@.float.fixed = private unnamed_addr constant [5 x i8] c"%.*f\00"
+@.float.large = private unnamed_addr constant [11 x i8] c"%.*s%.*s%s\00"
+@.float.zeros = private unnamed_addr constant [40 x i8] c"000000000000000000000000000000000000000\00"
+
+define internal void @tan_float_string(ptr %out, float %value) {
+entry:
+  %scientific = alloca [32 x i8]
+  %double = fpext float %value to double
+  %nan = fcmp uno float %value, %value
+  br i1 %nan, label %not.a.number, label %check.infinite
+not.a.number:
+  call i32 (ptr, i64, ptr, ...) @snprintf(ptr %out, i64 64, ptr @.float.nan)
+  ret void
+check.infinite:
+  %abs = call float @llvm.fabs.f32(float %value)
+  %infinite = fcmp oeq float %abs, 0x7FF0000000000000
+  %negative = fcmp olt float %value, 0.0
+  br i1 %infinite, label %infinity, label %search
+infinity:
+  %infinity.text = select i1 %negative, ptr @.float.minus.inf, ptr @.float.inf
+  call i32 (ptr, i64, ptr, ...) @snprintf(ptr %out, i64 64, ptr %infinity.text)
+  ret void
+search:
+  %precision = phi i32 [ 0, %check.infinite ], [ %next, %search ]
+  call i32 (ptr, i64, ptr, ...) @snprintf(ptr %scientific, i64 32, ptr @.float.scientific, i32 %precision, double %double)
+  %back = call float @strtof(ptr %scientific, ptr null)
+  %same = fcmp oeq float %back, %value
+  %next = add i32 %precision, 1
+  %last = icmp eq i32 %next, 9
+  %found = or i1 %same, %last
+  br i1 %found, label %format, label %search
+format:
+  %e = call ptr @strchr(ptr %scientific, i32 101)
+  %exponent.text = getelementptr i8, ptr %e, i64 1
+  %exponent = call i32 @atoi(ptr %exponent.text)
+  %decimals = sub i32 %precision, %exponent
+  %fraction = icmp sge i32 %decimals, 0
+  br i1 %fraction, label %fixed, label %large
+fixed:
+  call i32 (ptr, i64, ptr, ...) @snprintf(ptr %out, i64 64, ptr @.float.fixed, i32 %decimals, double %double)
+  ret void
+large:
+  %sign = zext i1 %negative to i32
+  %leading = add i32 %sign, 1
+  %sign.offset = zext i1 %negative to i64
+  %first = getelementptr i8, ptr %scientific, i64 %sign.offset
+  %rest = getelementptr i8, ptr %first, i64 2
+  %zero.count = add i32 %decimals, 39
+  %zero.offset = sext i32 %zero.count to i64
+  %zeros = getelementptr i8, ptr @.float.zeros, i64 %zero.offset
+  call i32 (ptr, i64, ptr, ...) @snprintf(ptr %out, i64 64, ptr @.float.large, i32 %leading, ptr %scientific, i32 %precision, ptr %rest, ptr %zeros)
+  ret void
+}
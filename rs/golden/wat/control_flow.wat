@@ -0,0 +1,233 @@
+(module
+  (import "env" "abs" (func $abs (param i32) (result i32)))
+  (import "env" "putchar" (func $putchar (param i32) (result i32)))
+  (import "host" "print_i32" (func $print_i32 (param i32)))
+  (import "host" "print_string" (func $print_string (param i32)))
+  (import "host" "print_u64" (func $print_u64 (param i64)))
+  (import "host" "print_u32" (func $print_u32 (param i32)))
+  (import "host" "print_bool" (func $print_bool (param i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 8) "negative\00")
+  (data (i32.const 17) "zero\00")
+  (data (i32.const 22) "positive\00")
+  (data (i32.const 31) "unreachable\00")
+  (data (i32.const 43) "\00")
+  (data (i32.const 44) ", \00")
+  (data (i32.const 47) "\0a\00")
+  (global $i (mut i32) (i32.const 0))
+  (global $x (mut i32) (i32.const 0))
+  (global $huge (mut i64) (i64.const 0))
+  (global $mid (mut i32) (i32.const 0))
+  (global $g (mut f32) (f32.const 0))
+  (func $classify (param $n i32) (result i32)
+    local.get $n
+    i32.const 0
+    i32.lt_s
+    if
+      i32.const 8
+      return
+    else
+      local.get $n
+      i32.const 0
+      i32.eq
+      if
+        i32.const 17
+        return
+      else
+        i32.const 22
+        return
+      end
+    end
+    i32.const 31
+    return
+    i32.const 43)
+  (func $clampByte (param $x f32) (result i32)
+    local.get $x
+    f32.const 0
+    f32.max
+    f32.const 255
+    f32.min
+    i32.trunc_sat_f32_u
+    return
+    i32.const 0)
+  (func $main (export "main") (result i32)
+    (local $j i32)
+    (local $x i32)
+    i32.const 0
+    global.set $i
+    block $while.end.0
+      loop $while.cond.1
+        global.get $i
+        i32.const 3
+        i32.lt_s
+        i32.eqz
+        br_if $while.end.0
+        i32.const 0
+        local.set $j
+        block $while.end.2
+          loop $while.cond.3
+            i32.const 1
+            i32.eqz
+            br_if $while.end.2
+            local.get $j
+            i32.const 1
+            i32.add
+            local.set $j
+            local.get $j
+            i32.const 2
+            i32.eq
+            if
+              global.get $i
+              i32.const 1
+              i32.add
+              global.set $i
+              br $while.cond.1
+            end
+            global.get $i
+            i32.const 2
+            i32.eq
+            if
+              br $while.end.0
+            end
+            global.get $i
+            call $print_i32
+            i32.const 44
+            call $print_string
+            local.get $j
+            call $print_i32
+            i32.const 47
+            call $print_string
+            br $while.cond.3
+          end
+        end
+        br $while.cond.1
+      end
+    end
+    global.get $i
+    call $print_i32
+    i32.const 47
+    call $print_string
+    i32.const 5
+    global.set $x
+    global.get $x
+    i32.const 2
+    i32.mul
+    local.set $x
+    local.get $x
+    call $print_i32
+    i32.const 47
+    call $print_string
+    global.get $x
+    call $print_i32
+    i32.const 44
+    call $print_string
+    i32.const 0
+    i32.const 7
+    i32.sub
+    call $abs
+    call $print_i32
+    i32.const 44
+    call $print_string
+    i32.const 0
+    i32.const 3
+    i32.sub
+    call $classify
+    call $print_string
+    i32.const 44
+    call $print_string
+    i32.const 0
+    call $classify
+    call $print_string
+    i32.const 47
+    call $print_string
+    i32.const 65
+    call $putchar
+    drop
+    i32.const 10
+    call $putchar
+    drop
+    i32.const 300
+    f32.convert_i32_s
+    call $clampByte
+    call $print_i32
+    i32.const 44
+    call $print_string
+    i32.const 0
+    f32.convert_i32_s
+    i32.const 5
+    f32.convert_i32_s
+    f32.sub
+    call $clampByte
+    call $print_i32
+    i32.const 44
+    call $print_string
+    i32.const 0
+    i32.const 1
+    i32.sub
+    i32.const 255
+    i32.and
+    call $print_i32
+    i32.const 44
+    call $print_string
+    i32.const 70000
+    i32.extend16_s
+    call $print_i32
+    i32.const 47
+    call $print_string
+    i64.const 18446744073709551615
+    global.set $huge
+    i32.const 4000000000
+    global.set $mid
+    global.get $huge
+    call $print_u64
+    i32.const 44
+    call $print_string
+    global.get $mid
+    call $print_u32
+    i32.const 44
+    call $print_string
+    global.get $huge
+    i64.const 2
+    i64.div_u
+    call $print_u64
+    i32.const 47
+    call $print_string
+    i32.const 1
+    f32.convert_i32_s
+    i32.const 3
+    f32.convert_i32_s
+    f32.div
+    global.set $g
+    global.get $g
+    i32.const 1
+    f32.convert_i32_s
+    f32.lt
+    call $print_bool
+    i32.const 44
+    call $print_string
+    global.get $g
+    global.get $g
+    f32.eq
+    call $print_bool
+    i32.const 44
+    call $print_string
+    global.get $g
+    f32.const 0
+    f32.ne
+    call $print_bool
+    i32.const 44
+    call $print_string
+    i32.const 0
+    i32.const 0
+    i32.ne
+    call $print_bool
+    i32.const 44
+    call $print_string
+    i32.const 1
+    i32.const 1
+    i32.add
+    call $print_i32
+    i32.const 47
+    call $print_string
+    i32.const 0)
+)
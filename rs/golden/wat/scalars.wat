@@ -0,0 +1,227 @@
+(module
+  (import "host" "print_i32" (func $print_i32 (param i32)))
+  (import "host" "print_string" (func $print_string (param i32)))
+  (import "host" "print_f32" (func $print_f32 (param f32)))
+  (import "host" "print_bool" (func $print_bool (param i32)))
+  (import "host" "print_i64" (func $print_i64 (param i64)))
+  (memory (export "memory") 1)
+  (data (i32.const 8) "\00")
+  (data (i32.const 9) "\0a\00")
+  (data (i32.const 11) ", \00")
+  (data (i32.const 14) "hi \22there\22\00")
+  (data (i32.const 25) "x\00")
+  (global $limit (mut i32) (i32.const 0))
+  (global $scale i32 (i32.const 3))
+  (global $i (mut i32) (i32.const 0))
+  (global $total (mut i32) (i32.const 0))
+  (global $f (mut f32) (f32.const 0))
+  (global $b (mut i32) (i32.const 0))
+  (global $small (mut i32) (i32.const 0))
+  (global $big (mut i64) (i64.const 0))
+  (global $s (mut i32) (i32.const 8))
+  (global $m (mut i32) (i32.const 0))
+  (func $tan_streq (param $a i32) (param $b i32) (result i32)
+    (local $c i32)
+    loop $next
+      local.get $a
+      i32.load8_u
+      local.tee $c
+      local.get $b
+      i32.load8_u
+      i32.ne
+      if
+        i32.const 0
+        return
+      end
+      local.get $c
+      i32.eqz
+      if
+        i32.const 1
+        return
+      end
+      local.get $a
+      i32.const 1
+      i32.add
+      local.set $a
+      local.get $b
+      i32.const 1
+      i32.add
+      local.set $b
+      br $next
+    end
+    i32.const 0)
+  (func $fib (param $n i32) (result i32)
+    local.get $n
+    i32.const 2
+    i32.lt_s
+    if
+      local.get $n
+      return
+    end
+    local.get $n
+    i32.const 1
+    i32.sub
+    call $fib
+    local.get $n
+    i32.const 2
+    i32.sub
+    call $fib
+    i32.add
+    return
+    i32.const 0)
+  (func $half (param $x f32) (result f32)
+    local.get $x
+    i32.const 2
+    f32.convert_i32_s
+    f32.div
+    return
+    f32.const 0)
+  (func $main (export "main") (result i32)
+    i32.const 10
+    global.set $limit
+    i32.const 0
+    global.set $i
+    i32.const 0
+    global.set $total
+    block $while.end.0
+      loop $while.cond.1
+        global.get $i
+        global.get $limit
+        i32.lt_s
+        i32.eqz
+        br_if $while.end.0
+        global.get $i
+        i32.const 1
+        i32.add
+        global.set $i
+        global.get $i
+        i32.const 2
+        i32.rem_s
+        i32.const 0
+        i32.eq
+        if
+          br $while.cond.1
+        end
+        global.get $total
+        global.get $i
+        global.get $scale
+        i32.mul
+        i32.add
+        global.set $total
+        br $while.cond.1
+      end
+    end
+    global.get $total
+    call $print_i32
+    i32.const 9
+    call $print_string
+    i32.const 10
+    call $fib
+    call $print_i32
+    i32.const 9
+    call $print_string
+    i32.const 79
+    f32.convert_i32_s
+    i32.const 10
+    f32.convert_i32_s
+    f32.div
+    global.set $f
+    global.get $f
+    call $half
+    call $print_f32
+    i32.const 11
+    call $print_string
+    global.get $f
+    i32.trunc_sat_f32_s
+    call $print_i32
+    i32.const 11
+    call $print_string
+    global.get $f
+    i32.const 7
+    f32.convert_i32_s
+    f32.gt
+    call $print_bool
+    i32.const 9
+    call $print_string
+    global.get $i
+    i32.const 10
+    i32.eq
+    if (result i32)
+      global.get $total
+      i32.const 0
+      i32.gt_s
+    else
+      i32.const 0
+    end
+    if (result i32)
+      i32.const 1
+    else
+      i32.const 0
+    end
+    global.set $b
+    global.get $b
+    call $print_bool
+    i32.const 11
+    call $print_string
+    global.get $b
+    i32.eqz
+    call $print_bool
+    i32.const 9
+    call $print_string
+    i32.const 250
+    global.set $small
+    global.get $small
+    i32.const 10
+    i32.add
+    i32.const 255
+    i32.and
+    global.set $small
+    global.get $small
+    call $print_i32
+    i32.const 9
+    call $print_string
+    i64.const 3000000000
+    global.set $big
+    global.get $big
+    i64.const 2
+    i64.mul
+    call $print_i64
+    i32.const 9
+    call $print_string
+    i32.const 14
+    global.set $s
+    global.get $s
+    call $print_string
+    i32.const 11
+    call $print_string
+    global.get $s
+    i32.const 25
+    call $tan_streq
+    call $print_bool
+    i32.const 9
+    call $print_string
+    global.get $b
+    if (result i32)
+      i32.const 1
+    else
+      i32.const 2
+    end
+    global.set $m
+    global.get $m
+    call $print_i32
+    i32.const 11
+    call $print_string
+    global.get $m
+    i32.const -1
+    i32.xor
+    call $print_i32
+    i32.const 11
+    call $print_string
+    global.get $m
+    i32.const 3
+    i32.xor
+    call $print_i32
+    i32.const 9
+    call $print_string
+    i32.const 0)
+)
@@ -0,0 +1,732 @@
+	.section .rodata
+.Lstr.0:
+	.string "hello\011world"
+.Lstr.1:
+	.string "negative"
+.Lstr.2:
+	.string "zero"
+.Lstr.3:
+	.string "positive"
+.Lstr.4:
+	.string ", "
+.Lstr.5:
+	.string "\012"
+.Lstr.6:
+	.string "x"
+.Ltan_int:
+	.string "%lld"
+.Ltan_uint:
+	.string "%llu"
+.Ltan_string:
+	.string "%s"
+.Ltan_true:
+	.string "true"
+.Ltan_false:
+	.string "false"
+	.section .data.rel.ro
+	.balign 8
+scale:
+	.quad 3
+greeting:
+	.quad .Lstr.0
+	.data
+	.balign 8
+limit:
+	.quad 0
+i:
+	.quad 0
+total:
+	.quad 0
+b:
+	.quad 0
+small:
+	.quad 0
+tiny:
+	.quad 0
+mid:
+	.quad 0
+big:
+	.quad 0
+huge:
+	.quad 0
+m:
+	.quad 0
+neg:
+	.quad 0
+	.text
+	.globl main
+tan_print_int:
+	pushq %rbp
+	movq %rsp, %rbp
+	movq %rdi, %rsi
+	leaq .Ltan_int(%rip), %rdi
+	xorl %eax, %eax
+	call printf@PLT
+	popq %rbp
+	ret
+tan_print_uint:
+	pushq %rbp
+	movq %rsp, %rbp
+	movq %rdi, %rsi
+	leaq .Ltan_uint(%rip), %rdi
+	xorl %eax, %eax
+	call printf@PLT
+	popq %rbp
+	ret
+tan_print_bool:
+	leaq .Ltan_true(%rip), %rax
+	leaq .Ltan_false(%rip), %rsi
+	testq %rdi, %rdi
+	cmovneq %rax, %rsi
+	movq %rsi, %rdi
+tan_print_string:
+	pushq %rbp
+	movq %rsp, %rbp
+	movq %rdi, %rsi
+	leaq .Ltan_string(%rip), %rdi
+	xorl %eax, %eax
+	call printf@PLT
+	popq %rbp
+	ret
+fib:
+	pushq %rbp
+	movq %rsp, %rbp
+	subq $16, %rsp
+	movq %rbx, -8(%rbp)
+	movq %r12, -16(%rbp)
+	movq %rdi, %rbx
+	movq %rbx, %rax
+	movq $2, %rcx
+	cmpq %rcx, %rax
+	setl %al
+	movzbq %al, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	testq %rax, %rax
+	je .Lfib.2
+.Lfib.1:
+	movq %rbx, %rax
+	movq -8(%rbp), %rbx
+	movq -16(%rbp), %r12
+	leave
+	ret
+.Lfib.2:
+	movq %rbx, %rax
+	movq $1, %rcx
+	subq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r12
+	movq %r12, %rdi
+	call fib
+	movq %rax, %r12
+	movq %rbx, %rax
+	movq $2, %rcx
+	subq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %rbx
+	movq %rbx, %rdi
+	call fib
+	movq %rax, %r10
+	movq %r12, %rax
+	movq %r10, %rcx
+	addq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq -8(%rbp), %rbx
+	movq -16(%rbp), %r12
+	leave
+	ret
+sum8:
+	pushq %rbp
+	movq %rsp, %rbp
+	subq $48, %rsp
+	movq %rbx, -8(%rbp)
+	movq %r12, -16(%rbp)
+	movq %r13, -24(%rbp)
+	movq %r14, -32(%rbp)
+	movq %r15, -40(%rbp)
+	movq %rdi, %r10
+	movq %rsi, %r11
+	movq %rdx, %rbx
+	movq %rcx, %r12
+	movq %r8, %r13
+	movq %r9, %r14
+	movq 16(%rbp), %rax
+	movq %rax, %r15
+	movq 24(%rbp), %rax
+	movq %rax, -48(%rbp)
+	movq %r10, %rax
+	movq %r11, %rcx
+	subq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq %rbx, %rcx
+	addq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq %r12, %rcx
+	subq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq %r13, %rcx
+	addq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq %r14, %rcx
+	subq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r10
+	movq %r15, %rax
+	movslq %eax, %rax
+	movq %rax, %r11
+	movq %r11, %rax
+	movq $1000, %rcx
+	imulq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r11
+	movq %r10, %rax
+	movq %r11, %rcx
+	addq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r10
+	movq -48(%rbp), %rax
+	movslq %eax, %rax
+	movq %rax, %r11
+	movq %r10, %rax
+	movq %r11, %rcx
+	addq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq -8(%rbp), %rbx
+	movq -16(%rbp), %r12
+	movq -24(%rbp), %r13
+	movq -32(%rbp), %r14
+	movq -40(%rbp), %r15
+	leave
+	ret
+classify:
+	pushq %rbp
+	movq %rsp, %rbp
+	movq %rdi, %r10
+	movq %r10, %rax
+	movq $0, %rcx
+	cmpq %rcx, %rax
+	setl %al
+	movzbq %al, %rax
+	movq %rax, %r11
+	movq %r11, %rax
+	testq %rax, %rax
+	je .Lclassify.3
+.Lclassify.1:
+	leaq .Lstr.1(%rip), %rax
+	leave
+	ret
+.Lclassify.2:
+	jmp .Lclassify.6
+.Lclassify.3:
+	movq %r10, %rax
+	movq $0, %rcx
+	cmpq %rcx, %rax
+	sete %al
+	movzbq %al, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	testq %rax, %rax
+	je .Lclassify.5
+.Lclassify.4:
+	leaq .Lstr.2(%rip), %rax
+	leave
+	ret
+.Lclassify.5:
+.Lclassify.6:
+	leaq .Lstr.3(%rip), %rax
+	leave
+	ret
+main:
+	pushq %rbp
+	movq %rsp, %rbp
+	subq $80, %rsp
+	movq %rbx, -8(%rbp)
+	movq %r12, -16(%rbp)
+	movq %r13, -24(%rbp)
+	movq %r14, -32(%rbp)
+	movq %r15, -40(%rbp)
+	movq $10, %rax
+	movq %rax, limit(%rip)
+	movq $0, %rax
+	movq %rax, i(%rip)
+	movq $0, %rax
+	movq %rax, total(%rip)
+.Lmain.1:
+	movq i(%rip), %rax
+	movq %rax, %r10
+	movq limit(%rip), %rax
+	movq %rax, %r11
+	movq %r10, %rax
+	movq %r11, %rcx
+	cmpq %rcx, %rax
+	setl %al
+	movzbq %al, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	testq %rax, %rax
+	je .Lmain.12
+.Lmain.2:
+	movq i(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq $1, %rcx
+	addq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq %rax, i(%rip)
+	movq i(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq $2, %rcx
+	cqto
+	idivq %rcx
+	movq %rdx, %rax
+	movslq %eax, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq $0, %rcx
+	cmpq %rcx, %rax
+	sete %al
+	movzbq %al, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	testq %rax, %rax
+	je .Lmain.4
+.Lmain.3:
+	jmp .Lmain.1
+.Lmain.4:
+	movq $0, %r10
+.Lmain.5:
+	movq $1, %rax
+	testq %rax, %rax
+	je .Lmain.11
+.Lmain.6:
+	movq %r10, %rax
+	movq $1, %rcx
+	addq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq $2, %rcx
+	cmpq %rcx, %rax
+	setg %al
+	movzbq %al, %rax
+	movq %rax, %r11
+	movq %r11, %rax
+	testq %rax, %rax
+	je .Lmain.8
+.Lmain.7:
+	jmp .Lmain.11
+.Lmain.8:
+	movq i(%rip), %rax
+	movq %rax, %r11
+	movq %r11, %rax
+	movq %r10, %rcx
+	imulq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r11
+	movq %r11, %rax
+	movq $12, %rcx
+	cmpq %rcx, %rax
+	setg %al
+	movzbq %al, %rax
+	movq %rax, %r11
+	movq %r11, %rax
+	testq %rax, %rax
+	je .Lmain.10
+.Lmain.9:
+	jmp .Lmain.12
+.Lmain.10:
+	movq total(%rip), %rax
+	movq %rax, %r11
+	movq i(%rip), %rax
+	movq %rax, %rbx
+	movq %rbx, %rax
+	movq %r10, %rcx
+	imulq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %rbx
+	movq scale(%rip), %rax
+	movq %rax, %r12
+	movq %rbx, %rax
+	movq %r12, %rcx
+	imulq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %rbx
+	movq %r11, %rax
+	movq %rbx, %rcx
+	addq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r11
+	movq %r11, %rax
+	movq %rax, total(%rip)
+	jmp .Lmain.5
+.Lmain.11:
+	jmp .Lmain.1
+.Lmain.12:
+	movq i(%rip), %rax
+	movq %rax, %rbx
+	movq total(%rip), %rax
+	movq %rax, %r12
+	movq $15, %rdi
+	call fib
+	movq %rax, %r13
+	movq %rbx, %rdi
+	call tan_print_int
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r12, %rdi
+	call tan_print_int
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r13, %rdi
+	call tan_print_int
+	leaq .Lstr.5(%rip), %rdi
+	call tan_print_string
+	movq $0, %rax
+	movq $7, %rcx
+	subq %rcx, %rax
+	movsbq %al, %rax
+	movq %rax, %rbx
+	subq $16, %rsp
+	movq %rbx, %rax
+	movq %rax, 0(%rsp)
+	movq $65535, %rax
+	movq %rax, 8(%rsp)
+	movq $1, %rdi
+	movq $2, %rsi
+	movq $3, %rdx
+	movq $4, %rcx
+	movq $5, %r8
+	movq $6, %r9
+	call sum8
+	addq $16, %rsp
+	movq %rax, %rbx
+	movq $0, %rax
+	movq $3, %rcx
+	subq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r12
+	movq %r12, %rdi
+	call classify
+	movq %rax, %r12
+	movq $0, %rdi
+	call classify
+	movq %rax, %r13
+	movq $8, %rdi
+	call classify
+	movq %rax, %r14
+	movq %rbx, %rdi
+	call tan_print_int
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r12, %rdi
+	call tan_print_string
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r13, %rdi
+	call tan_print_string
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r14, %rdi
+	call tan_print_string
+	leaq .Lstr.5(%rip), %rdi
+	call tan_print_string
+	movq i(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq $7, %rcx
+	cmpq %rcx, %rax
+	sete %al
+	movzbq %al, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	testq %rax, %rax
+	je .Lmain.14
+.Lmain.13:
+	movq total(%rip), %rax
+	movq %rax, %r11
+	movq %r11, %rax
+	movq $0, %rcx
+	cmpq %rcx, %rax
+	setg %al
+	movzbq %al, %rax
+	movq %rax, %r11
+	movq %r11, %r10
+.Lmain.14:
+	movq %r10, %rax
+	testq %rax, %rax
+	je .Lmain.15
+	jmp .Lmain.16
+.Lmain.15:
+	movq $0, %r10
+.Lmain.16:
+	movq %r10, %rax
+	movq %rax, b(%rip)
+	movq b(%rip), %rax
+	movq %rax, %rbx
+	movq b(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	xorq $1, %rax
+	movq %rax, %r12
+	movq greeting(%rip), %rax
+	movq %rax, %r13
+	movq greeting(%rip), %rax
+	movq %rax, %r14
+	movq %r14, %rdi
+	leaq .Lstr.6(%rip), %rsi
+	call strcmp@PLT
+	testl %eax, %eax
+	sete %al
+	movzbq %al, %rax
+	movq %rax, %r14
+	movq greeting(%rip), %rax
+	movq %rax, %r15
+	movq %r15, %rdi
+	leaq .Lstr.6(%rip), %rsi
+	call strcmp@PLT
+	testl %eax, %eax
+	setne %al
+	movzbq %al, %rax
+	movq %rax, %r15
+	movq %rbx, %rdi
+	call tan_print_bool
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r12, %rdi
+	call tan_print_bool
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r13, %rdi
+	call tan_print_string
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r14, %rdi
+	call tan_print_bool
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r15, %rdi
+	call tan_print_bool
+	leaq .Lstr.5(%rip), %rdi
+	call tan_print_string
+	movq $250, %rax
+	movq %rax, small(%rip)
+	movq small(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq $10, %rcx
+	addq %rcx, %rax
+	movzbq %al, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq %rax, small(%rip)
+	movq $127, %rax
+	movq %rax, tiny(%rip)
+	movq tiny(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq $1, %rcx
+	addq %rcx, %rax
+	movsbq %al, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq %rax, tiny(%rip)
+	movabsq $4000000000, %rax
+	movq %rax, mid(%rip)
+	movabsq $3000000000, %rax
+	movq %rax, big(%rip)
+	movq $-1, %rax
+	movq %rax, huge(%rip)
+	movq small(%rip), %rax
+	movq %rax, %rbx
+	movq tiny(%rip), %rax
+	movq %rax, %r12
+	movq mid(%rip), %rax
+	movq %rax, %r13
+	movq big(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq $2, %rcx
+	imulq %rcx, %rax
+	movq %rax, %r14
+	movq huge(%rip), %rax
+	movq %rax, %r15
+	movq huge(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq $3, %rcx
+	xorl %edx, %edx
+	divq %rcx
+	movq %rax, -48(%rbp)
+	movq $0, %rax
+	movq $1, %rcx
+	subq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movzbq %al, %rax
+	movq %rax, -56(%rbp)
+	movq %rbx, %rdi
+	call tan_print_uint
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r12, %rdi
+	call tan_print_int
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r13, %rdi
+	call tan_print_uint
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r14, %rdi
+	call tan_print_int
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r15, %rdi
+	call tan_print_uint
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq -48(%rbp), %rdi
+	call tan_print_uint
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq -56(%rbp), %rdi
+	call tan_print_uint
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq $4464, %rdi
+	call tan_print_int
+	leaq .Lstr.5(%rip), %rdi
+	call tan_print_string
+	movq b(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	testq %rax, %rax
+	je .Lmain.18
+.Lmain.17:
+	movq $4, %r10
+	movq %r10, %rax
+	movq $2, %rcx
+	imulq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r10
+	jmp .Lmain.19
+.Lmain.18:
+	movq $2, %r10
+.Lmain.19:
+	movq %r10, %rax
+	movq %rax, m(%rip)
+	movq $0, %rax
+	movq $17, %rcx
+	subq %rcx, %rax
+	movslq %eax, %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq %rax, neg(%rip)
+	movq m(%rip), %rax
+	movq %rax, %rbx
+	movq m(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	notq %rax
+	movslq %eax, %rax
+	movq %rax, %r12
+	movq m(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq $3, %rcx
+	xorq %rcx, %rax
+	movq %rax, %r13
+	movq m(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq $6, %rcx
+	andq %rcx, %rax
+	movq %rax, %r14
+	movq m(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq $1, %rcx
+	orq %rcx, %rax
+	movq %rax, %r15
+	movq neg(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq $5, %rcx
+	cqto
+	idivq %rcx
+	movslq %eax, %rax
+	movq %rax, -64(%rbp)
+	movq neg(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movq $5, %rcx
+	cqto
+	idivq %rcx
+	movq %rdx, %rax
+	movslq %eax, %rax
+	movq %rax, -72(%rbp)
+	movq neg(%rip), %rax
+	movq %rax, %r10
+	movq %r10, %rax
+	movl %eax, %eax
+	movq %rax, -80(%rbp)
+	movq %rbx, %rdi
+	call tan_print_int
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r12, %rdi
+	call tan_print_int
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r13, %rdi
+	call tan_print_int
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r14, %rdi
+	call tan_print_int
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq %r15, %rdi
+	call tan_print_int
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq -64(%rbp), %rdi
+	call tan_print_int
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq -72(%rbp), %rdi
+	call tan_print_int
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq -80(%rbp), %rdi
+	call tan_print_uint
+	leaq .Lstr.4(%rip), %rdi
+	call tan_print_string
+	movq $1, %rdi
+	call tan_print_bool
+	leaq .Lstr.5(%rip), %rdi
+	call tan_print_string
+	movq $0, %rax
+	movq -8(%rbp), %rbx
+	movq -16(%rbp), %r12
+	movq -24(%rbp), %r13
+	movq -32(%rbp), %r14
+	movq -40(%rbp), %r15
+	leave
+	ret
+	.section .note.GNU-stack,"",@progbits
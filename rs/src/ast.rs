@@ -1,13 +1,16 @@
+use std::collections::HashMap;
+
 use crate::types::BitwiseOp;
 use crate::types::DataType;
 use crate::types::Operator;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum NodeType {
     // Factors
     Number,
     Identifier,
     Boolean,
+    String,
 
     // Flow control
     Block,
@@ -16,9 +19,63 @@ pub enum NodeType {
     Parameter,
     Type(DataType),
     FunctionCall,
+    // C function declared with extern fn, children are the parameters, return type and header
+    ExternDef,
+    // Call of a builtin function, resolved by the checker
+    BuiltinCall,
     Return,
     If,
+    IfExpression,
     While,
+    Break,
+    Continue,
+
+    // Structs
+    StructDef,
+    Field,
+    StructLiteral,
+    FieldAccess,
+    PointerFieldAccess,
+
+    // Function values
+    Lambda,
+    Captures,
+    Call,
+    FunctionRef,
+
+    // Generics
+    GenericDef,
+    TypeParameters,
+
+    // Objects
+    ObjectLiteral,
+    Property,
+    Index,
+
+    // Signals
+    Signal,
+    Computed,
+    SignalDeclare,
+    ComputedDeclare,
+    SignalAssign,
+
+    // Modules
+    Import,
+    Pub,
+
+    // Pointers
+    AddressOf,
+    Deref,
+    Null,
+    New,
+
+    // Enums
+    EnumDef,
+    EnumVariant,
+    Match,
+    MatchExpression,
+    MatchArm,
+    Wildcard,
 
     // Expressions
     Operator(Operator),
@@ -33,10 +90,14 @@ pub enum NodeType {
     Or,
     Not,
     BitwiseOp(BitwiseOp),
+    // Explicit conversion with as, children are the value and the Type to convert to
+    Cast,
 
     ShortAssign(Operator),
     Assign,
     Declare,
+    // Top level declaration whose value the checker computes at compile time
+    ConstDeclare,
 }
 
 pub fn operator_to_node_type(op: Operator) -> NodeType {
@@ -47,17 +108,199 @@ pub fn bitwise_op_to_node_type(op: BitwiseOp) -> NodeType {
     NodeType::BitwiseOp(op)
 }
 
-#[derive(Debug)]
+// Parameter types and return type of a function
+pub type Signature = (Vec<DataType>, DataType);
+
+// Signatures of the functions and externs of a checked program and the types of its
+// globals. Backends that compile in one pass look them up, since code can use a function or
+// global before its definition
+pub fn top_level_types(ast: &AstNode) -> (HashMap<String, Signature>, HashMap<String, DataType>) {
+    let mut signatures = HashMap::new();
+    let mut globals = HashMap::new();
+    for node in &ast.children {
+        match node.node_type {
+            NodeType::FunctionDef | NodeType::ExternDef => {
+                let params = node.children[0]
+                    .children
+                    .iter()
+                    .map(|param| param.children[0].data_type().clone())
+                    .collect();
+                let return_type = node.children[1].data_type().clone();
+                signatures.insert(node.value.clone().unwrap(), (params, return_type));
+            }
+            NodeType::Declare | NodeType::ConstDeclare => {
+                let target = &node.children[1];
+                let name = match target.node_type {
+                    NodeType::Assign => target.children[0].value.clone().unwrap(),
+                    _ => target.value.clone().unwrap(),
+                };
+                globals.insert(name, node.children[0].data_type().clone());
+            }
+            _ => {}
+        }
+    }
+    (signatures, globals)
+}
+
+#[derive(Debug, Clone)]
 pub struct AstNode {
     pub node_type: NodeType,
     pub children: Vec<AstNode>,
     pub value: Option<String>,
+    // Type of the value the node produces, filled in by the checker
+    pub data_type: Option<DataType>,
+    // Where a statement starts in the source, backends use it for debug info
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    // Path of the source file, set by the linker
+    pub file: Option<String>,
+    pub line: usize,
 }
 
 impl AstNode {
+    // The data type held by a Type node
+    pub fn data_type(&self) -> &DataType {
+        match &self.node_type {
+            NodeType::Type(data_type) => data_type,
+            node_type => panic!("Expected type, got {:?}", node_type),
+        }
+    }
+
+    #[allow(dead_code)]
     pub fn evaluate(&self) -> i32 {
         evaluate_node(self).unwrap()
     }
+
+    // Folds an expression of literals and the given constants into a literal of the type,
+    // None when its value is not known at compile time or can not be computed
+    pub fn fold(
+        &self,
+        constants: &HashMap<String, AstNode>,
+        data_type: &DataType,
+    ) -> Option<AstNode> {
+        let (node_type, value) = match (fold_node(self, constants)?, data_type) {
+            (Constant::Integer(value), DataType::Integer | DataType::Sized(_)) => {
+                let (min, max) = data_type.integer_range().unwrap();
+                if value < min || value > max {
+                    panic!("Constant value {} does not fit in {}", value, data_type);
+                }
+                (NodeType::Number, value.to_string())
+            }
+            (Constant::Integer(value), DataType::Float) => (NodeType::Number, value.to_string()),
+            (Constant::Integer(value), DataType::Boolean) => {
+                (NodeType::Boolean, (value != 0).to_string())
+            }
+            (Constant::Boolean(value), DataType::Boolean) => (NodeType::Boolean, value.to_string()),
+            (
+                Constant::Boolean(value),
+                DataType::Integer | DataType::Sized(_) | DataType::Float,
+            ) => (NodeType::Number, (value as i32).to_string()),
+            (Constant::String(value), DataType::String) => (NodeType::String, value),
+            _ => return None,
+        };
+
+        Some(AstNode {
+            node_type,
+            children: vec![],
+            value: Some(value),
+            data_type: Some(data_type.clone()),
+            span: None,
+        })
+    }
+}
+
+enum Constant {
+    Integer(i128),
+    Boolean(bool),
+    String(String),
+}
+
+fn fold_node(node: &AstNode, constants: &HashMap<String, AstNode>) -> Option<Constant> {
+    let value = node.value.clone().unwrap_or_default();
+    let operands = || -> Option<(Constant, Constant)> {
+        Some((
+            fold_node(&node.children[0], constants)?,
+            fold_node(&node.children[1], constants)?,
+        ))
+    };
+    let integers = || match operands()? {
+        (Constant::Integer(l), Constant::Integer(r)) => Some((l, r)),
+        _ => None,
+    };
+    let booleans = || match operands()? {
+        (Constant::Boolean(l), Constant::Boolean(r)) => Some((l, r)),
+        _ => None,
+    };
+
+    let constant = match &node.node_type {
+        NodeType::Number => Constant::Integer(value.parse().ok()?),
+        NodeType::Boolean => Constant::Boolean(value == "true"),
+        NodeType::String => Constant::String(value),
+        NodeType::Identifier => fold_node(constants.get(&value)?, constants)?,
+        NodeType::LParen => fold_node(&node.children[0], constants)?,
+        NodeType::Cast => match (
+            fold_node(&node.children[0], constants)?,
+            node.children[1].data_type(),
+        ) {
+            (Constant::Integer(value), DataType::Boolean) => Constant::Boolean(value != 0),
+            (Constant::Integer(value), data_type @ (DataType::Integer | DataType::Sized(_))) => {
+                Constant::Integer(data_type.wrap_integer(value))
+            }
+            (Constant::Boolean(value), DataType::Integer | DataType::Sized(_)) => {
+                Constant::Integer(value as i128)
+            }
+            (constant @ Constant::Boolean(_), DataType::Boolean) => constant,
+            _ => return None,
+        },
+        NodeType::Operator(op) => {
+            let (l, r) = integers()?;
+            Constant::Integer(match op {
+                Operator::Add => l.checked_add(r)?,
+                Operator::Subtract => l.checked_sub(r)?,
+                Operator::Multiply => l.checked_mul(r)?,
+                Operator::Divide => l.checked_div(r)?,
+                Operator::Mod => l.checked_rem(r)?,
+            })
+        }
+        NodeType::BitwiseOp(BitwiseOp::Not) => match fold_node(&node.children[0], constants)? {
+            Constant::Integer(value) => Constant::Integer(!value),
+            _ => return None,
+        },
+        NodeType::BitwiseOp(op) => {
+            let (l, r) = integers()?;
+            Constant::Integer(match op {
+                BitwiseOp::And => l & r,
+                BitwiseOp::Or => l | r,
+                BitwiseOp::Xor => l ^ r,
+                BitwiseOp::Not => unreachable!(),
+            })
+        }
+        NodeType::Eq | NodeType::NotEq => {
+            let equal = match operands()? {
+                (Constant::Integer(l), Constant::Integer(r)) => l == r,
+                (Constant::Boolean(l), Constant::Boolean(r)) => l == r,
+                (Constant::String(l), Constant::String(r)) => l == r,
+                _ => return None,
+            };
+            Constant::Boolean(equal == (node.node_type == NodeType::Eq))
+        }
+        NodeType::LessThan => integers().map(|(l, r)| Constant::Boolean(l < r))?,
+        NodeType::GreaterThan => integers().map(|(l, r)| Constant::Boolean(l > r))?,
+        NodeType::Leq => integers().map(|(l, r)| Constant::Boolean(l <= r))?,
+        NodeType::Geq => integers().map(|(l, r)| Constant::Boolean(l >= r))?,
+        NodeType::And => booleans().map(|(l, r)| Constant::Boolean(l && r))?,
+        NodeType::Or => booleans().map(|(l, r)| Constant::Boolean(l || r))?,
+        NodeType::Not => match fold_node(&node.children[0], constants)? {
+            Constant::Boolean(value) => Constant::Boolean(!value),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some(constant)
 }
 
 fn evaluate_node(node: &AstNode) -> Option<i32> {
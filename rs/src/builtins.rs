@@ -0,0 +1,136 @@
+use crate::types::DataType;
+
+// Functions provided by the language. The checker turns calls of them into builtin calls,
+// unless a function of the program has the same name. C, JS and the interpreter implement
+// all of them, the LLVM, WAT and assembly backends only print
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Builtin {
+    Print,
+    Alloc,
+    Free,
+
+    // Math
+    Abs,
+    Min,
+    Max,
+    Pow,
+    Sqrt,
+
+    // Strings
+    Len,
+    Concat,
+    Substr,
+    Str,
+
+    // Input
+    ReadInt,
+    ReadLine,
+
+    Assert,
+    Exit,
+
+    // Random numbers, the same generator in every backend so a seed gives the same numbers
+    Random,
+    Seed,
+}
+
+impl Builtin {
+    pub fn from_name(name: &str) -> Option<Builtin> {
+        match name {
+            "print" => Some(Builtin::Print),
+            "alloc" => Some(Builtin::Alloc),
+            "free" => Some(Builtin::Free),
+            "abs" => Some(Builtin::Abs),
+            "min" => Some(Builtin::Min),
+            "max" => Some(Builtin::Max),
+            "pow" => Some(Builtin::Pow),
+            "sqrt" => Some(Builtin::Sqrt),
+            "len" => Some(Builtin::Len),
+            "concat" => Some(Builtin::Concat),
+            "substr" => Some(Builtin::Substr),
+            "str" => Some(Builtin::Str),
+            "readInt" => Some(Builtin::ReadInt),
+            "readLine" => Some(Builtin::ReadLine),
+            "assert" => Some(Builtin::Assert),
+            "exit" => Some(Builtin::Exit),
+            "random" => Some(Builtin::Random),
+            "seed" => Some(Builtin::Seed),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Print => "print",
+            Builtin::Alloc => "alloc",
+            Builtin::Free => "free",
+            Builtin::Abs => "abs",
+            Builtin::Min => "min",
+            Builtin::Max => "max",
+            Builtin::Pow => "pow",
+            Builtin::Sqrt => "sqrt",
+            Builtin::Len => "len",
+            Builtin::Concat => "concat",
+            Builtin::Substr => "substr",
+            Builtin::Str => "str",
+            Builtin::ReadInt => "readInt",
+            Builtin::ReadLine => "readLine",
+            Builtin::Assert => "assert",
+            Builtin::Exit => "exit",
+            Builtin::Random => "random",
+            Builtin::Seed => "seed",
+        }
+    }
+
+    // Builtins that only compute a value from their arguments, so it does not matter when
+    // they run
+    pub fn is_pure(&self) -> bool {
+        matches!(
+            self,
+            Builtin::Abs
+                | Builtin::Min
+                | Builtin::Max
+                | Builtin::Pow
+                | Builtin::Sqrt
+                | Builtin::Len
+                | Builtin::Concat
+                | Builtin::Substr
+                | Builtin::Str
+        )
+    }
+
+    // Parameter types and return type, print, alloc and free take any values and pointers
+    // so the checker handles them itself
+    pub fn signature(&self) -> Option<(Vec<DataType>, Option<DataType>)> {
+        use DataType::{Boolean, Integer, String};
+
+        let (params, return_type) = match self {
+            Builtin::Print | Builtin::Alloc | Builtin::Free => return None,
+            Builtin::Abs | Builtin::Sqrt => (vec![Integer], Some(Integer)),
+            Builtin::Min | Builtin::Max | Builtin::Pow => (vec![Integer, Integer], Some(Integer)),
+            Builtin::Len => (vec![String], Some(Integer)),
+            Builtin::Concat => (vec![String, String], Some(String)),
+            Builtin::Substr => (vec![String, Integer, Integer], Some(String)),
+            Builtin::Str => (vec![Integer], Some(String)),
+            Builtin::ReadInt => (vec![], Some(Integer)),
+            Builtin::ReadLine => (vec![], Some(String)),
+            Builtin::Assert => (vec![Boolean], None),
+            Builtin::Exit | Builtin::Seed => (vec![Integer], None),
+            Builtin::Random => (vec![Integer], Some(Integer)),
+        };
+
+        Some((params, return_type))
+    }
+
+    // Parameter types and return type of the float version of the math builtins, the
+    // checker picks it when an argument is a float
+    pub fn float_signature(&self) -> Option<(Vec<DataType>, Option<DataType>)> {
+        use DataType::Float;
+
+        match self {
+            Builtin::Abs | Builtin::Sqrt => Some((vec![Float], Some(Float))),
+            Builtin::Min | Builtin::Max | Builtin::Pow => Some((vec![Float, Float], Some(Float))),
+            _ => None,
+        }
+    }
+}
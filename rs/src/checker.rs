@@ -0,0 +1,2523 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+use crate::builtins::Builtin;
+use crate::parser::type_node;
+use crate::types::*;
+
+struct Signature {
+    params: Vec<DataType>,
+    return_type: DataType,
+}
+
+impl Signature {
+    fn function_type(&self) -> DataType {
+        DataType::Function(self.params.clone(), Box::new(self.return_type.clone()))
+    }
+}
+
+// Instances of a generic are limited, so that recursion with growing types terminates
+const MAX_INSTANCES: usize = 64;
+
+// A lambda being checked and the variables of the scopes around it that it uses
+struct Closure {
+    // Number of scopes outside of the lambda
+    depth: usize,
+    captures: Vec<(String, DataType)>,
+}
+
+struct Checker {
+    // Labels of the loops enclosing the current node, innermost last
+    loops: Vec<Option<String>>,
+    scopes: Vec<HashMap<String, DataType>>,
+    functions: HashMap<String, Signature>,
+    structs: HashMap<String, Vec<(String, DataType)>>,
+    enums: HashMap<String, Vec<String>>,
+    // Return type of the function being checked, None at the top level
+    return_type: Option<DataType>,
+    // Whether a function body is being checked, its outermost scope is then its parameters
+    in_function: bool,
+    // Lambdas enclosing the current node, innermost last
+    closures: Vec<Closure>,
+    // Number of lambdas seen so far, used to give each one a unique name
+    lambdas: usize,
+    // Function type the value of the if or match being checked ends up in, lambdas in its
+    // branches take their types from it
+    branch_type: Option<DataType>,
+    // Type of each signal and whether it is computed
+    signals: HashMap<String, (DataType, bool)>,
+    // Computed signal being checked and the number of scopes outside of its expression
+    computing: Option<(String, usize)>,
+    // Generic definitions by name, with their type parameters
+    generics: HashMap<String, AstNode>,
+    // Name of the instance of each generic and type arguments instantiated so far
+    instance_names: Vec<(String, Vec<DataType>, String)>,
+    // Checked instances and the index of the top level node they are placed after
+    instances: Vec<(usize, AstNode)>,
+    // Function instances waiting to be checked
+    pending: Vec<(usize, AstNode)>,
+    // Index of every top level definition
+    positions: HashMap<String, usize>,
+    // Functions declared with extern fn, they have no definition to refer to as a value
+    externs: HashSet<String>,
+    // Types of the top level variables, functions see all of them
+    globals: HashMap<String, DataType>,
+    // Names of all constants, and the values of those checked so far folded into literals
+    constant_names: HashSet<String>,
+    constants: HashMap<String, AstNode>,
+}
+
+pub fn check(ast: &mut AstNode) {
+    let mut checker = Checker {
+        loops: vec![],
+        scopes: vec![HashMap::new()],
+        functions: HashMap::new(),
+        structs: HashMap::new(),
+        enums: HashMap::new(),
+        return_type: None,
+        in_function: false,
+        closures: vec![],
+        lambdas: 0,
+        branch_type: None,
+        signals: HashMap::new(),
+        computing: None,
+        generics: HashMap::new(),
+        instance_names: vec![],
+        instances: vec![],
+        pending: vec![],
+        positions: HashMap::new(),
+        externs: HashSet::new(),
+        globals: HashMap::new(),
+        constant_names: HashSet::new(),
+        constants: HashMap::new(),
+    };
+
+    for (i, child) in ast.children.iter().enumerate() {
+        if let NodeType::StructDef
+        | NodeType::EnumDef
+        | NodeType::FunctionDef
+        | NodeType::GenericDef = child.node_type
+        {
+            let name = child.value.clone().unwrap();
+            checker.positions.insert(name.clone(), i);
+            if child.node_type == NodeType::GenericDef {
+                checker.generics.insert(name, child.clone());
+            }
+        }
+    }
+
+    // Generic struct types are instantiated before anything uses them
+    for child in &mut ast.children {
+        if child.node_type != NodeType::GenericDef {
+            checker.resolve_types(child, &HashMap::new());
+        }
+    }
+
+    // Definitions are hoisted, so they can be used before they appear
+    for child in &ast.children {
+        checker.define(child);
+    }
+
+    for child in &mut ast.children {
+        match child.node_type {
+            NodeType::GenericDef | NodeType::ExternDef => {}
+            NodeType::Declare | NodeType::ConstDeclare => checker.check_global(child),
+            _ => {
+                checker.check_node(child);
+            }
+        }
+    }
+
+    // Checking an instance can instantiate more generics
+    while !checker.pending.is_empty() {
+        let (position, mut def) = checker.pending.remove(0);
+        checker.check_function_def(&mut def);
+        checker.instances.push((position, def));
+    }
+
+    // Generic definitions are replaced by their instances, which come after the
+    // definitions they use
+    let mut instances = std::mem::take(&mut checker.instances);
+    let mut children = vec![];
+    for (i, child) in std::mem::take(&mut ast.children).into_iter().enumerate() {
+        if child.node_type != NodeType::GenericDef {
+            children.push(child);
+        }
+
+        let (placed, rest) = instances
+            .into_iter()
+            .partition(|(position, _)| *position == i);
+        instances = rest;
+        children.extend(
+            placed
+                .into_iter()
+                .map(|(_, instance): (usize, AstNode)| instance),
+        );
+    }
+    ast.children = children;
+}
+
+impl Checker {
+    fn define(&mut self, node: &AstNode) {
+        let name = node.value.clone().unwrap_or_default();
+        match node.node_type {
+            NodeType::FunctionDef => {
+                let signature = Signature {
+                    params: node.children[0]
+                        .children
+                        .iter()
+                        .map(|param| param.children[0].data_type().clone())
+                        .collect(),
+                    return_type: node.children[1].data_type().clone(),
+                };
+                self.functions.insert(name, signature);
+            }
+            // The checker trusts the declaration, the C compiler checks it against the library
+            NodeType::ExternDef => {
+                let signature = Signature {
+                    params: node.children[0]
+                        .children
+                        .iter()
+                        .map(|param| param.children[0].data_type().clone())
+                        .collect(),
+                    return_type: node.children[1].data_type().clone(),
+                };
+                self.functions.insert(name.clone(), signature);
+                self.externs.insert(name);
+            }
+            NodeType::StructDef => {
+                let fields = node
+                    .children
+                    .iter()
+                    .map(|field| {
+                        (
+                            field.children[1].value.clone().unwrap(),
+                            field.children[0].data_type().clone(),
+                        )
+                    })
+                    .collect();
+                self.structs.insert(name, fields);
+            }
+            NodeType::Declare | NodeType::ConstDeclare => {
+                let name = declared_name(node);
+                if self.globals.contains_key(&name) {
+                    panic!("Variable {} is already declared in this scope", name);
+                }
+
+                if node.node_type == NodeType::ConstDeclare {
+                    self.constant_names.insert(name.clone());
+                }
+                self.globals
+                    .insert(name, node.children[0].data_type().clone());
+            }
+            NodeType::EnumDef => {
+                let variants = node
+                    .children
+                    .iter()
+                    .map(|variant| variant.value.clone().unwrap())
+                    .collect();
+                self.enums.insert(name, variants);
+            }
+            _ => {}
+        }
+    }
+
+    fn declare(&mut self, name: &str, data_type: DataType) {
+        let scope = self.scopes.last_mut().unwrap();
+        if scope.contains_key(name) {
+            panic!("Variable {} is already declared in this scope", name);
+        }
+
+        scope.insert(name.to_string(), data_type);
+    }
+
+    fn is_variable(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains_key(name)) || self.is_global(name)
+    }
+
+    // Top level code declares variables in its scope like locals, so it can not use them
+    // before their declaration. Functions use globals directly, even those declared after them
+    fn is_global(&self, name: &str) -> bool {
+        self.globals.contains_key(name)
+            && (self.return_type.is_some() || self.constants.contains_key(name))
+    }
+
+    // Whether a variable found at a depth is a global, lambdas refer to those instead of
+    // capturing them
+    fn is_global_at(&self, name: &str, depth: usize) -> bool {
+        depth == 0 && !self.in_function && self.globals.contains_key(name)
+    }
+
+    // Constants are never in a scope, a variable with the name shadows them
+    fn is_constant(&self, name: &str) -> bool {
+        self.constant_names.contains(name)
+            && !self.scopes.iter().any(|scope| scope.contains_key(name))
+    }
+
+    fn expect_mutable(&self, target: &AstNode) {
+        // Assigning a field of a struct assigns the variable holding it. Objects and pointers
+        // are shared, writes through them reach the original
+        let mut target = target;
+        while target.node_type == NodeType::FieldAccess {
+            target = &target.children[0];
+        }
+        if target.node_type != NodeType::Identifier {
+            return;
+        }
+
+        let name = target.value.as_ref().unwrap();
+        if self.is_constant(name) {
+            panic!("Cannot assign to constant {}", name);
+        }
+
+        // A lambda has a copy of the variables it captures, assigning one would be lost
+        if let (Some(closure), Some((depth, _))) = (self.closures.last(), self.find(name)) {
+            if closure.depth > depth && !self.is_global_at(name, depth) {
+                panic!("Cannot assign to captured variable {} in a lambda", name);
+            }
+        }
+    }
+
+    // The innermost scope declaring a variable and its type
+    fn find(&self, name: &str) -> Option<(usize, &DataType)> {
+        self.scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(depth, scope)| scope.get(name).map(|data_type| (depth, data_type)))
+    }
+
+    fn lookup(&mut self, name: &str) -> DataType {
+        let found = self.find(name);
+
+        let (depth, data_type) = match found {
+            Some((depth, data_type)) => (depth, data_type.clone()),
+            None if self.is_global(name) => return self.globals[name].clone(),
+            None => panic!("Use of undeclared variable {}", name),
+        };
+
+        // Computed signals are updated outside of the code that declares them
+        if let Some((signal, outer)) = &self.computing {
+            if depth < *outer {
+                panic!(
+                    "Computed signal {} can only depend on signals, not on variable {}",
+                    signal, name
+                );
+            }
+        }
+
+        if self.is_global_at(name, depth) {
+            return data_type;
+        }
+
+        // Lambdas created inside the scope of the variable capture it
+        for closure in &mut self.closures {
+            if closure.depth > depth && !closure.captures.iter().any(|(n, _)| n == name) {
+                closure.captures.push((name.to_string(), data_type.clone()));
+            }
+        }
+
+        data_type
+    }
+
+    // Checks a node, records the type of the value it produces on it and returns that type
+    fn check_node(&mut self, node: &mut AstNode) -> Option<DataType> {
+        let data_type = self.infer(node);
+        node.data_type = data_type.clone();
+        data_type
+    }
+
+    fn infer(&mut self, node: &mut AstNode) -> Option<DataType> {
+        match node.node_type.clone() {
+            NodeType::Number => {
+                // Without a suffix a literal is an int, or a 64 bit integer if it is too large
+                let data_type = match node.children.first() {
+                    Some(suffix) => suffix.data_type().clone(),
+                    None => [DataType::Integer, DataType::Sized(IntType::I64)]
+                        .into_iter()
+                        .find(|data_type| literal_fits(node, data_type))
+                        .unwrap_or(DataType::Sized(IntType::U64)),
+                };
+                expect_fits(node, &data_type);
+                Some(data_type)
+            }
+            NodeType::Boolean => Some(DataType::Boolean),
+            NodeType::String => Some(DataType::String),
+            NodeType::Identifier => {
+                let name = node.value.clone().unwrap();
+                if self.is_variable(&name) {
+                    return Some(self.lookup(&name));
+                }
+
+                if self.generics.contains_key(&name) {
+                    panic!("Generic function {} can only be called", name);
+                }
+
+                if self.externs.contains(&name) {
+                    panic!("Extern function {} can only be called", name);
+                }
+
+                // Named functions can be used as values
+                match self.functions.get(&name) {
+                    Some(signature) => {
+                        node.node_type = NodeType::FunctionRef;
+                        Some(signature.function_type())
+                    }
+                    None => panic!("Use of undeclared variable {}", name),
+                }
+            }
+            NodeType::LParen => Some(self.check_value(&mut node.children[0])),
+            NodeType::Operator(op) => {
+                let (left, right) = self.check_operands(node);
+                Some(operator_type(&op, &left, &right))
+            }
+            NodeType::Eq | NodeType::NotEq => {
+                let (left, right) = self.check_operands(node);
+                if !(common_type(&left, &right).is_some()
+                    || left == right && is_comparable(&left)
+                    || pointers_compatible(&left, &right))
+                {
+                    panic!("Cannot compare {} and {}", left, right);
+                }
+
+                Some(DataType::Boolean)
+            }
+            NodeType::LessThan | NodeType::GreaterThan | NodeType::Leq | NodeType::Geq => {
+                let (left, right) = self.check_operands(node);
+                // Pointers into the same allocation can be ordered
+                let same_pointers = matches!(left, DataType::Pointer(_)) && left == right;
+                if !(common_type(&left, &right).is_some() || same_pointers) {
+                    panic!("Cannot compare {} and {}", left, right);
+                }
+
+                Some(DataType::Boolean)
+            }
+            NodeType::And | NodeType::Or => {
+                self.check_condition(&mut node.children[0]);
+                self.check_condition(&mut node.children[1]);
+                Some(DataType::Boolean)
+            }
+            NodeType::Not => {
+                self.check_condition(&mut node.children[0]);
+                Some(DataType::Boolean)
+            }
+            NodeType::Cast => {
+                let value = self.check_value(&mut node.children[0]);
+                let target = node.children[1].data_type().clone();
+                if value != target && !(is_scalar(&value) && is_scalar(&target)) {
+                    panic!("Cannot cast {} to {}", value, target);
+                }
+
+                Some(target)
+            }
+            NodeType::BitwiseOp(BitwiseOp::Not) => {
+                let data_type = self.check_value(&mut node.children[0]);
+                if !is_integral(&data_type) {
+                    panic!("Bitwise operators require integers, got {}", data_type);
+                }
+
+                common_type(&data_type, &data_type)
+            }
+            NodeType::BitwiseOp(_) => {
+                let (left, right) = self.check_operands(node);
+                for data_type in [&left, &right] {
+                    if !is_integral(data_type) {
+                        panic!("Bitwise operators require integers, got {}", data_type);
+                    }
+                }
+
+                match common_type(&left, &right) {
+                    Some(data_type) => Some(data_type),
+                    None => panic!(
+                        "Cannot apply bitwise operator to {} and {}, use as to convert one of them",
+                        left, right
+                    ),
+                }
+            }
+            NodeType::Block => {
+                self.scopes.push(HashMap::new());
+                for child in &mut node.children {
+                    self.check_node(child);
+                }
+                self.scopes.pop();
+                None
+            }
+            NodeType::Declare => {
+                self.check_declare(node);
+                None
+            }
+            NodeType::ConstDeclare => panic!("Constants can only be declared at the top level"),
+            NodeType::Assign => {
+                let target = self.check_value(&mut node.children[0]);
+                self.expect_mutable(&node.children[0]);
+                if node.children[0].node_type == NodeType::FunctionRef {
+                    panic!(
+                        "Cannot assign to function {}",
+                        node.children[0].value.clone().unwrap()
+                    );
+                }
+
+                self.check_expecting(&mut node.children[1], &target);
+                None
+            }
+            NodeType::ShortAssign(op) => {
+                let target = self.check_value(&mut node.children[0]);
+                self.expect_mutable(&node.children[0]);
+                self.check_value(&mut node.children[1]);
+                let value = adapt_literal(&mut node.children[1], &target);
+                expect_assignable(&target, &operator_type(&op, &target, &value));
+                None
+            }
+            NodeType::FunctionDef => {
+                self.check_function_def(node);
+                None
+            }
+            NodeType::FunctionCall => self.check_function_call(node),
+            NodeType::ExternDef => panic!("Extern functions can only be declared at the top level"),
+            NodeType::BuiltinCall => {
+                let builtin = Builtin::from_name(node.value.as_ref().unwrap()).unwrap();
+                self.check_builtin_call(node, builtin)
+            }
+            NodeType::Return => {
+                // Top level code ends up in main, which returns an int
+                let return_type = self.return_type.clone().unwrap_or(DataType::Integer);
+                self.check_expecting(&mut node.children[0], &return_type);
+                None
+            }
+            NodeType::If => {
+                self.check_condition(&mut node.children[0]);
+                for child in &mut node.children[1..] {
+                    self.check_node(child);
+                }
+                None
+            }
+            NodeType::IfExpression => {
+                self.check_condition(&mut node.children[0]);
+                let then_type = self.check_branch_value(&mut node.children[1]);
+                let else_type = self.check_branch_value(&mut node.children[2]);
+                Some(unify(&then_type, &else_type))
+            }
+            NodeType::While => {
+                self.check_while(node);
+                None
+            }
+            NodeType::Break => {
+                self.check_loop_jump(node, "break");
+                None
+            }
+            NodeType::Continue => {
+                self.check_loop_jump(node, "continue");
+                None
+            }
+            NodeType::StructDef => {
+                self.check_struct_def(node);
+                None
+            }
+            NodeType::StructLiteral => Some(self.check_struct_literal(node)),
+            NodeType::FieldAccess => match self.check_value(&mut node.children[0]) {
+                DataType::Object(value) => {
+                    node.node_type = NodeType::Property;
+                    Some(*value)
+                }
+                object => Some(self.field_type(&object, node.value.as_ref().unwrap())),
+            },
+            NodeType::ObjectLiteral => Some(self.check_object_literal(node, None)),
+            NodeType::Index => {
+                let value = match self.check_value(&mut node.children[0]) {
+                    DataType::Object(value) => *value,
+                    object => panic!("Cannot index non object type {}", object),
+                };
+
+                self.check_expecting(&mut node.children[1], &DataType::String);
+                Some(value)
+            }
+            NodeType::Property => match self.check_value(&mut node.children[0]) {
+                DataType::Object(value) => Some(*value),
+                object => panic!("Cannot access property of non object type {}", object),
+            },
+            NodeType::PointerFieldAccess => match self.check_value(&mut node.children[0]) {
+                DataType::Pointer(object) if *object != DataType::Void => {
+                    Some(self.field_type(&object, node.value.as_ref().unwrap()))
+                }
+                object => panic!("Cannot use -> on non pointer type {}", object),
+            },
+            NodeType::EnumDef => {
+                self.check_enum_def(node);
+                None
+            }
+            NodeType::EnumVariant => {
+                let name = node.children[0].value.as_ref().unwrap();
+                let variant = node.value.as_ref().unwrap();
+                if !self.enums[name].contains(variant) {
+                    panic!("Enum {} has no variant {}", name, variant);
+                }
+
+                Some(DataType::Enum(name.clone()))
+            }
+            NodeType::AddressOf => {
+                let target = &mut node.children[0];
+                let data_type = self.check_value(target);
+                if !matches!(
+                    target.node_type,
+                    NodeType::Identifier
+                        | NodeType::FieldAccess
+                        | NodeType::PointerFieldAccess
+                        | NodeType::Deref
+                ) {
+                    panic!("Cannot take the address of {:?}", target.node_type);
+                }
+                if target.node_type == NodeType::Identifier
+                    && self.is_constant(target.value.as_ref().unwrap())
+                {
+                    panic!(
+                        "Cannot take the address of constant {}",
+                        target.value.as_ref().unwrap()
+                    );
+                }
+
+                Some(DataType::Pointer(Box::new(data_type)))
+            }
+            NodeType::Lambda => Some(self.check_lambda(node, None)),
+            NodeType::Signal => Some(self.signal_type(node.value.as_ref().unwrap(), false)),
+            NodeType::Computed => Some(self.signal_type(node.value.as_ref().unwrap(), true)),
+            NodeType::SignalDeclare | NodeType::ComputedDeclare => {
+                self.check_signal_declare(node);
+                None
+            }
+            NodeType::SignalAssign => {
+                let name = node.value.as_ref().unwrap();
+                let data_type = match self.signals.get(name) {
+                    Some((data_type, false)) => data_type.clone(),
+                    Some((_, true)) => panic!("Computed signal {} can not be assigned", name),
+                    None => panic!("Use of undeclared signal {}", name),
+                };
+
+                self.check_expecting(&mut node.children[0], &data_type);
+                None
+            }
+            NodeType::Call => self.check_call(node),
+            NodeType::Deref => Some(pointee(&self.check_value(&mut node.children[0]))),
+            NodeType::Null => Some(DataType::Pointer(Box::new(DataType::Void))),
+            NodeType::New => Some(DataType::Pointer(Box::new(
+                node.children[0].data_type().clone(),
+            ))),
+            NodeType::Match => {
+                self.check_match(node);
+                None
+            }
+            NodeType::MatchExpression => self.check_match(node),
+            NodeType::Parameters => panic!("Unexpected Parameters node"),
+            NodeType::Type(_) => panic!("Unexpected Type node"),
+            NodeType::Parameter => panic!("Unexpected Parameter node"),
+            NodeType::Field => panic!("Unexpected Field node"),
+            NodeType::FunctionRef => {
+                Some(self.functions[node.value.as_ref().unwrap()].function_type())
+            }
+            NodeType::Captures => panic!("Unexpected Captures node"),
+            NodeType::MatchArm => panic!("Unexpected MatchArm node"),
+            NodeType::Wildcard => panic!("Unexpected Wildcard node"),
+            NodeType::GenericDef => panic!(
+                "Generic {} can only be defined at the top level",
+                node.value.as_ref().unwrap()
+            ),
+            NodeType::TypeParameters => panic!("Unexpected TypeParameters node"),
+            NodeType::Import | NodeType::Pub => panic!(
+                "Unexpected {:?} node, modules are linked before checking",
+                node.node_type
+            ),
+        }
+    }
+
+    // Checks both operands of a binary operator, a literal takes the integer type of the other
+    // operand so x + 1 keeps the type of x
+    fn check_operands(&mut self, node: &mut AstNode) -> (DataType, DataType) {
+        self.check_value(&mut node.children[0]);
+        let right = self.check_value(&mut node.children[1]);
+        let left = adapt_literal(&mut node.children[0], &right);
+        let right = adapt_literal(&mut node.children[1], &left);
+        (left, right)
+    }
+
+    fn check_value(&mut self, node: &mut AstNode) -> DataType {
+        match self.check_node(node) {
+            Some(data_type) => data_type,
+            None => panic!("Expected a value, got {:?}", node.node_type),
+        }
+    }
+
+    // Checks a value that ends up in a slot of the given type, allocations take their
+    // element type from it
+    fn check_expecting(&mut self, node: &mut AstNode, expected: &DataType) -> DataType {
+        if is_literal_expression(node) && expected.integer_layout().is_some() {
+            self.check_value(node);
+            retype_literals(node, expected);
+            return expected.clone();
+        }
+
+        // Integers do not widen to floats when they can be too large, literals that a float
+        // holds exactly still convert
+        if is_literal_expression(node) && *expected == DataType::Float {
+            let value = self.check_value(node);
+            return match node.fold(&HashMap::new(), &DataType::Float) {
+                Some(folded) if float_holds(&folded) => expected.clone(),
+                _ => {
+                    expect_assignable(expected, &value);
+                    value
+                }
+            };
+        }
+
+        if node.node_type == NodeType::FunctionCall
+            && node.value.as_deref() == Some("alloc")
+            && !self.functions.contains_key("alloc")
+            && !self.is_variable("alloc")
+        {
+            node.node_type = NodeType::BuiltinCall;
+            if !matches!(expected, DataType::Pointer(element) if **element != DataType::Void) {
+                panic!("Cannot infer the element type of alloc");
+            }
+
+            self.check_args("alloc", &mut node.children, &[DataType::Integer]);
+            node.data_type = Some(expected.clone());
+            return expected.clone();
+        }
+
+        if node.node_type == NodeType::Lambda {
+            let value = self.check_lambda(node, Some(expected));
+            node.data_type = Some(value.clone());
+            expect_assignable(expected, &value);
+            return value;
+        }
+
+        if let (NodeType::IfExpression | NodeType::MatchExpression, DataType::Function(..)) =
+            (&node.node_type, expected)
+        {
+            let outer = self.branch_type.replace(expected.clone());
+            let value = self.check_value(node);
+            self.branch_type = outer;
+            expect_assignable(expected, &value);
+            return value;
+        }
+
+        // The type arguments of a generic struct literal can come from the slot
+        if let (NodeType::StructLiteral, DataType::Struct(instance)) = (&node.node_type, expected) {
+            if let Some((generic, _)) = self.instance_of(instance) {
+                if node.value.as_ref() == Some(generic) {
+                    node.value = Some(instance.clone());
+                }
+            }
+        }
+
+        if node.node_type == NodeType::ObjectLiteral {
+            let value = self.check_object_literal(node, Some(expected));
+            node.data_type = Some(value.clone());
+            expect_assignable(expected, &value);
+            return value;
+        }
+
+        let value = self.check_value(node);
+        expect_assignable(expected, &value);
+        value
+    }
+
+    fn check_condition(&mut self, node: &mut AstNode) {
+        let data_type = self.check_value(node);
+        // Like in C a pointer is true when it is not null
+        if !is_scalar(&data_type) && !matches!(data_type, DataType::Pointer(_)) {
+            panic!("Expected a condition, got {}", data_type);
+        }
+    }
+
+    // The value of an if or match branch, blocks produce the value of their last child
+    fn check_branch_value(&mut self, node: &mut AstNode) -> DataType {
+        let expected = self.branch_type.take();
+        let data_type = match node.node_type {
+            NodeType::Block => {
+                self.scopes.push(HashMap::new());
+                let (value, statements) = node.children.split_last_mut().unwrap();
+                for statement in statements {
+                    self.check_node(statement);
+                }
+                let data_type = self.check_branch_result(value, &expected);
+                self.scopes.pop();
+                data_type
+            }
+            _ => self.check_branch_result(node, &expected),
+        };
+        self.branch_type = expected;
+
+        data_type
+    }
+
+    fn check_branch_result(&mut self, node: &mut AstNode, expected: &Option<DataType>) -> DataType {
+        match expected {
+            Some(expected)
+                if matches!(
+                    node.node_type,
+                    NodeType::Lambda | NodeType::IfExpression | NodeType::MatchExpression
+                ) =>
+            {
+                self.check_expecting(node, expected)
+            }
+            _ => self.check_value(node),
+        }
+    }
+
+    fn check_declare(&mut self, node: &mut AstNode) {
+        let data_type = node.children[0].data_type().clone();
+        let target = &mut node.children[1];
+
+        let name = match target.node_type {
+            NodeType::Assign => {
+                self.check_expecting(&mut target.children[1], &data_type);
+                target.children[0].value.clone().unwrap()
+            }
+            _ => target.value.clone().unwrap(),
+        };
+
+        self.declare(&name, data_type);
+    }
+
+    // Top level declarations are globals, constants get the value of their initializer
+    fn check_global(&mut self, node: &mut AstNode) {
+        let data_type = node.children[0].data_type().clone();
+        let name = declared_name(node);
+
+        if node.children[1].node_type == NodeType::Assign {
+            let value = &mut node.children[1].children[1];
+            self.check_expecting(value, &data_type);
+
+            if node.node_type == NodeType::ConstDeclare {
+                *value = match value.fold(&self.constants, &data_type) {
+                    Some(literal) => literal,
+                    None => panic!(
+                        "Cannot compute the value of constant {} at compile time",
+                        name
+                    ),
+                };
+                self.constants.insert(name, value.clone());
+                return;
+            }
+        }
+
+        self.declare(&name, data_type);
+    }
+
+    fn check_while(&mut self, node: &mut AstNode) {
+        // A loop can not have the label of a loop around it, a jump would be ambiguous
+        if let Some(label) = &node.value {
+            if self.loops.contains(&node.value) {
+                panic!("Label '{} is already used by an enclosing loop", label);
+            }
+        }
+
+        self.check_condition(&mut node.children[0]);
+
+        self.loops.push(node.value.clone());
+        self.check_node(&mut node.children[1]);
+        self.loops.pop();
+    }
+
+    fn check_loop_jump(&self, node: &AstNode, keyword: &str) {
+        if self.loops.is_empty() {
+            panic!("'{}' outside of a loop", keyword);
+        }
+
+        if let Some(label) = &node.value {
+            if !self.loops.contains(&Some(label.clone())) {
+                panic!("Use of undeclared label '{} in '{}'", label, keyword);
+            }
+        }
+    }
+
+    fn check_function_def(&mut self, node: &mut AstNode) {
+        self.define(node);
+        let params = parameter_scope(&node.children[0]);
+
+        // Functions can not see the locals or loops of the code around them
+        let loops = std::mem::take(&mut self.loops);
+        let scopes = std::mem::replace(&mut self.scopes, vec![params]);
+        let closures = std::mem::take(&mut self.closures);
+        let return_type = self
+            .return_type
+            .replace(node.children[1].data_type().clone());
+        let in_function = std::mem::replace(&mut self.in_function, true);
+
+        self.check_node(&mut node.children[2]);
+
+        self.loops = loops;
+        self.scopes = scopes;
+        self.closures = closures;
+        self.return_type = return_type;
+        self.in_function = in_function;
+    }
+
+    // Names the lambda, fills in the types of a pipe lambda and records what it captures
+    fn check_lambda(&mut self, node: &mut AstNode, expected: Option<&DataType>) -> DataType {
+        // Pipe lambdas take their types from the function type they are used as
+        if node.children.len() == 2 {
+            let param_count = node.children[0].children.len();
+            let (params, return_type) = match expected {
+                Some(DataType::Function(params, return_type)) if params.len() == param_count => {
+                    (params.clone(), *return_type.clone())
+                }
+                Some(expected) => panic!(
+                    "Mismatched types, expected {} got a lambda with {} parameters",
+                    expected, param_count
+                ),
+                None => panic!("Cannot infer the parameter types of the lambda"),
+            };
+
+            for (param, data_type) in node.children[0].children.iter_mut().zip(params) {
+                param.children.insert(0, type_node(data_type));
+            }
+            node.children.insert(1, type_node(return_type));
+        }
+
+        node.value = Some(format!("lambda_{}", self.lambdas));
+        self.lambdas += 1;
+
+        let params = parameter_scope(&node.children[0]);
+        let param_types = node.children[0]
+            .children
+            .iter()
+            .map(|param| param.children[0].data_type().clone())
+            .collect();
+        let return_type = node.children[1].data_type().clone();
+
+        // Lambdas become their own C functions, so loops do not carry over
+        let loops = std::mem::take(&mut self.loops);
+        let outer_return_type = self.return_type.replace(return_type.clone());
+        self.closures.push(Closure {
+            depth: self.scopes.len(),
+            captures: vec![],
+        });
+        self.scopes.push(params);
+
+        self.check_node(&mut node.children[2]);
+
+        self.scopes.pop();
+        let closure = self.closures.pop().unwrap();
+        self.loops = loops;
+        self.return_type = outer_return_type;
+
+        let captures = closure
+            .captures
+            .into_iter()
+            .map(|(name, data_type)| AstNode {
+                node_type: NodeType::Parameter,
+                children: vec![
+                    type_node(data_type),
+                    AstNode {
+                        node_type: NodeType::Identifier,
+                        children: vec![],
+                        value: Some(name),
+                        data_type: None,
+                        span: None,
+                    },
+                ],
+                value: None,
+                data_type: None,
+                span: None,
+            })
+            .collect();
+        node.children.push(AstNode {
+            node_type: NodeType::Captures,
+            children: captures,
+            value: None,
+            data_type: None,
+            span: None,
+        });
+
+        DataType::Function(param_types, Box::new(return_type))
+    }
+
+    fn check_signal_declare(&mut self, node: &mut AstNode) {
+        let name = node.value.clone().unwrap();
+        if self.scopes.len() != 1 || self.return_type.is_some() || !self.closures.is_empty() {
+            panic!("Signal {} can only be declared at the top level", name);
+        }
+
+        if self.signals.contains_key(&name) {
+            panic!("Signal {} is already declared", name);
+        }
+
+        let data_type = node.children[0].data_type().clone();
+        let computed = node.node_type == NodeType::ComputedDeclare;
+        if computed {
+            self.computing = Some((name.clone(), self.scopes.len()));
+        }
+
+        self.check_expecting(&mut node.children[1], &data_type);
+        self.computing = None;
+        self.signals.insert(name, (data_type, computed));
+    }
+
+    fn signal_type(&self, name: &str, computed: bool) -> DataType {
+        match self.signals.get(name) {
+            Some((data_type, is_computed)) if *is_computed == computed => data_type.clone(),
+            Some((_, true)) => panic!("{} is a computed signal, read it with ${}", name, name),
+            Some((_, false)) => panic!("{} is not a computed signal, read it with #{}", name, name),
+            None => panic!("Use of undeclared signal {}", name),
+        }
+    }
+
+    // Calls of function values, the callee is the first child
+    fn check_call(&mut self, node: &mut AstNode) -> Option<DataType> {
+        let (params, return_type) = match self.check_value(&mut node.children[0]) {
+            DataType::Function(params, return_type) => (params, *return_type),
+            callee => panic!("Cannot call non function type {}", callee),
+        };
+
+        let name = node.children[0]
+            .value
+            .clone()
+            .unwrap_or("value".to_string());
+        self.check_args(&name, &mut node.children[1..], &params);
+
+        Some(return_type)
+    }
+
+    fn check_function_call(&mut self, node: &mut AstNode) -> Option<DataType> {
+        let name = node.value.clone().unwrap();
+
+        // Variables holding function values shadow functions
+        if self.is_variable(&name) {
+            let callee = AstNode {
+                node_type: NodeType::Identifier,
+                children: vec![],
+                value: node.value.take(),
+                data_type: None,
+                span: None,
+            };
+            node.node_type = NodeType::Call;
+            node.children.insert(0, callee);
+            return self.check_call(node);
+        }
+
+        if self.generics.contains_key(&name) {
+            return Some(self.check_generic_call(node));
+        }
+
+        if !self.functions.contains_key(&name) {
+            if let Some(builtin) = Builtin::from_name(&name) {
+                node.node_type = NodeType::BuiltinCall;
+                return self.check_builtin_call(node, builtin);
+            }
+        }
+
+        let signature = match self.functions.get(&name) {
+            Some(signature) => signature,
+            None => panic!("Call to undefined function {}", name),
+        };
+
+        let params = signature.params.clone();
+        let return_type = signature.return_type.clone();
+        self.check_args(&name, &mut node.children, &params);
+
+        // Extern functions can return nothing
+        match return_type {
+            DataType::Void => None,
+            return_type => Some(return_type),
+        }
+    }
+
+    fn check_builtin_call(&mut self, node: &mut AstNode, builtin: Builtin) -> Option<DataType> {
+        let name = node.value.clone().unwrap();
+        match builtin {
+            Builtin::Print => {
+                for arg in &mut node.children {
+                    self.check_value(arg);
+                }
+                None
+            }
+            Builtin::Alloc => panic!("Cannot infer the element type of alloc"),
+            Builtin::Free => {
+                if node.children.len() != 1 {
+                    panic!(
+                        "Function free expects 1 arguments, got {}",
+                        node.children.len()
+                    );
+                }
+
+                let pointer = self.check_value(&mut node.children[0]);
+                if !matches!(pointer, DataType::Pointer(_)) {
+                    panic!("Cannot free non pointer type {}", pointer);
+                }
+                None
+            }
+            _ if builtin.float_signature().is_some() => self.check_math_call(node, builtin),
+            _ => {
+                let (params, return_type) = builtin.signature().unwrap();
+                self.check_args(&name, &mut node.children, &params);
+                return_type
+            }
+        }
+    }
+
+    // Math builtins take floats when one of the arguments is a float, literal arguments
+    // then convert to floats
+    fn check_math_call(&mut self, node: &mut AstNode, builtin: Builtin) -> Option<DataType> {
+        let name = node.value.clone().unwrap();
+        let mut arg_types = vec![];
+        for arg in &mut node.children {
+            match is_literal_expression(arg) {
+                true => arg_types.push(None),
+                false => arg_types.push(Some(self.check_value(arg))),
+            }
+        }
+
+        let (params, return_type) = match arg_types.contains(&Some(DataType::Float)) {
+            true => builtin.float_signature().unwrap(),
+            false => builtin.signature().unwrap(),
+        };
+        if params.len() != node.children.len() {
+            panic!(
+                "Function {} expects {} arguments, got {}",
+                name,
+                params.len(),
+                node.children.len()
+            );
+        }
+
+        for ((param, arg), arg_type) in params.iter().zip(&mut node.children).zip(arg_types) {
+            match arg_type {
+                Some(arg_type) => expect_assignable(param, &arg_type),
+                None => {
+                    self.check_expecting(arg, param);
+                }
+            }
+        }
+        return_type
+    }
+
+    // Infers the type arguments from the arguments and calls the instance for them
+    fn check_generic_call(&mut self, node: &mut AstNode) -> DataType {
+        let name = node.value.clone().unwrap();
+        let generic = self.generics[&name].clone();
+        let def = &generic.children[1];
+        if def.node_type != NodeType::FunctionDef {
+            panic!("Call to undefined function {}", name);
+        }
+
+        let params = &def.children[0].children;
+        if params.len() != node.children.len() {
+            panic!(
+                "Function {} expects {} arguments, got {}",
+                name,
+                params.len(),
+                node.children.len()
+            );
+        }
+
+        let mut bindings = HashMap::new();
+        let mut arg_types = vec![];
+        for (param, arg) in params.iter().zip(&mut node.children) {
+            let arg_type = self.check_value(arg);
+            self.infer_bindings(
+                param.children[0].data_type(),
+                &arg_type,
+                &mut bindings,
+                &name,
+            );
+            arg_types.push(arg_type);
+        }
+
+        let args = generic.children[0]
+            .children
+            .iter()
+            .map(|param| {
+                let param = param.value.as_ref().unwrap();
+                match bindings.get(param) {
+                    Some(data_type) => data_type.clone(),
+                    None => panic!("Cannot infer type parameter {} of {}", param, name),
+                }
+            })
+            .collect::<Vec<DataType>>();
+
+        let instance = self.instantiate(&name, args);
+        let signature = &self.functions[&instance];
+        for (param, arg) in signature.params.iter().zip(&arg_types) {
+            expect_assignable(param, arg);
+        }
+
+        node.value = Some(instance);
+        signature.return_type.clone()
+    }
+
+    // Matches the type of a parameter against the type of its argument to find the
+    // types of the type parameters in it
+    fn infer_bindings(
+        &self,
+        param: &DataType,
+        arg: &DataType,
+        bindings: &mut HashMap<String, DataType>,
+        name: &str,
+    ) {
+        match (param, arg) {
+            // Null says nothing about the type it points to
+            (_, DataType::Pointer(pointee)) if **pointee == DataType::Void => {}
+            (DataType::Param(param), _) => match bindings.get(param) {
+                Some(bound) if bound != arg => panic!(
+                    "Type parameter {} of {} is both {} and {}",
+                    param, name, bound, arg
+                ),
+                Some(_) => {}
+                None => {
+                    bindings.insert(param.clone(), arg.clone());
+                }
+            },
+            (DataType::Pointer(param), DataType::Pointer(arg))
+            | (DataType::Object(param), DataType::Object(arg)) => {
+                self.infer_bindings(param, arg, bindings, name)
+            }
+            (
+                DataType::Function(params, return_type),
+                DataType::Function(args, arg_return_type),
+            ) if params.len() == args.len() => {
+                for (param, arg) in params.iter().zip(args) {
+                    self.infer_bindings(param, arg, bindings, name);
+                }
+                self.infer_bindings(return_type, arg_return_type, bindings, name);
+            }
+            (DataType::Instance(generic, params), DataType::Struct(instance)) => {
+                if let Some((instance_generic, args)) = self.instance_of(instance) {
+                    if instance_generic == generic {
+                        for (param, arg) in params.iter().zip(args) {
+                            self.infer_bindings(param, arg, bindings, name);
+                        }
+                    }
+                }
+            }
+            // Mismatches are reported when the arguments are checked against the instance
+            _ => {}
+        }
+    }
+
+    // Replaces the type parameters in the types of a node and its children with their
+    // bindings and generic struct types with their instances
+    fn resolve_types(&mut self, node: &mut AstNode, bindings: &HashMap<String, DataType>) {
+        if let NodeType::Type(data_type) = &node.node_type {
+            node.node_type = NodeType::Type(self.resolve(data_type, bindings));
+        }
+
+        for child in &mut node.children {
+            self.resolve_types(child, bindings);
+        }
+    }
+
+    fn resolve(&mut self, data_type: &DataType, bindings: &HashMap<String, DataType>) -> DataType {
+        match data_type {
+            DataType::Param(name) => match bindings.get(name) {
+                Some(data_type) => data_type.clone(),
+                None => panic!("Unknown type parameter {}", name),
+            },
+            DataType::Instance(name, args) => {
+                let args = args.iter().map(|arg| self.resolve(arg, bindings)).collect();
+                DataType::Struct(self.instantiate(name, args))
+            }
+            DataType::Struct(name) if self.generics.contains_key(name) => {
+                panic!("Generic struct {} needs type arguments", name)
+            }
+            DataType::Pointer(data_type) => {
+                DataType::Pointer(Box::new(self.resolve(data_type, bindings)))
+            }
+            DataType::Object(data_type) => {
+                DataType::Object(Box::new(self.resolve(data_type, bindings)))
+            }
+            DataType::Function(params, return_type) => DataType::Function(
+                params
+                    .iter()
+                    .map(|param| self.resolve(param, bindings))
+                    .collect(),
+                Box::new(self.resolve(return_type, bindings)),
+            ),
+            _ => data_type.clone(),
+        }
+    }
+
+    // Returns the name of the instance of a generic for the type arguments, creating the
+    // instance the first time
+    fn instantiate(&mut self, name: &str, args: Vec<DataType>) -> String {
+        if let Some((_, _, instance)) = self
+            .instance_names
+            .iter()
+            .find(|(generic, instance_args, _)| generic == name && *instance_args == args)
+        {
+            return instance.clone();
+        }
+
+        let generic = match self.generics.get(name) {
+            Some(generic) => generic.clone(),
+            None => panic!("{} is not generic", name),
+        };
+
+        let params = &generic.children[0].children;
+        if params.len() != args.len() {
+            panic!(
+                "{} expects {} type arguments, got {}",
+                name,
+                params.len(),
+                args.len()
+            );
+        }
+
+        let count = self
+            .instance_names
+            .iter()
+            .filter(|(generic, _, _)| generic == name)
+            .count();
+        if count == MAX_INSTANCES {
+            panic!("Too many instances of generic {}", name);
+        }
+
+        // Generated names contain _, which TanScript names can not
+        let mut instance = format!(
+            "{}_{}",
+            name,
+            args.iter().map(mangle).collect::<Vec<String>>().join("_")
+        );
+        if self.instance_of(&instance).is_some() {
+            instance = format!("{}_{}", instance, self.instance_names.len());
+        }
+
+        // The name is known before the definition is resolved, so it can refer to itself
+        self.instance_names
+            .push((name.to_string(), args.clone(), instance.clone()));
+
+        let bindings = params
+            .iter()
+            .map(|param| param.value.clone().unwrap())
+            .zip(args.iter().cloned())
+            .collect();
+        let mut def = generic.children[1].clone();
+        def.value = Some(instance.clone());
+        self.resolve_types(&mut def, &bindings);
+
+        // A struct that contains another one by value has to come after it in C
+        let position = args
+            .iter()
+            .filter_map(|arg| match arg {
+                DataType::Struct(name) | DataType::Enum(name) => self.positions.get(name),
+                _ => None,
+            })
+            .fold(self.positions[name], |position, arg| position.max(*arg));
+        self.positions.insert(instance.clone(), position);
+
+        match def.node_type {
+            NodeType::StructDef => {
+                self.check_struct_def(&def);
+                self.instances.push((position, def));
+            }
+            _ => {
+                self.define(&def);
+                self.pending.push((position, def));
+            }
+        }
+
+        instance
+    }
+
+    // The generic and the type arguments of an instance
+    fn instance_of(&self, instance: &str) -> Option<(&String, &Vec<DataType>)> {
+        self.instance_names
+            .iter()
+            .find(|(_, _, name)| name == instance)
+            .map(|(generic, args, _)| (generic, args))
+    }
+
+    // Checks the argument count and types of a call
+    fn check_args(&mut self, name: &str, args: &mut [AstNode], params: &[DataType]) {
+        if params.len() != args.len() {
+            panic!(
+                "Function {} expects {} arguments, got {}",
+                name,
+                params.len(),
+                args.len()
+            );
+        }
+
+        for (param, arg) in params.iter().zip(args) {
+            self.check_expecting(arg, param);
+        }
+    }
+
+    fn check_struct_def(&mut self, node: &AstNode) {
+        self.define(node);
+
+        let name = node.value.as_ref().unwrap();
+        let mut seen = vec![];
+        for field in &node.children {
+            let field_name = field.children[1].value.as_ref().unwrap();
+            if seen.contains(&field_name) {
+                panic!("Duplicate field {} in struct {}", field_name, name);
+            }
+            seen.push(field_name);
+
+            // A struct can only refer to itself through a pointer
+            if *field.children[0].data_type() == DataType::Struct(name.clone()) {
+                panic!("Struct {} can not contain itself", name);
+            }
+        }
+    }
+
+    fn check_struct_literal(&mut self, node: &mut AstNode) -> DataType {
+        // Explicit type arguments were resolved to the instance with all other types
+        if let Some(NodeType::Type(DataType::Struct(instance))) =
+            node.children.first().map(|field| &field.node_type)
+        {
+            node.value = Some(instance.clone());
+            node.children.remove(0);
+        }
+
+        // Without them the type arguments are inferred from the field values
+        let name = node.value.clone().unwrap();
+        let mut value_types = None;
+        if let Some(generic) = self.generics.get(&name).cloned() {
+            let mut bindings = HashMap::new();
+            let mut types = vec![];
+            for field in &mut node.children {
+                let value_type = self.check_value(&mut field.children[1]);
+                let field_name = field.children[0].value.as_ref().unwrap();
+                if let Some(def) = generic.children[1]
+                    .children
+                    .iter()
+                    .find(|def| def.children[1].value.as_ref() == Some(field_name))
+                {
+                    self.infer_bindings(
+                        def.children[0].data_type(),
+                        &value_type,
+                        &mut bindings,
+                        &name,
+                    );
+                }
+                types.push(value_type);
+            }
+
+            let args = generic.children[0]
+                .children
+                .iter()
+                .map(|param| {
+                    let param = param.value.as_ref().unwrap();
+                    match bindings.get(param) {
+                        Some(data_type) => data_type.clone(),
+                        None => panic!("Cannot infer type parameter {} of {}", param, name),
+                    }
+                })
+                .collect();
+            node.value = Some(self.instantiate(&name, args));
+            value_types = Some(types);
+        }
+
+        let struct_type = DataType::Struct(node.value.clone().unwrap());
+
+        let mut seen = vec![];
+        for (i, field) in node.children.iter_mut().enumerate() {
+            let field_name = field.children[0].value.clone().unwrap();
+            if seen.contains(&field_name) {
+                panic!("Field {} is initialized twice", field_name);
+            }
+
+            let field_type = self.field_type(&struct_type, &field_name);
+            match &value_types {
+                Some(types) => expect_assignable(&field_type, &types[i]),
+                None => {
+                    self.check_expecting(&mut field.children[1], &field_type);
+                }
+            }
+            seen.push(field_name);
+        }
+
+        struct_type
+    }
+
+    // Every property of an object has the same type, it comes from the slot the object is
+    // assigned to or else from the first property
+    fn check_object_literal(
+        &mut self,
+        node: &mut AstNode,
+        expected: Option<&DataType>,
+    ) -> DataType {
+        let mut value_type = match expected {
+            Some(DataType::Object(value)) => Some(*value.clone()),
+            _ => None,
+        };
+
+        let mut seen = vec![];
+        for property in &mut node.children {
+            let key = property.children[0].value.clone().unwrap();
+            if seen.contains(&key) {
+                panic!("Property {} is initialized twice", key);
+            }
+            seen.push(key);
+
+            let value = &mut property.children[1];
+            match &value_type {
+                Some(data_type) => {
+                    self.check_expecting(value, data_type);
+                }
+                None => value_type = Some(self.check_value(value)),
+            }
+        }
+
+        match value_type {
+            Some(value) => DataType::Object(Box::new(value)),
+            None => panic!("Cannot infer the property type of an empty object"),
+        }
+    }
+
+    fn field_type(&self, object: &DataType, field: &str) -> DataType {
+        let name = match object {
+            DataType::Struct(name) => name,
+            _ => panic!(
+                "Cannot access field {} of non struct type {}",
+                field, object
+            ),
+        };
+
+        match self.structs[name]
+            .iter()
+            .find(|(field_name, _)| field_name == field)
+        {
+            Some((_, data_type)) => data_type.clone(),
+            None => panic!("Struct {} has no field {}", name, field),
+        }
+    }
+
+    fn check_enum_def(&mut self, node: &AstNode) {
+        self.define(node);
+
+        let name = node.value.as_ref().unwrap();
+        let variants = &self.enums[name];
+        for (i, variant) in variants.iter().enumerate() {
+            if variants[..i].contains(variant) {
+                panic!("Duplicate variant {} in enum {}", variant, name);
+            }
+        }
+    }
+
+    // Checks the arms of a match and returns the type of its value when used as an expression
+    fn check_match(&mut self, node: &mut AstNode) -> Option<DataType> {
+        let subject = self.check_value(&mut node.children[0]);
+        if !matches!(
+            subject,
+            DataType::Integer | DataType::Sized(_) | DataType::Boolean | DataType::Enum(_)
+        ) {
+            panic!("Cannot match on a value of type {}", subject);
+        }
+
+        let is_expression = node.node_type == NodeType::MatchExpression;
+        let mut covered = vec![];
+        let mut has_wildcard = false;
+        let mut result: Option<DataType> = None;
+
+        for arm in &mut node.children[1..] {
+            if has_wildcard {
+                panic!("Unreachable match arm after _");
+            }
+
+            let pattern = &mut arm.children[0];
+            if pattern.node_type == NodeType::Wildcard {
+                has_wildcard = true;
+            } else {
+                self.check_value(pattern);
+                if is_untyped_literal(pattern) && subject.integer_layout().is_some() {
+                    expect_fits(pattern, &subject);
+                    pattern.data_type = Some(subject.clone());
+                }
+                let pattern_type = pattern.data_type.clone().unwrap();
+                if pattern_type != subject {
+                    panic!(
+                        "Mismatched pattern type, expected {} got {}",
+                        subject, pattern_type
+                    );
+                }
+
+                let key = pattern.value.clone().unwrap();
+                if covered.contains(&key) {
+                    panic!("Unreachable match arm, {} is already covered", key);
+                }
+                covered.push(key);
+            }
+
+            let body = &mut arm.children[1];
+            if is_expression {
+                let arm_type = self.check_branch_value(body);
+                result = Some(match result {
+                    Some(previous) => unify(&previous, &arm_type),
+                    None => arm_type,
+                });
+            } else {
+                self.check_node(body);
+            }
+        }
+
+        if !has_wildcard {
+            let missing = match &subject {
+                DataType::Boolean => ["true", "false"]
+                    .iter()
+                    .filter(|value| !covered.contains(&value.to_string()))
+                    .map(|value| value.to_string())
+                    .collect(),
+                DataType::Enum(name) => self.enums[name]
+                    .iter()
+                    .filter(|variant| !covered.contains(variant))
+                    .map(|variant| format!("{}::{}", name, variant))
+                    .collect(),
+                _ => vec!["_".to_string()],
+            };
+
+            if !missing.is_empty() {
+                panic!(
+                    "Non-exhaustive match on {}, missing {}",
+                    subject,
+                    missing.join(", ")
+                );
+            }
+        }
+
+        result
+    }
+}
+
+// Part of the name of an instance that stands for a type argument
+fn mangle(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Pointer(data_type) => format!("{}_ptr", mangle(data_type)),
+        DataType::Object(data_type) => format!("object_{}", mangle(data_type)),
+        DataType::Function(params, return_type) => {
+            let params = params.iter().map(mangle).collect::<Vec<String>>();
+            format!("fn_{}_{}", params.join("_"), mangle(return_type))
+        }
+        data_type => data_type.to_string(),
+    }
+}
+
+fn is_scalar(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Integer | DataType::Sized(_) | DataType::Float | DataType::Boolean
+    )
+}
+
+fn is_integral(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Integer | DataType::Sized(_) | DataType::Boolean
+    )
+}
+
+fn is_untyped_literal(node: &AstNode) -> bool {
+    node.node_type == NodeType::Number && node.children.is_empty()
+}
+
+fn literal_fits(node: &AstNode, data_type: &DataType) -> bool {
+    let value = node.value.as_ref().unwrap().parse::<i128>().unwrap();
+    let (min, max) = data_type.integer_range().unwrap();
+    min <= value && value <= max
+}
+
+fn expect_fits(node: &AstNode, data_type: &DataType) {
+    if !literal_fits(node, data_type) {
+        panic!(
+            "Integer literal {} does not fit in {}",
+            node.value.as_ref().unwrap(),
+            data_type
+        );
+    }
+}
+
+// Arithmetic on literals without suffixes, like 2 * 3, which can be computed in any integer type
+fn is_literal_expression(node: &AstNode) -> bool {
+    match node.node_type {
+        NodeType::Number => node.children.is_empty(),
+        NodeType::LParen | NodeType::Operator(_) | NodeType::BitwiseOp(_) => {
+            node.children.iter().all(is_literal_expression)
+        }
+        _ => false,
+    }
+}
+
+// Whether the value of a literal expression fits, or each literal when it can not be computed
+fn literals_fit(node: &AstNode, data_type: &DataType) -> bool {
+    match folded_value(node) {
+        Some(value) => {
+            let (min, max) = data_type.integer_range().unwrap();
+            min <= value && value <= max
+        }
+        None => node
+            .children
+            .iter()
+            .all(|child| literals_fit(child, data_type)),
+    }
+}
+
+// Whether every part of a literal expression fits on its own, so computing it in the type
+// gives the same value
+fn parts_fit(node: &AstNode, data_type: &DataType) -> bool {
+    literals_fit(node, data_type)
+        && node
+            .children
+            .iter()
+            .all(|child| parts_fit(child, data_type))
+}
+
+fn folded_value(node: &AstNode) -> Option<i128> {
+    let folded = node.fold(&HashMap::new(), &DataType::Float)?;
+    folded.value.as_ref().unwrap().parse().ok()
+}
+
+// Floats hold every integer up to 2^24, larger ones only when the low bits are zero
+fn float_holds(literal: &AstNode) -> bool {
+    let value = literal.value.as_ref().unwrap().parse::<i128>().unwrap();
+    value as f32 as i128 == value
+}
+
+// The value is range checked rather than each literal, so 0 - 128 is an i8. A literal that
+// does not fit on its own is replaced with the value
+fn retype_literals(node: &mut AstNode, data_type: &DataType) {
+    if node.node_type != NodeType::Number && !parts_fit(node, data_type) {
+        if let Some(folded) = node.fold(&HashMap::new(), data_type) {
+            *node = folded;
+            return;
+        }
+    }
+
+    if node.node_type == NodeType::Number {
+        expect_fits(node, data_type);
+    }
+    for child in &mut node.children {
+        retype_literals(child, data_type);
+    }
+    node.data_type = Some(data_type.clone());
+}
+
+// Literals without a suffix take the integer type of the value they are used with, when they fit
+fn adapt_literal(node: &mut AstNode, data_type: &DataType) -> DataType {
+    if is_literal_expression(node)
+        && data_type.integer_layout().is_some()
+        && literals_fit(node, data_type)
+    {
+        retype_literals(node, data_type);
+    }
+
+    node.data_type.clone().unwrap()
+}
+
+// C can not compare structs with ==, function values are structs in C
+fn is_comparable(data_type: &DataType) -> bool {
+    !matches!(data_type, DataType::Struct(_) | DataType::Function(..))
+}
+
+fn declared_name(node: &AstNode) -> String {
+    let target = &node.children[1];
+    match target.node_type {
+        NodeType::Assign => target.children[0].value.clone().unwrap(),
+        _ => target.value.clone().unwrap(),
+    }
+}
+
+fn parameter_scope(params: &AstNode) -> HashMap<String, DataType> {
+    let mut scope = HashMap::new();
+    for param in &params.children {
+        let name = param.children[1].value.clone().unwrap();
+        if scope.contains_key(&name) {
+            panic!("Duplicate parameter {}", name);
+        }
+
+        scope.insert(name, param.children[0].data_type().clone());
+    }
+
+    scope
+}
+
+// The type pointed to, null and void pointers can not be dereferenced
+fn pointee(pointer: &DataType) -> DataType {
+    match pointer {
+        DataType::Pointer(data_type) if **data_type != DataType::Void => *data_type.clone(),
+        DataType::Pointer(_) => panic!("Cannot dereference a void pointer"),
+        _ => panic!("Cannot dereference non pointer type {}", pointer),
+    }
+}
+
+// Null converts to and compares with every pointer type
+fn pointers_compatible(a: &DataType, b: &DataType) -> bool {
+    let null = DataType::Pointer(Box::new(DataType::Void));
+    matches!((a, b), (DataType::Pointer(_), DataType::Pointer(_)))
+        && (a == b || *a == null || *b == null)
+}
+
+// Pointer arithmetic moves by whole elements, like in C
+fn operator_type(op: &Operator, left: &DataType, right: &DataType) -> DataType {
+    match (op, left, right) {
+        (Operator::Add | Operator::Subtract, DataType::Pointer(_), _) if is_integral(right) => {
+            pointee(left);
+            left.clone()
+        }
+        (Operator::Add, _, DataType::Pointer(_)) if is_integral(left) => {
+            pointee(right);
+            right.clone()
+        }
+        (Operator::Subtract, DataType::Pointer(_), DataType::Pointer(_)) if left == right => {
+            pointee(left);
+            DataType::Integer
+        }
+        _ => arithmetic_type(op, left, right),
+    }
+}
+
+fn arithmetic_type(op: &Operator, left: &DataType, right: &DataType) -> DataType {
+    if !is_scalar(left) || !is_scalar(right) {
+        panic!("Cannot apply {:?} to {} and {}", op, left, right);
+    }
+
+    if *op == Operator::Mod && !(is_integral(left) && is_integral(right)) {
+        panic!("Modulo requires integers, got {} and {}", left, right);
+    }
+
+    match common_type(left, right) {
+        Some(data_type) => data_type,
+        None => panic!(
+            "Cannot apply {:?} to {} and {}, use as to convert one of them",
+            op, left, right
+        ),
+    }
+}
+
+// The type both operands of an arithmetic operator or comparison are converted to, the one
+// the other widens into. Bools count as ints
+pub fn common_type(left: &DataType, right: &DataType) -> Option<DataType> {
+    if !is_scalar(left) || !is_scalar(right) {
+        return None;
+    }
+
+    if *left == DataType::Float || *right == DataType::Float {
+        return Some(DataType::Float);
+    }
+
+    let promote = |data_type: &DataType| match data_type {
+        DataType::Boolean => DataType::Integer,
+        data_type => data_type.clone(),
+    };
+    let (left, right) = (promote(left), promote(right));
+    if widens(&left, &right) {
+        Some(right)
+    } else if widens(&right, &left) {
+        Some(left)
+    } else {
+        None
+    }
+}
+
+// The common type of two branches that produce a value
+fn unify(a: &DataType, b: &DataType) -> DataType {
+    if a == b {
+        return a.clone();
+    }
+
+    if pointers_compatible(a, b) {
+        return if *a == DataType::Pointer(Box::new(DataType::Void)) {
+            b.clone()
+        } else {
+            a.clone()
+        };
+    }
+
+    match common_type(a, b) {
+        Some(data_type) => data_type,
+        None => panic!("Branches have incompatible types {} and {}", a, b),
+    }
+}
+
+// Scalars only widen implicitly, other conversions need as
+fn expect_assignable(target: &DataType, value: &DataType) {
+    if is_scalar(target) && is_scalar(value) && !widens(value, target) {
+        panic!(
+            "Cannot implicitly convert {} to {}, use as to convert explicitly",
+            value, target
+        );
+    }
+
+    if target != value
+        && !(is_scalar(target) && is_scalar(value))
+        && !pointers_compatible(target, value)
+    {
+        panic!("Mismatched types, expected {} got {}", target, value);
+    }
+}
+
+// Conversions that keep every value, to a wider integer of the same signedness, from unsigned
+// to a wider signed integer, from bools to anything and from integers that fit in the 24 bit
+// mantissa of a float to floats
+fn widens(from: &DataType, to: &DataType) -> bool {
+    if from == to {
+        return true;
+    }
+
+    match (from.integer_layout(), to.integer_layout()) {
+        (Some((from_bits, from_signed)), Some((to_bits, to_signed))) => {
+            from_bits < to_bits && (from_signed == to_signed || to_signed)
+        }
+        (Some((from_bits, _)), None) => *to == DataType::Float && from_bits <= 16,
+        (None, _) => *from == DataType::Boolean && is_scalar(to),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    fn check_script(script: &str) {
+        check(&mut parse(tokenize(script)));
+    }
+
+    #[test]
+    fn checker_allows_jumps_in_loops() {
+        check_script("'outer: while true { while true { break 'outer; continue; }; };");
+    }
+
+    #[test]
+    #[should_panic(expected = "'break' outside of a loop")]
+    fn checker_rejects_break_outside_loop() {
+        check_script("break;");
+    }
+
+    #[test]
+    #[should_panic(expected = "'continue' outside of a loop")]
+    fn checker_rejects_jump_out_of_function() {
+        check_script("while true { fn f() int { continue; return 1; }; };");
+    }
+
+    #[test]
+    #[should_panic(expected = "Use of undeclared label 'inner")]
+    fn checker_rejects_label_of_finished_loop() {
+        check_script("'inner: while true { }; while true { break 'inner; };");
+    }
+
+    #[test]
+    #[should_panic(expected = "Label 'outer is already used by an enclosing loop")]
+    fn checker_rejects_labels_of_enclosing_loops() {
+        check_script("'outer: while true { 'outer: while true { break 'outer; }; };");
+    }
+
+    #[test]
+    fn checker_allows_labels_of_sequential_loops() {
+        check_script(
+            "'outer: while true { break 'outer; }; 'outer: while true { continue 'outer; };",
+        );
+    }
+
+    #[test]
+    fn checker_allows_exhaustive_matches() {
+        check_script(
+            "enum Color { Red, Green };
+            Color c = Color::Red;
+            int a = match c { Color::Red => 1, Color::Green => 2 };
+            match a == 1 { true => print(1), false => print(2) };
+            match a { 1 => { print(1); } _ => print(2) };",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Non-exhaustive match on Color, missing Color::Blue")]
+    fn checker_rejects_missing_variant() {
+        check_script(
+            "enum Color { Red, Green, Blue };
+            Color c = Color::Red;
+            match c { Color::Red => print(1), Color::Green => print(2) };",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Non-exhaustive match on int, missing _")]
+    fn checker_rejects_int_match_without_wildcard() {
+        check_script("int a = match 1 { 1 => 1, 2 => 2 };");
+    }
+
+    #[test]
+    #[should_panic(expected = "Unreachable match arm, true is already covered")]
+    fn checker_rejects_duplicate_patterns() {
+        check_script("match true { true => print(1), true => print(2), _ => print(3) };");
+    }
+
+    #[test]
+    #[should_panic(expected = "Mismatched pattern type, expected int got bool")]
+    fn checker_rejects_mismatched_pattern() {
+        check_script("match 1 { true => print(1), _ => print(2) };");
+    }
+
+    #[test]
+    #[should_panic(expected = "Mismatched types, expected Point got int")]
+    fn checker_rejects_mismatched_declaration() {
+        check_script("struct Point { int x; }; Point p = 1;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Struct Point has no field z")]
+    fn checker_rejects_unknown_field() {
+        check_script("struct Point { int x; }; Point p; p.z = 1;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Function add expects 2 arguments, got 1")]
+    fn checker_rejects_wrong_argument_count() {
+        check_script("fn add(int a, int b) int { return a + b; }; add(1);");
+    }
+
+    #[test]
+    #[should_panic(expected = "Use of undeclared variable a")]
+    fn checker_rejects_caller_locals_in_function() {
+        check_script("fn g() int { int a = 1; return f(); }; fn f() int { return a; };");
+    }
+
+    #[test]
+    fn checker_allows_pointer_operations() {
+        check_script(
+            "struct Node { int value; Node* next; };
+            fn first(Node* node) int { return node->value; };
+            Node n = Node { value: 1, next: null };
+            Node* p = &n;
+            int* values = alloc(n.value + 1);
+            *(values + 1) = first(p);
+            if p != null && values < values + 1 { free(values); };",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot infer the element type of alloc")]
+    fn checker_rejects_untyped_alloc() {
+        check_script("print(alloc(1));");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot dereference non pointer type int")]
+    fn checker_rejects_dereference_of_non_pointer() {
+        check_script("int a = 1; print(*a);");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot dereference a void pointer")]
+    fn checker_rejects_null_dereference() {
+        check_script("print(*null);");
+    }
+
+    #[test]
+    #[should_panic(expected = "Mismatched types, expected float* got int*")]
+    fn checker_rejects_mismatched_pointers() {
+        check_script("int a = 1; float* p = &a;");
+    }
+
+    #[test]
+    fn checker_records_lambda_captures() {
+        let mut ast = parse(tokenize(
+            "int g = 0; fn run(int a, int b) int { fn(int) int f = |x| a + x + a + g; return f(1); };",
+        ));
+        check(&mut ast);
+
+        let statement = &ast.children[1].children[2].children[0];
+        let lambda = &statement.children[1].children[1];
+        assert_eq!(lambda.value, Some("lambda_0".to_string()));
+        assert_eq!(
+            *lambda.children[0].children[0].children[0].data_type(),
+            DataType::Integer
+        );
+        assert_eq!(*lambda.children[1].data_type(), DataType::Integer);
+
+        let captures = &lambda.children[3].children;
+        assert_eq!(captures.len(), 1);
+        assert_eq!(captures[0].children[1].value, Some("a".to_string()));
+    }
+
+    #[test]
+    fn checker_allows_functions_as_values() {
+        check_script(
+            "fn twice(fn(int) int f, int x) int { return f(f(x)); };
+            fn inc(int x) int { return x + 1; };
+            fn(int) int g = inc;
+            print(twice(g, 1), twice(|x| x * 2, 3), twice(inc, 0));",
+        );
+    }
+
+    #[test]
+    fn checker_types_lambdas_in_branches() {
+        let mut ast = parse(tokenize(
+            "enum Op { Add, Mul };
+            fn pick(bool c, Op op) fn(int, int) int {
+                fn(int, int) int f = if c { |a, b| a } else { match op { Op::Add => |a, b| a + b, Op::Mul => { int k = 1; |a, b| a * b * k } } };
+                return f;
+            };",
+        ));
+        check(&mut ast);
+
+        let if_ast = &ast.children[1].children[2].children[0].children[1].children[1];
+        assert_eq!(
+            if_ast.data_type,
+            Some(DataType::Function(
+                vec![DataType::Integer, DataType::Integer],
+                Box::new(DataType::Integer)
+            ))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot infer the parameter types of the lambda")]
+    fn checker_rejects_untyped_pipe_lambda() {
+        check_script("print(|x| x);");
+    }
+
+    #[test]
+    #[should_panic(expected = "Mismatched types, expected fn(int) int got fn(int) bool")]
+    fn checker_rejects_mismatched_lambda() {
+        check_script("fn(int) int f = fn (int x) bool { return true; };");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot call non function type int")]
+    fn checker_rejects_call_of_non_function() {
+        check_script("int f = 1; f(2);");
+    }
+
+    #[test]
+    fn checker_allows_globals_in_functions() {
+        let mut ast = parse(tokenize(
+            "fn bump() int { count += step; return count; };
+            const int step = (1 + 2) * 4 % 5;
+            const bool verbose = step > 1 && !false;
+            int count = step;",
+        ));
+        check(&mut ast);
+
+        let step = &ast.children[1].children[1].children[1];
+        assert_eq!(step.node_type, NodeType::Number);
+        assert_eq!(step.value, Some("2".to_string()));
+        let verbose = &ast.children[2].children[1].children[1];
+        assert_eq!(verbose.value, Some("true".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Use of undeclared variable count")]
+    fn checker_rejects_global_before_declaration() {
+        check_script("print(count); int count = 1;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot assign to captured variable c in a lambda")]
+    fn checker_rejects_assignment_to_captures() {
+        check_script(
+            "fn run() int { int c = 0; fn() int next = fn () int { c += 1; return c; }; return next(); };",
+        );
+    }
+
+    #[test]
+    fn checker_allows_assignment_to_globals_in_lambdas() {
+        check_script("int g = 0; fn() int inc = fn () int { g++; return g; }; inc();");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot assign to captured variable p in a lambda")]
+    fn checker_rejects_assignment_to_captured_fields() {
+        check_script(
+            "struct P { int x; }; fn run() int { P p = P { x: 1 }; fn() int f = fn () int { p.x = 5; return p.x; }; return f(); };",
+        );
+    }
+
+    #[test]
+    fn checker_allows_writes_through_captured_references() {
+        check_script(
+            "struct P { int x; };
+            fn run() int {
+                object<int> o = { a: 1 };
+                P* p = alloc(1);
+                fn() int f = fn () int { o[\"a\"] = 5; o.b = 6; p->x = 7; (*p).x = 8; return o.b; };
+                return f();
+            };",
+        );
+    }
+
+    #[test]
+    fn checker_allows_assignment_to_lambda_locals() {
+        check_script(
+            "int c = 0; fn() int next = fn () int { int d = c; d += 1; while true { d = 2; break; }; return d; };",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot assign to constant size")]
+    fn checker_rejects_assignment_to_constant() {
+        check_script("const int size = 1; fn grow() int { size += 1; return size; };");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot take the address of constant size")]
+    fn checker_rejects_address_of_constant() {
+        check_script("const int size = 1; int* p = &size;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot compute the value of constant size at compile time")]
+    fn checker_rejects_runtime_constant() {
+        check_script("int n = 2; const int size = n * 2;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Constants can only be declared at the top level")]
+    fn checker_rejects_local_constant() {
+        check_script("fn f() int { const int size = 1; return size; };");
+    }
+
+    #[test]
+    fn checker_types_sized_integers() {
+        let mut ast = parse(tokenize(
+            "u8 small = 200; int n = small + 1; u64 big = 3000000000 * 2; i64 wide = 5000000000;
+            u32 x = 7u32 & 0xF; i16 y = ~0;",
+        ));
+        check(&mut ast);
+
+        // Literals take the type of the other operand, the u8 sum then widens into int
+        let add = &ast.children[1].children[1].children[1];
+        assert_eq!(add.data_type, Some(DataType::Sized(IntType::U8)));
+        assert_eq!(
+            add.children[1].data_type,
+            Some(DataType::Sized(IntType::U8))
+        );
+        let product = &ast.children[2].children[1].children[1];
+        assert_eq!(product.data_type, Some(DataType::Sized(IntType::U64)));
+        let literal = &ast.children[3].children[1].children[1];
+        assert_eq!(literal.data_type, Some(DataType::Sized(IntType::I64)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Integer literal 300 does not fit in u8")]
+    fn checker_rejects_literal_out_of_range() {
+        check_script("u8 x = 300;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Integer literal 128 does not fit in i8")]
+    fn checker_rejects_suffixed_literal_out_of_range() {
+        check_script("int x = 128i8;");
+    }
+
+    #[test]
+    fn checker_range_checks_the_value_of_literal_expressions() {
+        let mut ast = parse(tokenize("i8 f = 0 - 128; i8 g = (100 + 100) / 2;"));
+        check(&mut ast);
+
+        let value = &ast.children[0].children[1].children[1];
+        assert_eq!(value.node_type, NodeType::Number);
+        assert_eq!(value.value, Some("-128".to_string()));
+        assert_eq!(value.data_type, Some(DataType::Sized(IntType::I8)));
+        let value = &ast.children[1].children[1].children[1];
+        assert_eq!(value.value, Some("100".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Constant value -1 does not fit in u8")]
+    fn checker_rejects_literal_expressions_out_of_range() {
+        check_script("u8 z = 0 - 1;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot apply Add to u32 and int, use as to convert one of them")]
+    fn checker_rejects_mixed_signedness() {
+        check_script("u32 a = 1; int b = 2; print(a + b);");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot implicitly convert i8 to u64, use as to convert explicitly")]
+    fn checker_rejects_signed_to_unsigned() {
+        check_script("i8 a = 1; u64 b = a;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Constant value 300 does not fit in u8")]
+    fn checker_rejects_constant_out_of_range() {
+        check_script("const u8 limit = 200u8 + 100u8;");
+    }
+
+    #[test]
+    fn checker_allows_widening_and_casts() {
+        let mut ast = parse(tokenize(
+            "i16 n = true; float f = n; int m = f as int; bool b = m as bool; f = f * n;",
+        ));
+        check(&mut ast);
+
+        let cast = &ast.children[2].children[1].children[1];
+        assert_eq!(cast.node_type, NodeType::Cast);
+        assert_eq!(cast.data_type, Some(DataType::Integer));
+        assert_eq!(cast.children[0].data_type, Some(DataType::Float));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Cannot implicitly convert float to int, use as to convert explicitly"
+    )]
+    fn checker_rejects_lossy_conversion() {
+        check_script("fn half(int x) float { return x as float / 2; }; int h = half(3);");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Cannot implicitly convert i64 to float, use as to convert explicitly"
+    )]
+    fn checker_rejects_wide_integers_to_float() {
+        check_script("i64 big = 16777217; float f = big;");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Cannot implicitly convert int to float, use as to convert explicitly"
+    )]
+    fn checker_rejects_literals_a_float_can_not_hold() {
+        check_script("float f = 16777216 + 1;");
+    }
+
+    #[test]
+    fn checker_allows_literals_a_float_holds() {
+        check_script("float f = 16777216; u16 small = 1; f = small; f = 4 * 4;");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Cannot implicitly convert int to bool, use as to convert explicitly"
+    )]
+    fn checker_rejects_int_to_bool() {
+        check_script("bool b = 1;");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Cannot implicitly convert float to int, use as to convert explicitly"
+    )]
+    fn checker_rejects_lossy_short_assign() {
+        check_script("int n = 1; float f = 2; n += f;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot cast string to int")]
+    fn checker_rejects_cast_of_string() {
+        check_script(r#"int n = "1" as int;"#);
+    }
+
+    #[test]
+    fn checker_resolves_builtins() {
+        let mut ast = parse(tokenize(
+            "fn abs(int x) int { return x; };
+            string s = concat(str(abs(1)), readLine());
+            int m = max(len(s), pow(2, 3));",
+        ));
+        check(&mut ast);
+
+        let concat = &ast.children[1].children[1].children[1];
+        assert_eq!(concat.node_type, NodeType::BuiltinCall);
+        assert_eq!(concat.children[0].node_type, NodeType::BuiltinCall);
+        // Functions of the program shadow builtins
+        assert_eq!(
+            concat.children[0].children[0].node_type,
+            NodeType::FunctionCall
+        );
+        assert_eq!(
+            ast.children[2].children[1].children[1].data_type,
+            Some(DataType::Integer)
+        );
+    }
+
+    #[test]
+    fn checker_picks_float_math_for_floats() {
+        let mut ast = parse(tokenize(
+            "float f = 1 as float / 2; float m = max(2, f); float r = sqrt(f); int s = sqrt(17);",
+        ));
+        check(&mut ast);
+
+        for (index, data_type) in [
+            (1, DataType::Float),
+            (2, DataType::Float),
+            (3, DataType::Integer),
+        ] {
+            assert_eq!(
+                ast.children[index].children[1].children[1].data_type,
+                Some(data_type)
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot implicitly convert int to float")]
+    fn checker_rejects_float_math_of_int_variables() {
+        check_script("float f = 1 as float / 2; int n = 3; float m = min(f, n);");
+    }
+
+    #[test]
+    #[should_panic(expected = "Mismatched types, expected string got int")]
+    fn checker_rejects_mismatched_builtin_argument() {
+        check_script("int n = len(1);");
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected a value, got BuiltinCall")]
+    fn checker_rejects_use_of_void_builtin() {
+        check_script("int n = assert(true);");
+    }
+
+    #[test]
+    fn checker_trusts_externs() {
+        let mut ast = parse(tokenize(
+            "print(atoi(\"12\") + 1); extern \"stdlib.h\" fn atoi(string s) int; extern fn srand(int seed); srand(1);",
+        ));
+        check(&mut ast);
+        assert_eq!(
+            ast.children[0].children[0].data_type,
+            Some(DataType::Integer)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected a value, got FunctionCall")]
+    fn checker_rejects_use_of_void_extern() {
+        check_script("extern fn srand(int seed); int a = srand(1);");
+    }
+
+    #[test]
+    #[should_panic(expected = "Extern function atoi can only be called")]
+    fn checker_rejects_extern_function_value() {
+        check_script("extern fn atoi(string s) int; fn(string) int f = atoi;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Extern functions can only be declared at the top level")]
+    fn checker_rejects_nested_extern() {
+        check_script("fn f() int { extern fn atoi(string s) int; return 0; };");
+    }
+
+    #[test]
+    fn checker_instantiates_generics() {
+        let mut ast = parse(tokenize(
+            "struct Pair<T> { T first; T second; };
+            fn max<T>(T a, T b) T { if a > b { return a; }; return b; };
+            fn larger<T>(Pair<T> pair) T { return max(pair.first, pair.second); };
+            print(max(1, 2), larger(Pair { first: true, second: false }));
+            Pair<int> p = Pair { first: 1, second: 2 };",
+        ));
+        check(&mut ast);
+
+        let names = ast
+            .children
+            .iter()
+            .map(|child| {
+                (
+                    child.node_type.clone(),
+                    child.value.clone().unwrap_or_default(),
+                )
+            })
+            .collect::<Vec<(NodeType, String)>>();
+        assert_eq!(
+            names,
+            vec![
+                (NodeType::StructDef, "Pair_int".to_string()),
+                (NodeType::StructDef, "Pair_bool".to_string()),
+                (NodeType::FunctionDef, "max_int".to_string()),
+                (NodeType::FunctionDef, "max_bool".to_string()),
+                (NodeType::FunctionDef, "larger_bool".to_string()),
+                (NodeType::BuiltinCall, "print".to_string()),
+                (NodeType::Declare, String::new()),
+            ]
+        );
+
+        let literal = &ast.children[6].children[1].children[1];
+        assert_eq!(literal.value, Some("Pair_int".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Type parameter T of max is both int and bool")]
+    fn checker_rejects_conflicting_type_arguments() {
+        check_script("fn max<T>(T a, T b) T { return a; }; print(max(1, true));");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot infer type parameter T of make")]
+    fn checker_rejects_uninferable_type_parameter() {
+        check_script("fn make<T>() T* { return null; }; print(make());");
+    }
+
+    #[test]
+    #[should_panic(expected = "Generic struct Pair needs type arguments")]
+    fn checker_rejects_generic_struct_without_arguments() {
+        check_script("struct Pair<T> { T first; }; Pair p;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many instances of generic nest")]
+    fn checker_rejects_unbounded_instantiation() {
+        check_script(
+            "fn nest<T>(T value, int n) int { if n == 0 { return 0; }; return nest(&value, n - 1); };
+            print(nest(1, 3));",
+        );
+    }
+
+    #[test]
+    fn checker_allows_objects() {
+        check_script(
+            r#"object<float> o = { a: 1, b: 2 };
+            object<int> counts = {};
+            counts["x"] = counts["x"] + 1;
+            o.a += o["b"];
+            fn size(object<int> o) int { return o.width * o.height; };
+            print(size({ width: 2, height: 3 }), o == o);"#,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot infer the property type of an empty object")]
+    fn checker_rejects_untyped_empty_object() {
+        check_script("print({});");
+    }
+
+    #[test]
+    #[should_panic(expected = "Mismatched types, expected int got string")]
+    fn checker_rejects_mismatched_property() {
+        check_script("object<int> o = { a: 1 }; o.b = \"one\";");
+    }
+
+    #[test]
+    #[should_panic(expected = "Mismatched types, expected string got int")]
+    fn checker_rejects_non_string_index() {
+        check_script("object<int> o = { a: 1 }; print(o[1]);");
+    }
+
+    #[test]
+    fn checker_allows_signals() {
+        check_script(
+            "fn square(int x) int { return x * x; };
+            int x #= 1;
+            int y $= square(#x) + 1;
+            fn bump() int { x #= #x + 1; return $y; };
+            x #= 2;",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Computed signal y can only depend on signals, not on variable a")]
+    fn checker_rejects_computed_signal_reading_variable() {
+        check_script("int a = 1; int x #= 1; int y $= #x + a;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Signal x can only be declared at the top level")]
+    fn checker_rejects_nested_signal() {
+        check_script("if true { int x #= 1; };");
+    }
+
+    #[test]
+    #[should_panic(expected = "Computed signal y can not be assigned")]
+    fn checker_rejects_computed_signal_assignment() {
+        check_script("int x #= 1; int y $= #x; y #= 2;");
+    }
+
+    #[test]
+    #[should_panic(expected = "y is a computed signal, read it with $y")]
+    fn checker_rejects_computed_signal_read_as_signal() {
+        check_script("int x #= 1; int y $= #x; print(#y);");
+    }
+}
@@ -1,42 +1,907 @@
 use crate::ast::*;
+use crate::builtins::Builtin;
+use crate::ir;
+use crate::optimize::PassManager;
+use crate::signals::SignalGraph;
 use crate::types::*;
+use std::collections::HashMap;
+
+// Hash map behind objects, open addressing keyed by the property names. Values are stored
+// inline, so the slot returned by get and set moves when the map grows
+const OBJECT_RUNTIME: &str = r#"
+typedef struct tan_object {
+	size_t value_size;
+	size_t count;
+	size_t capacity;
+	char** keys;
+	char* values;
+} tan_object;
+
+tan_object* tan_object_new(size_t value_size) {
+	tan_object* object = malloc(sizeof(tan_object));
+	object->value_size = value_size;
+	object->count = 0;
+	object->capacity = 8;
+	object->keys = calloc(object->capacity, sizeof(char*));
+	object->values = calloc(object->capacity, value_size);
+	return object;
+}
+
+size_t tan_object_slot(tan_object* object, const char* key) {
+	unsigned long long hash = 14695981039346656037ull;
+	for (const char* c = key; *c; c++) {
+		hash = (hash ^ (unsigned char)*c) * 1099511628211ull;
+	}
+
+	size_t slot = hash & (object->capacity - 1);
+	while (object->keys[slot] != NULL && strcmp(object->keys[slot], key) != 0) {
+		slot = (slot + 1) & (object->capacity - 1);
+	}
+	return slot;
+}
+
+void tan_object_grow(tan_object* object) {
+	tan_object old = *object;
+	object->capacity *= 2;
+	object->keys = calloc(object->capacity, sizeof(char*));
+	object->values = calloc(object->capacity, object->value_size);
+	for (size_t i = 0; i < old.capacity; i++) {
+		if (old.keys[i] != NULL) {
+			size_t slot = tan_object_slot(object, old.keys[i]);
+			object->keys[slot] = old.keys[i];
+			memcpy(object->values + slot * old.value_size, old.values + i * old.value_size, old.value_size);
+		}
+	}
+	free(old.keys);
+	free(old.values);
+}
+
+void* tan_object_get(tan_object* object, const char* key) {
+	if (object == NULL) {
+		fprintf(stderr, "Use of an unassigned object\n");
+		exit(1);
+	}
+
+	size_t slot = tan_object_slot(object, key);
+	if (object->keys[slot] == NULL) {
+		fprintf(stderr, "Object has no property %s\n", key);
+		exit(1);
+	}
+	return object->values + slot * object->value_size;
+}
+
+// Adds the property when it is missing, its value starts zeroed
+void* tan_object_set(tan_object* object, const char* key) {
+	if (object == NULL) {
+		fprintf(stderr, "Use of an unassigned object\n");
+		exit(1);
+	}
+
+	if ((object->count + 1) * 4 > object->capacity * 3) {
+		tan_object_grow(object);
+	}
+
+	size_t slot = tan_object_slot(object, key);
+	if (object->keys[slot] == NULL) {
+		object->keys[slot] = malloc(strlen(key) + 1);
+		strcpy(object->keys[slot], key);
+		object->count++;
+	}
+	return object->values + slot * object->value_size;
+}
+"#;
+
+const MATH_RUNTIME: &str = r#"
+int tan_min(int a, int b) {
+	return a < b ? a : b;
+}
+
+int tan_max(int a, int b) {
+	return a > b ? a : b;
+}
+
+int tan_pow(int base, int exponent) {
+	if (exponent < 0) {
+		fprintf(stderr, "Negative exponent %d\n", exponent);
+		exit(1);
+	}
+	unsigned int result = 1;
+	while (exponent-- > 0) {
+		result *= (unsigned int)base;
+	}
+	return (int)result;
+}
+
+int tan_sqrt(int x) {
+	if (x < 0) {
+		fprintf(stderr, "Square root of negative number %d\n", x);
+		exit(1);
+	}
+	long long root = 0;
+	while ((root + 1) * (root + 1) <= x) {
+		root++;
+	}
+	return (int)root;
+}
+"#;
+
+// Floats print like the interpreter prints them, with the fewest digits that read back as
+// the same float and without an exponent. The struct keeps the text alive until printf is done
+const FLOAT_PRINT_RUNTIME: &str = r#"
+typedef struct tan_float_text {
+	char text[64];
+} tan_float_text;
+
+tan_float_text tan_float_string(float value) {
+	tan_float_text result;
+	if (value != value) {
+		snprintf(result.text, sizeof(result.text), "NaN");
+		return result;
+	}
+	// Only infinities are not equal to themselves after subtracting
+	if (value - value != value - value) {
+		snprintf(result.text, sizeof(result.text), value < 0 ? "-inf" : "inf");
+		return result;
+	}
+
+	char scientific[32];
+	for (int precision = 0; precision < 9; precision++) {
+		snprintf(scientific, sizeof(scientific), "%.*e", precision, value);
+		if (strtof(scientific, NULL) == value) {
+			break;
+		}
+	}
+
+	char digits[16];
+	int count = 0;
+	char* c = scientific;
+	char* out = result.text;
+	if (*c == '-') {
+		*out++ = *c++;
+	}
+	for (; *c != 'e'; c++) {
+		if (*c != '.') {
+			digits[count++] = *c;
+		}
+	}
+	while (count > 1 && digits[count - 1] == '0') {
+		count--;
+	}
+
+	// Digits before the decimal point
+	int point = atoi(c + 1) + 1;
+	if (point <= 0) {
+		*out++ = '0';
+		*out++ = '.';
+		for (int i = 0; i < -point; i++) {
+			*out++ = '0';
+		}
+		for (int i = 0; i < count; i++) {
+			*out++ = digits[i];
+		}
+	} else {
+		for (int i = 0; i < point || i < count; i++) {
+			if (i == point) {
+				*out++ = '.';
+			}
+			*out++ = i < count ? digits[i] : '0';
+		}
+	}
+	*out = '\0';
+	return result;
+}
+"#;
+
+// Strings created at runtime are never freed, like objects
+const STRING_RUNTIME: &str = r#"
+const char* tan_concat(const char* a, const char* b) {
+	size_t length = strlen(a);
+	char* result = malloc(length + strlen(b) + 1);
+	memcpy(result, a, length);
+	strcpy(result + length, b);
+	return result;
+}
+
+const char* tan_substr(const char* s, int start, int length) {
+	int size = strlen(s);
+	if (start < 0 || length < 0 || start > size - length) {
+		fprintf(stderr, "Substring %d..%d out of range of a string of length %d\n", start, start + length, size);
+		exit(1);
+	}
+	char* result = malloc(length + 1);
+	memcpy(result, s + start, length);
+	result[length] = '\0';
+	return result;
+}
+
+const char* tan_str(int x) {
+	char* result = malloc(12);
+	snprintf(result, 12, "%d", x);
+	return result;
+}
+"#;
+
+const INPUT_RUNTIME: &str = r#"
+const char* tan_read_line(void) {
+	size_t capacity = 16;
+	size_t length = 0;
+	char* line = malloc(capacity);
+	int c;
+	while ((c = getchar()) != EOF && c != '\n') {
+		if (length + 1 == capacity) {
+			capacity *= 2;
+			line = realloc(line, capacity);
+		}
+		line[length++] = c;
+	}
+	if (length > 0 && line[length - 1] == '\r') {
+		length--;
+	}
+	line[length] = '\0';
+	return line;
+}
+
+int tan_read_int(void) {
+	const char* line = tan_read_line();
+	char* end;
+	long value = strtol(line, &end, 10);
+	while (*end == ' ' || *end == '\t') {
+		end++;
+	}
+	if (end == line || *end != '\0' || value != (int)value) {
+		fprintf(stderr, "Expected an integer, got \"%s\"\n", line);
+		exit(1);
+	}
+	return value;
+}
+"#;
+
+const ASSERT_RUNTIME: &str = r#"
+void tan_assert(bool condition) {
+	if (!condition) {
+		fprintf(stderr, "Assertion failed\n");
+		exit(1);
+	}
+}
+"#;
+
+// Xorshift, the interpreter uses the same generator so a seed gives the same numbers
+const RANDOM_RUNTIME: &str = r#"
+unsigned int tan_random_state = 0;
+
+void tan_seed(int seed) {
+	tan_random_state = seed == 0 ? 1 : (unsigned int)seed;
+}
+
+int tan_random(int bound) {
+	if (bound <= 0) {
+		fprintf(stderr, "Random bound must be positive, got %d\n", bound);
+		exit(1);
+	}
+	if (tan_random_state == 0) {
+		tan_seed(time(NULL));
+	}
+	unsigned int x = tan_random_state;
+	x ^= x << 13;
+	x ^= x >> 17;
+	x ^= x << 5;
+	tan_random_state = x;
+	return x % bound;
+}
+"#;
+
+// The math.h function a math builtin called with floats is compiled to
+fn float_math_name(builtin: Builtin) -> &'static str {
+    match builtin {
+        Builtin::Abs => "fabsf",
+        Builtin::Min => "fminf",
+        Builtin::Max => "fmaxf",
+        Builtin::Pow => "powf",
+        Builtin::Sqrt => "sqrtf",
+        _ => panic!("The builtin {} does not take floats", builtin.name()),
+    }
+}
+
+// Math builtins of the IR take floats when their arguments are floats
+fn is_float_math(function: &ir::Function, builtin: Builtin, args: &[ir::Operand]) -> bool {
+    builtin.float_signature().is_some()
+        && args
+            .iter()
+            .any(|arg| function.operand_type(arg) == DataType::Float)
+}
+
+// C functions a builtin is compiled to call
+fn builtin_runtime(builtin: Builtin) -> Option<&'static str> {
+    match builtin {
+        Builtin::Min | Builtin::Max | Builtin::Pow | Builtin::Sqrt => Some(MATH_RUNTIME),
+        Builtin::Concat | Builtin::Substr | Builtin::Str => Some(STRING_RUNTIME),
+        Builtin::ReadInt | Builtin::ReadLine => Some(INPUT_RUNTIME),
+        Builtin::Assert => Some(ASSERT_RUNTIME),
+        Builtin::Random | Builtin::Seed => Some(RANDOM_RUNTIME),
+        _ => None,
+    }
+}
 
 pub fn compile(ast: &AstNode) -> String {
-    // Find all function definitions and put them at the top
+    let mut ast = ast.clone();
+    number_labels(&mut ast, &mut vec![], &mut HashMap::new());
+    let ast = &ast;
+
+    // Find all type and function definitions and put them at the top
     // Then compile the rest of the code
+    let mut type_defs = vec![];
     let mut function_defs = vec![];
+    let mut extern_defs = vec![];
+    let mut global_defs = vec![];
     let mut rest = vec![];
 
     for child in &ast.children {
         match child.node_type {
+            NodeType::StructDef | NodeType::EnumDef => type_defs.push(child),
             NodeType::FunctionDef => function_defs.push(child),
+            NodeType::ExternDef => extern_defs.push(child),
+            NodeType::ConstDeclare => global_defs.push(child),
+            NodeType::Declare => {
+                global_defs.push(child);
+                rest.push(child);
+            }
             _ => rest.push(child),
         }
     }
 
+    // Forward declarations let structs point to themselves and to each other
+    let forward_decls = type_defs
+        .iter()
+        .filter(|node| node.node_type == NodeType::StructDef)
+        .map(|node| {
+            let name = node.value.clone().unwrap();
+            format!("typedef struct {} {};\n", name, name)
+        })
+        .collect::<String>();
+
+    let type_def_str = type_defs
+        .iter()
+        .map(|node| compile_node(node) + ";\n\n")
+        .collect::<String>();
+
+    let struct_defs = type_defs
+        .iter()
+        .filter(|node| node.node_type == NodeType::StructDef)
+        .copied()
+        .collect::<Vec<&AstNode>>();
+    let printed = printed_structs(ast, &struct_defs);
+    let enum_defs = type_defs
+        .iter()
+        .filter(|node| node.node_type == NodeType::EnumDef)
+        .copied()
+        .collect::<Vec<&AstNode>>();
+    let type_def_str = type_def_str
+        + &printed_enums(ast, &printed, &enum_defs)
+            .iter()
+            .map(|node| compile_enum_names(node) + ";\n\n")
+            .collect::<String>();
+    let print_prototype_str = printed
+        .iter()
+        .map(|node| compile_struct_print_signature(node.value.as_ref().unwrap()) + ";\n")
+        .collect::<String>();
+    let print_def_str = printed
+        .iter()
+        .map(|node| compile_struct_print_def(node) + "\n\n")
+        .collect::<String>();
+
     let function_def_str = function_defs
         .iter()
-        .map(|node| compile_node(&node))
-        .collect::<Vec<String>>()
-        .join("\n");
+        .map(|node| compile_line(node) + &compile_node(node) + "\n\n")
+        .collect::<String>();
+
+    // Lambdas and functions used as values are hoisted, with prototypes so that
+    // functions can create them and they can call functions
+    let mut lambdas = vec![];
+    let mut function_refs = vec![];
+    collect_function_values(ast, &mut lambdas, &mut function_refs);
+    let function_refs = function_refs
+        .iter()
+        .map(|name| {
+            match function_defs
+                .iter()
+                .find(|def| def.value.as_ref() == Some(name))
+            {
+                Some(def) => *def,
+                None => panic!("Use of undefined function {}", name),
+            }
+        })
+        .collect::<Vec<&AstNode>>();
+
+    let env_def_str = lambdas
+        .iter()
+        .filter(|lambda| !lambda.children[3].children.is_empty())
+        .map(|lambda| compile_lambda_env(lambda) + ";\n\n")
+        .collect::<String>();
+
+    // Every function gets a prototype, so functions can call each other in any order
+    // like they can in the interpreter
+    let prototype_str = extern_defs
+        .iter()
+        .filter(|def| def.children.len() == 2)
+        .map(|def| compile_extern_signature(def))
+        .chain(
+            function_defs
+                .iter()
+                .map(|def| compile_function_signature(def)),
+        )
+        .chain(
+            lambdas
+                .iter()
+                .map(|lambda| compile_lambda_signature(lambda)),
+        )
+        .chain(
+            function_refs
+                .iter()
+                .map(|def| compile_function_ref_signature(def)),
+        )
+        .map(|signature| signature + ";\n")
+        .collect::<String>();
+
+    let function_value_str = lambdas
+        .iter()
+        .map(|lambda| compile_line(lambda) + &compile_lambda_def(lambda))
+        .chain(
+            function_refs
+                .iter()
+                .map(|def| compile_function_ref_def(def)),
+        )
+        .map(|def| def + "\n\n")
+        .collect::<String>();
+
+    // Top level variables are globals so that functions can use them, main initializes them
+    let global_str = global_defs
+        .iter()
+        .map(|node| match node.node_type {
+            NodeType::ConstDeclare => compile_const_declare(node) + ";\n",
+            _ => format!(
+                "{} {};\n",
+                compile_data_type(&node.children[0].node_type),
+                declared_name(node)
+            ),
+        })
+        .collect::<String>();
+
+    // Signals are globals so that the update functions can reach them
+    let signal_defs = ast
+        .children
+        .iter()
+        .filter(|node| {
+            matches!(
+                node.node_type,
+                NodeType::SignalDeclare | NodeType::ComputedDeclare
+            )
+        })
+        .collect::<Vec<&AstNode>>();
+    let signal_graph = SignalGraph::from_ast(ast);
+    let signal_str = signal_defs
+        .iter()
+        .map(|node| {
+            format!(
+                "{} signal_{};\n",
+                compile_data_type(&node.children[0].node_type),
+                node.value.clone().unwrap()
+            )
+        })
+        .collect::<String>();
+    let updates = signal_defs
+        .iter()
+        .filter(|node| node.node_type == NodeType::SignalDeclare)
+        .collect::<Vec<&&AstNode>>();
+    let update_prototype_str = updates
+        .iter()
+        .map(|node| compile_signal_update_signature(node) + ";\n")
+        .collect::<String>();
+    let update_def_str = updates
+        .iter()
+        .map(|node| {
+            compile_line(node) + &compile_signal_update(node, &signal_defs, &signal_graph) + "\n\n"
+        })
+        .collect::<String>();
+
+    let mut builtins = vec![];
+    collect_builtins(ast, &mut builtins);
+
+    let mut header = "#include <stdio.h>\n#include <stdlib.h>\n#include <stdbool.h>\n".to_string();
+    if uses_float_math(ast) {
+        header += "#include <math.h>\n";
+    }
+    let uses_objects = uses_data_type(ast, |t| matches!(t, DataType::Object(_)));
+    if uses_objects || uses_data_type(ast, |t| *t == DataType::String) {
+        header += "#include <string.h>\n";
+    }
+    if uses_data_type(ast, is_sized_int) {
+        header += "#include <stdint.h>\n";
+    }
+    if builtins.contains(&Builtin::Random) || builtins.contains(&Builtin::Seed) {
+        header += "#include <time.h>\n";
+    }
+
+    // Extern functions naming a header are declared by it, the others get a prototype
+    for def in &extern_defs {
+        if let Some(extern_header) = def.children.get(2) {
+            let include = format!("#include <{}>\n", extern_header.value.clone().unwrap());
+            if !header.contains(&include) {
+                header += &include;
+            }
+        }
+    }
+    if uses_objects {
+        header += OBJECT_RUNTIME;
+    }
+    if uses_data_type(ast, |t| matches!(t, DataType::Function(..))) {
+        header +=
+            "\ntypedef struct tan_closure {\n\tvoid (*call)(void);\n\tvoid* env;\n} tan_closure;\n";
+    }
+
+    let mut cast_types = vec![];
+    collect_float_to_int(ast, &mut cast_types);
+    header += &cast_types.iter().map(cast_runtime).collect::<String>();
+    let prints_float_field = printed.iter().any(|def| {
+        def.children
+            .iter()
+            .any(|field| *field.children[0].data_type() == DataType::Float)
+    });
+    if prints_float_field || prints_data_type(ast, |t| *t == DataType::Float) {
+        header += FLOAT_PRINT_RUNTIME;
+    }
+
+    let mut runtimes = vec![];
+    for runtime in builtins.into_iter().filter_map(builtin_runtime) {
+        if !runtimes.contains(&runtime) {
+            runtimes.push(runtime);
+            header += runtime;
+        }
+    }
 
-    let header = "#include <stdio.h>\n#include <stdbool.h>\n";
-    let main = "int main() {\n";
     let body = rest
         .iter()
-        .map(|node| compile_node(&node))
-        .map(|s| "\t".to_string() + &s + ";")
+        .filter_map(|node| match node.node_type {
+            NodeType::Declare if node.children[1].node_type == NodeType::Assign => {
+                Some(compile_line(node) + &compile_assign(&node.children[1]) + ";")
+            }
+            NodeType::Declare => None,
+            _ => Some(compile_line(node) + &compile_statement(node)),
+        })
+        .chain(std::iter::once("return 0;".to_string()))
+        .collect::<Vec<String>>();
+    let main = format!("int main() {}", braces(&body));
+
+    // Keep a blank line between the globals and the functions
+    let signal_str = global_str + &signal_str;
+    let signal_str = match signal_str.is_empty() {
+        true => signal_str,
+        false => signal_str + "\n",
+    };
+
+    // And between the prototypes and the definitions, which are separated by blank lines
+    let prototype_str = prototype_str + &update_prototype_str + &print_prototype_str;
+    let prototype_str = match prototype_str.is_empty() {
+        true => prototype_str,
+        false => prototype_str + "\n",
+    };
+
+    format!(
+        "{}\n{}{}{}{}{}{}{}{}{}\n",
+        header,
+        forward_decls,
+        type_def_str,
+        signal_str,
+        env_def_str,
+        prototype_str,
+        function_def_str,
+        function_value_str,
+        update_def_str + &print_def_str,
+        main
+    )
+}
+
+fn compile_signal_update_signature(node: &AstNode) -> String {
+    format!(
+        "void update_{}({} value)",
+        node.value.clone().unwrap(),
+        compile_data_type(&node.children[0].node_type)
+    )
+}
+
+// Assigning a signal stores the value and recomputes the signals that depend on it
+pub fn compile_signal_update(
+    node: &AstNode,
+    signal_defs: &[&AstNode],
+    signal_graph: &SignalGraph,
+) -> String {
+    let name = node.value.clone().unwrap();
+    let statements = std::iter::once(format!("signal_{} = value;", name))
+        .chain(signal_graph.dependents(&name).iter().map(|dependent| {
+            let def = signal_defs
+                .iter()
+                .find(|def| def.value.as_ref() == Some(dependent))
+                .unwrap();
+            format!(
+                "{}signal_{} = {};",
+                compile_line(def),
+                dependent,
+                compile_expression(&def.children[1])
+            )
+        }))
+        .collect::<Vec<String>>();
+
+    format!(
+        "{} {}",
+        compile_signal_update_signature(node),
+        braces(&statements)
+    )
+}
+
+fn collect_function_values<'a>(
+    node: &'a AstNode,
+    lambdas: &mut Vec<&'a AstNode>,
+    function_refs: &mut Vec<String>,
+) {
+    match node.node_type {
+        NodeType::Lambda => lambdas.push(node),
+        NodeType::FunctionRef => {
+            let name = node.value.clone().unwrap();
+            if !function_refs.contains(&name) {
+                function_refs.push(name);
+            }
+        }
+        _ => {}
+    }
+
+    for child in &node.children {
+        collect_function_values(child, lambdas, function_refs);
+    }
+}
+
+// Builtins that need a runtime, math with floats uses math.h instead
+fn collect_builtins(node: &AstNode, builtins: &mut Vec<Builtin>) {
+    if node.node_type == NodeType::BuiltinCall && node.data_type != Some(DataType::Float) {
+        let builtin = Builtin::from_name(node.value.as_ref().unwrap()).unwrap();
+        if !builtins.contains(&builtin) {
+            builtins.push(builtin);
+        }
+    }
+
+    for child in &node.children {
+        collect_builtins(child, builtins);
+    }
+}
+
+// Whether a math builtin is called with floats, those are compiled to math.h functions
+fn uses_float_math(node: &AstNode) -> bool {
+    (node.node_type == NodeType::BuiltinCall && node.data_type == Some(DataType::Float))
+        || node.children.iter().any(uses_float_math)
+}
+
+// Integer types that floats are converted to, in the order of their first conversion
+fn collect_float_to_int(node: &AstNode, data_types: &mut Vec<DataType>) {
+    if is_float_to_int(node) {
+        let data_type = node.children[1].data_type();
+        if !data_types.contains(data_type) {
+            data_types.push(data_type.clone());
+        }
+    }
+    for child in &node.children {
+        collect_float_to_int(child, data_types);
+    }
+}
+
+fn is_float_to_int(node: &AstNode) -> bool {
+    node.node_type == NodeType::Cast
+        && node.children[0].data_type == Some(DataType::Float)
+        && node.children[1].data_type().integer_layout().is_some()
+}
+
+// Float to integer conversions saturate at the bounds of the type and turn NaN into 0, out
+// of range values are undefined in C. Each integer type converted to gets a function. The
+// bounds it compares with are powers of two, which floats hold exactly
+fn cast_runtime(data_type: &DataType) -> String {
+    let (min, max) = data_type.integer_range().unwrap();
+    let c_type = compile_pointer_type(data_type);
+    let integer = |value| compile_constant(&ir::Constant::Integer(value, data_type.clone()));
+    format!(
+        r#"
+{c_type} tan_float_to_{data_type}(float x) {{
+	if (x != x) {{
+		return 0;
+	}}
+	if (x >= {}.0f) {{
+		return {};
+	}}
+	if (x <= {}.0f) {{
+		return {};
+	}}
+	return ({c_type})x;
+}}
+"#,
+        max + 1,
+        integer(max),
+        min,
+        integer(min)
+    )
+}
+
+fn prints_data_type(node: &AstNode, matches: fn(&DataType) -> bool) -> bool {
+    let prints = node.node_type == NodeType::BuiltinCall
+        && node.value.as_deref() == Some("print")
+        && node
+            .children
+            .iter()
+            .any(|arg| arg.data_type.as_ref().is_some_and(matches));
+    prints
+        || node
+            .children
+            .iter()
+            .any(|child| prints_data_type(child, matches))
+}
+
+// Whether a value or declaration in the tree has a type that needs runtime support
+fn uses_data_type(node: &AstNode, matches: fn(&DataType) -> bool) -> bool {
+    node.data_type.as_ref().is_some_and(matches)
+        || matches!(&node.node_type, NodeType::Type(data_type) if matches(data_type))
+        || node
+            .children
+            .iter()
+            .any(|child| uses_data_type(child, matches))
+}
+
+fn is_sized_int(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Sized(_) => true,
+        DataType::Pointer(data_type) | DataType::Object(data_type) => is_sized_int(data_type),
+        DataType::Function(params, return_type) => {
+            params.iter().any(is_sized_int) || is_sized_int(return_type)
+        }
+        _ => false,
+    }
+}
+
+// Captured variables are copied into an environment when the lambda is created
+pub fn compile_lambda_env(node: &AstNode) -> String {
+    let name = node.value.clone().unwrap();
+    let fields = node.children[3]
+        .children
+        .iter()
+        .map(|capture| format!("\t{};\n", compile_parameter(capture)))
+        .collect::<String>();
+
+    format!("typedef struct {}_env {{\n{}}} {}_env", name, fields, name)
+}
+
+// Every function value takes its environment as the first argument
+fn compile_closure_signature(name: &str, params: &AstNode, return_type: &AstNode) -> String {
+    let params = std::iter::once("void* lambda_env".to_string())
+        .chain(params.children.iter().map(compile_parameter))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    format!(
+        "{} {}({})",
+        compile_data_type(&return_type.node_type),
+        name,
+        params
+    )
+}
+
+pub fn compile_lambda_signature(node: &AstNode) -> String {
+    compile_closure_signature(
+        node.value.as_ref().unwrap(),
+        &node.children[0],
+        &node.children[1],
+    )
+}
+
+pub fn compile_lambda_def(node: &AstNode) -> String {
+    let name = node.value.clone().unwrap();
+    let statements = node.children[3]
+        .children
+        .iter()
+        .map(|capture| {
+            format!(
+                "{} = (({}_env*)lambda_env)->{};",
+                compile_parameter(capture),
+                name,
+                capture.children[1].value.clone().unwrap()
+            )
+        })
+        .chain(compile_statements(&node.children[2]))
+        .collect::<Vec<String>>();
+
+    format!("{} {}", compile_lambda_signature(node), braces(&statements))
+}
+
+// Named functions used as values get a wrapper that ignores the environment
+pub fn compile_function_ref_signature(node: &AstNode) -> String {
+    compile_closure_signature(
+        &format!("{}_ref", node.value.clone().unwrap()),
+        &node.children[0],
+        &node.children[1],
+    )
+}
+
+pub fn compile_function_ref_def(node: &AstNode) -> String {
+    let args = node.children[0]
+        .children
+        .iter()
+        .map(|param| param.children[1].value.clone().unwrap())
         .collect::<Vec<String>>()
-        .join("\n");
+        .join(", ");
+
+    format!(
+        "{} {}",
+        compile_function_ref_signature(node),
+        braces(&[format!("return {}({});", node.value.clone().unwrap(), args)])
+    )
+}
 
-    let footer = "\n\treturn 0;\n}";
+pub fn compile_lambda(node: &AstNode) -> String {
+    let name = node.value.clone().unwrap();
+    let captures = &node.children[3].children;
+    if captures.is_empty() {
+        return format!("(tan_closure){{ (void (*)(void)){}, NULL }}", name);
+    }
+
+    let fields = captures
+        .iter()
+        .map(|capture| {
+            let capture = capture.children[1].value.clone().unwrap();
+            format!(".{} = {}", capture, capture)
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
 
     format!(
-        "{}\n{}\n\n{}{}{}",
-        header, function_def_str, main, body, footer
+        "({{ {}_env* lambda_env = malloc(sizeof({}_env)); *lambda_env = ({}_env){{ {} }}; (tan_closure){{ (void (*)(void)){}, lambda_env }}; }})",
+        name, name, name, fields, name
     )
 }
 
+// Calls through a function value cast its pointer back to the real signature
+pub fn compile_call(node: &AstNode) -> String {
+    let callee = &node.children[0];
+    let (params, return_type) = match callee.data_type {
+        Some(DataType::Function(ref params, ref return_type)) => (params, return_type),
+        _ => panic!("Unexpected call of a non function value"),
+    };
+
+    let signature = std::iter::once("void*".to_string())
+        .chain(params.iter().map(compile_pointer_type))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    let call = |closure: &str| {
+        compile_args(&node.children[1..], |args| {
+            let args = std::iter::once(format!("{}.env", closure))
+                .chain(args.iter().cloned())
+                .collect::<Vec<String>>()
+                .join(", ");
+
+            format!(
+                "(({} (*)({})){}.call)({})",
+                compile_pointer_type(return_type),
+                signature,
+                closure,
+                args
+            )
+        })
+    };
+
+    // The callee is only evaluated once
+    match callee.node_type {
+        NodeType::Identifier => call(callee.value.as_ref().unwrap()),
+        _ => format!(
+            "({{ tan_closure closure_value = {}; {}; }})",
+            compile_expression(callee),
+            call("closure_value")
+        ),
+    }
+}
+
 pub fn compile_node(node: &AstNode) -> String {
     match node.node_type {
         NodeType::Operator(_)
@@ -48,25 +913,250 @@ pub fn compile_node(node: &AstNode) -> String {
         | NodeType::Geq
         | NodeType::And
         | NodeType::Or
-        | NodeType::BitwiseOp(_) => compile_expression(node),
+        | NodeType::BitwiseOp(_)
+        | NodeType::Cast => compile_expression(node),
         NodeType::Not => format!("!{}", compile_expression(&node.children[0])),
-        NodeType::Number | NodeType::Identifier | NodeType::Boolean => node.value.clone().unwrap(),
+        NodeType::Number => compile_number(node),
+        NodeType::Identifier | NodeType::Boolean => node.value.clone().unwrap(),
+        NodeType::String => compile_string(node.value.as_ref().unwrap()),
         NodeType::Block => compile_block(node),
         NodeType::Declare => compile_declare(node),
         NodeType::Assign => compile_assign(node),
         NodeType::ShortAssign(_) => compile_short_assign(node),
         NodeType::LParen => compile_parentheses(&node.children[0]),
         NodeType::FunctionDef => compile_function_def(node),
-        NodeType::FunctionCall => compile_function_call(node),
+        NodeType::FunctionCall | NodeType::BuiltinCall => compile_function_call(node),
+        NodeType::ExternDef => compile_extern_signature(node),
+        NodeType::ConstDeclare => compile_const_declare(node),
         NodeType::Return => format!("return {}", compile_node(&node.children[0])),
         NodeType::If => compile_if(node),
+        NodeType::IfExpression => compile_if_expression(node),
         NodeType::While => compile_while(node),
+        NodeType::Break => compile_loop_jump(node, "break"),
+        NodeType::Continue => compile_loop_jump(node, "continue"),
+        NodeType::StructDef => compile_struct_def(node),
+        NodeType::StructLiteral => compile_struct_literal(node),
+        NodeType::FieldAccess => compile_field_access(node, "."),
+        NodeType::PointerFieldAccess => compile_field_access(node, "->"),
+        NodeType::ObjectLiteral => compile_object_literal(node),
+        NodeType::Property | NodeType::Index => format!(
+            "(*({}*)tan_object_get({}, {}))",
+            compile_pointer_type(node.data_type.as_ref().unwrap()),
+            compile_expression(&node.children[0]),
+            compile_property_key(node)
+        ),
+        NodeType::Lambda => compile_lambda(node),
+        NodeType::Call => compile_call(node),
+        NodeType::FunctionRef => format!(
+            "(tan_closure){{ (void (*)(void)){}_ref, NULL }}",
+            node.value.clone().unwrap()
+        ),
+        NodeType::Captures => panic!("Unexpected Captures node"),
+        NodeType::Signal | NodeType::Computed => format!("signal_{}", node.value.clone().unwrap()),
+        NodeType::SignalDeclare | NodeType::ComputedDeclare => format!(
+            "signal_{} = {}",
+            node.value.clone().unwrap(),
+            compile_expression(&node.children[1])
+        ),
+        NodeType::SignalAssign => format!(
+            "update_{}({})",
+            node.value.clone().unwrap(),
+            compile_expression(&node.children[0])
+        ),
+        NodeType::AddressOf => format!("&{}", compile_expression(&node.children[0])),
+        NodeType::Deref => format!("*{}", compile_expression(&node.children[0])),
+        NodeType::Null => "NULL".to_string(),
+        NodeType::New => format!(
+            "malloc(sizeof({}))",
+            compile_data_type(&node.children[0].node_type)
+        ),
+        NodeType::EnumDef => compile_enum_def(node),
+        NodeType::EnumVariant => compile_enum_variant(node),
+        NodeType::Match => compile_match(node),
+        NodeType::MatchExpression => compile_match_expression(node),
         NodeType::Parameters => panic!("Unexpected Parameters node"),
         NodeType::Type(_) => panic!("Unexpected Type node"),
         NodeType::Parameter => panic!("Unexpected Parameter node"),
+        NodeType::Field => panic!("Unexpected Field node"),
+        NodeType::MatchArm => panic!("Unexpected MatchArm node"),
+        NodeType::Wildcard => panic!("Unexpected Wildcard node"),
+        // The checker replaces generic definitions with their instances
+        NodeType::GenericDef => panic!("Unexpected generic definition"),
+        NodeType::TypeParameters => panic!("Unexpected TypeParameters node"),
+        NodeType::Import | NodeType::Pub => panic!("Unexpected {:?} node", node.node_type),
+    }
+}
+
+pub fn compile_enum_def(node: &AstNode) -> String {
+    let name = node.value.clone().unwrap();
+    let variants = node
+        .children
+        .iter()
+        .map(|variant| format!("\t{}_{},\n", name, variant.value.clone().unwrap()))
+        .collect::<String>();
+
+    format!("typedef enum {} {{\n{}}} {}", name, variants, name)
+}
+
+// C enum constants share one namespace, so they are prefixed with their enum
+pub fn compile_enum_variant(node: &AstNode) -> String {
+    format!(
+        "{}_{}",
+        node.children[0].value.clone().unwrap(),
+        node.value.clone().unwrap()
+    )
+}
+
+// Matches are lowered to if chains instead of a switch so that a break inside an
+// arm still targets the enclosing loop. The checker guarantees exhaustiveness,
+// so the last arm always becomes the final else
+pub fn compile_match(node: &AstNode) -> String {
+    let (subject, setup) = compile_match_subject(&node.children[0]);
+    let (last, arms) = node.children[1..].split_last().unwrap();
+
+    let mut chain = arms
+        .iter()
+        .map(|arm| {
+            format!(
+                "if ({}) {}",
+                compile_pattern_test(&subject, &arm.children[0]),
+                compile_arm_body(&arm.children[1])
+            )
+        })
+        .collect::<Vec<String>>();
+    chain.push(compile_arm_body(&last.children[1]));
+
+    let chain = chain.join(" else ");
+    match setup {
+        Some(setup) => braces(&[setup + ";", chain]),
+        None => chain,
+    }
+}
+
+pub fn compile_match_expression(node: &AstNode) -> String {
+    let (subject, setup) = compile_match_subject(&node.children[0]);
+    let (last, arms) = node.children[1..].split_last().unwrap();
+
+    let chain =
+        arms.iter()
+            .rev()
+            .fold(compile_branch_value(&last.children[1]), |otherwise, arm| {
+                format!(
+                    "({} ? {} : {})",
+                    compile_pattern_test(&subject, &arm.children[0]),
+                    compile_branch_value(&arm.children[1]),
+                    otherwise
+                )
+            });
+
+    match setup {
+        Some(setup) => format!("({{ {}; {}; }})", setup, chain),
+        None => chain,
+    }
+}
+
+// Anything but a plain variable is evaluated once into a temporary. Generated
+// names contain an underscore, which TanScript identifiers can not
+fn compile_match_subject(node: &AstNode) -> (String, Option<String>) {
+    if node.node_type == NodeType::Identifier {
+        return (node.value.clone().unwrap(), None);
+    }
+
+    let expression = compile_expression(node);
+    let setup = format!("__typeof__({}) match_value = {}", expression, expression);
+    ("match_value".to_string(), Some(setup))
+}
+
+fn compile_pattern_test(subject: &str, pattern: &AstNode) -> String {
+    format!("{} == {}", subject, compile_node(pattern))
+}
+
+fn compile_arm_body(node: &AstNode) -> String {
+    match node.node_type {
+        NodeType::Block => compile_block(node),
+        _ => braces(&[compile_statement(node)]),
+    }
+}
+
+pub fn compile_struct_def(node: &AstNode) -> String {
+    let name = node.value.clone().unwrap();
+    let fields = node
+        .children
+        .iter()
+        .map(|field| format!("\t{};\n", compile_parameter(field)))
+        .collect::<String>();
+
+    format!("typedef struct {} {{\n{}}} {}", name, fields, name)
+}
+
+pub fn compile_struct_literal(node: &AstNode) -> String {
+    let fields = node
+        .children
+        .iter()
+        .map(|field| {
+            format!(
+                ".{} = {}",
+                field.children[0].value.clone().unwrap(),
+                compile_expression(&field.children[1])
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    format!("({}){{ {} }}", node.value.clone().unwrap(), fields)
+}
+
+pub fn compile_string(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t");
+    format!("\"{}\"", escaped)
+}
+
+// Properties are set one by one on a new map, the map is the value of the literal
+pub fn compile_object_literal(node: &AstNode) -> String {
+    let value_type = match node.data_type {
+        Some(DataType::Object(ref value)) => compile_pointer_type(value),
+        _ => panic!("Unexpected object literal without a type"),
+    };
+
+    let properties = node
+        .children
+        .iter()
+        .map(|property| {
+            format!(
+                "*({}*)tan_object_set(object_value, {}) = {}; ",
+                value_type,
+                compile_string(property.children[0].value.as_ref().unwrap()),
+                compile_expression(&property.children[1])
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        "({{ tan_object* object_value = tan_object_new(sizeof({})); {}object_value; }})",
+        value_type, properties
+    )
+}
+
+fn compile_property_key(node: &AstNode) -> String {
+    match node.node_type {
+        NodeType::Index => compile_expression(&node.children[1]),
+        _ => compile_string(node.value.as_ref().unwrap()),
     }
 }
 
+pub fn compile_field_access(node: &AstNode, accessor: &str) -> String {
+    format!(
+        "{}{}{}",
+        compile_expression(&node.children[0]),
+        accessor,
+        node.value.clone().unwrap()
+    )
+}
+
 pub fn compile_short_assign(node: &AstNode) -> String {
     let ident = &node.children[0];
     let expression = &node.children[1];
@@ -82,7 +1172,7 @@ pub fn compile_short_assign(node: &AstNode) -> String {
 
     format!(
         "{} {}= {}",
-        ident.value.clone().unwrap(),
+        compile_expression(ident),
         op,
         compile_expression(expression)
     )
@@ -92,11 +1182,80 @@ pub fn compile_while(node: &AstNode) -> String {
     let cond_ast = &node.children[0];
     let body_ast = &node.children[1];
 
-    format!(
-        "while ({}) {{\n{}\n}}",
-        compile_expression(cond_ast),
-        compile_block(body_ast)
-    )
+    let mut body = compile_statements(body_ast);
+    let mut after = String::new();
+
+    // C has no labelled loops, so labelled jumps become gotos. The labels are
+    // only emitted when used to avoid unused label warnings, and label an empty
+    // statement since a label can not end a block
+    if let Some(label) = &node.value {
+        if targets_label(body_ast, &NodeType::Continue, label) {
+            body.push(format!("{}_continue:;", label));
+        }
+
+        if targets_label(body_ast, &NodeType::Break, label) {
+            after = format!("\n{}_break:;", label);
+        }
+    }
+
+    format!(
+        "while ({}) {}{}",
+        compile_expression(cond_ast),
+        braces(&body),
+        after
+    )
+}
+
+pub fn compile_loop_jump(node: &AstNode, keyword: &str) -> String {
+    match &node.value {
+        Some(label) => format!("goto {}_{}", label, keyword),
+        None => keyword.to_string(),
+    }
+}
+
+// C labels are function scoped while loop labels are not, so sequential loops can share
+// a label. Each labelled loop gets the number of loops with its label before it, and the
+// jumps to it are renamed with it
+fn number_labels(
+    node: &mut AstNode,
+    loops: &mut Vec<(String, String)>,
+    counts: &mut HashMap<String, usize>,
+) {
+    match (&node.node_type, node.value.clone()) {
+        (NodeType::While, Some(label)) => {
+            let count = counts.entry(label.clone()).or_insert(0);
+            let numbered = format!("{}_{}", label, count);
+            *count += 1;
+            node.value = Some(numbered.clone());
+
+            loops.push((label, numbered));
+            for child in &mut node.children {
+                number_labels(child, loops, counts);
+            }
+            loops.pop();
+            return;
+        }
+        (NodeType::Break | NodeType::Continue, Some(label)) => {
+            if let Some((_, numbered)) = loops.iter().rev().find(|(name, _)| *name == label) {
+                node.value = Some(numbered.clone());
+            }
+        }
+        _ => {}
+    }
+
+    for child in &mut node.children {
+        number_labels(child, loops, counts);
+    }
+}
+
+fn targets_label(node: &AstNode, jump: &NodeType, label: &str) -> bool {
+    if node.node_type == *jump && node.value.as_deref() == Some(label) {
+        return true;
+    }
+
+    node.children
+        .iter()
+        .any(|child| targets_label(child, jump, label))
 }
 
 pub fn compile_if(node: &AstNode) -> String {
@@ -105,7 +1264,7 @@ pub fn compile_if(node: &AstNode) -> String {
 
     if node.children.len() == 2 {
         return format!(
-            "if ({}) {{\n{}\n}}",
+            "if ({}) {}",
             compile_expression(cond_ast),
             compile_block(body_ast)
         );
@@ -115,43 +1274,131 @@ pub fn compile_if(node: &AstNode) -> String {
     let body = compile_block(body_ast);
     let else_ast = &node.children[2];
 
+    if else_ast.node_type == NodeType::If {
+        return format!("if ({}) {} else {}", cond, body, compile_if(else_ast));
+    }
+
+    format!("if ({}) {} else {}", cond, body, compile_block(else_ast))
+}
+
+pub fn compile_if_expression(node: &AstNode) -> String {
     format!(
-        "if ({}) {{\n{}\n}} else {{\n{}\n}}",
-        cond,
-        body,
-        compile_block(else_ast)
+        "({} ? {} : {})",
+        compile_expression(&node.children[0]),
+        compile_branch_value(&node.children[1]),
+        compile_branch_value(&node.children[2])
     )
 }
 
+// Branches that run statements before their value use a statement expression,
+// a GCC extension that clang supports as well
+fn compile_branch_value(node: &AstNode) -> String {
+    if node.node_type != NodeType::Block {
+        return compile_expression(node);
+    }
+
+    let (value, statements) = node.children.split_last().unwrap();
+    if statements.is_empty() {
+        return compile_expression(value);
+    }
+
+    let body = statements
+        .iter()
+        .map(compile_statement)
+        .map(|s| s + " ")
+        .collect::<String>();
+
+    format!("({{ {}{}; }})", body, compile_expression(value))
+}
+
 pub fn compile_block(node: &AstNode) -> String {
+    braces(&compile_statements(node))
+}
+
+fn compile_statements(node: &AstNode) -> Vec<String> {
     node.children
         .iter()
-        .map(compile_node)
-        .map(|s| "\t".to_string() + &s + ";")
-        .collect::<Vec<String>>()
-        .join("\n")
+        .map(|statement| compile_line(statement) + &compile_statement(statement))
+        .collect()
 }
 
-pub fn compile_function_def(node: &AstNode) -> String {
-    let name = &node.value.clone().unwrap();
-    let params = &node.children[0];
-    let explicit_type = &node.children[1];
-    let body = &node.children[2];
-
-    let param_str = compile_parameters(params);
-    let return_type = match explicit_type.node_type {
-        NodeType::Type(DataType::Integer) => "int",
-        NodeType::Type(DataType::Float) => "float",
-        NodeType::Type(DataType::Boolean) => "bool",
-        _ => panic!("Unexpected type"),
+// Line directives make C compiler errors and debuggers point at the TanScript source
+pub fn compile_line(node: &AstNode) -> String {
+    match &node.span {
+        Some(Span {
+            file: Some(file),
+            line,
+        }) => format!("#line {} {}\n", line, compile_string(file)),
+        _ => String::new(),
+    }
+}
+
+// Compound statements end with their closing brace, anything else needs a semicolon
+pub fn compile_statement(node: &AstNode) -> String {
+    match node.node_type {
+        NodeType::If | NodeType::While | NodeType::Match | NodeType::Block => compile_node(node),
+        _ => compile_node(node) + ";",
+    }
+}
+
+// Nested statements are indented one level further than the braces around them
+fn indent(code: &str) -> String {
+    code.lines()
+        .map(|line| match line.is_empty() {
+            true => "\n".to_string(),
+            false => format!("\t{}\n", line),
+        })
+        .collect()
+}
+
+fn braces(statements: &[String]) -> String {
+    match statements.is_empty() {
+        true => "{}".to_string(),
+        false => format!("{{\n{}}}", indent(&statements.join("\n"))),
+    }
+}
+
+pub fn compile_function_signature(node: &AstNode) -> String {
+    format!(
+        "{} {}({})",
+        compile_data_type(&node.children[1].node_type),
+        node.value.clone().unwrap(),
+        compile_parameters(&node.children[0])
+    )
+}
+
+// TanScript floats cross the extern boundary as doubles, the type the C math library uses
+pub fn compile_extern_signature(node: &AstNode) -> String {
+    let compile_type = |node: &AstNode| match node.node_type {
+        NodeType::Type(DataType::Float) => "double".to_string(),
+        ref node_type => compile_data_type(node_type),
     };
+    let params = node.children[0]
+        .children
+        .iter()
+        .map(|param| {
+            format!(
+                "{} {}",
+                compile_type(&param.children[0]),
+                param.children[1].value.clone().unwrap()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
 
     format!(
-        "{} {}({}) {{\n{}\n}}",
-        return_type,
-        name,
-        param_str,
-        compile_block(body)
+        "{} {}({})",
+        compile_type(&node.children[1]),
+        node.value.clone().unwrap(),
+        params
+    )
+}
+
+pub fn compile_function_def(node: &AstNode) -> String {
+    format!(
+        "{} {}",
+        compile_function_signature(node),
+        compile_block(&node.children[2])
     )
 }
 
@@ -166,26 +1413,76 @@ pub fn compile_parameters(node: &AstNode) -> String {
 pub fn compile_parameter(node: &AstNode) -> String {
     format!(
         "{} {}",
-        node.children[0].value.clone().unwrap(),
+        compile_data_type(&node.children[0].node_type),
         node.children[1].value.clone().unwrap()
     )
 }
 
 pub fn compile_function_call(node: &AstNode) -> String {
-    // Check if the function is a built-in function
-    if node.value.clone().unwrap().as_str() == "print" {
-        return compile_print(node);
+    let name = node.value.clone().unwrap();
+    let name = match node.node_type {
+        NodeType::BuiltinCall if node.data_type == Some(DataType::Float) => {
+            float_math_name(Builtin::from_name(&name).unwrap()).to_string()
+        }
+        NodeType::BuiltinCall => match Builtin::from_name(&name).unwrap() {
+            Builtin::Print => return compile_print(node),
+            Builtin::Alloc => return compile_alloc(node),
+            Builtin::Len => return format!("(int)strlen({})", compile_node(&node.children[0])),
+            Builtin::Free | Builtin::Abs | Builtin::Exit => name,
+            Builtin::ReadInt => "tan_read_int".to_string(),
+            Builtin::ReadLine => "tan_read_line".to_string(),
+            _ => format!("tan_{}", name),
+        },
+        _ => name,
+    };
+
+    compile_args(&node.children, |args| {
+        format!("{}({})", name, args.join(", "))
+    })
+}
+
+// C evaluates arguments in any order, when a call could change what another argument reads
+// they are all computed into temporaries from left to right before the call
+fn compile_args(args: &[AstNode], call: impl Fn(&[String]) -> String) -> String {
+    let reading = args.iter().filter(|arg| reads_state(arg)).count();
+    if reading < 2 || !args.iter().any(has_call) {
+        return call(&args.iter().map(compile_node).collect::<Vec<String>>());
     }
 
-    format!(
-        "{}({})",
-        node.value.clone().unwrap(),
-        node.children
-            .iter()
-            .map(compile_node)
-            .collect::<Vec<String>>()
-            .join(", ")
-    )
+    let bindings = args
+        .iter()
+        .enumerate()
+        .map(|(i, arg)| format!("__auto_type arg_{} = {}; ", i, compile_node(arg)))
+        .collect::<String>();
+    let names = (0..args.len())
+        .map(|i| format!("arg_{}", i))
+        .collect::<Vec<String>>();
+    format!("({{ {}{}; }})", bindings, call(&names))
+}
+
+// Calls that can have side effects, lambda bodies run when the lambda is called, not where
+// it is created
+fn has_call(node: &AstNode) -> bool {
+    match node.node_type {
+        NodeType::FunctionCall | NodeType::Call => true,
+        NodeType::BuiltinCall
+            if !Builtin::from_name(node.value.as_ref().unwrap())
+                .unwrap()
+                .is_pure() =>
+        {
+            true
+        }
+        NodeType::Lambda => false,
+        _ => node.children.iter().any(has_call),
+    }
+}
+
+// Literals give the same value whenever they are computed
+fn reads_state(node: &AstNode) -> bool {
+    match node.node_type {
+        NodeType::Identifier | NodeType::Lambda => true,
+        _ => has_call(node) || node.children.iter().any(reads_state),
+    }
 }
 
 pub fn compile_parentheses(node: &AstNode) -> String {
@@ -193,39 +1490,112 @@ pub fn compile_parentheses(node: &AstNode) -> String {
 }
 
 pub fn compile_declare(node: &AstNode) -> String {
+    let data_type = compile_data_type(&node.children[0].node_type);
     let value = match node.children[1].node_type {
-        NodeType::Assign => compile_assign(&node.children[1]),
+        NodeType::Assign => {
+            // The value may use a variable the declaration shadows, in C the new variable is
+            // already in scope in its initializer so the value is computed into another first
+            let (name, value) = (&node.children[1].children[0], &node.children[1].children[1]);
+            if uses_variable(value, name.value.as_ref().unwrap()) {
+                let name = name.value.clone().unwrap();
+                return format!(
+                    "{} {}_shadowed = {};\n{} {} = {}_shadowed",
+                    data_type,
+                    name,
+                    compile_expression(value),
+                    data_type,
+                    name,
+                    name
+                );
+            }
+            compile_assign(&node.children[1])
+        }
         NodeType::Identifier => node.children[1].value.clone().unwrap(),
         _ => panic!("Unexpected node type inside declare"),
     };
 
-    format!(
-        "{} {}",
-        compile_data_type(&node.children[0].node_type),
-        value
-    )
+    format!("{} {}", data_type, value)
+}
+
+fn uses_variable(node: &AstNode, name: &str) -> bool {
+    (node.node_type == NodeType::Identifier && node.value.as_deref() == Some(name))
+        || node.children.iter().any(|child| uses_variable(child, name))
+}
+
+// Constants are folded to literals by the checker
+pub fn compile_const_declare(node: &AstNode) -> String {
+    let data_type = match node.children[0].data_type() {
+        DataType::String => "const char* const".to_string(),
+        _ => format!("const {}", compile_data_type(&node.children[0].node_type)),
+    };
+
+    format!("{} {}", data_type, compile_assign(&node.children[1]))
+}
+
+fn declared_name(node: &AstNode) -> String {
+    let target = &node.children[1];
+    match target.node_type {
+        NodeType::Assign => target.children[0].value.clone().unwrap(),
+        _ => target.value.clone().unwrap(),
+    }
 }
 
 pub fn compile_data_type(node_type: &NodeType) -> String {
     match node_type {
         NodeType::Type(DataType::Integer) => "int".to_string(),
+        NodeType::Type(DataType::Sized(int_type)) => compile_int_type(int_type),
         NodeType::Type(DataType::Float) => "float".to_string(),
         NodeType::Type(DataType::Boolean) => "bool".to_string(),
+        NodeType::Type(DataType::String) => "const char*".to_string(),
+        NodeType::Type(DataType::Void) => "void".to_string(),
         NodeType::Type(DataType::Pointer(pointer_base)) => {
             format!("{}*", compile_pointer_type(pointer_base))
         }
+        NodeType::Type(DataType::Struct(name)) | NodeType::Type(DataType::Enum(name)) => {
+            name.clone()
+        }
+        NodeType::Type(DataType::Function(..)) => "tan_closure".to_string(),
+        NodeType::Type(DataType::Object(_)) => "tan_object*".to_string(),
         _ => panic!("Unexpected data type"),
     }
 }
 
-pub fn compile_pointer_type(node_type: &Box<DataType>) -> String {
-    match **node_type {
+// Literals too large for an int need a suffix in C
+fn compile_number(node: &AstNode) -> String {
+    let value = node.value.clone().unwrap();
+    if value.parse::<i32>().is_ok() {
+        return value;
+    }
+
+    match node.data_type {
+        Some(DataType::Sized(int_type)) if !int_type.is_signed() => value + "ULL",
+        _ => value + "LL",
+    }
+}
+
+// Sized integers are the fixed width types of stdint.h
+fn compile_int_type(int_type: &IntType) -> String {
+    let sign = if int_type.is_signed() { "" } else { "u" };
+    format!("{}int{}_t", sign, int_type.bits())
+}
+
+pub fn compile_pointer_type(node_type: &DataType) -> String {
+    match *node_type {
         DataType::Integer => "int".to_string(),
+        DataType::Sized(int_type) => compile_int_type(&int_type),
         DataType::Float => "float".to_string(),
         DataType::Boolean => "bool".to_string(),
+        DataType::String => "const char*".to_string(),
         DataType::Pointer(ref pointer_base) => {
             format!("{}*", compile_pointer_type(pointer_base))
         }
+        DataType::Struct(ref name) | DataType::Enum(ref name) => name.clone(),
+        DataType::Void => "void".to_string(),
+        DataType::Function(..) => "tan_closure".to_string(),
+        DataType::Object(_) => "tan_object*".to_string(),
+        DataType::Param(_) | DataType::Instance(..) => {
+            panic!("Unexpected generic type {}", node_type)
+        }
     }
 }
 
@@ -233,14 +1603,47 @@ pub fn compile_assign(node: &AstNode) -> String {
     let ident = &node.children[0];
     let expression = &node.children[1];
 
+    // The value is computed first, computing it may add properties and move the slot
+    if let NodeType::Property | NodeType::Index = ident.node_type {
+        let value_type = compile_pointer_type(ident.data_type.as_ref().unwrap());
+        return format!(
+            "({{ {} property_value = {}; *({}*)tan_object_set({}, {}) = property_value; }})",
+            value_type,
+            compile_expression(expression),
+            value_type,
+            compile_expression(&ident.children[0]),
+            compile_property_key(ident)
+        );
+    }
+
     format!(
         "{} = {}",
-        ident.value.clone().unwrap(),
+        compile_expression(ident),
         compile_expression(expression)
     )
 }
 
+// C computes with at least an int, so results of narrower types are cast back to wrap around
 pub fn compile_expression(node: &AstNode) -> String {
+    match node.data_type {
+        Some(DataType::Sized(int_type))
+            if int_type.bits() < 32
+                && matches!(
+                    node.node_type,
+                    NodeType::Operator(_) | NodeType::BitwiseOp(_)
+                ) =>
+        {
+            format!(
+                "({})({})",
+                compile_int_type(&int_type),
+                compile_operation(node)
+            )
+        }
+        _ => compile_operation(node),
+    }
+}
+
+fn compile_operation(node: &AstNode) -> String {
     macro_rules! compile_operator {
         ($op:tt) => {
             format!(
@@ -254,10 +1657,29 @@ pub fn compile_expression(node: &AstNode) -> String {
 
     match node.node_type {
         NodeType::Operator(Operator::Add) => compile_operator!("+"),
+        // The difference of two pointers is a ptrdiff_t, TanScript treats it as an int
+        NodeType::Operator(Operator::Subtract)
+            if matches!(node.children[0].data_type, Some(DataType::Pointer(_)))
+                && node.data_type == Some(DataType::Integer) =>
+        {
+            format!("(int)({})", compile_operator!("-"))
+        }
         NodeType::Operator(Operator::Subtract) => compile_operator!("-"),
         NodeType::Operator(Operator::Multiply) => compile_operator!("*"),
         NodeType::Operator(Operator::Divide) => compile_operator!("/"),
         NodeType::Operator(Operator::Mod) => compile_operator!("%"),
+        NodeType::Eq | NodeType::NotEq if node.children[0].data_type == Some(DataType::String) => {
+            format!(
+                "strcmp({}, {}) {} 0",
+                compile_expression(&node.children[0]),
+                compile_expression(&node.children[1]),
+                if node.node_type == NodeType::Eq {
+                    "=="
+                } else {
+                    "!="
+                }
+            )
+        }
         NodeType::Eq => compile_operator!("=="),
         NodeType::NotEq => compile_operator!("!="),
         NodeType::LessThan => compile_operator!("<"),
@@ -272,18 +1694,669 @@ pub fn compile_expression(node: &AstNode) -> String {
             format!("~{}", compile_expression(&node.children[0]))
         }
         NodeType::BitwiseOp(BitwiseOp::Xor) => compile_operator!("^"),
+        NodeType::Cast if is_float_to_int(node) => {
+            format!(
+                "tan_float_to_{}({})",
+                node.children[1].data_type(),
+                compile_expression(&node.children[0])
+            )
+        }
+        NodeType::Cast => format!(
+            "({})({})",
+            compile_data_type(&node.children[1].node_type),
+            compile_expression(&node.children[0])
+        ),
         _ => compile_node(node),
     }
 }
 
 // ---------------------------- Built-in functions ----------------------------
 pub fn compile_print(node: &AstNode) -> String {
-    let args = node
+    compile_args(&node.children, |args| compile_printf(node, args))
+}
+
+fn compile_printf(node: &AstNode, args: &[String]) -> String {
+    let mut printer = Printer::default();
+    for (i, (arg, value)) in node.children.iter().zip(args).enumerate() {
+        if i > 0 {
+            printer.text(", ");
+        }
+        printer.value(arg.data_type.as_ref().unwrap(), value);
+    }
+    printer.text("\\n");
+
+    match printer.finish().as_slice() {
+        [statement] => statement.clone(),
+        statements => format!("({{ {}; }})", statements.join("; ")),
+    }
+}
+
+// Collects the text of a print into printf calls, values printf can not format are printed
+// by helper functions in between
+#[derive(Default)]
+struct Printer {
+    statements: Vec<String>,
+    format: String,
+    args: Vec<String>,
+}
+
+impl Printer {
+    fn text(&mut self, text: &str) {
+        self.format += text;
+    }
+
+    fn value(&mut self, data_type: &DataType, value: &str) {
+        let (format, arg) = match data_type {
+            DataType::Struct(name) => {
+                self.flush();
+                self.statements
+                    .push(format!("tan_print_{}({})", name, value));
+                return;
+            }
+            DataType::String => ("%s", value.to_string()),
+            DataType::Enum(name) => ("%s", format!("tan_{}_names[{}]", name, value)),
+            DataType::Float => ("%s", format!("tan_float_string({}).text", value)),
+            DataType::Boolean => ("%s", format!("({}) ? \"true\" : \"false\"", value)),
+            DataType::Sized(IntType::I64) => ("%lld", format!("(long long)({})", value)),
+            DataType::Sized(IntType::U64) => ("%llu", format!("(unsigned long long)({})", value)),
+            DataType::Sized(IntType::U32) => ("%u", value.to_string()),
+            _ => ("%d", value.to_string()),
+        };
+        self.format += format;
+        self.args.push(arg);
+    }
+
+    fn flush(&mut self) {
+        if !self.format.is_empty() {
+            let args = std::iter::once(format!("\"{}\"", self.format))
+                .chain(self.args.drain(..))
+                .collect::<Vec<String>>()
+                .join(", ");
+            self.statements.push(format!("printf({})", args));
+            self.format.clear();
+        }
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        self.flush();
+        self.statements
+    }
+}
+
+// Enums print the names of their variants, like Shape::Circle, from a table per enum
+fn compile_enum_names(node: &AstNode) -> String {
+    let name = node.value.clone().unwrap();
+    let variants = node
         .children
         .iter()
-        .map(compile_node)
+        .map(|variant| format!("\t\"{}::{}\",\n", name, variant.value.clone().unwrap()))
+        .collect::<String>();
+
+    format!(
+        "const char* const tan_{}_names[] = {{\n{}}}",
+        name, variants
+    )
+}
+
+// Enums that are printed, directly or in the fields of printed structs
+fn printed_enums<'a>(
+    ast: &AstNode,
+    printed_structs: &[&AstNode],
+    enum_defs: &[&'a AstNode],
+) -> Vec<&'a AstNode> {
+    let mut types = vec![];
+    printed_types(ast, &mut types);
+    for def in printed_structs {
+        types.extend(
+            def.children
+                .iter()
+                .map(|field| field.children[0].data_type().clone()),
+        );
+    }
+
+    enum_defs
+        .iter()
+        .filter(|def| types.contains(&DataType::Enum(def.value.clone().unwrap())))
+        .copied()
+        .collect()
+}
+
+// Structs print like the interpreter prints them, Point { x: 0, y: 0 }
+fn compile_struct_print_signature(name: &str) -> String {
+    format!("void tan_print_{}({} value)", name, name)
+}
+
+fn compile_struct_print_def(node: &AstNode) -> String {
+    let name = node.value.clone().unwrap();
+    let mut printer = Printer::default();
+    printer.text(&format!("{} {{ ", name));
+    for (i, field) in node.children.iter().enumerate() {
+        let field_name = field.children[1].value.clone().unwrap();
+        if i > 0 {
+            printer.text(", ");
+        }
+        printer.text(&format!("{}: ", field_name));
+        printer.value(
+            field.children[0].data_type(),
+            &format!("value.{}", field_name),
+        );
+    }
+    printer.text(" }");
+
+    let statements = printer
+        .finish()
+        .into_iter()
+        .map(|statement| statement + ";")
+        .collect::<Vec<String>>();
+    format!(
+        "{} {}",
+        compile_struct_print_signature(&name),
+        braces(&statements)
+    )
+}
+
+// Types of the values passed to print
+fn printed_types(node: &AstNode, types: &mut Vec<DataType>) {
+    if node.node_type == NodeType::BuiltinCall && node.value.as_deref() == Some("print") {
+        for arg in &node.children {
+            let data_type = arg.data_type.clone().unwrap();
+            if !types.contains(&data_type) {
+                types.push(data_type);
+            }
+        }
+    }
+    for child in &node.children {
+        printed_types(child, types);
+    }
+}
+
+// Structs that are printed and the structs in their fields, which their helpers print
+fn printed_structs<'a>(ast: &AstNode, struct_defs: &[&'a AstNode]) -> Vec<&'a AstNode> {
+    let mut types = vec![];
+    printed_types(ast, &mut types);
+    let mut names = types
+        .into_iter()
+        .filter_map(|data_type| match data_type {
+            DataType::Struct(name) => Some(name),
+            _ => None,
+        })
+        .collect::<Vec<String>>();
+    let mut printed: Vec<&AstNode> = vec![];
+    while let Some(name) = names.pop() {
+        if printed.iter().any(|def| def.value.as_ref() == Some(&name)) {
+            continue;
+        }
+        let def = match struct_defs
+            .iter()
+            .find(|def| def.value.as_ref() == Some(&name))
+        {
+            Some(def) => *def,
+            None => panic!("Print of undefined struct {}", name),
+        };
+        for field in &def.children {
+            if let DataType::Struct(field_type) = field.children[0].data_type() {
+                names.push(field_type.clone());
+            }
+        }
+        printed.push(def);
+    }
+    printed
+}
+
+// The checker records the pointer type the allocation is assigned to
+pub fn compile_alloc(node: &AstNode) -> String {
+    let element = match node.data_type {
+        Some(DataType::Pointer(ref element)) => compile_pointer_type(element),
+        _ => panic!("Unexpected alloc without an element type"),
+    };
+
+    format!(
+        "malloc(sizeof({}) * ({}))",
+        element,
+        compile_expression(&node.children[0])
+    )
+}
+
+// ---------------------------- IR ----------------------------
+// C of a program after the passes ran on its IR, dumps of it go to the output. Without
+// passes, or when the program uses something the IR does not support, C comes from the AST
+pub fn compile_optimized(
+    ast: &AstNode,
+    manager: &PassManager,
+    output: &mut impl std::io::Write,
+) -> String {
+    if manager.passes.is_empty() || !ir::can_lower(ast) {
+        return compile(ast);
+    }
+    let mut program = ir::lower(ast);
+    manager.run(&mut program, output);
+    compile_program(&program)
+}
+
+// Compiles the IR, which the optimization passes work on, to C. Temporaries are locals and
+// blocks are labels jumped to with goto. Programs the IR can not lower are compiled from
+// the AST by compile. Keeping two C emitters is deliberate: the AST one covers the whole
+// language, this one lets the C compiler see what the passes did
+pub fn compile_program(program: &ir::Program) -> String {
+    let mut header = "#include <stdio.h>\n#include <stdlib.h>\n#include <stdbool.h>\n".to_string();
+    let data_types = program
+        .globals
+        .iter()
+        .map(|global| &global.data_type)
+        .chain(
+            program
+                .functions
+                .iter()
+                .flat_map(|function| &function.temps),
+        )
+        .chain(program.externs.iter().flat_map(|def| &def.params))
+        .collect::<Vec<&DataType>>();
+    if data_types.contains(&&DataType::String) {
+        header += "#include <string.h>\n";
+    }
+    if data_types.iter().any(|data_type| is_sized_int(data_type)) {
+        header += "#include <stdint.h>\n";
+    }
+
+    let instructions = program
+        .functions
+        .iter()
+        .flat_map(|function| {
+            function
+                .blocks
+                .iter()
+                .flat_map(|block| &block.instructions)
+                .map(move |instruction| (function, instruction))
+        })
+        .collect::<Vec<(&ir::Function, &ir::Instruction)>>();
+    let mut builtins = vec![];
+    for (function, instruction) in &instructions {
+        if let ir::Instruction::Builtin { builtin, args, .. } = instruction {
+            if is_float_math(function, *builtin, args) {
+                if !header.contains("#include <math.h>\n") {
+                    header += "#include <math.h>\n";
+                }
+            } else if !builtins.contains(builtin) {
+                builtins.push(*builtin);
+            }
+        }
+    }
+    if builtins.contains(&Builtin::Random) || builtins.contains(&Builtin::Seed) {
+        header += "#include <time.h>\n";
+    }
+
+    // Extern functions naming a header are declared by it, the others get a prototype
+    let mut prototypes = String::new();
+    for def in &program.externs {
+        match &def.header {
+            Some(extern_header) => {
+                let include = format!("#include <{}>\n", extern_header);
+                if !header.contains(&include) {
+                    header += &include;
+                }
+            }
+            None => {
+                let extern_type = |data_type: &DataType| match data_type {
+                    DataType::Float => "double".to_string(),
+                    data_type => compile_pointer_type(data_type),
+                };
+                let params = def.params.iter().map(extern_type).collect::<Vec<String>>();
+                prototypes += &format!(
+                    "{} {}({});\n",
+                    extern_type(&def.return_type),
+                    def.name,
+                    params.join(", ")
+                );
+            }
+        }
+    }
+
+    let mut cast_types = vec![];
+    for (function, instruction) in &instructions {
+        if let ir::Instruction::Convert { dest, src } = instruction {
+            let to = &function.temps[*dest];
+            if function.operand_type(src) == DataType::Float
+                && to.integer_layout().is_some()
+                && !cast_types.contains(to)
+            {
+                cast_types.push(to.clone());
+            }
+        }
+    }
+    header += &cast_types.iter().map(cast_runtime).collect::<String>();
+    let prints_float = instructions.iter().any(|(function, instruction)| {
+        matches!(instruction, ir::Instruction::Builtin { builtin: Builtin::Print, args, .. }
+            if args.iter().any(|arg| function.operand_type(arg) == DataType::Float))
+    });
+    if prints_float {
+        header += FLOAT_PRINT_RUNTIME;
+    }
+    let mut runtimes = vec![];
+    for runtime in builtins.into_iter().filter_map(builtin_runtime) {
+        if !runtimes.contains(&runtime) {
+            runtimes.push(runtime);
+            header += runtime;
+        }
+    }
+
+    let globals = program
+        .globals
+        .iter()
+        .map(|global| match (global.constant, &global.data_type) {
+            (false, data_type) => format!("{} {};\n", compile_pointer_type(data_type), global.name),
+            (true, DataType::String) => format!(
+                "const char* const {} = {};\n",
+                global.name,
+                compile_constant(&global.value)
+            ),
+            (true, data_type) => format!(
+                "const {} {} = {};\n",
+                compile_pointer_type(data_type),
+                global.name,
+                compile_constant(&global.value)
+            ),
+        })
+        .collect::<String>();
+
+    // Every function gets a prototype, so functions can call each other in any order
+    let (main, functions) = program.functions.split_last().unwrap();
+    prototypes += &functions
+        .iter()
+        .map(|function| compile_ir_signature(function, &ir_names(program, function)) + ";\n")
+        .collect::<String>();
+
+    let definitions = functions
+        .iter()
+        .chain(std::iter::once(main))
+        .map(|function| compile_ir_function(program, function))
+        .collect::<Vec<String>>();
+
+    let sections = [globals, prototypes]
+        .into_iter()
+        .filter(|section| !section.is_empty())
+        .chain(std::iter::once(definitions.join("\n\n")))
+        .collect::<Vec<String>>();
+    format!("{}\n{}\n", header, sections.join("\n"))
+}
+
+// Variables keep their name unless another variable of the function or a global has it,
+// other temporaries are numbered. Generated names have an underscore, which names in
+// TanScript do not
+fn ir_names(program: &ir::Program, function: &ir::Function) -> Vec<String> {
+    let taken = |name: &String| {
+        function
+            .names
+            .values()
+            .filter(|other| *other == name)
+            .count()
+            > 1
+            || program.globals.iter().any(|global| global.name == *name)
+            || program.functions.iter().any(|other| other.name == *name)
+            || program.externs.iter().any(|def| def.name == *name)
+    };
+
+    (0..function.temps.len())
+        .map(|temp| match function.names.get(&temp) {
+            Some(name) if !taken(name) => name.clone(),
+            Some(name) => format!("{}_{}", name, temp),
+            None => format!("t_{}", temp),
+        })
+        .collect()
+}
+
+fn compile_ir_signature(function: &ir::Function, names: &[String]) -> String {
+    if function.name == "main" {
+        return "int main()".to_string();
+    }
+
+    let params = function
+        .params
+        .iter()
+        .map(|param| {
+            format!(
+                "{} {}",
+                compile_pointer_type(&function.temps[*param]),
+                names[*param]
+            )
+        })
+        .collect::<Vec<String>>();
+    format!(
+        "{} {}({})",
+        compile_pointer_type(&function.return_type),
+        function.name,
+        params.join(", ")
+    )
+}
+
+fn compile_ir_function(program: &ir::Program, function: &ir::Function) -> String {
+    let names = ir_names(program, function);
+    let operand = |operand: &ir::Operand| match operand {
+        ir::Operand::Temp(temp) => names[*temp].clone(),
+        ir::Operand::Constant(constant) => compile_constant(constant),
+    };
+
+    // Temporaries that are not parameters are declared at the top, so labels are never
+    // followed by a declaration
+    let mut used = vec![];
+    for block in &function.blocks {
+        for instruction in &block.instructions {
+            used.extend(instruction.dest());
+            used.extend(instruction.uses());
+        }
+        used.extend(block.terminator.uses());
+    }
+    used.sort();
+    used.dedup();
+    let mut lines = used
+        .into_iter()
+        .filter(|temp| !function.params.contains(temp))
+        .map(|temp| {
+            format!(
+                "\t{} {};",
+                compile_pointer_type(&function.temps[temp]),
+                names[temp]
+            )
+        })
         .collect::<Vec<String>>();
 
-    let format = args.iter().map(|_| "%d").collect::<Vec<&str>>().join(", ");
-    format!("printf(\"{}\\n\", {})", format, args.join(", "))
+    // Blocks are placed in order, so jumps to the next one fall through
+    let mut targets = vec![];
+    let mut blocks = vec![];
+    for (index, block) in function.blocks.iter().enumerate() {
+        let next = index + 1;
+        let mut statements = block
+            .instructions
+            .iter()
+            .map(|instruction| compile_ir_instruction(function, instruction, &operand))
+            .collect::<Vec<String>>();
+        match &block.terminator {
+            ir::Terminator::Jump(target) if *target == next => {}
+            ir::Terminator::Jump(target) => {
+                targets.push(*target);
+                statements.push(format!("goto block_{};", target));
+            }
+            ir::Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let condition = operand(condition);
+                if *then == next {
+                    targets.push(*otherwise);
+                    statements.push(format!("if (!{}) goto block_{};", condition, otherwise));
+                } else {
+                    targets.push(*then);
+                    statements.push(format!("if ({}) goto block_{};", condition, then));
+                    if *otherwise != next {
+                        targets.push(*otherwise);
+                        statements.push(format!("goto block_{};", otherwise));
+                    }
+                }
+            }
+            ir::Terminator::Return(Some(value)) => {
+                statements.push(format!("return {};", operand(value)))
+            }
+            ir::Terminator::Return(None) => statements.push("return;".to_string()),
+        }
+        blocks.push(statements);
+    }
+
+    for (index, statements) in blocks.into_iter().enumerate() {
+        if targets.contains(&index) {
+            lines.push(format!("block_{}:", index));
+        }
+        lines.extend(
+            statements
+                .into_iter()
+                .map(|statement| format!("\t{}", statement)),
+        );
+    }
+
+    format!(
+        "{} {{\n{}\n}}",
+        compile_ir_signature(function, &names),
+        lines.join("\n")
+    )
+}
+
+fn compile_ir_instruction(
+    function: &ir::Function,
+    instruction: &ir::Instruction,
+    operand: &impl Fn(&ir::Operand) -> String,
+) -> String {
+    let assign = |dest: &Option<ir::Temp>, value: String| match dest {
+        Some(dest) => format!("{} = {};", operand(&ir::Operand::Temp(*dest)), value),
+        None => format!("{};", value),
+    };
+
+    // Assigning to a narrower type wraps the result around
+    match instruction {
+        ir::Instruction::Copy { dest, src } => assign(&Some(*dest), operand(src)),
+        ir::Instruction::Binary {
+            dest,
+            op,
+            left,
+            right,
+        } => {
+            let op = match op {
+                ir::BinaryOp::Add => "+",
+                ir::BinaryOp::Subtract => "-",
+                ir::BinaryOp::Multiply => "*",
+                ir::BinaryOp::Divide => "/",
+                ir::BinaryOp::Mod => "%",
+                ir::BinaryOp::And => "&",
+                ir::BinaryOp::Or => "|",
+                ir::BinaryOp::Xor => "^",
+            };
+            let value = format!("{} {} {}", operand(left), op, operand(right));
+            assign(&Some(*dest), value)
+        }
+        ir::Instruction::Unary { dest, op, src } => {
+            let op = match op {
+                ir::UnaryOp::Not => "!",
+                ir::UnaryOp::BitwiseNot => "~",
+            };
+            assign(&Some(*dest), format!("{}{}", op, operand(src)))
+        }
+        ir::Instruction::Compare {
+            dest,
+            op,
+            left,
+            right,
+        } => {
+            let op = match op {
+                ir::Comparison::Eq => "==",
+                ir::Comparison::NotEq => "!=",
+                ir::Comparison::LessThan => "<",
+                ir::Comparison::GreaterThan => ">",
+                ir::Comparison::Leq => "<=",
+                ir::Comparison::Geq => ">=",
+            };
+            let value = match function.operand_type(left) {
+                DataType::String => {
+                    format!("strcmp({}, {}) {} 0", operand(left), operand(right), op)
+                }
+                _ => format!("{} {} {}", operand(left), op, operand(right)),
+            };
+            assign(&Some(*dest), value)
+        }
+        ir::Instruction::Convert { dest, src } => {
+            let to = &function.temps[*dest];
+            let value = match function.operand_type(src) {
+                DataType::Float if to.integer_layout().is_some() => {
+                    format!("tan_float_to_{}({})", to, operand(src))
+                }
+                _ => format!("({}){}", compile_pointer_type(to), operand(src)),
+            };
+            assign(&Some(*dest), value)
+        }
+        ir::Instruction::Call {
+            dest,
+            function: name,
+            args,
+        } => {
+            let args = args.iter().map(operand).collect::<Vec<String>>();
+            assign(dest, format!("{}({})", name, args.join(", ")))
+        }
+        ir::Instruction::Builtin {
+            dest,
+            builtin,
+            args,
+        } => {
+            let values = args.iter().map(operand).collect::<Vec<String>>();
+            let name = match builtin {
+                _ if is_float_math(function, *builtin, args) => {
+                    float_math_name(*builtin).to_string()
+                }
+                Builtin::Print => {
+                    let mut printer = Printer::default();
+                    for (i, (arg, value)) in args.iter().zip(&values).enumerate() {
+                        if i > 0 {
+                            printer.text(", ");
+                        }
+                        printer.value(&function.operand_type(arg), value);
+                    }
+                    printer.text("\\n");
+                    return printer.finish().join(";\n") + ";";
+                }
+                Builtin::Len => return assign(dest, format!("(int)strlen({})", values[0])),
+                Builtin::Alloc => panic!("The IR does not support pointers"),
+                Builtin::Free | Builtin::Abs | Builtin::Exit => builtin.name().to_string(),
+                Builtin::ReadInt => "tan_read_int".to_string(),
+                Builtin::ReadLine => "tan_read_line".to_string(),
+                _ => format!("tan_{}", builtin.name()),
+            };
+            assign(dest, format!("{}({})", name, values.join(", ")))
+        }
+        ir::Instruction::LoadGlobal { dest, name } => assign(&Some(*dest), name.clone()),
+        ir::Instruction::StoreGlobal { name, src } => format!("{} = {};", name, operand(src)),
+        ir::Instruction::Line(span) => format!(
+            "#line {} {}",
+            span.line,
+            compile_string(span.file.as_ref().unwrap())
+        ),
+    }
+}
+
+// Floats are written with the digits that read back as the same float
+fn compile_constant(constant: &ir::Constant) -> String {
+    match constant {
+        ir::Constant::Integer(value, data_type) => {
+            if i32::try_from(*value).is_ok() {
+                return value.to_string();
+            }
+            match data_type {
+                DataType::Sized(int_type) if !int_type.is_signed() => format!("{}ULL", value),
+                _ if *value == i64::MIN as i128 => format!("({}LL - 1)", value + 1),
+                _ => format!("{}LL", value),
+            }
+        }
+        ir::Constant::Float(value) if value.is_nan() => "(0.0f / 0.0f)".to_string(),
+        ir::Constant::Float(value) if value.is_infinite() => {
+            format!("({}1.0f / 0.0f)", if *value < 0.0 { "-" } else { "" })
+        }
+        ir::Constant::Float(value) => format!("{:?}f", value),
+        ir::Constant::Boolean(value) => value.to_string(),
+        ir::Constant::String(value) => compile_string(value),
+    }
 }
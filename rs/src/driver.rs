@@ -0,0 +1,327 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// The generated C uses statement expressions and __typeof__, which the GNU dialect has.
+// Signed integers wrap around on overflow like in the other backends, which C leaves
+// undefined unless -fwrapv is given. Extern functions from math.h need the math library
+const FLAGS: [&str; 3] = ["-std=gnu11", "-O2", "-fwrapv"];
+const LIBRARIES: [&str; 1] = ["-lm"];
+
+// The C compiler is a command line like "ccache gcc", --cc comes before the CC variable
+pub fn compiler(flag: Option<&str>) -> String {
+    match flag {
+        Some(compiler) => compiler.to_string(),
+        None => std::env::var("CC")
+            .ok()
+            .filter(|compiler| !compiler.trim().is_empty())
+            .unwrap_or("cc".to_string()),
+    }
+}
+
+// The executable is named after the script, unless that would overwrite a script that has
+// no extension
+pub fn executable(script: &Path) -> PathBuf {
+    let executable = script.with_extension(std::env::consts::EXE_EXTENSION);
+    match executable == script {
+        true => script.with_extension("out"),
+        false => executable,
+    }
+}
+
+// Compiles C to an executable. Warnings are printed, errors are returned. Both point at
+// TanScript lines through the #line directives of the generated code
+pub fn build(compiler: &str, source: &Path, executable: &Path) -> Result<(), String> {
+    let mut words = compiler.split_whitespace();
+    let program = match words.next() {
+        Some(program) => program,
+        None => panic!("Expected a C compiler"),
+    };
+
+    let output = match Command::new(program)
+        .args(words)
+        .args(FLAGS)
+        .arg("-o")
+        .arg(executable)
+        .arg(source)
+        .args(LIBRARIES)
+        .output()
+    {
+        Ok(output) => output,
+        Err(error) => panic!("Could not run C compiler {}: {}", compiler, error),
+    };
+
+    let diagnostics = String::from_utf8_lossy(&output.stderr).to_string();
+    match output.status.success() {
+        true => {
+            eprint!("{}", diagnostics);
+            Ok(())
+        }
+        false => Err(diagnostics),
+    }
+}
+
+// Runs a built program with the terminal of the driver, a program killed by a signal
+// fails like it does in a shell
+pub fn run(executable: &Path) -> i32 {
+    // A bare file name would be looked up in PATH
+    let executable = match executable.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Path::new(".").join(executable),
+        _ => executable.to_path_buf(),
+    };
+
+    let status = match Command::new(&executable).status() {
+        Ok(status) => status,
+        Err(error) => panic!("Could not run {}: {}", executable.display(), error),
+    };
+    match status.code() {
+        Some(code) => code,
+        None => {
+            #[cfg(unix)]
+            if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+                return 128 + signal;
+            }
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::AstNode;
+    use crate::compiler::compile;
+    use crate::interpreter::{interpret, Externs};
+    use crate::modules::set_file;
+    use crate::optimize::{Pass, PassManager};
+    use crate::test::check_script;
+
+    // What --optimize compiles
+    fn compile_optimized(root: &AstNode) -> String {
+        let manager = PassManager {
+            passes: Pass::DEFAULT.to_vec(),
+            print_after: None,
+        };
+        crate::compiler::compile_optimized(root, &manager, &mut std::io::sink())
+    }
+
+    // Writes the C of a script to a temporary directory, None when there is no C compiler
+    fn write_c(script: &str, name: &str, compile: fn(&AstNode) -> String) -> Option<PathBuf> {
+        Command::new("cc").arg("--version").output().ok()?;
+
+        let directory = std::env::temp_dir().join(format!("tan_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let mut root = check_script(script);
+        set_file(&mut root, &directory.join("main.tan").display().to_string());
+
+        let source = directory.join("main.c");
+        std::fs::write(&source, compile(&root)).unwrap();
+        Some(source)
+    }
+
+    // Runs the C of a script and checks that it prints what the interpreter prints
+    fn expect_interpreter_output(script: &str, name: &str, compile: fn(&AstNode) -> String) {
+        let Some(source) = write_c(script, name, compile) else {
+            return;
+        };
+        let executable = executable(&source);
+        build("cc", &source, &executable).unwrap();
+        let output = Command::new(&executable).output().unwrap();
+
+        let mut expected = vec![];
+        interpret(
+            &check_script(script),
+            &Externs::standard(),
+            &mut std::io::empty(),
+            &mut expected,
+        );
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(expected).unwrap()
+        );
+        std::fs::remove_dir_all(source.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn driver_prefers_the_flag_over_cc() {
+        assert_eq!(compiler(Some("clang -g")), "clang -g");
+    }
+
+    #[test]
+    fn driver_does_not_overwrite_the_script() {
+        assert_eq!(executable(Path::new("lib/prog.tan")), Path::new("lib/prog"));
+        assert_eq!(executable(Path::new("lib/prog")), Path::new("lib/prog.out"));
+    }
+
+    #[test]
+    fn driver_forwards_the_exit_code() {
+        let Some(source) = write_c("int code = 3;\nexit(code);", "exit", compile) else {
+            return;
+        };
+        let executable = executable(&source);
+        build("cc", &source, &executable).unwrap();
+        assert_eq!(run(&executable), 3);
+        std::fs::remove_dir_all(source.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn driver_reports_c_errors_at_tanscript_lines() {
+        for (name, compile) in [
+            ("error", compile as fn(&AstNode) -> String),
+            ("optimized_error", compile_optimized),
+        ] {
+            let Some(source) = write_c(
+                "extern \"math.h\" fn sqrt(string x) float;\n\nprint(1);\nfloat x = sqrt(\"a\");",
+                name,
+                compile,
+            ) else {
+                return;
+            };
+            let diagnostics = build("cc", &source, &executable(&source)).unwrap_err();
+            assert!(
+                diagnostics.contains("main.tan:4:"),
+                "Unexpected diagnostics {}",
+                diagnostics
+            );
+            std::fs::remove_dir_all(source.parent().unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn driver_prints_scalars_like_the_interpreter() {
+        expect_interpreter_output(include_str!("../golden/scalars.tan"), "scalars", compile);
+        expect_interpreter_output(
+            "float third = 1 as float / 3; float big = 16777216; float small = 1 as float / 1000000;
+            print(third, big, big * 1000000, small, 0 as float - 5 / 2 as float, third > 0, 1 == 2);",
+            "floats",
+            compile,
+        );
+    }
+
+    #[test]
+    fn driver_prints_structs_like_the_interpreter() {
+        expect_interpreter_output(
+            "struct Point { int x; int y; };
+            struct Line { Point a; Point b; string label; bool visible; float width; };
+            Point p = Point { x: 0, y: 0 };
+            print(p);
+            print(1, Line { a: p, b: Point { x: 3, y: 4 }, label: \"up\", visible: true, width: 1 as float / 2 }, p);
+            print();",
+            "structs",
+            compile,
+        );
+    }
+
+    #[test]
+    fn driver_prints_enums_like_the_interpreter() {
+        expect_interpreter_output(
+            "enum Shape { Circle, Square };
+            struct Tile { Shape shape; int size; };
+            Shape s = Shape::Square;
+            print(s, Shape::Circle, Tile { shape: s, size: 2 });",
+            "enums",
+            compile,
+        );
+    }
+
+    #[test]
+    fn driver_wraps_integers_like_the_interpreter() {
+        let script = "int i = 2147483600;
+            int steps = 0;
+            while i > 0 { i += 1; steps += 1; };
+            i64 wide = 9223372036854775807;
+            print(i, steps, wide + 1, i * 2 - 1);";
+        expect_interpreter_output(script, "wrap", compile);
+        expect_interpreter_output(script, "optimized_wrap", compile_optimized);
+    }
+
+    #[test]
+    fn driver_shares_globals_with_lambdas_like_the_interpreter() {
+        let script = "int g = 1;
+            fn() int read = || g;
+            g = 5;
+            fn() int inc = fn () int { g++; return g; };
+            inc();
+            print(read(), inc(), g);";
+        expect_interpreter_output(script, "lambda_globals", compile);
+    }
+
+    #[test]
+    fn driver_computes_float_math_like_the_interpreter() {
+        let script = "fn run(int n) int {
+                float f = n as float / 7;
+                float g = 0 as float - f * 3;
+                print(abs(g), min(f, g), max(g, 1), pow(f, 3), pow(2, g), sqrt(f), sqrt(g));
+                print(min(sqrt(g), f), max(1, sqrt(g)), abs(0 - n), sqrt(n * 5), pow(n, 2));
+                return 0;
+            };
+            run(10);";
+        expect_interpreter_output(script, "float_math", compile);
+        expect_interpreter_output(script, "optimized_float_math", compile_optimized);
+    }
+
+    #[test]
+    fn driver_jumps_to_loops_sharing_a_label_like_the_interpreter() {
+        let script = "int n = 0;
+            'outer: while n < 10 { n += 1; if n == 3 { break 'outer; }; continue 'outer; };
+            'outer: while n < 10 { 'inner: while true { n += 2; break 'outer; }; };
+            fn count() int {
+                int i = 0;
+                'step: while true { i += 1; break 'step; };
+                'step: while i < 5 { i += 1; continue 'step; };
+                return i;
+            };
+            print(n, count());";
+        expect_interpreter_output(script, "labels", compile);
+    }
+
+    #[test]
+    fn driver_saturates_float_casts_like_the_interpreter() {
+        let script = "fn clampByte(float x) u8 { return x as u8; };
+            fn run(int n) int {
+                float f = n as float * 100;
+                float big = f * 2147483647 as float * 2147483647 as float;
+                print(clampByte(f), clampByte(0 as float - f), (f * 1000) as i16, (0 as float - f) as u32);
+                print(big as i64, (0 as float - big) as i64, big as u64, big as int);
+                return 0;
+            };
+            run(3);";
+        expect_interpreter_output(script, "casts", compile);
+        expect_interpreter_output(script, "optimized_casts", compile_optimized);
+    }
+
+    #[test]
+    fn driver_passes_floats_to_externs_as_doubles() {
+        expect_interpreter_output(
+            "extern fn sqrt(float x) float;\nextern \"math.h\" fn floor(float x) float;\nprint(sqrt(16 as float), floor(sqrt(2 as float)));",
+            "externs",
+            compile,
+        );
+    }
+
+    #[test]
+    fn driver_optimized_c_prints_like_the_interpreter() {
+        let scripts = [
+            ("optimized_integers", include_str!("../golden/integers.tan")),
+            ("optimized_scalars", include_str!("../golden/scalars.tan")),
+            ("optimized_pressure", include_str!("../golden/pressure.tan")),
+        ];
+        for (name, script) in scripts {
+            expect_interpreter_output(script, name, compile_optimized);
+        }
+    }
+
+    #[test]
+    fn driver_optimizes_programs_the_ir_can_not_lower() {
+        expect_interpreter_output(
+            "struct Point { int x; int y; };
+            enum Color { Red, Green };
+            fn shift(Point p, int by) Point { return Point { x: p.x + by, y: p.y }; };
+            fn(int) int twice = |x| x * 2;
+            Color c = Color::Green;
+            int code = match c { Color::Red => 1, Color::Green => 2 };
+            print(shift(Point { x: 1, y: 2 }, twice(code)), c);",
+            "optimized_structs",
+            compile_optimized,
+        );
+    }
+}
@@ -0,0 +1,1257 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, Write};
+
+use crate::ast::*;
+use crate::builtins::Builtin;
+use crate::signals::SignalGraph;
+use crate::types::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    // Wide enough for every integer type, results wrap to the type the checker gave them
+    Integer(i128),
+    Float(f32),
+    Boolean(bool),
+    String(String),
+    Struct(String, Vec<(String, Value)>),
+    Enum(String, String),
+    Pointer(Option<Location>),
+    // None until a function is assigned
+    Function(Option<FunctionValue>),
+    // The memory block holding the properties, None until an object is assigned
+    Object(Option<usize>),
+    Void,
+}
+
+// A named function or lambda and the values the lambda captured when it was created
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionValue {
+    name: String,
+    captures: Vec<(String, Value)>,
+}
+
+// Where a pointer points, an element of a memory block and the struct fields inside it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    block: usize,
+    offset: isize,
+    path: Vec<String>,
+}
+
+// Storage of a variable or of a heap allocation
+struct Block {
+    values: Vec<Value>,
+    heap: bool,
+    // Property names of an object, one per value, empty for other blocks
+    keys: Vec<String>,
+    // Cleared when the allocation is freed or the variable goes out of scope
+    live: bool,
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Boolean(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{}", value),
+            Value::Struct(name, fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(field, value)| format!("{}: {}", field, value))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "{} {{ {} }}", name, fields)
+            }
+            Value::Enum(name, variant) => write!(f, "{}::{}", name, variant),
+            Value::Pointer(None) | Value::Function(None) | Value::Object(None) => {
+                write!(f, "null")
+            }
+            Value::Object(Some(block)) => write!(f, "<object {}>", block),
+            Value::Function(Some(function)) => write!(f, "<fn {}>", function.name),
+            Value::Pointer(Some(location)) => {
+                write!(f, "<pointer {}+{}>", location.block, location.offset)
+            }
+            Value::Void => write!(f, "void"),
+        }
+    }
+}
+
+// Non local control flow, propagated with `?` until the node it targets handles it
+enum Jump {
+    Break(Option<String>),
+    Continue(Option<String>),
+    Return(Value),
+    // Ends the program with an exit code
+    Exit(i32),
+}
+
+type Exec = Result<Value, Jump>;
+
+struct Interpreter<'a, 'w> {
+    functions: HashMap<String, &'a AstNode>,
+    structs: HashMap<String, &'a AstNode>,
+    enums: HashMap<String, &'a AstNode>,
+    // Variables map to the memory block that holds their value
+    scopes: Vec<HashMap<String, usize>>,
+    memory: Vec<Block>,
+    // Current value and declaration of every signal
+    signals: HashMap<String, (Value, &'a AstNode)>,
+    signal_graph: SignalGraph,
+    input: &'w mut dyn BufRead,
+    out: &'w mut dyn Write,
+    // State of the random number generator, zero until it is seeded
+    random: u32,
+    extern_defs: HashMap<String, &'a AstNode>,
+    externs: &'w Externs,
+    // Memory blocks of the top level variables, which every function can use
+    globals: HashMap<String, usize>,
+}
+
+// Rust implementations of extern functions, the interpreter can not call into C
+#[derive(Default)]
+pub struct Externs {
+    functions: HashMap<String, ExternFn>,
+}
+
+type ExternFn = Box<dyn Fn(&[Value]) -> Value>;
+
+impl Externs {
+    pub fn register(&mut self, name: &str, function: impl Fn(&[Value]) -> Value + 'static) {
+        self.functions.insert(name.to_string(), Box::new(function));
+    }
+
+    // The functions of the C math library, so programs using them also run here
+    pub fn standard() -> Externs {
+        let float = |value: &Value| match value {
+            Value::Float(value) => *value,
+            value => panic!("Expected a float, got {}", value),
+        };
+        let unary = [
+            ("sin", f32::sin as fn(f32) -> f32),
+            ("cos", f32::cos),
+            ("tan", f32::tan),
+            ("exp", f32::exp),
+            ("log", f32::ln),
+            ("sqrt", f32::sqrt),
+            ("floor", f32::floor),
+            ("ceil", f32::ceil),
+            ("fabs", f32::abs),
+        ];
+
+        let mut externs = Externs::default();
+        for (name, function) in unary {
+            externs.register(name, move |args| Value::Float(function(float(&args[0]))));
+        }
+        externs.register("pow", move |args| {
+            Value::Float(float(&args[0]).powf(float(&args[1])))
+        });
+        externs.register("atan2", move |args| {
+            Value::Float(float(&args[0]).atan2(float(&args[1])))
+        });
+        externs
+    }
+}
+
+// Runs the program and returns its exit code
+pub fn interpret(
+    ast: &AstNode,
+    externs: &Externs,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> i32 {
+    let mut interpreter = Interpreter {
+        functions: HashMap::new(),
+        structs: HashMap::new(),
+        enums: HashMap::new(),
+        scopes: vec![HashMap::new()],
+        memory: vec![],
+        signals: HashMap::new(),
+        signal_graph: SignalGraph::new(),
+        input,
+        out,
+        random: 0,
+        extern_defs: HashMap::new(),
+        externs,
+        globals: HashMap::new(),
+    };
+
+    // Definitions are hoisted the same way the compiler moves them above main
+    for child in &ast.children {
+        match child.node_type {
+            NodeType::FunctionDef => interpreter.define_function(child),
+            NodeType::StructDef => interpreter.define_struct(child),
+            NodeType::EnumDef => interpreter.define_enum(child),
+            NodeType::ExternDef => {
+                let name = child.value.clone().unwrap();
+                interpreter.extern_defs.insert(name, child);
+            }
+            _ => {}
+        }
+    }
+
+    // Like in C globals exist before the top level code runs, starting out zeroed and
+    // constants with their value
+    for child in &ast.children {
+        if let NodeType::Declare | NodeType::ConstDeclare = child.node_type {
+            interpreter.define_global(child);
+        }
+    }
+
+    for child in &ast.children {
+        let result = match child.node_type {
+            NodeType::FunctionDef
+            | NodeType::StructDef
+            | NodeType::EnumDef
+            | NodeType::ExternDef
+            | NodeType::ConstDeclare => continue,
+            NodeType::Declare => interpreter.initialize_global(child),
+            _ => interpreter.execute(child),
+        };
+
+        match result {
+            Ok(_) => {}
+            Err(Jump::Return(_)) => return 0,
+            Err(Jump::Exit(code)) => return code,
+            Err(_) => panic!("Loop jump escaped its loop"),
+        }
+    }
+
+    0
+}
+
+impl<'a, 'w> Interpreter<'a, 'w> {
+    fn define_function(&mut self, node: &'a AstNode) {
+        self.functions.insert(node.value.clone().unwrap(), node);
+    }
+
+    fn define_struct(&mut self, node: &'a AstNode) {
+        self.structs.insert(node.value.clone().unwrap(), node);
+    }
+
+    fn define_enum(&mut self, node: &'a AstNode) {
+        self.enums.insert(node.value.clone().unwrap(), node);
+    }
+
+    fn define_global(&mut self, node: &'a AstNode) {
+        let data_type = node.children[0].data_type();
+        let target = &node.children[1];
+        let (name, value) = match (&node.node_type, &target.node_type) {
+            (NodeType::ConstDeclare, _) => {
+                let value = match self.execute(&target.children[1]) {
+                    Ok(value) => value,
+                    Err(_) => panic!("Unexpected jump in a constant"),
+                };
+                (&target.children[0], convert(value, data_type))
+            }
+            (_, NodeType::Assign) => (&target.children[0], self.default_value(data_type)),
+            _ => (target, self.default_value(data_type)),
+        };
+
+        let block = self.allocate(vec![value], false);
+        self.globals.insert(name.value.clone().unwrap(), block);
+    }
+
+    fn initialize_global(&mut self, node: &'a AstNode) -> Exec {
+        let target = &node.children[1];
+        if target.node_type == NodeType::Assign {
+            let value = self.execute(&target.children[1])?;
+            let value = convert(value, node.children[0].data_type());
+            let location = self.lookup(target.children[0].value.as_ref().unwrap());
+            *self.at(&location) = value;
+        }
+
+        Ok(Value::Void)
+    }
+
+    fn struct_def(&self, name: &str) -> &'a AstNode {
+        match self.structs.get(name) {
+            Some(node) => node,
+            None => panic!("Use of undefined struct {}", name),
+        }
+    }
+
+    fn default_value(&self, data_type: &DataType) -> Value {
+        match data_type {
+            DataType::Integer | DataType::Sized(_) => Value::Integer(0),
+            DataType::Float => Value::Float(0.0),
+            DataType::Boolean => Value::Boolean(false),
+            DataType::String => Value::String(String::new()),
+            DataType::Pointer(_) => Value::Pointer(None),
+            DataType::Struct(name) => {
+                let fields = self
+                    .struct_def(name)
+                    .children
+                    .iter()
+                    .map(|field| {
+                        (
+                            field.children[1].value.clone().unwrap(),
+                            self.default_value(field.children[0].data_type()),
+                        )
+                    })
+                    .collect();
+
+                Value::Struct(name.clone(), fields)
+            }
+            DataType::Enum(name) => match self.enums.get(name) {
+                Some(enum_def) => {
+                    Value::Enum(name.clone(), enum_def.children[0].value.clone().unwrap())
+                }
+                None => panic!("Use of undefined enum {}", name),
+            },
+            DataType::Function(..) => Value::Function(None),
+            DataType::Object(_) => Value::Object(None),
+            DataType::Param(_) | DataType::Instance(..) => {
+                panic!("Unexpected generic type {}", data_type)
+            }
+            DataType::Void => panic!("Unexpected void value"),
+        }
+    }
+
+    fn allocate(&mut self, values: Vec<Value>, heap: bool) -> usize {
+        self.memory.push(Block {
+            values,
+            heap,
+            keys: vec![],
+            live: true,
+        });
+        self.memory.len() - 1
+    }
+
+    fn declare(&mut self, name: &str, value: Value) {
+        let block = self.allocate(vec![value], false);
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), block);
+    }
+
+    // Ends the lifetime of the variables of the innermost scope
+    fn pop_scope(&mut self) {
+        for block in self.scopes.pop().unwrap().into_values() {
+            self.memory[block].live = false;
+            self.memory[block].values.clear();
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Location {
+        for scope in self.scopes.iter().rev() {
+            if let Some(block) = scope.get(name) {
+                return Location {
+                    block: *block,
+                    offset: 0,
+                    path: vec![],
+                };
+            }
+        }
+
+        match self.globals.get(name) {
+            Some(block) => Location {
+                block: *block,
+                offset: 0,
+                path: vec![],
+            },
+            None => panic!("Use of undeclared variable {}", name),
+        }
+    }
+
+    // Finds the storage an assignment target or address of refers to
+    fn location(&mut self, target: &'a AstNode) -> Result<Location, Jump> {
+        match target.node_type {
+            NodeType::Identifier => Ok(self.lookup(target.value.as_ref().unwrap())),
+            NodeType::LParen => self.location(&target.children[0]),
+            NodeType::FieldAccess => {
+                let mut location = self.location(&target.children[0])?;
+                location.path.push(target.value.clone().unwrap());
+                Ok(location)
+            }
+            NodeType::PointerFieldAccess => {
+                let mut location = self.pointee(&target.children[0])?;
+                location.path.push(target.value.clone().unwrap());
+                Ok(location)
+            }
+            NodeType::Deref => self.pointee(&target.children[0]),
+            NodeType::Property | NodeType::Index => self.property(target, false),
+            _ => panic!("Invalid assignment target {:?}", target.node_type),
+        }
+    }
+
+    // Finds the slot of an object property, assignments add missing properties
+    fn property(&mut self, node: &'a AstNode, create: bool) -> Result<Location, Jump> {
+        let block = match self.execute(&node.children[0])? {
+            Value::Object(Some(block)) => block,
+            Value::Object(None) => panic!("Use of an unassigned object"),
+            value => panic!("Cannot access property of {:?}", value),
+        };
+
+        let key = match node.node_type {
+            NodeType::Index => match self.execute(&node.children[1])? {
+                Value::String(key) => key,
+                value => panic!("Property names must be strings, got {:?}", value),
+            },
+            _ => node.value.clone().unwrap(),
+        };
+
+        let offset = match self.memory[block].keys.iter().position(|k| *k == key) {
+            Some(offset) => offset,
+            None if create => {
+                let value = self.default_value(node.data_type.as_ref().unwrap());
+                let object = &mut self.memory[block];
+                object.keys.push(key);
+                object.values.push(value);
+                object.values.len() - 1
+            }
+            None => panic!("Object has no property {}", key),
+        };
+
+        Ok(Location {
+            block,
+            offset: offset as isize,
+            path: vec![],
+        })
+    }
+
+    // Evaluates a pointer and returns the location it points to
+    fn pointee(&mut self, pointer: &'a AstNode) -> Result<Location, Jump> {
+        match self.execute(pointer)? {
+            Value::Pointer(Some(location)) => Ok(location),
+            Value::Pointer(None) => panic!("Null pointer dereference"),
+            value => panic!("Cannot dereference {:?}", value),
+        }
+    }
+
+    fn at(&mut self, location: &Location) -> &mut Value {
+        let block = &mut self.memory[location.block];
+        if !block.live && block.heap {
+            panic!("Use of freed memory");
+        }
+        if !block.live {
+            panic!("Use of a pointer to a variable that went out of scope");
+        }
+
+        let mut value = match usize::try_from(location.offset)
+            .ok()
+            .and_then(|offset| block.values.get_mut(offset))
+        {
+            Some(value) => value,
+            None => panic!("Pointer out of bounds"),
+        };
+
+        for field in &location.path {
+            value = match value {
+                Value::Struct(_, fields) => field_slot(fields, field),
+                value => panic!("Cannot access field {} of {:?}", field, value),
+            };
+        }
+
+        value
+    }
+
+    fn load(&mut self, location: &Location) -> Value {
+        self.at(location).clone()
+    }
+
+    fn store(&mut self, target: &'a AstNode, value: Value) -> Result<(), Jump> {
+        let location = match target.node_type {
+            NodeType::Property | NodeType::Index => self.property(target, true)?,
+            _ => self.location(target)?,
+        };
+        let slot = self.at(&location);
+        *slot = coerce_to(value, slot);
+        Ok(())
+    }
+
+    fn execute(&mut self, node: &'a AstNode) -> Exec {
+        match node.node_type {
+            NodeType::Number => Ok(Value::Integer(
+                node.value.clone().unwrap().parse::<i128>().unwrap(),
+            )),
+            NodeType::Boolean => Ok(Value::Boolean(node.value.clone().unwrap() == "true")),
+            NodeType::String => Ok(Value::String(node.value.clone().unwrap())),
+            NodeType::Identifier => {
+                let location = self.lookup(node.value.as_ref().unwrap());
+                Ok(self.load(&location))
+            }
+            NodeType::LParen => self.execute(&node.children[0]),
+            NodeType::Operator(_)
+            | NodeType::Eq
+            | NodeType::NotEq
+            | NodeType::LessThan
+            | NodeType::GreaterThan
+            | NodeType::Leq
+            | NodeType::Geq
+            | NodeType::BitwiseOp(BitwiseOp::And)
+            | NodeType::BitwiseOp(BitwiseOp::Or)
+            | NodeType::BitwiseOp(BitwiseOp::Xor) => {
+                let left = self.execute(&node.children[0])?;
+                let right = self.execute(&node.children[1])?;
+                Ok(wrap(
+                    binary_op(&node.node_type, left, right),
+                    &node.data_type,
+                ))
+            }
+            NodeType::And => Ok(Value::Boolean(
+                self.condition(&node.children[0])? && self.condition(&node.children[1])?,
+            )),
+            NodeType::Or => Ok(Value::Boolean(
+                self.condition(&node.children[0])? || self.condition(&node.children[1])?,
+            )),
+            NodeType::Not => Ok(Value::Boolean(!self.condition(&node.children[0])?)),
+            NodeType::Cast => Ok(convert(
+                self.execute(&node.children[0])?,
+                node.children[1].data_type(),
+            )),
+            NodeType::BitwiseOp(BitwiseOp::Not) => match self.execute(&node.children[0])? {
+                Value::Integer(value) => Ok(wrap(Value::Integer(!value), &node.data_type)),
+                value => panic!("Cannot apply ~ to {:?}", value),
+            },
+            NodeType::Block => self.execute_block(node),
+            NodeType::Declare => self.execute_declare(node),
+            NodeType::ConstDeclare => panic!("Unexpected constant below the top level"),
+            NodeType::Assign => {
+                let value = self.execute(&node.children[1])?;
+                self.store(&node.children[0], value)?;
+                Ok(Value::Void)
+            }
+            NodeType::ShortAssign(ref op) => {
+                let left = self.execute(&node.children[0])?;
+                let right = self.execute(&node.children[1])?;
+                let value = binary_op(&NodeType::Operator(op.clone()), left, right);
+                let value = wrap(value, &node.children[0].data_type);
+                self.store(&node.children[0], value)?;
+                Ok(Value::Void)
+            }
+            NodeType::FunctionDef => {
+                self.define_function(node);
+                Ok(Value::Void)
+            }
+            NodeType::FunctionCall | NodeType::BuiltinCall => self.execute_function_call(node),
+            NodeType::ExternDef => panic!("Unexpected extern definition"),
+            NodeType::Return => Err(Jump::Return(self.execute(&node.children[0])?)),
+            NodeType::If => self.execute_if(node),
+            NodeType::IfExpression => self.execute_if_expression(node),
+            NodeType::While => self.execute_while(node),
+            NodeType::Break => Err(Jump::Break(node.value.clone())),
+            NodeType::Continue => Err(Jump::Continue(node.value.clone())),
+            NodeType::StructDef => {
+                self.define_struct(node);
+                Ok(Value::Void)
+            }
+            NodeType::StructLiteral => self.execute_struct_literal(node),
+            NodeType::FieldAccess => {
+                let name = node.value.as_ref().unwrap();
+                match self.execute(&node.children[0])? {
+                    Value::Struct(_, mut fields) => Ok(field_slot(&mut fields, name).clone()),
+                    value => panic!("Cannot access field {} of {:?}", name, value),
+                }
+            }
+            NodeType::PointerFieldAccess
+            | NodeType::Deref
+            | NodeType::Property
+            | NodeType::Index => {
+                let location = self.location(node)?;
+                Ok(self.load(&location))
+            }
+            NodeType::ObjectLiteral => self.execute_object_literal(node),
+            NodeType::AddressOf => Ok(Value::Pointer(Some(self.location(&node.children[0])?))),
+            NodeType::Lambda => {
+                let name = node.value.clone().unwrap();
+                // Lambda names can not clash with function names
+                self.define_function(node);
+
+                let captures = node.children[3]
+                    .children
+                    .iter()
+                    .map(|capture| {
+                        let capture = capture.children[1].value.clone().unwrap();
+                        let location = self.lookup(&capture);
+                        (capture, self.load(&location))
+                    })
+                    .collect();
+
+                Ok(Value::Function(Some(FunctionValue { name, captures })))
+            }
+            NodeType::FunctionRef => Ok(Value::Function(Some(FunctionValue {
+                name: node.value.clone().unwrap(),
+                captures: vec![],
+            }))),
+            NodeType::Call => {
+                let function = match self.execute(&node.children[0])? {
+                    Value::Function(Some(function)) => function,
+                    Value::Function(None) => panic!("Call of an unassigned function value"),
+                    value => panic!("Cannot call {:?}", value),
+                };
+
+                let mut args = vec![];
+                for child in &node.children[1..] {
+                    args.push(self.execute(child)?);
+                }
+
+                self.call(&function.name, function.captures, args)
+            }
+            NodeType::Captures => panic!("Unexpected Captures node"),
+            NodeType::Signal | NodeType::Computed => {
+                let name = node.value.as_ref().unwrap();
+                match self.signals.get(name) {
+                    Some((value, _)) => Ok(value.clone()),
+                    None => panic!("Use of undeclared signal {}", name),
+                }
+            }
+            NodeType::SignalDeclare | NodeType::ComputedDeclare => {
+                let value = self.execute(&node.children[1])?;
+                let value = convert(value, node.children[0].data_type());
+                self.signals
+                    .insert(node.value.clone().unwrap(), (value, node));
+                self.signal_graph.declare(node);
+                Ok(Value::Void)
+            }
+            NodeType::SignalAssign => {
+                let value = self.execute(&node.children[0])?;
+                self.set_signal(node.value.as_ref().unwrap(), value);
+
+                // Re-evaluate everything that depends on the signal, dependencies first
+                for name in self.signal_graph.dependents(node.value.as_ref().unwrap()) {
+                    let declaration = self.signals[&name].1;
+                    let value = self.execute(&declaration.children[1])?;
+                    self.set_signal(&name, value);
+                }
+
+                Ok(Value::Void)
+            }
+            NodeType::Null => Ok(Value::Pointer(None)),
+            NodeType::New => {
+                let value = self.default_value(node.children[0].data_type());
+                let block = self.allocate(vec![value], true);
+                Ok(Value::Pointer(Some(Location {
+                    block,
+                    offset: 0,
+                    path: vec![],
+                })))
+            }
+            NodeType::Parameters => panic!("Unexpected Parameters node"),
+            NodeType::Type(_) => panic!("Unexpected Type node"),
+            NodeType::Parameter => panic!("Unexpected Parameter node"),
+            NodeType::Field => panic!("Unexpected Field node"),
+            NodeType::EnumDef => {
+                self.define_enum(node);
+                Ok(Value::Void)
+            }
+            NodeType::EnumVariant => Ok(Value::Enum(
+                node.children[0].value.clone().unwrap(),
+                node.value.clone().unwrap(),
+            )),
+            NodeType::Match => {
+                let arm = self.select_arm(node)?;
+                self.execute(arm)?;
+                Ok(Value::Void)
+            }
+            NodeType::MatchExpression => {
+                let arm = self.select_arm(node)?;
+                self.execute_branch_value(arm)
+            }
+            NodeType::MatchArm => panic!("Unexpected MatchArm node"),
+            NodeType::Wildcard => panic!("Unexpected Wildcard node"),
+            NodeType::GenericDef => panic!("Unexpected generic definition"),
+            NodeType::TypeParameters => panic!("Unexpected TypeParameters node"),
+            NodeType::Import | NodeType::Pub => panic!("Unexpected {:?} node", node.node_type),
+        }
+    }
+
+    fn set_signal(&mut self, name: &str, value: Value) {
+        let declaration = match self.signals.get(name) {
+            Some((_, declaration)) => *declaration,
+            None => panic!("Use of undeclared signal {}", name),
+        };
+
+        let value = convert(value, declaration.children[0].data_type());
+        self.signals.insert(name.to_string(), (value, declaration));
+    }
+
+    // Returns the body of the first arm whose pattern matches the subject
+    fn select_arm(&mut self, node: &'a AstNode) -> Result<&'a AstNode, Jump> {
+        let subject = self.execute(&node.children[0])?;
+
+        for arm in &node.children[1..] {
+            let pattern = &arm.children[0];
+            if pattern.node_type == NodeType::Wildcard || self.execute(pattern)? == subject {
+                return Ok(&arm.children[1]);
+            }
+        }
+
+        panic!("No match arm matched {}", subject);
+    }
+
+    // Evaluates an if or match branch, blocks produce the value of their last child
+    fn execute_branch_value(&mut self, node: &'a AstNode) -> Exec {
+        if node.node_type != NodeType::Block {
+            return self.execute(node);
+        }
+
+        self.scopes.push(HashMap::new());
+        let result = node
+            .children
+            .iter()
+            .try_fold(Value::Void, |_, child| self.execute(child));
+        self.pop_scope();
+
+        result
+    }
+
+    fn execute_struct_literal(&mut self, node: &'a AstNode) -> Exec {
+        let name = node.value.as_ref().unwrap();
+        let mut value = self.default_value(&DataType::Struct(name.clone()));
+
+        let struct_def = self.struct_def(name);
+        for field in &node.children {
+            let field_name = field.children[0].value.as_ref().unwrap();
+            let data_type = match struct_def
+                .children
+                .iter()
+                .find(|def| def.children[1].value.as_ref() == Some(field_name))
+            {
+                Some(def) => def.children[0].data_type(),
+                None => panic!("Struct {} has no field {}", name, field_name),
+            };
+
+            let field_value = convert(self.execute(&field.children[1])?, data_type);
+            if let Value::Struct(_, fields) = &mut value {
+                *field_slot(fields, field_name) = field_value;
+            }
+        }
+
+        Ok(value)
+    }
+
+    // Objects live on the heap and are shared, like in the TypeScript engine
+    fn execute_object_literal(&mut self, node: &'a AstNode) -> Exec {
+        let value_type = match &node.data_type {
+            Some(DataType::Object(value)) => value,
+            _ => panic!("Unexpected object literal without a type"),
+        };
+
+        let mut keys = vec![];
+        let mut values = vec![];
+        for property in &node.children {
+            keys.push(property.children[0].value.clone().unwrap());
+            values.push(convert(self.execute(&property.children[1])?, value_type));
+        }
+
+        let block = self.allocate(values, true);
+        self.memory[block].keys = keys;
+        Ok(Value::Object(Some(block)))
+    }
+
+    fn condition(&mut self, node: &'a AstNode) -> Result<bool, Jump> {
+        match self.execute(node)? {
+            Value::Boolean(value) => Ok(value),
+            Value::Integer(value) => Ok(value != 0),
+            Value::Pointer(location) => Ok(location.is_some()),
+            value => panic!("Expected a condition, got {:?}", value),
+        }
+    }
+
+    fn execute_block(&mut self, node: &'a AstNode) -> Exec {
+        self.scopes.push(HashMap::new());
+        let result = node
+            .children
+            .iter()
+            .try_for_each(|child| self.execute(child).map(|_| ()));
+        self.pop_scope();
+
+        result.map(|_| Value::Void)
+    }
+
+    fn execute_declare(&mut self, node: &'a AstNode) -> Exec {
+        let data_type = node.children[0].data_type();
+
+        let target = &node.children[1];
+        match target.node_type {
+            NodeType::Assign => {
+                let value = self.execute(&target.children[1])?;
+                self.declare(
+                    target.children[0].value.as_ref().unwrap(),
+                    convert(value, data_type),
+                );
+            }
+            _ => {
+                let value = self.default_value(data_type);
+                self.declare(target.value.as_ref().unwrap(), value);
+            }
+        }
+
+        Ok(Value::Void)
+    }
+
+    fn execute_if(&mut self, node: &'a AstNode) -> Exec {
+        if self.condition(&node.children[0])? {
+            self.execute(&node.children[1])?;
+        } else if let Some(else_ast) = node.children.get(2) {
+            self.execute(else_ast)?;
+        }
+
+        Ok(Value::Void)
+    }
+
+    fn execute_if_expression(&mut self, node: &'a AstNode) -> Exec {
+        let branch = match self.condition(&node.children[0])? {
+            true => &node.children[1],
+            false => &node.children[2],
+        };
+
+        self.execute_branch_value(branch)
+    }
+
+    fn execute_while(&mut self, node: &'a AstNode) -> Exec {
+        // An unlabelled jump targets the innermost loop, a labelled one only its own loop
+        let targets = |label: &Option<String>| label.is_none() || *label == node.value;
+
+        while self.condition(&node.children[0])? {
+            match self.execute(&node.children[1]) {
+                Ok(_) => {}
+                Err(Jump::Break(label)) if targets(&label) => break,
+                Err(Jump::Continue(label)) if targets(&label) => continue,
+                Err(jump) => return Err(jump),
+            }
+        }
+
+        Ok(Value::Void)
+    }
+
+    fn execute_function_call(&mut self, node: &'a AstNode) -> Exec {
+        let name = node.value.as_ref().unwrap();
+
+        let mut args = vec![];
+        for child in &node.children {
+            args.push(self.execute(child)?);
+        }
+
+        if node.node_type == NodeType::BuiltinCall {
+            let builtin = Builtin::from_name(name).unwrap();
+            return self.call_builtin(node, builtin, args);
+        }
+
+        if let Some(def) = self.extern_defs.get(name) {
+            return Ok(self.call_extern(def, args));
+        }
+
+        self.call(name, vec![], args)
+    }
+
+    fn call(&mut self, name: &str, captures: Vec<(String, Value)>, args: Vec<Value>) -> Exec {
+        let function = match self.functions.get(name) {
+            Some(function) => *function,
+            None => panic!("Call to undefined function {}", name),
+        };
+
+        let params = &function.children[0];
+        if params.children.len() != args.len() {
+            panic!(
+                "Function {} expects {} arguments, got {}",
+                name,
+                params.children.len(),
+                args.len()
+            );
+        }
+
+        // Functions only see their own parameters and locals, lambdas also see the
+        // copies of the variables they captured
+        let caller_scopes = std::mem::replace(&mut self.scopes, vec![HashMap::new()]);
+        for (capture, value) in captures {
+            self.declare(&capture, value);
+        }
+        for (param, arg) in params.children.iter().zip(args) {
+            self.declare(
+                param.children[1].value.as_ref().unwrap(),
+                convert(arg, param.children[0].data_type()),
+            );
+        }
+
+        let result = self.execute(&function.children[2]);
+        self.pop_scope();
+        self.scopes = caller_scopes;
+
+        let return_type = function.children[1].data_type();
+
+        match result {
+            Ok(_) => panic!("Function {} ended without returning a value", name),
+            Err(Jump::Return(value)) => Ok(convert(value, return_type)),
+            Err(Jump::Exit(code)) => Err(Jump::Exit(code)),
+            Err(_) => panic!("Loop jump escaped function {}", name),
+        }
+    }
+
+    fn call_extern(&self, def: &AstNode, args: Vec<Value>) -> Value {
+        let name = def.value.as_ref().unwrap();
+        let function = match self.externs.functions.get(name) {
+            Some(function) => function,
+            None => panic!(
+                "Extern function {} has no implementation in the interpreter",
+                name
+            ),
+        };
+
+        let args = def.children[0]
+            .children
+            .iter()
+            .zip(args)
+            .map(|(param, arg)| convert(arg, param.children[0].data_type()))
+            .collect::<Vec<Value>>();
+        match def.children[1].data_type() {
+            DataType::Void => {
+                function(&args);
+                Value::Void
+            }
+            return_type => convert(function(&args), return_type),
+        }
+    }
+
+    fn call_builtin(&mut self, node: &AstNode, builtin: Builtin, args: Vec<Value>) -> Exec {
+        let int = |index: usize| match &args[index] {
+            Value::Integer(value) => *value as i32,
+            value => panic!("Expected an integer, got {}", value),
+        };
+        let string = |index: usize| match &args[index] {
+            Value::String(value) => value.clone(),
+            value => panic!("Expected a string, got {}", value),
+        };
+
+        let value = match builtin {
+            Builtin::Print => {
+                let line = args
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                writeln!(self.out, "{}", line).expect("Could not write output");
+                Value::Void
+            }
+            Builtin::Alloc => self.alloc(node, &args[0]),
+            Builtin::Free => {
+                self.free(&args[0]);
+                Value::Void
+            }
+            // The checker picks the float version of math when an argument is a float
+            Builtin::Abs | Builtin::Min | Builtin::Max | Builtin::Pow | Builtin::Sqrt
+                if node.data_type == Some(DataType::Float) =>
+            {
+                // Literal arguments are still integers
+                let float = |index: usize| match convert(args[index].clone(), &DataType::Float) {
+                    Value::Float(value) => value,
+                    value => panic!("Expected a float, got {}", value),
+                };
+                Value::Float(match builtin {
+                    Builtin::Abs => float(0).abs(),
+                    Builtin::Min => float(0).min(float(1)),
+                    Builtin::Max => float(0).max(float(1)),
+                    Builtin::Pow => float(0).powf(float(1)),
+                    _ => float(0).sqrt(),
+                })
+            }
+            Builtin::Abs => Value::Integer(int(0).wrapping_abs().into()),
+            Builtin::Min => Value::Integer(int(0).min(int(1)).into()),
+            Builtin::Max => Value::Integer(int(0).max(int(1)).into()),
+            Builtin::Pow => {
+                let exponent = int(1);
+                if exponent < 0 {
+                    panic!("Negative exponent {}", exponent);
+                }
+                Value::Integer(int(0).wrapping_pow(exponent as u32).into())
+            }
+            Builtin::Sqrt => {
+                let x = int(0);
+                if x < 0 {
+                    panic!("Square root of negative number {}", x);
+                }
+                Value::Integer((x as f64).sqrt() as i128)
+            }
+            Builtin::Len => Value::Integer(string(0).len() as i128),
+            Builtin::Concat => Value::String(string(0) + &string(1)),
+            Builtin::Substr => {
+                let value = string(0);
+                let (start, length) = (int(1), int(2));
+                if start < 0 || length < 0 || start as usize + length as usize > value.len() {
+                    panic!(
+                        "Substring {}..{} out of range of a string of length {}",
+                        start,
+                        start + length,
+                        value.len()
+                    );
+                }
+
+                let bytes = &value.as_bytes()[start as usize..(start + length) as usize];
+                Value::String(String::from_utf8_lossy(bytes).to_string())
+            }
+            Builtin::Str => Value::String(int(0).to_string()),
+            Builtin::ReadInt => {
+                let line = self.read_line();
+                match line.trim().parse::<i32>() {
+                    Ok(value) => Value::Integer(value.into()),
+                    Err(_) => panic!("Expected an integer, got {:?}", line),
+                }
+            }
+            Builtin::ReadLine => Value::String(self.read_line()),
+            Builtin::Assert => {
+                if args[0] != Value::Boolean(true) {
+                    panic!("Assertion failed");
+                }
+                Value::Void
+            }
+            Builtin::Exit => return Err(Jump::Exit(int(0))),
+            Builtin::Random => {
+                let bound = int(0);
+                if bound <= 0 {
+                    panic!("Random bound must be positive, got {}", bound);
+                }
+                if self.random == 0 {
+                    let time = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map_or(0, |time| time.as_secs());
+                    self.random = seed_state(time as i32);
+                }
+
+                self.random = next_random(self.random);
+                Value::Integer((self.random % bound as u32).into())
+            }
+            Builtin::Seed => {
+                self.random = seed_state(int(0));
+                Value::Void
+            }
+        };
+
+        Ok(value)
+    }
+
+    // A line of input without its line break, empty at the end of the input
+    fn read_line(&mut self) -> String {
+        let mut line = String::new();
+        self.input
+            .read_line(&mut line)
+            .expect("Could not read input");
+
+        let end = line.trim_end_matches(['\n', '\r']).len();
+        line.truncate(end);
+        line
+    }
+
+    // The checker records the pointer type the allocation is assigned to
+    fn alloc(&mut self, node: &AstNode, count: &Value) -> Value {
+        let element = match &node.data_type {
+            Some(DataType::Pointer(element)) => element,
+            _ => panic!("Unexpected alloc without an element type"),
+        };
+
+        let count = match count {
+            Value::Integer(count) if *count >= 0 => *count as usize,
+            count => panic!("Invalid allocation size {}", count),
+        };
+
+        let values = vec![self.default_value(element); count];
+        let block = self.allocate(values, true);
+        Value::Pointer(Some(Location {
+            block,
+            offset: 0,
+            path: vec![],
+        }))
+    }
+
+    fn free(&mut self, pointer: &Value) {
+        let location = match pointer {
+            // Like in C freeing null does nothing
+            Value::Pointer(None) => return,
+            Value::Pointer(Some(location)) => location,
+            value => panic!("Cannot free {:?}", value),
+        };
+
+        let block = &mut self.memory[location.block];
+        if !block.heap || location.offset != 0 || !location.path.is_empty() {
+            panic!("Cannot free memory that was not returned by new or alloc");
+        }
+        if !block.live {
+            panic!("Double free");
+        }
+
+        block.live = false;
+        block.values.clear();
+    }
+}
+
+fn field_slot<'v>(fields: &'v mut [(String, Value)], name: &str) -> &'v mut Value {
+    match fields.iter_mut().find(|(field, _)| field == name) {
+        Some((_, value)) => value,
+        None => panic!("Struct has no field {}", name),
+    }
+}
+
+// Applies the implicit conversions C performs when storing into a typed slot
+fn convert(value: Value, data_type: &DataType) -> Value {
+    match (value, data_type) {
+        (Value::Integer(value), DataType::Integer | DataType::Sized(_)) => {
+            Value::Integer(data_type.wrap_integer(value))
+        }
+        (Value::Integer(value), DataType::Float) => Value::Float(value as f32),
+        (Value::Integer(value), DataType::Boolean) => Value::Boolean(value != 0),
+        // Like Rust, floats saturate at the bounds of the type and NaN becomes 0
+        (Value::Float(value), DataType::Integer | DataType::Sized(_)) => {
+            let (min, max) = data_type.integer_range().unwrap();
+            Value::Integer((value as i128).clamp(min, max))
+        }
+        (Value::Float(value), DataType::Boolean) => Value::Boolean(value != 0.0),
+        (Value::Boolean(value), DataType::Integer | DataType::Sized(_)) => {
+            Value::Integer(value as i128)
+        }
+        (Value::Boolean(value), DataType::Float) => Value::Float(value as i32 as f32),
+        (value, _) => value,
+    }
+}
+
+// Integer results wrap around like they do in C
+fn wrap(value: Value, data_type: &Option<DataType>) -> Value {
+    match data_type {
+        Some(data_type) if data_type.integer_layout().is_some() => convert(value, data_type),
+        _ => value,
+    }
+}
+
+fn coerce_to(value: Value, like: &Value) -> Value {
+    match like {
+        // The slot may hold any integer type, the checker only lets values that fit into it
+        Value::Integer(_) if matches!(value, Value::Integer(_)) => value,
+        Value::Integer(_) => convert(value, &DataType::Integer),
+        Value::Float(_) => convert(value, &DataType::Float),
+        Value::Boolean(_) => convert(value, &DataType::Boolean),
+        Value::String(_)
+        | Value::Struct(..)
+        | Value::Object(_)
+        | Value::Enum(..)
+        | Value::Pointer(_)
+        | Value::Function(_)
+        | Value::Void => value,
+    }
+}
+
+fn binary_op(node_type: &NodeType, left: Value, right: Value) -> Value {
+    match (left, right) {
+        (Value::Integer(l), Value::Integer(r)) => integer_op(node_type, l, r),
+        (Value::Float(l), Value::Float(r)) => float_op(node_type, l, r),
+        (Value::Integer(l), Value::Float(r)) => float_op(node_type, l as f32, r),
+        (Value::Float(l), Value::Integer(r)) => float_op(node_type, l, r as f32),
+        (Value::Boolean(l), Value::Boolean(r)) => match node_type {
+            NodeType::Eq => Value::Boolean(l == r),
+            NodeType::NotEq => Value::Boolean(l != r),
+            _ => integer_op(node_type, l as i128, r as i128),
+        },
+        (Value::String(l), Value::String(r)) => match node_type {
+            NodeType::Eq => Value::Boolean(l == r),
+            NodeType::NotEq => Value::Boolean(l != r),
+            _ => panic!("Unsupported operator {:?} for strings", node_type),
+        },
+        // Objects are compared by reference
+        (Value::Object(l), Value::Object(r)) => match node_type {
+            NodeType::Eq => Value::Boolean(l == r),
+            NodeType::NotEq => Value::Boolean(l != r),
+            _ => panic!("Unsupported operator {:?} for objects", node_type),
+        },
+        (Value::Enum(_, l), Value::Enum(_, r)) => match node_type {
+            NodeType::Eq => Value::Boolean(l == r),
+            NodeType::NotEq => Value::Boolean(l != r),
+            _ => panic!("Unsupported operator {:?} for enums", node_type),
+        },
+        (Value::Pointer(l), Value::Integer(r)) => pointer_offset(node_type, l, r),
+        (Value::Integer(l), Value::Pointer(r)) => pointer_offset(node_type, r, l),
+        (Value::Pointer(l), Value::Pointer(r)) => pointer_op(node_type, l, r),
+        (l, r) => panic!("Unsupported operands {:?} and {:?}", l, r),
+    }
+}
+
+// Xorshift, the C runtime uses the same generator
+fn next_random(state: u32) -> u32 {
+    let mut x = state;
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    x
+}
+
+// A zero state would only produce zeros
+fn seed_state(seed: i32) -> u32 {
+    match seed as u32 {
+        0 => 1,
+        state => state,
+    }
+}
+
+// Moves a pointer by whole elements of its allocation
+fn pointer_offset(node_type: &NodeType, pointer: Option<Location>, by: i128) -> Value {
+    let mut location = match pointer {
+        Some(location) if location.path.is_empty() => location,
+        Some(_) => panic!("Pointer arithmetic on a pointer to a field"),
+        None => panic!("Pointer arithmetic on null"),
+    };
+
+    location.offset += match node_type {
+        NodeType::Operator(Operator::Add) => by as isize,
+        NodeType::Operator(Operator::Subtract) => -(by as isize),
+        _ => panic!("Unsupported operator {:?} for pointers", node_type),
+    };
+
+    Value::Pointer(Some(location))
+}
+
+fn pointer_op(node_type: &NodeType, l: Option<Location>, r: Option<Location>) -> Value {
+    match node_type {
+        NodeType::Eq => return Value::Boolean(l == r),
+        NodeType::NotEq => return Value::Boolean(l != r),
+        _ => {}
+    }
+
+    // Only pointers into the same allocation can be subtracted or ordered
+    let (l, r) = match (l, r) {
+        (Some(l), Some(r)) if l.block == r.block && l.path == r.path => (l, r),
+        _ => panic!("Pointers into different allocations can not be compared"),
+    };
+
+    match node_type {
+        NodeType::Operator(Operator::Subtract) => Value::Integer((l.offset - r.offset) as i128),
+        _ => compare(node_type, l.offset, r.offset),
+    }
+}
+
+fn integer_op(node_type: &NodeType, l: i128, r: i128) -> Value {
+    match node_type {
+        NodeType::Operator(Operator::Add) => Value::Integer(l.wrapping_add(r)),
+        NodeType::Operator(Operator::Subtract) => Value::Integer(l.wrapping_sub(r)),
+        NodeType::Operator(Operator::Multiply) => Value::Integer(l.wrapping_mul(r)),
+        NodeType::Operator(Operator::Divide) if r == 0 => panic!("Division by zero"),
+        NodeType::Operator(Operator::Divide) => Value::Integer(l.wrapping_div(r)),
+        NodeType::Operator(Operator::Mod) if r == 0 => panic!("Division by zero"),
+        NodeType::Operator(Operator::Mod) => Value::Integer(l.wrapping_rem(r)),
+        NodeType::BitwiseOp(BitwiseOp::And) => Value::Integer(l & r),
+        NodeType::BitwiseOp(BitwiseOp::Or) => Value::Integer(l | r),
+        NodeType::BitwiseOp(BitwiseOp::Xor) => Value::Integer(l ^ r),
+        _ => compare(node_type, l, r),
+    }
+}
+
+fn float_op(node_type: &NodeType, l: f32, r: f32) -> Value {
+    match node_type {
+        NodeType::Operator(Operator::Add) => Value::Float(l + r),
+        NodeType::Operator(Operator::Subtract) => Value::Float(l - r),
+        NodeType::Operator(Operator::Multiply) => Value::Float(l * r),
+        NodeType::Operator(Operator::Divide) => Value::Float(l / r),
+        NodeType::Operator(Operator::Mod) => Value::Float(l % r),
+        _ => compare(node_type, l, r),
+    }
+}
+
+fn compare<T: PartialOrd>(node_type: &NodeType, l: T, r: T) -> Value {
+    Value::Boolean(match node_type {
+        NodeType::Eq => l == r,
+        NodeType::NotEq => l != r,
+        NodeType::LessThan => l < r,
+        NodeType::GreaterThan => l > r,
+        NodeType::Leq => l <= r,
+        NodeType::Geq => l >= r,
+        _ => panic!("Unsupported operator {:?}", node_type),
+    })
+}
@@ -0,0 +1,1497 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::ast::*;
+use crate::builtins::Builtin;
+use crate::checker::common_type;
+use crate::compiler::compile_string;
+use crate::types::*;
+
+// Typed three address code lowered from the checked AST. A function is a list of basic
+// blocks, each ending in an explicit jump, branch or return, and the entry block comes first.
+// Temporaries are virtual registers with a type, variables are temporaries that can be
+// assigned more than once, so the IR is not in SSA form. Scalars, strings, functions and
+// control flow are supported, the rest panics, can_lower tells if a program only uses them
+pub type Temp = usize;
+pub type BlockId = usize;
+
+pub struct Program {
+    pub globals: Vec<Global>,
+    pub externs: Vec<Extern>,
+    // The top level code is the function main, which comes last
+    pub functions: Vec<Function>,
+}
+
+// Constants hold their folded value, other globals start out with a zero value and main
+// assigns their initializer
+pub struct Global {
+    pub name: String,
+    pub data_type: DataType,
+    pub value: Constant,
+    pub constant: bool,
+}
+
+pub struct Extern {
+    pub name: String,
+    pub params: Vec<DataType>,
+    pub return_type: DataType,
+    // The C header that declares the function, if the program names one
+    pub header: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<Temp>,
+    pub return_type: DataType,
+    // Type of every temporary, and the variable it holds if any
+    pub temps: Vec<DataType>,
+    pub names: HashMap<Temp, String>,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Clone)]
+pub struct Block {
+    pub instructions: Vec<Instruction>,
+    pub terminator: Terminator,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Integer(i128, DataType),
+    Float(f32),
+    Boolean(bool),
+    String(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Temp(Temp),
+    Constant(Constant),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Mod,
+    And,
+    Or,
+    Xor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Not,
+    BitwiseNot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Eq,
+    NotEq,
+    LessThan,
+    GreaterThan,
+    Leq,
+    Geq,
+}
+
+// Operations compute in the type of their destination and integers wrap around in it.
+// Comparisons compare operands of the same type, conversions go from the type of the
+// operand to the type of the destination
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Copy {
+        dest: Temp,
+        src: Operand,
+    },
+    Binary {
+        dest: Temp,
+        op: BinaryOp,
+        left: Operand,
+        right: Operand,
+    },
+    Unary {
+        dest: Temp,
+        op: UnaryOp,
+        src: Operand,
+    },
+    Compare {
+        dest: Temp,
+        op: Comparison,
+        left: Operand,
+        right: Operand,
+    },
+    Convert {
+        dest: Temp,
+        src: Operand,
+    },
+    Call {
+        dest: Option<Temp>,
+        function: String,
+        args: Vec<Operand>,
+    },
+    Builtin {
+        dest: Option<Temp>,
+        builtin: Builtin,
+        args: Vec<Operand>,
+    },
+    LoadGlobal {
+        dest: Temp,
+        name: String,
+    },
+    StoreGlobal {
+        name: String,
+        src: Operand,
+    },
+    // Source line of the statement that follows, only in programs read from a file
+    Line(Span),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch {
+        condition: Operand,
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    Return(Option<Operand>),
+}
+
+impl Constant {
+    pub fn data_type(&self) -> DataType {
+        match self {
+            Constant::Integer(_, data_type) => data_type.clone(),
+            Constant::Float(_) => DataType::Float,
+            Constant::Boolean(_) => DataType::Boolean,
+            Constant::String(_) => DataType::String,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Constant::Integer(value, _) => *value == 0,
+            Constant::Float(value) => *value == 0.0,
+            _ => false,
+        }
+    }
+
+    pub fn zero(data_type: &DataType) -> Constant {
+        match data_type {
+            DataType::Integer | DataType::Sized(_) => Constant::Integer(0, data_type.clone()),
+            DataType::Float => Constant::Float(0.0),
+            DataType::Boolean => Constant::Boolean(false),
+            DataType::String => Constant::String(String::new()),
+            data_type => panic!("The IR does not support values of type {}", data_type),
+        }
+    }
+
+    // Converts with the semantics of the interpreter, conversions from floats are left to
+    // the backends
+    pub fn convert(&self, to: &DataType) -> Option<Constant> {
+        match (self, to) {
+            (value, to) if value.data_type() == *to => Some(value.clone()),
+            (Constant::Integer(value, _), DataType::Float) => Some(Constant::Float(*value as f32)),
+            (Constant::Integer(value, _), DataType::Boolean) => {
+                Some(Constant::Boolean(*value != 0))
+            }
+            (Constant::Integer(value, _), DataType::Integer | DataType::Sized(_)) => {
+                Some(Constant::Integer(to.wrap_integer(*value), to.clone()))
+            }
+            (Constant::Boolean(value), DataType::Integer | DataType::Sized(_)) => {
+                Some(Constant::Integer(*value as i128, to.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Instruction {
+    pub fn dest(&self) -> Option<Temp> {
+        match self {
+            Instruction::Copy { dest, .. }
+            | Instruction::Binary { dest, .. }
+            | Instruction::Unary { dest, .. }
+            | Instruction::Compare { dest, .. }
+            | Instruction::Convert { dest, .. }
+            | Instruction::LoadGlobal { dest, .. } => Some(*dest),
+            Instruction::Call { dest, .. } | Instruction::Builtin { dest, .. } => *dest,
+            Instruction::StoreGlobal { .. } | Instruction::Line(_) => None,
+        }
+    }
+
+    pub fn dest_mut(&mut self) -> Option<&mut Temp> {
+        match self {
+            Instruction::Copy { dest, .. }
+            | Instruction::Binary { dest, .. }
+            | Instruction::Unary { dest, .. }
+            | Instruction::Compare { dest, .. }
+            | Instruction::Convert { dest, .. }
+            | Instruction::LoadGlobal { dest, .. } => Some(dest),
+            Instruction::Call { dest, .. } | Instruction::Builtin { dest, .. } => dest.as_mut(),
+            Instruction::StoreGlobal { .. } | Instruction::Line(_) => None,
+        }
+    }
+
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Instruction::Copy { src, .. }
+            | Instruction::Unary { src, .. }
+            | Instruction::Convert { src, .. }
+            | Instruction::StoreGlobal { src, .. } => vec![src],
+            Instruction::Binary { left, right, .. } | Instruction::Compare { left, right, .. } => {
+                vec![left, right]
+            }
+            Instruction::Call { args, .. } | Instruction::Builtin { args, .. } => {
+                args.iter().collect()
+            }
+            Instruction::LoadGlobal { .. } | Instruction::Line(_) => vec![],
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Instruction::Copy { src, .. }
+            | Instruction::Unary { src, .. }
+            | Instruction::Convert { src, .. }
+            | Instruction::StoreGlobal { src, .. } => vec![src],
+            Instruction::Binary { left, right, .. } | Instruction::Compare { left, right, .. } => {
+                vec![left, right]
+            }
+            Instruction::Call { args, .. } | Instruction::Builtin { args, .. } => {
+                args.iter_mut().collect()
+            }
+            Instruction::LoadGlobal { .. } | Instruction::Line(_) => vec![],
+        }
+    }
+
+    // Temporaries read by the instruction
+    pub fn uses(&self) -> Vec<Temp> {
+        temps(self.operands())
+    }
+
+    // Whether the instruction can be removed when its result is not used. Calls may have
+    // side effects, division traps when dividing by zero and lines have no result
+    pub fn is_pure(&self) -> bool {
+        match self {
+            Instruction::Binary {
+                op: BinaryOp::Divide | BinaryOp::Mod,
+                right,
+                ..
+            } => matches!(right, Operand::Constant(value) if !value.is_zero()),
+            Instruction::Call { .. }
+            | Instruction::Builtin { .. }
+            | Instruction::StoreGlobal { .. }
+            | Instruction::Line(_) => false,
+            _ => true,
+        }
+    }
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                then, otherwise, ..
+            } => vec![*then, *otherwise],
+            Terminator::Return(_) => vec![],
+        }
+    }
+
+    pub fn successors_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![target],
+            Terminator::Branch {
+                then, otherwise, ..
+            } => vec![then, otherwise],
+            Terminator::Return(_) => vec![],
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Terminator::Branch { condition, .. } => vec![condition],
+            Terminator::Return(Some(value)) => vec![value],
+            _ => vec![],
+        }
+    }
+
+    pub fn uses(&self) -> Vec<Temp> {
+        match self {
+            Terminator::Branch { condition, .. } => temps(vec![condition]),
+            Terminator::Return(Some(value)) => temps(vec![value]),
+            _ => vec![],
+        }
+    }
+}
+
+fn temps(operands: Vec<&Operand>) -> Vec<Temp> {
+    operands
+        .into_iter()
+        .filter_map(|operand| match operand {
+            Operand::Temp(temp) => Some(*temp),
+            Operand::Constant(_) => None,
+        })
+        .collect()
+}
+
+impl Function {
+    pub fn operand_type(&self, operand: &Operand) -> DataType {
+        match operand {
+            Operand::Temp(temp) => self.temps[*temp].clone(),
+            Operand::Constant(constant) => constant.data_type(),
+        }
+    }
+
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![vec![]; self.blocks.len()];
+        for (index, block) in self.blocks.iter().enumerate() {
+            for successor in block.terminator.successors() {
+                if !predecessors[successor].contains(&index) {
+                    predecessors[successor].push(index);
+                }
+            }
+        }
+        predecessors
+    }
+
+    // Temporaries live at the start and at the end of every block
+    pub fn liveness(&self) -> (Vec<HashSet<Temp>>, Vec<HashSet<Temp>>) {
+        let mut live_in = vec![HashSet::new(); self.blocks.len()];
+        let mut live_out = vec![HashSet::new(); self.blocks.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, block) in self.blocks.iter().enumerate().rev() {
+                let out = block
+                    .terminator
+                    .successors()
+                    .iter()
+                    .flat_map(|successor| live_in[*successor].iter().copied())
+                    .collect::<HashSet<Temp>>();
+                let mut live = out.clone();
+                live.extend(block.terminator.uses());
+                for instruction in block.instructions.iter().rev() {
+                    if let Some(dest) = instruction.dest() {
+                        live.remove(&dest);
+                    }
+                    live.extend(instruction.uses());
+                }
+                if live != live_in[index] || out != live_out[index] {
+                    live_in[index] = live;
+                    live_out[index] = out;
+                    changed = true;
+                }
+            }
+        }
+        (live_in, live_out)
+    }
+}
+
+pub fn lower(ast: &AstNode) -> Program {
+    let mut lowering = Lowering::default();
+    let mut program = Program {
+        globals: vec![],
+        externs: vec![],
+        functions: vec![],
+    };
+    let mut main = vec![];
+    (lowering.signatures, lowering.globals) = top_level_types(ast);
+
+    for node in &ast.children {
+        match node.node_type {
+            NodeType::FunctionDef => program.functions.push(lowering.function_def(node)),
+            NodeType::ExternDef => {
+                let name = node.value.clone().unwrap();
+                let (params, return_type) = lowering.signatures[&name].clone();
+                program.externs.push(Extern {
+                    name,
+                    params,
+                    return_type,
+                    header: node
+                        .children
+                        .get(2)
+                        .map(|header| header.value.clone().unwrap()),
+                });
+            }
+            // Constants are folded to literals by the checker
+            NodeType::ConstDeclare => {
+                let data_type = node.children[0].data_type();
+                let value = match lowering.value(&node.children[1].children[1]) {
+                    Operand::Constant(constant) => constant,
+                    Operand::Temp(_) => panic!("Unexpected constant that is not a literal"),
+                };
+                program.globals.push(Global {
+                    name: declared_name(node),
+                    data_type: data_type.clone(),
+                    value,
+                    constant: true,
+                });
+            }
+            NodeType::Declare => {
+                let data_type = node.children[0].data_type();
+                program.globals.push(Global {
+                    name: declared_name(node),
+                    data_type: data_type.clone(),
+                    value: Constant::zero(data_type),
+                    constant: false,
+                });
+                if node.children[1].node_type == NodeType::Assign {
+                    main.push((node, &node.children[1]));
+                }
+            }
+            _ => main.push((node, node)),
+        }
+    }
+
+    program.functions.push(
+        lowering.function("main", &[], &DataType::Integer, |lowering| {
+            for (node, statement) in main {
+                lowering.line(node);
+                lowering.statement(statement);
+            }
+        }),
+    );
+    program
+}
+
+// Whether lower supports everything the program uses. It only uses values of the types the
+// IR has, and no node lower panics on
+pub fn can_lower(ast: &AstNode) -> bool {
+    let (signatures, _) = top_level_types(ast);
+    ast.children.iter().all(|node| lowers(node, &signatures))
+}
+
+fn lowers(node: &AstNode, signatures: &HashMap<String, Signature>) -> bool {
+    let supported = match &node.node_type {
+        NodeType::Type(data_type) => has_type(data_type),
+        NodeType::Assign | NodeType::ShortAssign(_) => {
+            node.children[0].node_type == NodeType::Identifier
+        }
+        NodeType::FunctionCall => signatures.contains_key(node.value.as_ref().unwrap()),
+        NodeType::BuiltinCall => match Builtin::from_name(node.value.as_ref().unwrap()) {
+            Some(Builtin::Print) => true,
+            Some(builtin) => builtin.signature().is_some(),
+            None => false,
+        },
+        NodeType::Number
+        | NodeType::Boolean
+        | NodeType::String
+        | NodeType::Identifier
+        | NodeType::LParen
+        | NodeType::Operator(_)
+        | NodeType::BitwiseOp(_)
+        | NodeType::Eq
+        | NodeType::NotEq
+        | NodeType::LessThan
+        | NodeType::GreaterThan
+        | NodeType::Leq
+        | NodeType::Geq
+        | NodeType::And
+        | NodeType::Or
+        | NodeType::Not
+        | NodeType::Cast
+        | NodeType::IfExpression
+        | NodeType::Block
+        | NodeType::If
+        | NodeType::While
+        | NodeType::Break
+        | NodeType::Continue
+        | NodeType::Return
+        | NodeType::Declare
+        | NodeType::ConstDeclare
+        | NodeType::FunctionDef
+        | NodeType::ExternDef
+        | NodeType::Parameters
+        | NodeType::Parameter => true,
+        _ => false,
+    };
+
+    supported
+        && node.data_type.as_ref().is_none_or(has_type)
+        && node.children.iter().all(|child| lowers(child, signatures))
+}
+
+// Void is the type of calls that return nothing
+fn has_type(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Integer
+            | DataType::Sized(_)
+            | DataType::Float
+            | DataType::Boolean
+            | DataType::String
+            | DataType::Void
+    )
+}
+
+struct Loop {
+    label: Option<String>,
+    next: BlockId,
+    end: BlockId,
+}
+
+#[derive(Default)]
+struct Lowering {
+    globals: HashMap<String, DataType>,
+    signatures: HashMap<String, Signature>,
+
+    // State of the function being lowered. Blocks are numbered when they are created and
+    // renumbered in the order they are placed once the function is done
+    return_type: Option<DataType>,
+    temps: Vec<DataType>,
+    names: HashMap<Temp, String>,
+    blocks: Vec<(Vec<Instruction>, Option<Terminator>)>,
+    order: Vec<BlockId>,
+    current: BlockId,
+    scopes: Vec<HashMap<String, Temp>>,
+    loops: Vec<Loop>,
+}
+
+impl Lowering {
+    fn function_def(&mut self, node: &AstNode) -> Function {
+        let params = node.children[0]
+            .children
+            .iter()
+            .map(|param| {
+                (
+                    param.children[1].value.clone().unwrap(),
+                    param.children[0].data_type().clone(),
+                )
+            })
+            .collect::<Vec<(String, DataType)>>();
+
+        self.function(
+            node.value.as_ref().unwrap(),
+            &params,
+            node.children[1].data_type(),
+            |lowering| lowering.statement(&node.children[2]),
+        )
+    }
+
+    fn function(
+        &mut self,
+        name: &str,
+        params: &[(String, DataType)],
+        return_type: &DataType,
+        body: impl FnOnce(&mut Lowering),
+    ) -> Function {
+        self.return_type = Some(return_type.clone());
+        self.temps = vec![];
+        self.names = HashMap::new();
+        self.blocks = vec![];
+        self.order = vec![];
+        self.scopes = vec![HashMap::new()];
+
+        let entry = self.new_block();
+        self.start_block(entry);
+        let params = params
+            .iter()
+            .map(|(param, data_type)| self.variable(param, data_type))
+            .collect();
+
+        body(self);
+
+        // Falling off the end returns a zero value
+        if self.blocks[self.current].1.is_none() {
+            let value = match return_type {
+                DataType::Void => None,
+                data_type => Some(Operand::Constant(Constant::zero(data_type))),
+            };
+            self.terminate(Terminator::Return(value));
+        }
+
+        let numbers = self
+            .order
+            .iter()
+            .enumerate()
+            .map(|(number, block)| (*block, number))
+            .collect::<HashMap<BlockId, BlockId>>();
+        let mut blocks = std::mem::take(&mut self.blocks)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<Option<(Vec<Instruction>, Option<Terminator>)>>>();
+        let blocks = self
+            .order
+            .iter()
+            .map(|block| {
+                let (instructions, terminator) = blocks[*block].take().unwrap();
+                let terminator = match terminator.unwrap() {
+                    Terminator::Jump(target) => Terminator::Jump(numbers[&target]),
+                    Terminator::Branch {
+                        condition,
+                        then,
+                        otherwise,
+                    } => Terminator::Branch {
+                        condition,
+                        then: numbers[&then],
+                        otherwise: numbers[&otherwise],
+                    },
+                    terminator => terminator,
+                };
+                Block {
+                    instructions,
+                    terminator,
+                }
+            })
+            .collect();
+
+        Function {
+            name: name.to_string(),
+            params,
+            return_type: return_type.clone(),
+            temps: std::mem::take(&mut self.temps),
+            names: std::mem::take(&mut self.names),
+            blocks,
+        }
+    }
+
+    fn temp(&mut self, data_type: &DataType) -> Temp {
+        if let DataType::Void = data_type {
+            panic!("Unexpected temporary without a value");
+        }
+        Constant::zero(data_type);
+        self.temps.push(data_type.clone());
+        self.temps.len() - 1
+    }
+
+    fn variable(&mut self, name: &str, data_type: &DataType) -> Temp {
+        let temp = self.temp(data_type);
+        self.names.insert(temp, name.to_string());
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), temp);
+        temp
+    }
+
+    fn operand_type(&self, operand: &Operand) -> DataType {
+        match operand {
+            Operand::Temp(temp) => self.temps[*temp].clone(),
+            Operand::Constant(constant) => constant.data_type(),
+        }
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push((vec![], None));
+        self.blocks.len() - 1
+    }
+
+    // Falls through into the block when the current one is not terminated yet
+    fn start_block(&mut self, block: BlockId) {
+        if !self.order.is_empty() && self.blocks[self.current].1.is_none() {
+            self.terminate(Terminator::Jump(block));
+        }
+        self.order.push(block);
+        self.current = block;
+    }
+
+    // Code after a return or jump is unreachable, but still needs a block
+    fn emit(&mut self, instruction: Instruction) {
+        if self.blocks[self.current].1.is_some() {
+            let block = self.new_block();
+            self.start_block(block);
+        }
+        self.blocks[self.current].0.push(instruction);
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        if self.blocks[self.current].1.is_some() {
+            let block = self.new_block();
+            self.start_block(block);
+        }
+        self.blocks[self.current].1 = Some(terminator);
+    }
+
+    // The C backend turns lines into #line directives, like it does for the AST
+    fn line(&mut self, node: &AstNode) {
+        if let Some(span @ Span { file: Some(_), .. }) = &node.span {
+            self.emit(Instruction::Line(span.clone()));
+        }
+    }
+
+    fn branch(&mut self, condition: &AstNode, then: BlockId, otherwise: BlockId) {
+        let condition = self.value_as(condition, &DataType::Boolean);
+        self.terminate(Terminator::Branch {
+            condition,
+            then,
+            otherwise,
+        });
+    }
+
+    fn lookup(&self, name: &str) -> Option<Temp> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .copied()
+    }
+
+    // ------------------------------ Statements ------------------------------
+    fn statement(&mut self, node: &AstNode) {
+        match &node.node_type {
+            NodeType::Declare => {
+                let data_type = node.children[0].data_type();
+                let target = &node.children[1];
+
+                // Lowered before the variable enters the scope, so it reads a variable it shadows
+                let (name, value) = match target.node_type {
+                    NodeType::Assign => (
+                        target.children[0].value.clone().unwrap(),
+                        self.value_as(&target.children[1], data_type),
+                    ),
+                    _ => (
+                        target.value.clone().unwrap(),
+                        Operand::Constant(Constant::zero(data_type)),
+                    ),
+                };
+                let dest = self.variable(&name, data_type);
+                self.emit(Instruction::Copy { dest, src: value });
+            }
+            NodeType::Assign => {
+                let name = self.target(&node.children[0]);
+                match self.lookup(&name) {
+                    Some(dest) => {
+                        let src = self.value_as(&node.children[1], &self.temps[dest].clone());
+                        self.emit(Instruction::Copy { dest, src });
+                    }
+                    None => {
+                        let src = self.value_as(&node.children[1], &self.global(&name));
+                        self.emit(Instruction::StoreGlobal { name, src });
+                    }
+                }
+            }
+            NodeType::ShortAssign(op) => {
+                let name = self.target(&node.children[0]);
+                let op = binary_op(op);
+                match self.lookup(&name) {
+                    Some(dest) => {
+                        let right = self.value_as(&node.children[1], &self.temps[dest].clone());
+                        self.emit(Instruction::Binary {
+                            dest,
+                            op,
+                            left: Operand::Temp(dest),
+                            right,
+                        });
+                    }
+                    None => {
+                        let data_type = self.global(&name);
+                        let current = self.temp(&data_type);
+                        self.emit(Instruction::LoadGlobal {
+                            dest: current,
+                            name: name.clone(),
+                        });
+                        let right = self.value_as(&node.children[1], &data_type);
+                        let dest = self.temp(&data_type);
+                        self.emit(Instruction::Binary {
+                            dest,
+                            op,
+                            left: Operand::Temp(current),
+                            right,
+                        });
+                        self.emit(Instruction::StoreGlobal {
+                            name,
+                            src: Operand::Temp(dest),
+                        });
+                    }
+                }
+            }
+            NodeType::Block => {
+                self.scopes.push(HashMap::new());
+                for child in &node.children {
+                    self.line(child);
+                    self.statement(child);
+                }
+                self.scopes.pop();
+            }
+            NodeType::If => {
+                let then = self.new_block();
+                let otherwise = match node.children.len() {
+                    3 => Some(self.new_block()),
+                    _ => None,
+                };
+                let end = self.new_block();
+
+                self.branch(&node.children[0], then, otherwise.unwrap_or(end));
+                self.start_block(then);
+                self.statement(&node.children[1]);
+                if let Some(otherwise) = otherwise {
+                    self.terminate(Terminator::Jump(end));
+                    self.start_block(otherwise);
+                    self.statement(&node.children[2]);
+                }
+                self.start_block(end);
+            }
+            NodeType::While => {
+                let condition = self.new_block();
+                let body = self.new_block();
+                let end = self.new_block();
+
+                self.start_block(condition);
+                self.branch(&node.children[0], body, end);
+                self.start_block(body);
+                self.loops.push(Loop {
+                    label: node.value.clone(),
+                    next: condition,
+                    end,
+                });
+                self.statement(&node.children[1]);
+                self.loops.pop();
+                self.terminate(Terminator::Jump(condition));
+                self.start_block(end);
+            }
+            NodeType::Break | NodeType::Continue => {
+                let target = match &node.value {
+                    Some(label) => self
+                        .loops
+                        .iter()
+                        .rev()
+                        .find(|target| target.label.as_ref() == Some(label)),
+                    None => self.loops.last(),
+                };
+                let target = match target {
+                    Some(target) if node.node_type == NodeType::Break => target.end,
+                    Some(target) => target.next,
+                    None => panic!("Unexpected {:?} outside of a loop", node.node_type),
+                };
+                self.terminate(Terminator::Jump(target));
+            }
+            NodeType::Return => {
+                let return_type = self.return_type.clone().unwrap();
+                let value = match return_type {
+                    DataType::Void => {
+                        self.value(&node.children[0]);
+                        None
+                    }
+                    return_type => Some(self.value_as(&node.children[0], &return_type)),
+                };
+                self.terminate(Terminator::Return(value));
+            }
+            _ => {
+                self.value(node);
+            }
+        }
+    }
+
+    fn target(&self, node: &AstNode) -> String {
+        match node.node_type {
+            NodeType::Identifier => node.value.clone().unwrap(),
+            _ => panic!("The IR can only assign to variables"),
+        }
+    }
+
+    fn global(&self, name: &str) -> DataType {
+        match self.globals.get(name) {
+            Some(data_type) => data_type.clone(),
+            None => panic!("Use of undeclared variable {}", name),
+        }
+    }
+
+    // ------------------------------ Expressions ------------------------------
+    fn value(&mut self, node: &AstNode) -> Operand {
+        let data_type = node.data_type.clone().unwrap_or(DataType::Void);
+        match &node.node_type {
+            NodeType::Number => {
+                let value = node.value.as_ref().unwrap();
+                Operand::Constant(match data_type {
+                    DataType::Float => Constant::Float(value.parse().unwrap()),
+                    data_type => Constant::Integer(value.parse().unwrap(), data_type),
+                })
+            }
+            NodeType::Boolean => {
+                Operand::Constant(Constant::Boolean(node.value.as_deref() == Some("true")))
+            }
+            NodeType::String => Operand::Constant(Constant::String(node.value.clone().unwrap())),
+            NodeType::Identifier => {
+                let name = node.value.clone().unwrap();
+                if let Some(temp) = self.lookup(&name) {
+                    return Operand::Temp(temp);
+                }
+                let dest = self.temp(&self.global(&name));
+                self.emit(Instruction::LoadGlobal { dest, name });
+                Operand::Temp(dest)
+            }
+            NodeType::LParen => self.value(&node.children[0]),
+            NodeType::Operator(op) => self.binary(node, binary_op(op), &data_type),
+            NodeType::BitwiseOp(BitwiseOp::Not) => {
+                let src = self.value_as(&node.children[0], &data_type);
+                let dest = self.temp(&data_type);
+                self.emit(Instruction::Unary {
+                    dest,
+                    op: UnaryOp::BitwiseNot,
+                    src,
+                });
+                Operand::Temp(dest)
+            }
+            NodeType::BitwiseOp(op) => {
+                let op = match op {
+                    BitwiseOp::And => BinaryOp::And,
+                    BitwiseOp::Or => BinaryOp::Or,
+                    BitwiseOp::Xor => BinaryOp::Xor,
+                    BitwiseOp::Not => unreachable!(),
+                };
+                self.binary(node, op, &data_type)
+            }
+            NodeType::Eq
+            | NodeType::NotEq
+            | NodeType::LessThan
+            | NodeType::GreaterThan
+            | NodeType::Leq
+            | NodeType::Geq => self.comparison(node),
+            NodeType::And | NodeType::Or => self.logical(node),
+            NodeType::Not => {
+                let src = self.value_as(&node.children[0], &DataType::Boolean);
+                let dest = self.temp(&DataType::Boolean);
+                self.emit(Instruction::Unary {
+                    dest,
+                    op: UnaryOp::Not,
+                    src,
+                });
+                Operand::Temp(dest)
+            }
+            NodeType::Cast => self.value_as(&node.children[0], node.children[1].data_type()),
+            NodeType::IfExpression => {
+                let dest = self.temp(&data_type);
+                let then = self.new_block();
+                let otherwise = self.new_block();
+                let end = self.new_block();
+
+                self.branch(&node.children[0], then, otherwise);
+                for (block, branch) in [(then, &node.children[1]), (otherwise, &node.children[2])] {
+                    self.start_block(block);
+                    let src = self.branch_value(branch, &data_type);
+                    self.emit(Instruction::Copy { dest, src });
+                    self.terminate(Terminator::Jump(end));
+                }
+                self.start_block(end);
+                Operand::Temp(dest)
+            }
+            NodeType::FunctionCall => self.call(node),
+            NodeType::BuiltinCall => self.builtin_call(node),
+            node_type => panic!("The IR does not support {:?}", node_type),
+        }
+    }
+
+    fn value_as(&mut self, node: &AstNode, data_type: &DataType) -> Operand {
+        let value = self.value(node);
+        self.convert(value, data_type)
+    }
+
+    // Converts with the semantics of the interpreter, constants are converted right away
+    fn convert(&mut self, operand: Operand, to: &DataType) -> Operand {
+        let from = self.operand_type(&operand);
+        if from == *to {
+            return operand;
+        }
+
+        if let Operand::Constant(value) = &operand {
+            if let Some(constant) = value.convert(to) {
+                return Operand::Constant(constant);
+            }
+        }
+
+        let dest = self.temp(to);
+        self.emit(Instruction::Convert { dest, src: operand });
+        Operand::Temp(dest)
+    }
+
+    fn binary(&mut self, node: &AstNode, op: BinaryOp, data_type: &DataType) -> Operand {
+        let left = self.value_as(&node.children[0], data_type);
+        let right = self.value_as(&node.children[1], data_type);
+        let dest = self.temp(data_type);
+        self.emit(Instruction::Binary {
+            dest,
+            op,
+            left,
+            right,
+        });
+        Operand::Temp(dest)
+    }
+
+    fn comparison(&mut self, node: &AstNode) -> Operand {
+        let left = self.value(&node.children[0]);
+        let right = self.value(&node.children[1]);
+        let (left_type, right_type) = (self.operand_type(&left), self.operand_type(&right));
+        let data_type = match (&left_type, &right_type) {
+            (DataType::String, DataType::String) => DataType::String,
+            _ => match common_type(&left_type, &right_type) {
+                Some(data_type) => data_type,
+                None => panic!("The IR can not compare {} and {}", left_type, right_type),
+            },
+        };
+        let left = self.convert(left, &data_type);
+        let right = self.convert(right, &data_type);
+
+        let op = match node.node_type {
+            NodeType::Eq => Comparison::Eq,
+            NodeType::NotEq => Comparison::NotEq,
+            NodeType::LessThan => Comparison::LessThan,
+            NodeType::GreaterThan => Comparison::GreaterThan,
+            NodeType::Leq => Comparison::Leq,
+            _ => Comparison::Geq,
+        };
+        let dest = self.temp(&DataType::Boolean);
+        self.emit(Instruction::Compare {
+            dest,
+            op,
+            left,
+            right,
+        });
+        Operand::Temp(dest)
+    }
+
+    // And and or only evaluate their right side when the left side does not decide
+    fn logical(&mut self, node: &AstNode) -> Operand {
+        let dest = self.temp(&DataType::Boolean);
+        let right = self.new_block();
+        let end = self.new_block();
+
+        let src = self.value_as(&node.children[0], &DataType::Boolean);
+        self.emit(Instruction::Copy { dest, src });
+        let (then, otherwise) = match node.node_type {
+            NodeType::And => (right, end),
+            _ => (end, right),
+        };
+        self.terminate(Terminator::Branch {
+            condition: Operand::Temp(dest),
+            then,
+            otherwise,
+        });
+
+        self.start_block(right);
+        let src = self.value_as(&node.children[1], &DataType::Boolean);
+        self.emit(Instruction::Copy { dest, src });
+        self.start_block(end);
+        Operand::Temp(dest)
+    }
+
+    // Blocks produce the value of their last child
+    fn branch_value(&mut self, node: &AstNode, data_type: &DataType) -> Operand {
+        if node.node_type != NodeType::Block {
+            return self.value_as(node, data_type);
+        }
+
+        self.scopes.push(HashMap::new());
+        let (value, statements) = node.children.split_last().unwrap();
+        for statement in statements {
+            self.line(statement);
+            self.statement(statement);
+        }
+        let value = self.value_as(value, data_type);
+        self.scopes.pop();
+        value
+    }
+
+    fn call(&mut self, node: &AstNode) -> Operand {
+        let name = node.value.clone().unwrap();
+        let (params, return_type) = match self.signatures.get(&name) {
+            Some(signature) => signature.clone(),
+            None => panic!("The IR does not support calling {}", name),
+        };
+
+        let args = node
+            .children
+            .iter()
+            .zip(&params)
+            .map(|(arg, param)| self.value_as(arg, param))
+            .collect();
+        let dest = match return_type {
+            DataType::Void => None,
+            ref data_type => Some(self.temp(data_type)),
+        };
+        self.emit(Instruction::Call {
+            dest,
+            function: name,
+            args,
+        });
+        result(dest)
+    }
+
+    fn builtin_call(&mut self, node: &AstNode) -> Operand {
+        let builtin = Builtin::from_name(node.value.as_ref().unwrap()).unwrap();
+        let signature = match node.data_type {
+            Some(DataType::Float) => builtin.float_signature(),
+            _ => builtin.signature(),
+        };
+        let (args, return_type) = match signature {
+            Some((params, return_type)) => (
+                node.children
+                    .iter()
+                    .zip(&params)
+                    .map(|(arg, param)| self.value_as(arg, param))
+                    .collect(),
+                return_type,
+            ),
+            None if builtin == Builtin::Print => (
+                node.children.iter().map(|arg| self.value(arg)).collect(),
+                None,
+            ),
+            None => panic!("The IR does not support the builtin {}", builtin.name()),
+        };
+
+        let dest = return_type.map(|data_type| self.temp(&data_type));
+        self.emit(Instruction::Builtin {
+            dest,
+            builtin,
+            args,
+        });
+        result(dest)
+    }
+}
+
+// The result of a call that returns nothing is never used
+fn result(dest: Option<Temp>) -> Operand {
+    match dest {
+        Some(dest) => Operand::Temp(dest),
+        None => Operand::Constant(Constant::Boolean(false)),
+    }
+}
+
+fn binary_op(op: &Operator) -> BinaryOp {
+    match op {
+        Operator::Add => BinaryOp::Add,
+        Operator::Subtract => BinaryOp::Subtract,
+        Operator::Multiply => BinaryOp::Multiply,
+        Operator::Divide => BinaryOp::Divide,
+        Operator::Mod => BinaryOp::Mod,
+    }
+}
+
+fn declared_name(node: &AstNode) -> String {
+    let target = &node.children[1];
+    match target.node_type {
+        NodeType::Assign => target.children[0].value.clone().unwrap(),
+        _ => target.value.clone().unwrap(),
+    }
+}
+
+// ------------------------------ Dump ------------------------------
+// A textual form for debugging and tests. Variables are shown by name, numbered apart when
+// several share one, and the other temporaries by number
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for global in &self.globals {
+            writeln!(
+                f,
+                "{} @{}: {} = {}",
+                if global.constant { "const" } else { "global" },
+                global.name,
+                global.data_type,
+                constant(&global.value)
+            )?;
+        }
+        for def in &self.externs {
+            let params = def
+                .params
+                .iter()
+                .map(|param| param.to_string())
+                .collect::<Vec<String>>();
+            writeln!(
+                f,
+                "extern {}({}) {}",
+                def.name,
+                params.join(", "),
+                def.return_type
+            )?;
+        }
+
+        for (index, function) in self.functions.iter().enumerate() {
+            if index > 0 || !self.globals.is_empty() || !self.externs.is_empty() {
+                writeln!(f)?;
+            }
+            write!(f, "{}", function)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut counts = HashMap::new();
+        let mut anonymous = 0;
+        let names = (0..self.temps.len())
+            .map(|temp| match self.names.get(&temp) {
+                Some(name) => {
+                    let count = counts.entry(name).or_insert(0);
+                    *count += 1;
+                    match *count {
+                        1 => format!("%{}", name),
+                        count => format!("%{}.{}", name, count - 1),
+                    }
+                }
+                None => {
+                    anonymous += 1;
+                    format!("%{}", anonymous - 1)
+                }
+            })
+            .collect::<Vec<String>>();
+        let operand = |operand: &Operand| match operand {
+            Operand::Temp(temp) => names[*temp].clone(),
+            Operand::Constant(value) => constant(value),
+        };
+        let operands = |operands: &[Operand]| {
+            operands
+                .iter()
+                .map(operand)
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        let params = self
+            .params
+            .iter()
+            .map(|param| format!("{} {}", self.temps[*param], names[*param]))
+            .collect::<Vec<String>>();
+        writeln!(
+            f,
+            "fn {}({}) {} {{",
+            self.name,
+            params.join(", "),
+            self.return_type
+        )?;
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "bb{}:", index)?;
+            for instruction in &block.instructions {
+                let text = match instruction {
+                    Instruction::Copy { dest, src } => format!(
+                        "{} = copy {} {}",
+                        names[*dest],
+                        self.temps[*dest],
+                        operand(src)
+                    ),
+                    Instruction::Binary {
+                        dest,
+                        op,
+                        left,
+                        right,
+                    } => format!(
+                        "{} = {} {} {}, {}",
+                        names[*dest],
+                        format!("{:?}", op).to_lowercase(),
+                        self.temps[*dest],
+                        operand(left),
+                        operand(right)
+                    ),
+                    Instruction::Unary { dest, op, src } => format!(
+                        "{} = {} {} {}",
+                        names[*dest],
+                        match op {
+                            UnaryOp::Not => "not",
+                            UnaryOp::BitwiseNot => "bitnot",
+                        },
+                        self.temps[*dest],
+                        operand(src)
+                    ),
+                    Instruction::Compare {
+                        dest,
+                        op,
+                        left,
+                        right,
+                    } => format!(
+                        "{} = {} {} {}, {}",
+                        names[*dest],
+                        match op {
+                            Comparison::Eq => "eq",
+                            Comparison::NotEq => "ne",
+                            Comparison::LessThan => "lt",
+                            Comparison::GreaterThan => "gt",
+                            Comparison::Leq => "le",
+                            Comparison::Geq => "ge",
+                        },
+                        self.operand_type(left),
+                        operand(left),
+                        operand(right)
+                    ),
+                    Instruction::Convert { dest, src } => format!(
+                        "{} = convert {} {} to {}",
+                        names[*dest],
+                        self.operand_type(src),
+                        operand(src),
+                        self.temps[*dest]
+                    ),
+                    Instruction::Call {
+                        dest,
+                        function,
+                        args,
+                    } => match dest {
+                        Some(dest) => format!(
+                            "{} = call {} {}({})",
+                            names[*dest],
+                            self.temps[*dest],
+                            function,
+                            operands(args)
+                        ),
+                        None => format!("call void {}({})", function, operands(args)),
+                    },
+                    Instruction::Builtin {
+                        dest,
+                        builtin,
+                        args,
+                    } => match dest {
+                        Some(dest) => format!(
+                            "{} = builtin {} {}({})",
+                            names[*dest],
+                            self.temps[*dest],
+                            builtin.name(),
+                            operands(args)
+                        ),
+                        None => format!("builtin void {}({})", builtin.name(), operands(args)),
+                    },
+                    Instruction::LoadGlobal { dest, name } => {
+                        format!("{} = load {} @{}", names[*dest], self.temps[*dest], name)
+                    }
+                    Instruction::StoreGlobal { name, src } => format!(
+                        "store {} {}, @{}",
+                        self.operand_type(src),
+                        operand(src),
+                        name
+                    ),
+                    Instruction::Line(span) => {
+                        format!("line {}:{}", span.file.as_ref().unwrap(), span.line)
+                    }
+                };
+                writeln!(f, "  {}", text)?;
+            }
+
+            let text = match &block.terminator {
+                Terminator::Jump(target) => format!("jmp bb{}", target),
+                Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                } => format!("br {}, bb{}, bb{}", operand(condition), then, otherwise),
+                Terminator::Return(Some(value)) => {
+                    format!("ret {} {}", self.operand_type(value), operand(value))
+                }
+                Terminator::Return(None) => "ret void".to_string(),
+            };
+            writeln!(f, "  {}", text)?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+fn constant(value: &Constant) -> String {
+    match value {
+        Constant::Integer(value, _) => value.to_string(),
+        Constant::Float(value) => format!("{:?}", value),
+        Constant::Boolean(value) => value.to_string(),
+        Constant::String(value) => compile_string(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{self, test_golden};
+
+    fn lower_script(script: &str) -> Program {
+        test::lower_script(script, &[])
+    }
+
+    test_golden!(
+        ir_golden_scalars,
+        |root| lower(root).to_string(),
+        "scalars.tan",
+        "ir/scalars.ir"
+    );
+
+    #[test]
+    fn ir_lowers_function_to_blocks() {
+        let program = lower_script(
+            "fn fib(int n) int { if n < 2 { return n; }; return fib(n - 1) + fib(n - 2); };",
+        );
+        assert_eq!(
+            program.functions[0].to_string(),
+            "fn fib(int %n) int {
+bb0:
+  %0 = lt int %n, 2
+  br %0, bb1, bb2
+bb1:
+  ret int %n
+bb2:
+  %1 = subtract int %n, 1
+  %2 = call int fib(%1)
+  %3 = subtract int %n, 2
+  %4 = call int fib(%3)
+  %5 = add int %2, %4
+  ret int %5
+}
+"
+        );
+    }
+
+    #[test]
+    fn ir_numbers_shadowed_variables_apart() {
+        let program = lower_script("fn f() int { int x = 1; { int x = x + 1; return x; }; };");
+        let dump = program.functions[0].to_string();
+        assert!(dump.contains("  %x = copy int 1\n  %0 = add int %x, 1\n  %x.1 = copy int %0\n"));
+        assert!(dump.ends_with("  ret int %x.1\n}\n"));
+    }
+
+    #[test]
+    fn ir_makes_conversions_explicit() {
+        let program =
+            lower_script("u8 small = 200; i64 wide = small; bool set = wide as bool; float f = 3;");
+        let dump = program.to_string();
+        assert!(dump.contains(
+            "  %0 = load u8 @small\n  %1 = convert u8 %0 to i64\n  store i64 %1, @wide\n"
+        ));
+        assert!(dump.contains("  %3 = convert i64 %2 to bool\n"));
+        assert!(dump.contains("  store float 3.0, @f\n"));
+    }
+
+    #[test]
+    fn ir_short_circuits_logical_operators() {
+        let program = lower_script("fn f(bool a, bool b) bool { return a && b; };");
+        assert_eq!(
+            program.functions[0].blocks[0].terminator,
+            Terminator::Branch {
+                condition: Operand::Temp(2),
+                then: 1,
+                otherwise: 2
+            }
+        );
+        assert_eq!(
+            program.functions[0].blocks[1].instructions,
+            vec![Instruction::Copy {
+                dest: 2,
+                src: Operand::Temp(1)
+            }]
+        );
+    }
+
+    #[test]
+    fn ir_tells_which_programs_it_can_lower() {
+        assert!(can_lower(&test::check_script(include_str!(
+            "../golden/control_flow.tan"
+        ))));
+        for script in [
+            "struct Point { int x; int y; }; Point p = Point { x: 1, y: 2 };",
+            "fn(int) int twice = |x| x * 2; print(twice(1));",
+            "int* p = alloc(1); *p = 2;",
+            "fn f() int { int a = 1; fn() int g = || a; return g(); };",
+        ] {
+            assert!(!can_lower(&test::check_script(script)), "{}", script);
+        }
+    }
+
+    #[test]
+    fn ir_keeps_source_lines_of_files() {
+        let mut root = test::check_script("int a = 1;\nprint(a);");
+        crate::modules::set_file(&mut root, "main.tan");
+        let dump = lower(&root).to_string();
+        assert!(dump.contains("  line main.tan:1\n  store int 1, @a\n  line main.tan:2\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The IR does not support")]
+    fn ir_rejects_unsupported_nodes() {
+        lower_script("struct Point { int x; int y; }; Point p = Point { x: 1, y: 2 };");
+    }
+}
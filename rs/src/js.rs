@@ -0,0 +1,1436 @@
+use std::collections::HashMap;
+
+use crate::ast::*;
+use crate::builtins::Builtin;
+use crate::checker::common_type;
+use crate::compiler::compile_string;
+use crate::signals::SignalGraph;
+use crate::sourcemap::{extract, marker, SourceMap};
+use crate::types::*;
+
+// Floats saturate at the bounds of the integer type and NaN becomes 0, like the interpreter
+const CAST_RUNTIME: &str = r#"function tan_float_to_int(value, min, max) {
+  if (Number.isNaN(value)) {
+    return 0;
+  }
+  return Math.trunc(Math.min(Math.max(value, min), max));
+}
+
+function tan_float_to_bigint(value, min, max) {
+  if (Number.isNaN(value)) {
+    return 0n;
+  }
+  const truncated = Math.trunc(value);
+  if (truncated <= Number(min)) {
+    return min;
+  }
+  if (truncated >= Number(max)) {
+    return max;
+  }
+  return BigInt(truncated);
+}
+"#;
+
+// The shortest digits that give back the float, written without an exponent like the
+// interpreter prints them
+const FLOAT_PRINT_RUNTIME: &str = r#"function tan_float_string(value) {
+  if (Number.isNaN(value)) {
+    return "NaN";
+  }
+  if (!Number.isFinite(value)) {
+    return value < 0 ? "-inf" : "inf";
+  }
+  let scientific = value.toExponential();
+  for (let precision = 0; precision < 9; precision++) {
+    scientific = value.toExponential(precision);
+    if (Math.fround(Number(scientific)) === value) {
+      break;
+    }
+  }
+
+  const [mantissa, exponent] = scientific.replace(/^-/, "").split("e");
+  const digits = mantissa.replace(".", "").replace(/0+$/, "") || "0";
+  const point = Number(exponent) + 1;
+  const sign = value < 0 || Object.is(value, -0) ? "-" : "";
+  if (point <= 0) {
+    return `${sign}0.${"0".repeat(-point)}${digits}`;
+  }
+  if (point >= digits.length) {
+    return `${sign}${digits}${"0".repeat(point - digits.length)}`;
+  }
+  return `${sign}${digits.slice(0, point)}.${digits.slice(point)}`;
+}
+"#;
+
+// Like fminf and fmaxf in C and the interpreter, a NaN argument is ignored
+const FLOAT_MATH_RUNTIME: &str = r#"function tan_fmin(a, b) {
+  return Number.isNaN(a) ? b : Number.isNaN(b) ? a : Math.min(a, b);
+}
+
+function tan_fmax(a, b) {
+  return Number.isNaN(a) ? b : Number.isNaN(b) ? a : Math.max(a, b);
+}
+"#;
+
+const ASSERT_RUNTIME: &str = r#"function tan_assert(condition) {
+  if (!condition) {
+    throw new Error("Assertion failed");
+  }
+}
+"#;
+
+// Node can end the process, a browser can only stop the script
+const EXIT_RUNTIME: &str = r#"function tan_exit(code) {
+  globalThis.process?.exit(code);
+  throw new Error(`Exited with code ${code}`);
+}
+"#;
+
+// Xorshift, the interpreter uses the same generator so a seed gives the same numbers
+const RANDOM_RUNTIME: &str = r#"let tan_random_state = 0;
+
+function tan_seed(seed) {
+  tan_random_state = seed === 0 ? 1 : seed >>> 0;
+}
+
+function tan_random(bound) {
+  if (bound <= 0) {
+    throw new Error(`Random bound must be positive, got ${bound}`);
+  }
+  if (tan_random_state === 0) {
+    tan_seed(Math.floor(Date.now() / 1000) | 0);
+  }
+  let x = tan_random_state;
+  x = (x ^ (x << 13)) >>> 0;
+  x = (x ^ (x >>> 17)) >>> 0;
+  x = (x ^ (x << 5)) >>> 0;
+  tan_random_state = x;
+  return x % bound;
+}
+"#;
+
+// Names that can not be JavaScript identifiers, or that the generated code relies on
+const RESERVED: &[&str] = &[
+    "arguments",
+    "await",
+    "BigInt",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "console",
+    "const",
+    "continue",
+    "Date",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "Error",
+    "eval",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "globalThis",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "Infinity",
+    "instanceof",
+    "interface",
+    "let",
+    "Math",
+    "NaN",
+    "new",
+    "null",
+    "Number",
+    "Object",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "String",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+// The C math functions the interpreter provides, and their JavaScript counterparts
+const MATH_FUNCTIONS: &[(&str, &str)] = &[
+    ("sin", "Math.sin"),
+    ("cos", "Math.cos"),
+    ("tan", "Math.tan"),
+    ("exp", "Math.exp"),
+    ("log", "Math.log"),
+    ("sqrt", "Math.sqrt"),
+    ("floor", "Math.floor"),
+    ("ceil", "Math.ceil"),
+    ("fabs", "Math.abs"),
+    ("pow", "Math.pow"),
+    ("atan2", "Math.atan2"),
+];
+
+// Transpiles to a readable ES module. Structs become classes, enums frozen objects of
+// strings that print like the interpreter prints variants, i64 and u64 are BigInts and
+// the other numbers plain numbers. Integer arithmetic wraps and floats are rounded to
+// single precision after every operation, so numbers keep the semantics of the interpreter.
+// The source map points the statements back to the files they were linked from
+pub fn compile_js(ast: &AstNode) -> (String, SourceMap) {
+    let mut transpiler = Transpiler {
+        signal_graph: SignalGraph::from_ast(ast),
+        ..Transpiler::default()
+    };
+
+    let mut globals = HashMap::new();
+    for node in &ast.children {
+        match node.node_type {
+            NodeType::StructDef => {
+                transpiler
+                    .structs
+                    .insert(node.value.clone().unwrap(), node.clone());
+            }
+            NodeType::EnumDef => {
+                transpiler
+                    .enums
+                    .insert(node.value.clone().unwrap(), node.clone());
+            }
+            NodeType::FunctionDef => {
+                let params = node.children[0]
+                    .children
+                    .iter()
+                    .map(|param| param.children[0].data_type().clone())
+                    .collect();
+                transpiler
+                    .signatures
+                    .insert(node.value.clone().unwrap(), params);
+            }
+            NodeType::SignalDeclare | NodeType::ComputedDeclare => {
+                transpiler.signals.push(node.clone());
+            }
+            NodeType::Declare | NodeType::ConstDeclare => {
+                let name = declared_name(node);
+                globals.insert(name.clone(), js_name(&name));
+            }
+            _ => {}
+        }
+    }
+    transpiler.scopes.push(globals);
+
+    // Classes are not hoisted, so types come first and constants before the code using them
+    let mut definitions = vec![];
+    let mut program = vec![];
+    for node in &ast.children {
+        match node.node_type {
+            NodeType::StructDef => definitions.push(transpiler.struct_def(node)),
+            NodeType::EnumDef => definitions.push(transpiler.enum_def(node)),
+            NodeType::ConstDeclare => definitions.push(format!(
+                "export const {} = {};",
+                js_name(&declared_name(node)),
+                transpiler.expression(&node.children[1].children[1])
+            )),
+            NodeType::FunctionDef => {
+                program.push((marker(node) + &transpiler.function_def(node), true))
+            }
+            // The checker resolves calls of the math functions, which map to Math
+            NodeType::ExternDef => {}
+            _ => program.push((marker(node) + &transpiler.statement(node), false)),
+        }
+    }
+
+    let signal_updates = transpiler
+        .signals
+        .clone()
+        .iter()
+        .filter(|node| node.node_type == NodeType::SignalDeclare)
+        .map(|node| (transpiler.signal_update(node), true))
+        .collect::<Vec<(String, bool)>>();
+    program.extend(signal_updates);
+
+    let mut sections = transpiler.runtimes.clone();
+    if !definitions.is_empty() {
+        sections.push(definitions.join("\n\n") + "\n");
+    }
+
+    // Functions are set apart by blank lines, consecutive statements are not
+    let mut code = String::new();
+    let mut previous_definition = false;
+    for (index, (item, definition)) in program.iter().enumerate() {
+        if index > 0 && (*definition || previous_definition) {
+            code += "\n";
+        }
+        code += item;
+        code += "\n";
+        previous_definition = *definition;
+    }
+    if !code.is_empty() {
+        sections.push(code);
+    }
+
+    extract(&sections.join("\n"))
+}
+
+#[derive(Default)]
+struct Transpiler {
+    structs: HashMap<String, AstNode>,
+    enums: HashMap<String, AstNode>,
+    signatures: HashMap<String, Vec<DataType>>,
+    signals: Vec<AstNode>,
+    signal_graph: SignalGraph,
+    runtimes: Vec<String>,
+
+    // Variables map to their JavaScript name, a declaration gets a new name when it would
+    // shadow a visible variable, since let can not use the variable it shadows
+    scopes: Vec<HashMap<String, String>>,
+    renames: HashMap<String, usize>,
+    return_types: Vec<DataType>,
+}
+
+impl Transpiler {
+    fn runtime(&mut self, runtime: &str) {
+        if !self.runtimes.iter().any(|used| used == runtime) {
+            self.runtimes.push(runtime.to_string());
+        }
+    }
+
+    fn declare(&mut self, name: &str) -> String {
+        let visible = self.scopes.iter().any(|scope| scope.contains_key(name));
+        let js = match visible {
+            true => {
+                let count = self.renames.entry(name.to_string()).or_insert(0);
+                *count += 1;
+                format!("{}_{}", js_name(name), count)
+            }
+            false => js_name(name),
+        };
+
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), js.clone());
+        js
+    }
+
+    fn lookup(&self, name: &str) -> String {
+        match self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            Some(js) => js.clone(),
+            None => js_name(name),
+        }
+    }
+
+    // ------------------------------ Definitions ------------------------------
+    // Structs are values, so they are copied whenever they are read from a variable
+    fn struct_def(&mut self, node: &AstNode) -> String {
+        let name = js_name(node.value.as_ref().unwrap());
+        let fields = node
+            .children
+            .iter()
+            .map(|field| {
+                (
+                    field.children[1].value.clone().unwrap(),
+                    field.children[0].data_type().clone(),
+                )
+            })
+            .collect::<Vec<(String, DataType)>>();
+
+        let params = fields
+            .iter()
+            .map(|(field, _)| js_name(field))
+            .collect::<Vec<String>>();
+        let assignments = fields
+            .iter()
+            .map(|(field, _)| format!("this.{} = {};", field, js_name(field)))
+            .collect::<Vec<String>>();
+        let copies = fields
+            .iter()
+            .map(|(field, data_type)| match data_type {
+                DataType::Struct(_) => format!("this.{}.copy()", field),
+                _ => format!("this.{}", field),
+            })
+            .collect::<Vec<String>>();
+        let text = fields
+            .iter()
+            .map(|(field, data_type)| match data_type {
+                DataType::Float => {
+                    self.runtime(FLOAT_PRINT_RUNTIME);
+                    format!("{}: ${{tan_float_string(this.{})}}", field, field)
+                }
+                _ => format!("{}: ${{this.{}}}", field, field),
+            })
+            .collect::<Vec<String>>();
+
+        let methods = [
+            format!(
+                "constructor({}) {}",
+                params.join(", "),
+                braces(&assignments)
+            ),
+            format!(
+                "copy() {}",
+                braces(&[format!("return new {}({});", name, copies.join(", "))])
+            ),
+            format!(
+                "toString() {}",
+                braces(&[format!(
+                    "return `{} {{ {} }}`;",
+                    node.value.as_ref().unwrap(),
+                    text.join(", ")
+                )])
+            ),
+        ];
+
+        format!(
+            "export class {} {{\n{}}}",
+            name,
+            indent(&methods.join("\n\n"))
+        )
+    }
+
+    fn enum_def(&mut self, node: &AstNode) -> String {
+        let name = node.value.clone().unwrap();
+        let variants = node
+            .children
+            .iter()
+            .map(|variant| {
+                let variant = variant.value.clone().unwrap();
+                format!(
+                    "{}: {},",
+                    variant,
+                    compile_string(&format!("{}::{}", name, variant))
+                )
+            })
+            .collect::<Vec<String>>();
+
+        format!(
+            "export const {} = Object.freeze({{\n{}}});",
+            js_name(&name),
+            indent(&variants.join("\n"))
+        )
+    }
+
+    fn function_def(&mut self, node: &AstNode) -> String {
+        let (params, body) = self.function(
+            &node.children[0],
+            node.children[1].data_type(),
+            &node.children[2],
+        );
+
+        format!(
+            "export function {}({}) {}",
+            js_name(node.value.as_ref().unwrap()),
+            params,
+            body
+        )
+    }
+
+    // Parameters and body of a function or lambda
+    fn function(
+        &mut self,
+        params: &AstNode,
+        return_type: &DataType,
+        body: &AstNode,
+    ) -> (String, String) {
+        let (params, scope) = parameters(params);
+        self.scopes.push(scope);
+        self.return_types.push(return_type.clone());
+        let body = self.block(body);
+
+        self.return_types.pop();
+        self.scopes.pop();
+        (params, body)
+    }
+
+    // Assigning a signal stores the value and recomputes the signals that depend on it
+    fn signal_update(&mut self, node: &AstNode) -> String {
+        let name = node.value.clone().unwrap();
+        let mut statements = vec![format!("signal_{} = value;", name)];
+        for dependent in self.signal_graph.dependents(&name) {
+            let def = self
+                .signals
+                .iter()
+                .find(|def| def.value.as_ref() == Some(&dependent))
+                .unwrap()
+                .clone();
+            statements.push(format!(
+                "signal_{} = {};",
+                dependent,
+                self.value(&def.children[1], def.children[0].data_type())
+            ));
+        }
+
+        format!("function update_{}(value) {}", name, braces(&statements))
+    }
+
+    // ------------------------------ Statements ------------------------------
+    fn block(&mut self, node: &AstNode) -> String {
+        self.scopes.push(HashMap::new());
+        let statements = match node.node_type {
+            NodeType::Block => node
+                .children
+                .iter()
+                .map(|child| marker(child) + &self.statement(child))
+                .collect::<Vec<String>>(),
+            _ => vec![self.statement(node)],
+        };
+        self.scopes.pop();
+        braces(&statements)
+    }
+
+    fn statement(&mut self, node: &AstNode) -> String {
+        match &node.node_type {
+            NodeType::Declare => {
+                let data_type = node.children[0].data_type();
+                let target = &node.children[1];
+                let (name, value) = match target.node_type {
+                    NodeType::Assign => (
+                        target.children[0].value.clone().unwrap(),
+                        self.value(&target.children[1], data_type),
+                    ),
+                    _ => (target.value.clone().unwrap(), self.default_value(data_type)),
+                };
+
+                // Top level variables keep their name so that functions can use them
+                let name = match self.scopes.len() {
+                    1 => self.lookup(&name),
+                    _ => self.declare(&name),
+                };
+                format!("let {} = {};", name, value)
+            }
+            NodeType::Assign => {
+                let target = &node.children[0];
+                let data_type = target.data_type.clone().unwrap();
+                format!(
+                    "{} = {};",
+                    self.expression(target),
+                    self.value(&node.children[1], &data_type)
+                )
+            }
+            NodeType::ShortAssign(op) => self.short_assign(node, op),
+            NodeType::Block => self.block(node),
+            NodeType::If => self.if_statement(node),
+            NodeType::While => {
+                let label = match &node.value {
+                    Some(label) => format!("{}: ", js_name(label)),
+                    None => String::new(),
+                };
+                format!(
+                    "{}while ({}) {}",
+                    label,
+                    self.expression(&node.children[0]),
+                    self.block(&node.children[1])
+                )
+            }
+            NodeType::Break | NodeType::Continue => {
+                let keyword = match node.node_type {
+                    NodeType::Break => "break",
+                    _ => "continue",
+                };
+                match &node.value {
+                    Some(label) => format!("{} {};", keyword, js_name(label)),
+                    None => format!("{};", keyword),
+                }
+            }
+            NodeType::Return => {
+                let return_type = self.return_types.last().unwrap().clone();
+                format!("return {};", self.value(&node.children[0], &return_type))
+            }
+            NodeType::Match => self.match_statement(node),
+            NodeType::SignalDeclare | NodeType::ComputedDeclare => format!(
+                "let signal_{} = {};",
+                node.value.clone().unwrap(),
+                self.value(&node.children[1], node.children[0].data_type())
+            ),
+            _ => format!("{};", self.expression(node)),
+        }
+    }
+
+    // Numbers wrap or round the result of the whole operation, strings keep the short form
+    fn short_assign(&mut self, node: &AstNode, op: &Operator) -> String {
+        let target = &node.children[0];
+        let data_type = target.data_type.clone().unwrap();
+        let place = self.expression(target);
+        let value = self.value(&node.children[1], &data_type);
+
+        if data_type.integer_layout().is_none() && data_type != DataType::Float {
+            return format!("{} {}= {};", place, operator(op), value);
+        }
+
+        format!(
+            "{} = {};",
+            place,
+            arithmetic(op, place.clone(), value, &data_type)
+        )
+    }
+
+    fn if_statement(&mut self, node: &AstNode) -> String {
+        let mut code = format!(
+            "if ({}) {}",
+            self.expression(&node.children[0]),
+            self.block(&node.children[1])
+        );
+
+        match node.children.get(2) {
+            Some(else_ast) if else_ast.node_type == NodeType::If => {
+                code += &format!(" else {}", self.if_statement(else_ast));
+            }
+            Some(else_ast) => code += &format!(" else {}", self.block(else_ast)),
+            None => {}
+        }
+        code
+    }
+
+    // Matches become if chains, the checker guarantees exhaustiveness so the last arm is
+    // the final else. Generated names contain an underscore, which TanScript identifiers can not
+    fn match_statement(&mut self, node: &AstNode) -> String {
+        let subject = &node.children[0];
+        let (subject_name, setup) = match subject.node_type {
+            NodeType::Identifier => (self.expression(subject), None),
+            _ => (
+                "match_value".to_string(),
+                Some(format!("const match_value = {};", self.expression(subject))),
+            ),
+        };
+
+        let (last, arms) = node.children[1..].split_last().unwrap();
+        let mut chain = arms
+            .iter()
+            .map(|arm| {
+                format!(
+                    "if ({}) {}",
+                    self.pattern_test(&subject_name, subject, &arm.children[0]),
+                    self.block(&arm.children[1])
+                )
+            })
+            .collect::<Vec<String>>();
+        chain.push(self.block(&last.children[1]));
+
+        let chain = chain.join(" else ");
+        match setup {
+            Some(setup) => braces(&[setup, chain]),
+            None => chain,
+        }
+    }
+
+    fn pattern_test(&mut self, subject_name: &str, subject: &AstNode, pattern: &AstNode) -> String {
+        let data_type = subject.data_type.clone().unwrap();
+        format!("{} === {}", subject_name, self.value(pattern, &data_type))
+    }
+
+    // ------------------------------ Expressions ------------------------------
+    // An expression converted to the type it is stored as, structs are copied out of places
+    fn value(&mut self, node: &AstNode, to: &DataType) -> String {
+        let from = node.data_type.clone().unwrap_or(DataType::Void);
+        let value = self.expression(node);
+        match to {
+            DataType::Struct(_) if is_place(node) => format!("{}.copy()", value),
+            _ => convert(value, &from, to, self),
+        }
+    }
+
+    // Operand of a member access or call, which binds tighter than any operator
+    fn operand(&mut self, node: &AstNode) -> String {
+        let value = self.expression(node);
+        match node.node_type {
+            NodeType::Identifier
+            | NodeType::String
+            | NodeType::LParen
+            | NodeType::FunctionCall
+            | NodeType::Call
+            | NodeType::FieldAccess
+            | NodeType::Property
+            | NodeType::Index
+            | NodeType::Signal
+            | NodeType::Computed
+            | NodeType::FunctionRef => value,
+            _ => format!("({})", value),
+        }
+    }
+
+    fn expression(&mut self, node: &AstNode) -> String {
+        let data_type = node.data_type.clone().unwrap_or(DataType::Void);
+        match &node.node_type {
+            NodeType::Number => match data_type {
+                DataType::Sized(IntType::I64 | IntType::U64) => {
+                    format!("{}n", node.value.clone().unwrap())
+                }
+                _ => node.value.clone().unwrap(),
+            },
+            NodeType::Boolean => node.value.clone().unwrap(),
+            NodeType::String => compile_string(node.value.as_ref().unwrap()),
+            NodeType::Identifier => self.lookup(node.value.as_ref().unwrap()),
+            NodeType::LParen => format!("({})", self.expression(&node.children[0])),
+            NodeType::Operator(op) => {
+                let left = self.value(&node.children[0], &data_type);
+                let right = self.value(&node.children[1], &data_type);
+                arithmetic(op, left, right, &data_type)
+            }
+            NodeType::Eq
+            | NodeType::NotEq
+            | NodeType::LessThan
+            | NodeType::GreaterThan
+            | NodeType::Leq
+            | NodeType::Geq => {
+                let (left, right) = (&node.children[0], &node.children[1]);
+                let operand_type = match (&left.data_type, &right.data_type) {
+                    (Some(left), Some(right)) => common_type(left, right).unwrap_or(left.clone()),
+                    _ => DataType::Void,
+                };
+                let comparison = match node.node_type {
+                    NodeType::Eq => "===",
+                    NodeType::NotEq => "!==",
+                    NodeType::LessThan => "<",
+                    NodeType::GreaterThan => ">",
+                    NodeType::Leq => "<=",
+                    _ => ">=",
+                };
+                format!(
+                    "{} {} {}",
+                    self.value(left, &operand_type),
+                    comparison,
+                    self.value(right, &operand_type)
+                )
+            }
+            NodeType::And => format!(
+                "{} && {}",
+                self.expression(&node.children[0]),
+                self.expression(&node.children[1])
+            ),
+            NodeType::Or => format!(
+                "{} || {}",
+                self.expression(&node.children[0]),
+                self.expression(&node.children[1])
+            ),
+            NodeType::Not => format!("!{}", self.expression(&node.children[0])),
+            NodeType::BitwiseOp(BitwiseOp::Not) => {
+                let value = self.value(&node.children[0], &data_type);
+                wrap(format!("~{}", value), &data_type)
+            }
+            NodeType::BitwiseOp(op) => {
+                let left = self.value(&node.children[0], &data_type);
+                let right = self.value(&node.children[1], &data_type);
+                let op = match op {
+                    BitwiseOp::And => "&",
+                    BitwiseOp::Or => "|",
+                    BitwiseOp::Xor => "^",
+                    BitwiseOp::Not => unreachable!(),
+                };
+                // Only u32 leaves the range, JavaScript bitwise operators give signed results
+                match data_type {
+                    DataType::Sized(IntType::U32) => format!("(({} {} {}) >>> 0)", left, op, right),
+                    _ => format!("{} {} {}", left, op, right),
+                }
+            }
+            NodeType::Cast => {
+                let value = self.expression(&node.children[0]);
+                convert(
+                    value,
+                    &node.children[0].data_type.clone().unwrap(),
+                    &data_type,
+                    self,
+                )
+            }
+            NodeType::IfExpression => format!(
+                "({} ? {} : {})",
+                self.expression(&node.children[0]),
+                self.branch_value(&node.children[1], &data_type),
+                self.branch_value(&node.children[2], &data_type)
+            ),
+            NodeType::FunctionCall => self.call(node),
+            NodeType::BuiltinCall => self.builtin_call(node),
+            NodeType::StructLiteral => self.struct_literal(node),
+            NodeType::FieldAccess => format!(
+                "{}.{}",
+                self.operand(&node.children[0]),
+                node.value.clone().unwrap()
+            ),
+            NodeType::ObjectLiteral => {
+                let value_type = match &data_type {
+                    DataType::Object(value_type) => *value_type.clone(),
+                    _ => panic!("Unexpected object literal without a type"),
+                };
+                let properties = node
+                    .children
+                    .iter()
+                    .map(|property| {
+                        let key = property.children[0].value.clone().unwrap();
+                        let key = match is_identifier(&key) {
+                            true => key,
+                            false => compile_string(&key),
+                        };
+                        format!(
+                            "{}: {}",
+                            key,
+                            self.value(&property.children[1], &value_type)
+                        )
+                    })
+                    .collect::<Vec<String>>();
+                match properties.is_empty() {
+                    true => "{}".to_string(),
+                    false => format!("{{ {} }}", properties.join(", ")),
+                }
+            }
+            NodeType::Property => format!(
+                "{}.{}",
+                self.operand(&node.children[0]),
+                node.value.clone().unwrap()
+            ),
+            NodeType::Index => format!(
+                "{}[{}]",
+                self.operand(&node.children[0]),
+                self.expression(&node.children[1])
+            ),
+            NodeType::Lambda => self.lambda(node),
+            NodeType::Call => {
+                let params = match &node.children[0].data_type {
+                    Some(DataType::Function(params, _)) => params.clone(),
+                    _ => panic!("Unexpected call of a non function value"),
+                };
+                let callee = self.operand(&node.children[0]);
+                format!("{}({})", callee, self.args(&node.children[1..], &params))
+            }
+            NodeType::FunctionRef => js_name(node.value.as_ref().unwrap()),
+            NodeType::Signal | NodeType::Computed => {
+                format!("signal_{}", node.value.clone().unwrap())
+            }
+            NodeType::SignalAssign => {
+                let name = node.value.clone().unwrap();
+                let data_type = self
+                    .signals
+                    .iter()
+                    .find(|def| def.value.as_ref() == Some(&name))
+                    .map(|def| def.children[0].data_type().clone())
+                    .unwrap();
+                format!(
+                    "update_{}({})",
+                    name,
+                    self.value(&node.children[0], &data_type)
+                )
+            }
+            NodeType::EnumVariant => format!(
+                "{}.{}",
+                js_name(node.children[0].value.as_ref().unwrap()),
+                node.value.clone().unwrap()
+            ),
+            NodeType::MatchExpression => self.match_expression(node),
+            NodeType::AddressOf
+            | NodeType::Deref
+            | NodeType::Null
+            | NodeType::New
+            | NodeType::PointerFieldAccess => {
+                panic!("The JS backend does not support pointers")
+            }
+            node_type => panic!("The JS backend does not support {:?}", node_type),
+        }
+    }
+
+    // Branches that run statements before their value become a function called right away
+    fn branch_value(&mut self, node: &AstNode, data_type: &DataType) -> String {
+        if node.node_type != NodeType::Block {
+            return self.value(node, data_type);
+        }
+
+        let (value, statements) = node.children.split_last().unwrap();
+        if statements.is_empty() {
+            return self.value(value, data_type);
+        }
+
+        self.scopes.push(HashMap::new());
+        let mut body = statements
+            .iter()
+            .map(|statement| self.statement(statement))
+            .collect::<Vec<String>>();
+        body.push(format!("return {};", self.value(value, data_type)));
+        self.scopes.pop();
+
+        format!("(() => {})()", braces(&body))
+    }
+
+    fn match_expression(&mut self, node: &AstNode) -> String {
+        let data_type = node.data_type.clone().unwrap();
+        let subject = &node.children[0];
+        let subject_name = match subject.node_type {
+            NodeType::Identifier => self.expression(subject),
+            _ => "match_value".to_string(),
+        };
+
+        let (last, arms) = node.children[1..].split_last().unwrap();
+        let mut chain = arms
+            .iter()
+            .map(|arm| {
+                format!(
+                    "{} ? {} : ",
+                    self.pattern_test(&subject_name, subject, &arm.children[0]),
+                    self.branch_value(&arm.children[1], &data_type)
+                )
+            })
+            .collect::<String>();
+        chain += &self.branch_value(&last.children[1], &data_type);
+
+        match subject.node_type {
+            NodeType::Identifier => format!("({})", chain),
+            _ => format!("((match_value) => {})({})", chain, self.expression(subject)),
+        }
+    }
+
+    fn args(&mut self, args: &[AstNode], params: &[DataType]) -> String {
+        args.iter()
+            .zip(params)
+            .map(|(arg, param)| self.value(arg, param))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    fn call(&mut self, node: &AstNode) -> String {
+        let name = node.value.clone().unwrap();
+        if let Some(params) = self.signatures.get(&name).cloned() {
+            return format!("{}({})", js_name(&name), self.args(&node.children, &params));
+        }
+
+        let function = match MATH_FUNCTIONS.iter().find(|(c_name, _)| *c_name == name) {
+            Some((_, function)) => function,
+            None => panic!("The JS backend can not call the C function {}", name),
+        };
+        let params = vec![DataType::Float; node.children.len()];
+        format!(
+            "Math.fround({}({}))",
+            function,
+            self.args(&node.children, &params)
+        )
+    }
+
+    fn builtin_call(&mut self, node: &AstNode) -> String {
+        let name = node.value.as_ref().unwrap();
+        let builtin = Builtin::from_name(name).unwrap();
+        let ints = vec![DataType::Integer; node.children.len()];
+        let args = &node.children;
+        match builtin {
+            Builtin::Print => self.print(node),
+            // The checker picks the float version of math when an argument is a float
+            Builtin::Abs | Builtin::Min | Builtin::Max | Builtin::Pow | Builtin::Sqrt
+                if node.data_type == Some(DataType::Float) =>
+            {
+                let floats = vec![DataType::Float; node.children.len()];
+                let args = self.args(args, &floats);
+                match builtin {
+                    Builtin::Abs => format!("Math.abs({})", args),
+                    Builtin::Min | Builtin::Max => {
+                        self.runtime(FLOAT_MATH_RUNTIME);
+                        format!("tan_f{}({})", name, args)
+                    }
+                    Builtin::Pow => format!("Math.fround(Math.pow({}))", args),
+                    _ => format!("Math.fround(Math.sqrt({}))", args),
+                }
+            }
+            Builtin::Abs | Builtin::Min | Builtin::Max | Builtin::Pow => {
+                let function = match builtin {
+                    Builtin::Abs => "abs",
+                    Builtin::Min => "min",
+                    Builtin::Max => "max",
+                    _ => "pow",
+                };
+                format!("Math.{}({})", function, self.args(args, &ints))
+            }
+            Builtin::Sqrt => format!("Math.trunc(Math.sqrt({}))", self.args(args, &ints)),
+            Builtin::Len => format!("{}.length", self.operand(&args[0])),
+            Builtin::Concat => {
+                format!("{} + {}", self.expression(&args[0]), self.operand(&args[1]))
+            }
+            Builtin::Substr => format!(
+                "{}.substr({})",
+                self.operand(&args[0]),
+                self.args(&args[1..], &ints)
+            ),
+            Builtin::Str => format!("String({})", self.args(args, &ints)),
+            Builtin::Assert => {
+                self.runtime(ASSERT_RUNTIME);
+                format!("tan_assert({})", self.expression(&args[0]))
+            }
+            Builtin::Exit => {
+                self.runtime(EXIT_RUNTIME);
+                format!("tan_exit({})", self.args(args, &ints))
+            }
+            Builtin::Random | Builtin::Seed => {
+                self.runtime(RANDOM_RUNTIME);
+                format!("tan_{}({})", name, self.args(args, &ints))
+            }
+            Builtin::ReadInt | Builtin::ReadLine => {
+                panic!("The JS backend does not support reading input")
+            }
+            Builtin::Alloc | Builtin::Free => panic!("The JS backend does not support pointers"),
+        }
+    }
+
+    // Printed like the interpreter does, the arguments separated by commas. Integers and
+    // strings print as they are, floats with their shortest digits, anything else goes
+    // through a template literal
+    fn print(&mut self, node: &AstNode) -> String {
+        if let [arg] = &node.children[..] {
+            if arg.data_type == Some(DataType::Float) {
+                self.runtime(FLOAT_PRINT_RUNTIME);
+                return format!("console.log(tan_float_string({}))", self.expression(arg));
+            }
+            if let Some(
+                DataType::Integer
+                | DataType::Boolean
+                | DataType::String
+                | DataType::Enum(_)
+                | DataType::Sized(IntType::I8 | IntType::I16)
+                | DataType::Sized(IntType::U8 | IntType::U16 | IntType::U32),
+            ) = arg.data_type
+            {
+                return format!("console.log({})", self.expression(arg));
+            }
+        }
+
+        let text = node
+            .children
+            .iter()
+            .map(|arg| match arg.node_type {
+                NodeType::String | NodeType::Number | NodeType::Boolean
+                    if arg.data_type != Some(DataType::Float) =>
+                {
+                    escape_template(arg.value.as_ref().unwrap())
+                }
+                _ if arg.data_type == Some(DataType::Float) => {
+                    self.runtime(FLOAT_PRINT_RUNTIME);
+                    format!("${{tan_float_string({})}}", self.expression(arg))
+                }
+                _ => format!("${{{}}}", self.expression(arg)),
+            })
+            .collect::<Vec<String>>();
+        format!("console.log(`{}`)", text.join(", "))
+    }
+
+    // Fields are passed in the order of the definition
+    fn struct_literal(&mut self, node: &AstNode) -> String {
+        let name = node.value.clone().unwrap();
+        let def = match self.structs.get(&name) {
+            Some(def) => def.clone(),
+            None => panic!("Use of undefined struct {}", name),
+        };
+
+        let args = def
+            .children
+            .iter()
+            .map(|field| {
+                let field_name = field.children[1].value.as_ref().unwrap();
+                let data_type = field.children[0].data_type();
+                match node
+                    .children
+                    .iter()
+                    .find(|value| value.children[0].value.as_ref() == Some(field_name))
+                {
+                    Some(value) => self.value(&value.children[1], data_type),
+                    None => self.default_value(data_type),
+                }
+            })
+            .collect::<Vec<String>>();
+
+        format!("new {}({})", js_name(&name), args.join(", "))
+    }
+
+    // Lambdas copy the variables they capture when they are created, like the interpreter
+    fn lambda(&mut self, node: &AstNode) -> String {
+        let return_type = node.children[1].data_type();
+        let lambda = match &node.children[2].children[..] {
+            // A body that only returns a value becomes an expression
+            [statement] if statement.node_type == NodeType::Return => {
+                let (params, scope) = parameters(&node.children[0]);
+                self.scopes.push(scope);
+                let value = self.value(&statement.children[0], return_type);
+                self.scopes.pop();
+                match statement.children[0].node_type {
+                    NodeType::ObjectLiteral => format!("({}) => ({})", params, value),
+                    _ => format!("({}) => {}", params, value),
+                }
+            }
+            _ => {
+                let (params, body) =
+                    self.function(&node.children[0], return_type, &node.children[2]);
+                format!("({}) => {}", params, body)
+            }
+        };
+
+        let captures = &node.children[3].children;
+        if captures.is_empty() {
+            return lambda;
+        }
+
+        let names = captures
+            .iter()
+            .map(|capture| self.lookup(capture.children[1].value.as_ref().unwrap()))
+            .collect::<Vec<String>>();
+        let values = captures
+            .iter()
+            .zip(&names)
+            .map(|(capture, name)| match capture.children[0].data_type() {
+                DataType::Struct(_) => format!("{}.copy()", name),
+                _ => name.clone(),
+            })
+            .collect::<Vec<String>>();
+
+        format!(
+            "(({}) => {})({})",
+            names.join(", "),
+            lambda,
+            values.join(", ")
+        )
+    }
+
+    fn default_value(&mut self, data_type: &DataType) -> String {
+        match data_type {
+            DataType::Integer | DataType::Float | DataType::Sized(_) => {
+                convert("0".to_string(), &DataType::Integer, data_type, self)
+            }
+            DataType::Boolean => "false".to_string(),
+            DataType::String => "\"\"".to_string(),
+            DataType::Struct(name) => {
+                let def = match self.structs.get(name) {
+                    Some(def) => def.clone(),
+                    None => panic!("Use of undefined struct {}", name),
+                };
+                let fields = def
+                    .children
+                    .iter()
+                    .map(|field| self.default_value(field.children[0].data_type()))
+                    .collect::<Vec<String>>();
+                format!("new {}({})", js_name(name), fields.join(", "))
+            }
+            DataType::Enum(name) => match self.enums.get(name) {
+                Some(def) => format!(
+                    "{}.{}",
+                    js_name(name),
+                    def.children[0].value.clone().unwrap()
+                ),
+                None => panic!("Use of undefined enum {}", name),
+            },
+            DataType::Function(..) | DataType::Object(_) => "null".to_string(),
+            data_type => panic!(
+                "The JS backend does not support values of type {}",
+                data_type
+            ),
+        }
+    }
+}
+
+fn is_bigint(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Sized(IntType::I64 | IntType::U64))
+}
+
+// Converts between scalar types with the semantics of the interpreter, integers wrap and
+// floats saturate when converted to integers. Other values are left as they are
+fn convert(value: String, from: &DataType, to: &DataType, transpiler: &mut Transpiler) -> String {
+    if from == to {
+        return value;
+    }
+
+    // Literals are converted right away
+    if let (Some(_), Ok(literal)) = (from.integer_layout(), value.trim_end_matches('n').parse()) {
+        return match to {
+            DataType::Float => (literal as f32 as f64).to_string(),
+            DataType::Boolean => (literal != 0).to_string(),
+            _ if is_bigint(to) => format!("{}n", to.wrap_integer(literal)),
+            _ if to.integer_layout().is_some() => to.wrap_integer(literal).to_string(),
+            _ => value,
+        };
+    }
+
+    match (from, to) {
+        (_, DataType::Boolean) if is_bigint(from) => format!("({} !== 0n)", value),
+        (DataType::Integer | DataType::Sized(_) | DataType::Float, DataType::Boolean) => {
+            format!("({} !== 0)", value)
+        }
+        (DataType::Boolean, _) if is_bigint(to) => format!("BigInt({})", value),
+        (DataType::Boolean, DataType::Integer | DataType::Sized(_) | DataType::Float) => {
+            format!("Number({})", value)
+        }
+        (DataType::Float, _) if to.integer_layout().is_some() => {
+            transpiler.runtime(CAST_RUNTIME);
+            let (min, max) = to.integer_range().unwrap();
+            match is_bigint(to) {
+                true => format!("tan_float_to_bigint({}, {}n, {}n)", value, min, max),
+                false => format!("tan_float_to_int({}, {}, {})", value, min, max),
+            }
+        }
+        (_, DataType::Float) if is_bigint(from) => format!("Math.fround(Number({}))", value),
+        (DataType::Integer | DataType::Sized(_), DataType::Float) => {
+            format!("Math.fround({})", value)
+        }
+        (DataType::Integer | DataType::Sized(_), DataType::Integer | DataType::Sized(_)) => {
+            let (from_min, from_max) = from.integer_range().unwrap();
+            let (to_min, to_max) = to.integer_range().unwrap();
+            let fits = to_min <= from_min && from_max <= to_max;
+            match (is_bigint(from), is_bigint(to)) {
+                (true, true) => wrap(value, to),
+                (false, true) if fits => format!("BigInt({})", value),
+                (false, true) => wrap(format!("BigInt({})", value), to),
+                (true, false) if fits => format!("Number({})", value),
+                (true, false) => {
+                    let (bits, signed) = to.integer_layout().unwrap();
+                    let function = if signed { "asIntN" } else { "asUintN" };
+                    format!("Number(BigInt.{}({}, {}))", function, bits, value)
+                }
+                (false, false) if fits => value,
+                (false, false) if *to == DataType::Integer => format!("({} | 0)", value),
+                (false, false) => wrap(value, to),
+            }
+        }
+        _ => value,
+    }
+}
+
+// Keeps the result of an operation in the range of its type, floats are rounded to the
+// nearest single precision float
+fn wrap(value: String, data_type: &DataType) -> String {
+    match data_type {
+        DataType::Integer => format!("({} | 0)", value),
+        DataType::Float => format!("Math.fround({})", value),
+        DataType::Sized(IntType::I8) => format!("({} << 24 >> 24)", value),
+        DataType::Sized(IntType::I16) => format!("({} << 16 >> 16)", value),
+        DataType::Sized(IntType::U8) => format!("({} & 255)", value),
+        DataType::Sized(IntType::U16) => format!("({} & 65535)", value),
+        DataType::Sized(IntType::U32) => format!("({} >>> 0)", value),
+        DataType::Sized(IntType::I64) => format!("BigInt.asIntN(64, {})", value),
+        DataType::Sized(IntType::U64) => format!("BigInt.asUintN(64, {})", value),
+        _ => value,
+    }
+}
+
+// Integer division truncates towards zero, BigInt division already does
+fn arithmetic(op: &Operator, left: String, right: String, data_type: &DataType) -> String {
+    let value = match (op, data_type) {
+        (Operator::Divide, DataType::Integer | DataType::Sized(_)) if !is_bigint(data_type) => {
+            format!("Math.trunc({} / {})", left, right)
+        }
+        // The product of two 32 bit integers can be too large for a double to hold exactly
+        (Operator::Multiply, DataType::Integer) => {
+            return format!("Math.imul({}, {})", left, right);
+        }
+        (Operator::Multiply, DataType::Sized(IntType::U32)) => {
+            return format!("(Math.imul({}, {}) >>> 0)", left, right);
+        }
+        (op, _) => format!("{} {} {}", left, operator(op), right),
+    };
+    wrap(value, data_type)
+}
+
+fn operator(op: &Operator) -> &'static str {
+    match op {
+        Operator::Add => "+",
+        Operator::Subtract => "-",
+        Operator::Multiply => "*",
+        Operator::Divide => "/",
+        Operator::Mod => "%",
+    }
+}
+
+// Reading a struct out of a place must not share it with the place
+fn is_place(node: &AstNode) -> bool {
+    matches!(
+        node.node_type,
+        NodeType::Identifier
+            | NodeType::FieldAccess
+            | NodeType::Property
+            | NodeType::Index
+            | NodeType::Signal
+            | NodeType::Computed
+    )
+}
+
+// Parameters keep their name, they can shadow variables of the enclosing scopes
+fn parameters(params: &AstNode) -> (String, HashMap<String, String>) {
+    let mut scope = HashMap::new();
+    let names = params
+        .children
+        .iter()
+        .map(|param| {
+            let name = param.children[1].value.clone().unwrap();
+            let js = js_name(&name);
+            scope.insert(name, js.clone());
+            js
+        })
+        .collect::<Vec<String>>();
+
+    (names.join(", "), scope)
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn js_name(name: &str) -> String {
+    match RESERVED.contains(&name) {
+        true => format!("{}_", name),
+        false => name.to_string(),
+    }
+}
+
+fn escape_template(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('`', "\\`")
+        .replace("${", "\\${")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+}
+
+fn indent(code: &str) -> String {
+    code.lines()
+        .map(|line| match line.is_empty() {
+            true => "\n".to_string(),
+            false => format!("  {}\n", line),
+        })
+        .collect()
+}
+
+fn braces(statements: &[String]) -> String {
+    match statements.is_empty() {
+        true => "{}".to_string(),
+        false => format!("{{\n{}}}", indent(&statements.join("\n"))),
+    }
+}
+
+fn declared_name(node: &AstNode) -> String {
+    let target = &node.children[1];
+    match target.node_type {
+        NodeType::Assign => target.children[0].value.clone().unwrap(),
+        _ => target.value.clone().unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::set_file;
+    use crate::test::{check_script, test_golden};
+
+    fn compile_script(script: &str) -> String {
+        compile_js(&check_script(script)).0
+    }
+
+    // The golden files were checked with node
+    test_golden!(
+        js_golden_scalars,
+        |root| compile_js(root).0,
+        "scalars.tan",
+        "js/scalars.mjs"
+    );
+    test_golden!(
+        js_golden_integers,
+        |root| compile_js(root).0,
+        "integers.tan",
+        "js/integers.mjs"
+    );
+    test_golden!(
+        js_golden_values,
+        |root| compile_js(root).0,
+        "values.tan",
+        "js/values.mjs"
+    );
+
+    #[test]
+    fn js_truncates_integer_division() {
+        let js = compile_script(
+            "int a = 7; float b = a as float; i64 c = 7; print(a / 2, b / 2, c / 2);",
+        );
+        assert!(js.contains("(Math.trunc(a / 2) | 0)"));
+        assert!(js.contains("${tan_float_string(Math.fround(b / 2))}"));
+        assert!(js.contains("${BigInt.asIntN(64, c / 2n)}"));
+    }
+
+    #[test]
+    fn js_wraps_integers_and_rounds_floats() {
+        let js = compile_script(
+            "int m = 2147483647; m += 1; print(m * 3, m - 1); float f = 16777217 as float; f += f;",
+        );
+        assert!(js.contains("m = (m + 1 | 0);\n"));
+        assert!(js.contains("${Math.imul(m, 3)}, ${(m - 1 | 0)}"));
+        assert!(js.contains("let f = 16777216;\nf = Math.fround(f + f);\n"));
+    }
+
+    #[test]
+    fn js_maps_statements_to_source_lines() {
+        let mut root = check_script("fn f(int x) int {\n\n    return x;\n};\nprint(f(1));");
+        set_file(&mut root, "main.tan");
+
+        let (js, map) = compile_js(&root);
+        assert_eq!(
+            js,
+            "export function f(x) {\n  return x;\n}\n\nconsole.log(f(1));\n"
+        );
+        assert_eq!(map.sources, vec!["main.tan"]);
+        assert_eq!(
+            map.lines,
+            vec![
+                Some((0, 1)),
+                Some((0, 3)),
+                Some((0, 3)),
+                Some((0, 3)),
+                Some((0, 5))
+            ]
+        );
+    }
+
+    #[test]
+    fn js_declares_with_let_and_const() {
+        let js =
+            compile_script("const int limit = 3; int count = limit; count += 1; print(count);");
+        assert!(js.starts_with(
+            "export const limit = 3;\n\nlet count = limit;\ncount = (count + 1 | 0);\n"
+        ));
+        assert!(js.ends_with("console.log(count);\n"));
+    }
+
+    #[test]
+    fn js_renames_shadowing_declarations() {
+        let js = compile_script("fn f(int x) int { int x = x + 1; return x; };");
+        assert!(js.contains("  let x_1 = (x + 1 | 0);\n  return x_1;\n"));
+    }
+
+    #[test]
+    fn js_avoids_reserved_words() {
+        let js = compile_script("fn delete(int this) int { return this; }; print(delete(1));");
+        assert!(js.contains("export function delete_(this_) {\n  return this_;\n}"));
+    }
+
+    #[test]
+    fn js_rounds_float_math() {
+        let js = compile_script("float f = 1 as float / 3; print(sqrt(f), min(f, 1), abs(f));");
+        assert!(js.contains("Math.fround(Math.sqrt(f))"));
+        assert!(js.contains("tan_fmin(f, 1)"));
+        assert!(js.contains("function tan_fmin(a, b)"));
+        assert!(js.contains("Math.abs(f)"));
+    }
+
+    #[test]
+    fn js_only_adds_used_runtimes() {
+        assert!(!compile_script("print(1);").contains("function tan_"));
+        let js = compile_script("float f = 5 as float / 2; seed(1); print(random(6), f as u8);");
+        assert!(js.contains("function tan_random(bound)"));
+        assert!(js.contains("tan_float_to_int(f, 0, 255)"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The JS backend does not support pointers")]
+    fn js_rejects_pointers() {
+        compile_script("int x = 1; int* p = &x;");
+    }
+}
@@ -3,6 +3,7 @@ use crate::types::*;
 #[derive(Debug, Clone)]
 pub struct LexerToken {
     pub token: Token,
+    #[allow(dead_code)]
     pub position: usize,
     pub line: usize,
 }
@@ -26,7 +27,7 @@ impl Lexer {
     fn create_token(&mut self, token: Token) {
         self.tokens.push(LexerToken {
             position: self.position,
-            token: token,
+            token,
             line: self.line_number,
         });
     }
@@ -46,15 +47,16 @@ pub fn tokenize(script: &str) -> Vec<LexerToken> {
 
     while !lexer.is_end() {
         if is_whitespace(lexer.cur_char()) {
+            // Blank lines count too, \r\n is one line break
+            if lexer.cur_char() == '\n' {
+                lexer.line_number += 1;
+            }
             lexer.position += 1;
             continue;
         }
 
         let next_token = next_token(&mut lexer);
         lexer.create_token(next_token);
-        if !lexer.is_end() && is_newline(lexer.cur_char()) {
-            lexer.line_number += 1;
-        }
     }
 
     lexer.tokens
@@ -62,10 +64,22 @@ pub fn tokenize(script: &str) -> Vec<LexerToken> {
 
 fn next_token(lexer: &mut Lexer) -> Token {
     let c = lexer.cur_char();
-    if c.is_digit(10) {
+    if c.is_ascii_digit() {
         return lex_number(lexer);
     }
 
+    if c == '\'' {
+        return lex_label(lexer);
+    }
+
+    if c == '#' || c == '$' {
+        return lex_signal(lexer);
+    }
+
+    if c == '"' {
+        return lex_string(lexer);
+    }
+
     if let Some(token) = match_operator(lexer) {
         return token;
     }
@@ -77,8 +91,8 @@ fn next_token(lexer: &mut Lexer) -> Token {
     let possibly_ident = lex_identifier(lexer);
     if let Some(keyword) = Keywords::from_string(&possibly_ident) {
         // if the keyword is a data type
-        if let Keywords::Integer | Keywords::Float | Keywords::Boolean = keyword {
-            return lex_data_type(lexer, keyword);
+        if let Token::Type(data_type) = keyword.to_token() {
+            return lex_data_type(lexer, data_type);
         }
 
         return keyword.to_token();
@@ -88,14 +102,7 @@ fn next_token(lexer: &mut Lexer) -> Token {
 }
 
 // Grabs all * after a data type for pointers
-fn lex_data_type(lexer: &mut Lexer, keyword: Keywords) -> Token {
-    let mut data_type = match keyword {
-        Keywords::Integer => DataType::Integer,
-        Keywords::Float => DataType::Float,
-        Keywords::Boolean => DataType::Boolean,
-        _ => panic!("Unexpected keyword"),
-    };
-
+fn lex_data_type(lexer: &mut Lexer, mut data_type: DataType) -> Token {
     while !lexer.is_end() && lexer.cur_char() == '*' {
         lexer.position += 1;
         data_type = DataType::Pointer(Box::new(data_type));
@@ -119,6 +126,73 @@ fn lex_identifier(lexer: &mut Lexer) -> String {
     ident
 }
 
+// Loop labels are written as 'name
+fn lex_label(lexer: &mut Lexer) -> Token {
+    lexer.position += 1;
+    let label = lex_identifier(lexer);
+    if label.is_empty() {
+        panic!("Expected label name after '");
+    }
+
+    Token::Label(label)
+}
+
+// Signals are read as #name and computed signals as $name, #= and $= assign them
+fn lex_signal(lexer: &mut Lexer) -> Token {
+    let sigil = lexer.cur_char();
+    lexer.position += 1;
+
+    if !lexer.is_end() && lexer.cur_char() == '=' {
+        lexer.position += 1;
+        return match sigil {
+            '#' => Token::SignalAssign,
+            _ => Token::ComputeAssign,
+        };
+    }
+
+    let name = lex_identifier(lexer);
+    if name.is_empty() {
+        panic!("Expected signal name or = after {}", sigil);
+    }
+
+    match sigil {
+        '#' => Token::Signal(name),
+        _ => Token::Computed(name),
+    }
+}
+
+// String literals support the escapes \n, \t, \" and \\
+fn lex_string(lexer: &mut Lexer) -> Token {
+    lexer.position += 1;
+
+    let mut string = String::new();
+    loop {
+        if lexer.is_end() {
+            panic!("Unterminated string on line {}", lexer.line_number);
+        }
+
+        let c = lexer.cur_char();
+        lexer.position += 1;
+        match c {
+            '"' => break,
+            '\\' if !lexer.is_end() => {
+                string.push(match lexer.cur_char() {
+                    'n' => '\n',
+                    't' => '\t',
+                    '"' => '"',
+                    '\\' => '\\',
+                    escape => panic!("Unknown escape sequence \\{}", escape),
+                });
+                lexer.position += 1;
+            }
+            '\n' => panic!("Unterminated string on line {}", lexer.line_number),
+            c => string.push(c),
+        }
+    }
+
+    Token::String(string)
+}
+
 fn is_whitespace(c: char) -> bool {
     c == ' ' || c == '\t' || is_newline(c)
 }
@@ -148,12 +222,16 @@ fn match_operator(lexer: &mut Lexer) -> Option<Token> {
         ')' => Some(Token::RParen),
         '{' => Some(Token::LCurly),
         '}' => Some(Token::RCurly),
+        '[' => Some(Token::LBracket),
+        ']' => Some(Token::RBracket),
         ',' => Some(Token::Comma),
         '&' => Some(Token::BitwiseOp(BitwiseOp::And)),
         '|' => Some(Token::BitwiseOp(BitwiseOp::Or)),
         '~' => Some(Token::BitwiseOp(BitwiseOp::Not)),
         '^' => Some(Token::BitwiseOp(BitwiseOp::Xor)),
         ':' => Some(Token::Colon),
+        '.' => Some(Token::Dot),
+        '_' => Some(Token::Underscore),
         _ => None,
     };
 
@@ -185,6 +263,9 @@ fn match_double_char_op(lexer: &mut Lexer) -> Option<Token> {
         ('%', '=') => Some(Token::ShortAssign(Operator::Mod)),
         ('+', '+') => Some(Token::Increment),
         ('-', '-') => Some(Token::Decrement),
+        ('-', '>') => Some(Token::Arrow),
+        ('=', '>') => Some(Token::FatArrow),
+        (':', ':') => Some(Token::DoubleColon),
         _ => None,
     };
 
@@ -195,20 +276,69 @@ fn match_double_char_op(lexer: &mut Lexer) -> Option<Token> {
     matched
 }
 
+// Decimal, 0x hex, 0b binary and 0o octal literals, with _ separators and an optional
+// type suffix like 255u8
 fn lex_number(lexer: &mut Lexer) -> Token {
-    let mut number = String::new();
+    let start = lexer.position;
+    let mut radix = 10;
+    if lexer.cur_char() == '0' && lexer.has_two_more_chars() {
+        radix = match lexer.script.chars().nth(lexer.position + 1).unwrap() {
+            'x' => 16,
+            'b' => 2,
+            'o' => 8,
+            _ => 10,
+        };
+        if radix != 10 {
+            lexer.position += 2;
+        }
+    }
 
+    let mut digits = String::new();
     while !lexer.is_end() {
         let next_c = lexer.cur_char();
-        if next_c.is_digit(10) {
-            number.push(next_c);
-            lexer.position += 1;
-        } else {
+        if next_c.is_digit(radix) {
+            digits.push(next_c);
+        } else if next_c != '_' {
             break;
         }
+        lexer.position += 1;
+    }
+
+    let mut suffix = String::new();
+    while !lexer.is_end() && lexer.cur_char().is_alphanumeric() {
+        suffix.push(lexer.cur_char());
+        lexer.position += 1;
     }
 
-    Token::Number(number.parse::<i32>().unwrap())
+    let literal = lexer
+        .script
+        .chars()
+        .skip(start)
+        .take(lexer.position - start)
+        .collect::<String>();
+    let data_type = match suffix.as_str() {
+        "" => None,
+        "i32" => Some(DataType::Integer),
+        name => match IntType::from_name(name) {
+            Some(int_type) => Some(DataType::Sized(int_type)),
+            None => panic!(
+                "Invalid integer literal {} on line {}",
+                literal, lexer.line_number
+            ),
+        },
+    };
+
+    match u64::from_str_radix(&digits, radix) {
+        Ok(value) => Token::Number(value, data_type),
+        Err(_) if digits.is_empty() => panic!(
+            "Invalid integer literal {} on line {}",
+            literal, lexer.line_number
+        ),
+        Err(_) => panic!(
+            "Integer literal {} on line {} is too large",
+            literal, lexer.line_number
+        ),
+    }
 }
 
 // Tests
@@ -218,19 +348,19 @@ mod tests {
 
     #[test]
     fn test_is_whitespace() {
-        assert_eq!(is_whitespace(' '), true);
-        assert_eq!(is_whitespace('\n'), true);
-        assert_eq!(is_whitespace('\t'), true);
-        assert_eq!(is_whitespace('a'), false);
+        assert!(is_whitespace(' '));
+        assert!(is_whitespace('\n'));
+        assert!(is_whitespace('\t'));
+        assert!(!is_whitespace('a'));
     }
 
     #[test]
     fn lex_simple_expression() {
         let tokens = tokenize("1 + 2");
         assert_eq!(tokens.len(), 3);
-        assert_eq!(tokens[0].token, Token::Number(1));
+        assert_eq!(tokens[0].token, Token::Number(1, None));
         assert_eq!(tokens[1].token, Token::Operator(Operator::Add));
-        assert_eq!(tokens[2].token, Token::Number(2));
+        assert_eq!(tokens[2].token, Token::Number(2, None));
     }
 
     #[test]
@@ -251,4 +381,101 @@ mod tests {
         assert_eq!(tokens[11].token, Token::And);
         assert_eq!(tokens[12].token, Token::Or);
     }
+
+    #[test]
+    fn lex_loop_labels() {
+        let tokens = tokenize("'outer: while true { break 'outer; continue; };");
+        assert_eq!(tokens[0].token, Token::Label("outer".to_string()));
+        assert_eq!(tokens[1].token, Token::Colon);
+        assert_eq!(tokens[2].token, Token::While);
+        assert_eq!(tokens[5].token, Token::Break);
+        assert_eq!(tokens[6].token, Token::Label("outer".to_string()));
+        assert_eq!(tokens[8].token, Token::Continue);
+    }
+
+    #[test]
+    fn lex_signals() {
+        let tokens = tokenize("int x #= 1; int y $= #x + $z; x #= 2;");
+        assert_eq!(tokens[2].token, Token::SignalAssign);
+        assert_eq!(tokens[7].token, Token::ComputeAssign);
+        assert_eq!(tokens[8].token, Token::Signal("x".to_string()));
+        assert_eq!(tokens[10].token, Token::Computed("z".to_string()));
+        assert_eq!(tokens[12].token, Token::Identifier("x".to_string()));
+        assert_eq!(tokens[13].token, Token::SignalAssign);
+    }
+
+    #[test]
+    fn lex_imports() {
+        let tokens = tokenize(r#"import "lib/math.tan"; pub fn"#);
+        assert_eq!(tokens[0].token, Token::Import);
+        assert_eq!(tokens[1].token, Token::String("lib/math.tan".to_string()));
+        assert_eq!(tokens[3].token, Token::Pub);
+    }
+
+    #[test]
+    fn lex_integer_literals() {
+        let tokens = tokenize("0xFF 0b1010 0o17 1_000_000 255u8 0x7fi32 u64");
+        assert_eq!(tokens[0].token, Token::Number(255, None));
+        assert_eq!(tokens[1].token, Token::Number(10, None));
+        assert_eq!(tokens[2].token, Token::Number(15, None));
+        assert_eq!(tokens[3].token, Token::Number(1_000_000, None));
+        assert_eq!(
+            tokens[4].token,
+            Token::Number(255, Some(DataType::Sized(IntType::U8)))
+        );
+        assert_eq!(tokens[5].token, Token::Number(127, Some(DataType::Integer)));
+        assert_eq!(tokens[6].token, Token::Type(DataType::Sized(IntType::U64)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Integer literal 18446744073709551616 on line 2 is too large")]
+    fn lex_rejects_too_large_literal() {
+        tokenize("1;\n18446744073709551616");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid integer literal 12ab on line 1")]
+    fn lex_rejects_invalid_suffix() {
+        tokenize("12ab");
+    }
+
+    #[test]
+    fn lex_counts_blank_lines() {
+        let tokens = tokenize("a\n\n  b\r\n\r\nc");
+        let lines = tokens.iter().map(|token| token.line).collect::<Vec<_>>();
+        assert_eq!(lines, vec![1, 3, 5]);
+    }
+
+    #[test]
+    fn lex_casts() {
+        let tokens = tokenize("x as float");
+        assert_eq!(tokens[1].token, Token::As);
+        assert_eq!(tokens[2].token, Token::Type(DataType::Float));
+    }
+
+    #[test]
+    fn lex_strings() {
+        let tokens = tokenize(r#"o["a b"] = "say \"hi\"\n";"#);
+        assert_eq!(tokens[1].token, Token::LBracket);
+        assert_eq!(tokens[2].token, Token::String("a b".to_string()));
+        assert_eq!(tokens[3].token, Token::RBracket);
+        assert_eq!(tokens[5].token, Token::String("say \"hi\"\n".to_string()));
+    }
+
+    #[test]
+    fn lex_field_access() {
+        let tokens = tokenize("p.x->y");
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[1].token, Token::Dot);
+        assert_eq!(tokens[3].token, Token::Arrow);
+    }
+
+    #[test]
+    fn lex_match_arm() {
+        let tokens = tokenize("Color::Red => 1, _ => 2");
+        assert_eq!(tokens.len(), 9);
+        assert_eq!(tokens[1].token, Token::DoubleColon);
+        assert_eq!(tokens[3].token, Token::FatArrow);
+        assert_eq!(tokens[6].token, Token::Underscore);
+    }
 }
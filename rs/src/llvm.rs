@@ -0,0 +1,1078 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+use crate::builtins::Builtin;
+use crate::checker::common_type;
+use crate::types::*;
+
+// Emits textual LLVM IR. Variables live in allocas that the mem2reg pass of LLVM turns into
+// SSA registers, control flow becomes basic blocks. Functions, scalars, strings and control
+// flow are supported, the rest of the language panics
+pub fn compile_llvm(ast: &AstNode) -> String {
+    let mut generator = Generator::default();
+    let mut globals = String::new();
+    let mut functions = vec![];
+    let mut main = vec![];
+
+    (generator.signatures, generator.globals) = top_level_types(ast);
+    generator.externs = ast
+        .children
+        .iter()
+        .filter(|node| node.node_type == NodeType::ExternDef)
+        .map(|node| node.value.clone().unwrap())
+        .collect();
+
+    for node in &ast.children {
+        match node.node_type {
+            NodeType::FunctionDef => functions.push(generator.function_def(node)),
+            NodeType::ExternDef => {
+                let (params, return_type) = &generator.signatures[node.value.as_ref().unwrap()];
+                let params = params.iter().map(extern_type).collect::<Vec<String>>();
+                let declaration = format!(
+                    "declare {} @{}({})",
+                    extern_type(return_type),
+                    node.value.as_ref().unwrap(),
+                    params.join(", ")
+                );
+                generator.declare(&declaration);
+            }
+            // Constants are folded to literals by the checker
+            NodeType::ConstDeclare => {
+                let data_type = node.children[0].data_type();
+                let value = generator.value(&node.children[1].children[1]);
+                globals += &format!(
+                    "@{} = constant {} {}\n",
+                    declared_name(node),
+                    llvm_type(data_type),
+                    value.value
+                );
+            }
+            // Globals start out zeroed, main runs their initializers in order
+            NodeType::Declare => {
+                let data_type = node.children[0].data_type();
+                globals += &format!(
+                    "@{} = global {} {}\n",
+                    declared_name(node),
+                    llvm_type(data_type),
+                    generator.default_value(data_type)
+                );
+                if node.children[1].node_type == NodeType::Assign {
+                    main.push(&node.children[1]);
+                }
+            }
+            _ => main.push(node),
+        }
+    }
+
+    functions.push(
+        generator.function("main", &[], &DataType::Integer, |generator| {
+            for statement in main {
+                generator.statement(statement);
+            }
+        }),
+    );
+
+    if generator.float_string {
+        for declaration in [
+            "declare i32 @snprintf(ptr, i64, ptr, ...)",
+            "declare float @strtof(ptr, ptr)",
+            "declare ptr @strchr(ptr, i32)",
+            "declare i32 @atoi(ptr)",
+            "declare float @llvm.fabs.f32(float)",
+        ] {
+            generator.declare(declaration);
+        }
+        functions.push(FLOAT_STRING_RUNTIME.to_string());
+    }
+
+    let mut sections = vec![];
+    if !generator.strings.is_empty() {
+        sections.push(
+            generator
+                .strings
+                .iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    format!(
+                        "@.str.{} = private unnamed_addr constant [{} x i8] c\"{}\\00\"\n",
+                        index,
+                        value.len() + 1,
+                        escape(value)
+                    )
+                })
+                .collect::<String>(),
+        );
+    }
+    if !globals.is_empty() {
+        sections.push(globals);
+    }
+    if !generator.declarations.is_empty() {
+        sections.push(generator.declarations.join(""));
+    }
+    sections.extend(functions);
+
+    sections.join("\n")
+}
+
+// Floats print like the interpreter prints them, with the fewest digits that read back as
+// the same float and without an exponent. The digits come from %.*e, they are printed with %f
+// when the number has a fraction and followed by zeros when it does not
+const FLOAT_STRING_RUNTIME: &str = r#"@.float.nan = private unnamed_addr constant [4 x i8] c"NaN\00"
+@.float.inf = private unnamed_addr constant [4 x i8] c"inf\00"
+@.float.minus.inf = private unnamed_addr constant [5 x i8] c"-inf\00"
+@.float.scientific = private unnamed_addr constant [5 x i8] c"%.*e\00"
+@.float.fixed = private unnamed_addr constant [5 x i8] c"%.*f\00"
+@.float.large = private unnamed_addr constant [11 x i8] c"%.*s%.*s%s\00"
+@.float.zeros = private unnamed_addr constant [40 x i8] c"000000000000000000000000000000000000000\00"
+
+define internal void @tan_float_string(ptr %out, float %value) {
+entry:
+  %scientific = alloca [32 x i8]
+  %double = fpext float %value to double
+  %nan = fcmp uno float %value, %value
+  br i1 %nan, label %not.a.number, label %check.infinite
+not.a.number:
+  call i32 (ptr, i64, ptr, ...) @snprintf(ptr %out, i64 64, ptr @.float.nan)
+  ret void
+check.infinite:
+  %abs = call float @llvm.fabs.f32(float %value)
+  %infinite = fcmp oeq float %abs, 0x7FF0000000000000
+  %negative = fcmp olt float %value, 0.0
+  br i1 %infinite, label %infinity, label %search
+infinity:
+  %infinity.text = select i1 %negative, ptr @.float.minus.inf, ptr @.float.inf
+  call i32 (ptr, i64, ptr, ...) @snprintf(ptr %out, i64 64, ptr %infinity.text)
+  ret void
+search:
+  %precision = phi i32 [ 0, %check.infinite ], [ %next, %search ]
+  call i32 (ptr, i64, ptr, ...) @snprintf(ptr %scientific, i64 32, ptr @.float.scientific, i32 %precision, double %double)
+  %back = call float @strtof(ptr %scientific, ptr null)
+  %same = fcmp oeq float %back, %value
+  %next = add i32 %precision, 1
+  %last = icmp eq i32 %next, 9
+  %found = or i1 %same, %last
+  br i1 %found, label %format, label %search
+format:
+  %e = call ptr @strchr(ptr %scientific, i32 101)
+  %exponent.text = getelementptr i8, ptr %e, i64 1
+  %exponent = call i32 @atoi(ptr %exponent.text)
+  %decimals = sub i32 %precision, %exponent
+  %fraction = icmp sge i32 %decimals, 0
+  br i1 %fraction, label %fixed, label %large
+fixed:
+  call i32 (ptr, i64, ptr, ...) @snprintf(ptr %out, i64 64, ptr @.float.fixed, i32 %decimals, double %double)
+  ret void
+large:
+  %sign = zext i1 %negative to i32
+  %leading = add i32 %sign, 1
+  %sign.offset = zext i1 %negative to i64
+  %first = getelementptr i8, ptr %scientific, i64 %sign.offset
+  %rest = getelementptr i8, ptr %first, i64 2
+  %zero.count = add i32 %decimals, 39
+  %zero.offset = sext i32 %zero.count to i64
+  %zeros = getelementptr i8, ptr @.float.zeros, i64 %zero.offset
+  call i32 (ptr, i64, ptr, ...) @snprintf(ptr %out, i64 64, ptr @.float.large, i32 %leading, ptr %scientific, i32 %precision, ptr %rest, ptr %zeros)
+  ret void
+}
+"#;
+
+// A register or a constant with the TanScript type of its value
+#[derive(Clone)]
+struct Operand {
+    value: String,
+    data_type: DataType,
+}
+
+struct Loop {
+    label: Option<String>,
+    next: String,
+    end: String,
+}
+
+#[derive(Default)]
+struct Generator {
+    strings: Vec<String>,
+    declarations: Vec<String>,
+    globals: HashMap<String, DataType>,
+    signatures: HashMap<String, Signature>,
+    externs: HashSet<String>,
+    float_string: bool,
+
+    // State of the function being generated, allocas are kept apart so they all end up in
+    // the entry block where mem2reg promotes them
+    return_type: Option<DataType>,
+    allocas: String,
+    body: String,
+    block: String,
+    terminated: bool,
+    registers: usize,
+    labels: usize,
+    slots: HashMap<String, usize>,
+    scopes: Vec<HashMap<String, (String, DataType)>>,
+    loops: Vec<Loop>,
+}
+
+impl Generator {
+    fn function_def(&mut self, node: &AstNode) -> String {
+        let params = node.children[0]
+            .children
+            .iter()
+            .map(|param| {
+                (
+                    param.children[1].value.clone().unwrap(),
+                    param.children[0].data_type().clone(),
+                )
+            })
+            .collect::<Vec<(String, DataType)>>();
+
+        self.function(
+            node.value.as_ref().unwrap(),
+            &params,
+            node.children[1].data_type(),
+            |generator| generator.statement(&node.children[2]),
+        )
+    }
+
+    fn function(
+        &mut self,
+        name: &str,
+        params: &[(String, DataType)],
+        return_type: &DataType,
+        body: impl FnOnce(&mut Generator),
+    ) -> String {
+        self.return_type = Some(return_type.clone());
+        self.allocas = String::new();
+        self.body = String::new();
+        self.block = "entry".to_string();
+        self.terminated = false;
+        self.registers = 0;
+        self.labels = 0;
+        self.slots = HashMap::new();
+        self.scopes = vec![HashMap::new()];
+
+        // Parameters are copied into allocas so they can be assigned like other variables
+        for (param, data_type) in params {
+            let slot = self.alloca(param, data_type);
+            self.emit(format!(
+                "store {} %{}, ptr {}",
+                llvm_type(data_type),
+                param,
+                slot
+            ));
+        }
+
+        body(self);
+
+        // Falling off the end returns a zero value
+        if !self.terminated {
+            let value = self.default_value(return_type);
+            self.terminate(format!("ret {} {}", llvm_type(return_type), value));
+        }
+
+        let params = params
+            .iter()
+            .map(|(param, data_type)| format!("{} %{}", llvm_type(data_type), param))
+            .collect::<Vec<String>>();
+
+        format!(
+            "define {} @{}({}) {{\nentry:\n{}{}}}\n",
+            llvm_type(return_type),
+            name,
+            params.join(", "),
+            self.allocas,
+            self.body
+        )
+    }
+
+    fn emit(&mut self, instruction: String) {
+        // Code after a return or jump is unreachable, but still needs a block
+        if self.terminated {
+            let label = self.label("dead");
+            self.start_block(&label);
+        }
+        self.body += &format!("  {}\n", instruction);
+    }
+
+    fn instruction(&mut self, instruction: String) -> String {
+        let register = format!("%{}", self.registers);
+        self.registers += 1;
+        self.emit(format!("{} = {}", register, instruction));
+        register
+    }
+
+    fn terminate(&mut self, instruction: String) {
+        self.emit(instruction);
+        self.terminated = true;
+    }
+
+    fn branch(&mut self, label: &str) {
+        if !self.terminated {
+            self.terminate(format!("br label %{}", label));
+        }
+    }
+
+    fn label(&mut self, name: &str) -> String {
+        self.labels += 1;
+        format!("{}.{}", name, self.labels - 1)
+    }
+
+    fn start_block(&mut self, label: &str) {
+        self.branch(label);
+        self.body += &format!("{}:\n", label);
+        self.block = label.to_string();
+        self.terminated = false;
+    }
+
+    fn declare(&mut self, declaration: &str) {
+        let declaration = format!("{}\n", declaration);
+        if !self.declarations.contains(&declaration) {
+            self.declarations.push(declaration);
+        }
+    }
+
+    fn string(&mut self, value: &str) -> String {
+        let index = match self.strings.iter().position(|string| string == value) {
+            Some(index) => index,
+            None => {
+                self.strings.push(value.to_string());
+                self.strings.len() - 1
+            }
+        };
+        format!("@.str.{}", index)
+    }
+
+    fn default_value(&mut self, data_type: &DataType) -> String {
+        match data_type {
+            DataType::Boolean => "false".to_string(),
+            DataType::Float => float_constant(0.0),
+            DataType::String => self.string(""),
+            _ => "0".to_string(),
+        }
+    }
+
+    fn alloca(&mut self, name: &str, data_type: &DataType) -> String {
+        let count = self.slots.entry(name.to_string()).or_insert(0);
+        let slot = match *count {
+            0 => format!("%{}.addr", name),
+            count => format!("%{}.addr{}", name, count),
+        };
+        *count += 1;
+
+        self.allocas += &format!("  {} = alloca {}\n", slot, llvm_type(data_type));
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), (slot.clone(), data_type.clone()));
+        slot
+    }
+
+    fn lookup(&self, name: &str) -> (String, DataType) {
+        if let Some(found) = self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            return found.clone();
+        }
+
+        match self.globals.get(name) {
+            Some(data_type) => (format!("@{}", name), data_type.clone()),
+            None => panic!("Use of undeclared variable {}", name),
+        }
+    }
+
+    fn slot(&self, node: &AstNode) -> (String, DataType) {
+        match node.node_type {
+            NodeType::Identifier => self.lookup(node.value.as_ref().unwrap()),
+            _ => panic!("The LLVM backend can only assign to variables"),
+        }
+    }
+
+    fn store(&mut self, value: Operand, slot: &str) {
+        self.emit(format!(
+            "store {} {}, ptr {}",
+            llvm_type(&value.data_type),
+            value.value,
+            slot
+        ));
+    }
+
+    // ------------------------------ Statements ------------------------------
+    fn statement(&mut self, node: &AstNode) {
+        match &node.node_type {
+            NodeType::Declare => {
+                let data_type = node.children[0].data_type();
+                let target = &node.children[1];
+                let (name, value) = match target.node_type {
+                    NodeType::Assign => {
+                        let value = self.value(&target.children[1]);
+                        (target.children[0].value.clone().unwrap(), value)
+                    }
+                    _ => {
+                        let value = self.default_value(data_type);
+                        let operand = Operand {
+                            value,
+                            data_type: data_type.clone(),
+                        };
+                        (target.value.clone().unwrap(), operand)
+                    }
+                };
+
+                // The slot is allocated after the value, which may read a variable it shadows
+                let value = self.convert(value, data_type);
+                let slot = self.alloca(&name, data_type);
+                self.store(value, &slot);
+            }
+            NodeType::Assign => {
+                let (slot, data_type) = self.slot(&node.children[0]);
+                let value = self.value(&node.children[1]);
+                let value = self.convert(value, &data_type);
+                self.store(value, &slot);
+            }
+            NodeType::ShortAssign(op) => {
+                let (slot, data_type) = self.slot(&node.children[0]);
+                let current = self.value(&node.children[0]);
+                let value = self.value(&node.children[1]);
+                let value = self.arithmetic(op, current, value, &data_type);
+                self.store(value, &slot);
+            }
+            NodeType::Block => {
+                self.scopes.push(HashMap::new());
+                for child in &node.children {
+                    self.statement(child);
+                }
+                self.scopes.pop();
+            }
+            NodeType::If => self.if_statement(node),
+            NodeType::While => self.while_loop(node),
+            NodeType::Break | NodeType::Continue => {
+                let target = match &node.value {
+                    Some(label) => self
+                        .loops
+                        .iter()
+                        .rev()
+                        .find(|target| target.label.as_ref() == Some(label)),
+                    None => self.loops.last(),
+                };
+                let target = match target {
+                    Some(target) if node.node_type == NodeType::Break => target.end.clone(),
+                    Some(target) => target.next.clone(),
+                    None => panic!("Unexpected {:?} outside of a loop", node.node_type),
+                };
+                self.terminate(format!("br label %{}", target));
+            }
+            NodeType::Return => {
+                let return_type = self.return_type.clone().unwrap();
+                let value = self.value(&node.children[0]);
+                let value = self.convert(value, &return_type);
+                self.terminate(format!("ret {} {}", llvm_type(&return_type), value.value));
+            }
+            _ => {
+                self.value(node);
+            }
+        }
+    }
+
+    fn if_statement(&mut self, node: &AstNode) {
+        let condition = self.condition(&node.children[0]);
+        let then = self.label("if.then");
+        let end = self.label("if.end");
+        let otherwise = match node.children.len() {
+            3 => self.label("if.else"),
+            _ => end.clone(),
+        };
+
+        self.terminate(format!(
+            "br i1 {}, label %{}, label %{}",
+            condition, then, otherwise
+        ));
+        self.start_block(&then);
+        self.statement(&node.children[1]);
+        if let Some(else_ast) = node.children.get(2) {
+            self.branch(&end);
+            self.start_block(&otherwise);
+            self.statement(else_ast);
+        }
+        self.start_block(&end);
+    }
+
+    fn while_loop(&mut self, node: &AstNode) {
+        let condition_label = self.label("while.cond");
+        let body = self.label("while.body");
+        let end = self.label("while.end");
+
+        self.start_block(&condition_label);
+        let condition = self.condition(&node.children[0]);
+        self.terminate(format!(
+            "br i1 {}, label %{}, label %{}",
+            condition, body, end
+        ));
+
+        self.start_block(&body);
+        self.loops.push(Loop {
+            label: node.value.clone(),
+            next: condition_label.clone(),
+            end: end.clone(),
+        });
+        self.statement(&node.children[1]);
+        self.loops.pop();
+        self.branch(&condition_label);
+        self.start_block(&end);
+    }
+
+    // ------------------------------ Expressions ------------------------------
+    fn value(&mut self, node: &AstNode) -> Operand {
+        let data_type = node.data_type.clone().unwrap_or(DataType::Void);
+        match &node.node_type {
+            NodeType::Number => {
+                let value = node.value.clone().unwrap();
+                let value = match data_type {
+                    DataType::Float => float_constant(value.parse().unwrap()),
+                    _ => value,
+                };
+                Operand { value, data_type }
+            }
+            NodeType::Boolean => Operand {
+                value: node.value.clone().unwrap(),
+                data_type,
+            },
+            NodeType::String => Operand {
+                value: self.string(node.value.as_ref().unwrap()),
+                data_type,
+            },
+            NodeType::Identifier => {
+                let (slot, data_type) = self.lookup(node.value.as_ref().unwrap());
+                let value =
+                    self.instruction(format!("load {}, ptr {}", llvm_type(&data_type), slot));
+                Operand { value, data_type }
+            }
+            NodeType::LParen => self.value(&node.children[0]),
+            NodeType::Operator(op) => {
+                let left = self.value(&node.children[0]);
+                let right = self.value(&node.children[1]);
+                self.arithmetic(op, left, right, &data_type)
+            }
+            NodeType::Eq
+            | NodeType::NotEq
+            | NodeType::LessThan
+            | NodeType::GreaterThan
+            | NodeType::Leq
+            | NodeType::Geq => self.comparison(node),
+            NodeType::And | NodeType::Or => self.logical(node),
+            NodeType::Not => {
+                let condition = self.condition(&node.children[0]);
+                let value = self.instruction(format!("xor i1 {}, true", condition));
+                Operand { value, data_type }
+            }
+            NodeType::BitwiseOp(BitwiseOp::Not) => {
+                let value = self.value(&node.children[0]);
+                let value = self.convert(value, &data_type);
+                let value =
+                    self.instruction(format!("xor {} {}, -1", llvm_type(&data_type), value.value));
+                Operand { value, data_type }
+            }
+            NodeType::BitwiseOp(op) => {
+                let left = self.value(&node.children[0]);
+                let left = self.convert(left, &data_type);
+                let right = self.value(&node.children[1]);
+                let right = self.convert(right, &data_type);
+                let instruction = match op {
+                    BitwiseOp::And => "and",
+                    BitwiseOp::Or => "or",
+                    BitwiseOp::Xor => "xor",
+                    BitwiseOp::Not => unreachable!(),
+                };
+                let value = self.instruction(format!(
+                    "{} {} {}, {}",
+                    instruction,
+                    llvm_type(&data_type),
+                    left.value,
+                    right.value
+                ));
+                Operand { value, data_type }
+            }
+            NodeType::Cast => {
+                let value = self.value(&node.children[0]);
+                self.convert(value, node.children[1].data_type())
+            }
+            NodeType::IfExpression => self.if_expression(node),
+            NodeType::FunctionCall => self.call(node),
+            NodeType::BuiltinCall => self.builtin_call(node),
+            node_type => panic!("The LLVM backend does not support {:?}", node_type),
+        }
+    }
+
+    fn condition(&mut self, node: &AstNode) -> String {
+        let value = self.value(node);
+        self.convert(value, &DataType::Boolean).value
+    }
+
+    fn arithmetic(
+        &mut self,
+        op: &Operator,
+        left: Operand,
+        right: Operand,
+        data_type: &DataType,
+    ) -> Operand {
+        let left = self.convert(left, data_type);
+        let right = self.convert(right, data_type);
+        let instruction = match (data_type.integer_layout(), op) {
+            (None, Operator::Add) => "fadd",
+            (None, Operator::Subtract) => "fsub",
+            (None, Operator::Multiply) => "fmul",
+            (None, Operator::Divide) => "fdiv",
+            (None, Operator::Mod) => "frem",
+            (Some(_), Operator::Add) => "add",
+            (Some(_), Operator::Subtract) => "sub",
+            (Some(_), Operator::Multiply) => "mul",
+            (Some((_, true)), Operator::Divide) => "sdiv",
+            (Some((_, false)), Operator::Divide) => "udiv",
+            (Some((_, true)), Operator::Mod) => "srem",
+            (Some((_, false)), Operator::Mod) => "urem",
+        };
+
+        let value = self.instruction(format!(
+            "{} {} {}, {}",
+            instruction,
+            llvm_type(data_type),
+            left.value,
+            right.value
+        ));
+        Operand {
+            value,
+            data_type: data_type.clone(),
+        }
+    }
+
+    fn comparison(&mut self, node: &AstNode) -> Operand {
+        let left = self.value(&node.children[0]);
+        let right = self.value(&node.children[1]);
+
+        let value = if left.data_type == DataType::String {
+            self.declare("declare i32 @strcmp(ptr, ptr)");
+            let order = self.instruction(format!(
+                "call i32 @strcmp(ptr {}, ptr {})",
+                left.value, right.value
+            ));
+            let predicate = match node.node_type {
+                NodeType::Eq => "eq",
+                _ => "ne",
+            };
+            self.instruction(format!("icmp {} i32 {}, 0", predicate, order))
+        } else {
+            let data_type = match common_type(&left.data_type, &right.data_type) {
+                Some(data_type) => data_type,
+                None => panic!(
+                    "The LLVM backend can not compare {} and {}",
+                    left.data_type, right.data_type
+                ),
+            };
+            let left = self.convert(left, &data_type);
+            let right = self.convert(right, &data_type);
+            let (instruction, predicate) = match data_type.integer_layout() {
+                Some((_, signed)) => ("icmp", integer_predicate(&node.node_type, signed)),
+                None => ("fcmp", float_predicate(&node.node_type)),
+            };
+            self.instruction(format!(
+                "{} {} {} {}, {}",
+                instruction,
+                predicate,
+                llvm_type(&data_type),
+                left.value,
+                right.value
+            ))
+        };
+
+        Operand {
+            value,
+            data_type: DataType::Boolean,
+        }
+    }
+
+    // And and or only evaluate their right side when the left side does not decide
+    fn logical(&mut self, node: &AstNode) -> Operand {
+        let is_and = node.node_type == NodeType::And;
+        let name = if is_and { "and" } else { "or" };
+        let right_label = self.label(&format!("{}.rhs", name));
+        let end = self.label(&format!("{}.end", name));
+
+        let left = self.condition(&node.children[0]);
+        let left_block = self.block.clone();
+        let (on_true, on_false) = match is_and {
+            true => (&right_label, &end),
+            false => (&end, &right_label),
+        };
+        self.terminate(format!(
+            "br i1 {}, label %{}, label %{}",
+            left, on_true, on_false
+        ));
+
+        self.start_block(&right_label);
+        let right = self.condition(&node.children[1]);
+        let right_block = self.block.clone();
+        self.start_block(&end);
+
+        let value = self.instruction(format!(
+            "phi i1 [ {}, %{} ], [ {}, %{} ]",
+            !is_and, left_block, right, right_block
+        ));
+        Operand {
+            value,
+            data_type: DataType::Boolean,
+        }
+    }
+
+    fn if_expression(&mut self, node: &AstNode) -> Operand {
+        let data_type = node.data_type.clone().unwrap();
+        let condition = self.condition(&node.children[0]);
+        let then = self.label("if.then");
+        let otherwise = self.label("if.else");
+        let end = self.label("if.end");
+
+        self.terminate(format!(
+            "br i1 {}, label %{}, label %{}",
+            condition, then, otherwise
+        ));
+
+        let mut incoming = vec![];
+        for (label, branch) in [(then, &node.children[1]), (otherwise, &node.children[2])] {
+            self.start_block(&label);
+            let value = self.branch_value(branch);
+            let value = self.convert(value, &data_type);
+            incoming.push(format!("[ {}, %{} ]", value.value, self.block));
+            self.branch(&end);
+        }
+
+        self.start_block(&end);
+        let value = self.instruction(format!(
+            "phi {} {}",
+            llvm_type(&data_type),
+            incoming.join(", ")
+        ));
+        Operand { value, data_type }
+    }
+
+    // Blocks produce the value of their last child
+    fn branch_value(&mut self, node: &AstNode) -> Operand {
+        if node.node_type != NodeType::Block {
+            return self.value(node);
+        }
+
+        self.scopes.push(HashMap::new());
+        let (value, statements) = node.children.split_last().unwrap();
+        for statement in statements {
+            self.statement(statement);
+        }
+        let value = self.value(value);
+        self.scopes.pop();
+        value
+    }
+
+    fn call(&mut self, node: &AstNode) -> Operand {
+        let name = node.value.clone().unwrap();
+        let (params, return_type) = match self.signatures.get(&name) {
+            Some(signature) => signature.clone(),
+            None => panic!("The LLVM backend does not support calling {}", name),
+        };
+
+        let is_extern = self.externs.contains(&name);
+        let args = node
+            .children
+            .iter()
+            .zip(&params)
+            .map(|(arg, param)| {
+                let value = self.value(arg);
+                let value = self.convert(value, param);
+                if is_extern && *param == DataType::Float {
+                    let value = self.instruction(format!("fpext float {} to double", value.value));
+                    return format!("double {}", value);
+                }
+                format!("{} {}", llvm_type(param), value.value)
+            })
+            .collect::<Vec<String>>();
+
+        let return_llvm_type = match is_extern {
+            true => extern_type(&return_type),
+            false => llvm_type(&return_type),
+        };
+        let call = format!("call {} @{}({})", return_llvm_type, name, args.join(", "));
+        if return_type == DataType::Void {
+            self.emit(call);
+            return Operand {
+                value: String::new(),
+                data_type: DataType::Void,
+            };
+        }
+
+        let mut value = self.instruction(call);
+        if is_extern && return_type == DataType::Float {
+            value = self.instruction(format!("fptrunc double {} to float", value));
+        }
+        Operand {
+            value,
+            data_type: return_type,
+        }
+    }
+
+    fn builtin_call(&mut self, node: &AstNode) -> Operand {
+        let name = node.value.as_ref().unwrap();
+        if Builtin::from_name(name) != Some(Builtin::Print) {
+            panic!("The LLVM backend does not support the builtin {}", name);
+        }
+
+        // Printed like the interpreter does, bools as true and false
+        let mut formats = vec![];
+        let mut args = vec![];
+        for arg in &node.children {
+            let value = self.value(arg);
+            let (format, arg) = match &value.data_type {
+                DataType::String => ("%s", format!("ptr {}", value.value)),
+                DataType::Boolean => {
+                    let (yes, no) = (self.string("true"), self.string("false"));
+                    let text = self.instruction(format!(
+                        "select i1 {}, ptr {}, ptr {}",
+                        value.value, yes, no
+                    ));
+                    ("%s", format!("ptr {}", text))
+                }
+                DataType::Float => {
+                    let count = self.slots.entry("float.text".to_string()).or_insert(0);
+                    let buffer = format!("%float.text{}", count);
+                    *count += 1;
+                    self.allocas += &format!("  {} = alloca [64 x i8]\n", buffer);
+
+                    self.float_string = true;
+                    self.emit(format!(
+                        "call void @tan_float_string(ptr {}, float {})",
+                        buffer, value.value
+                    ));
+                    ("%s", format!("ptr {}", buffer))
+                }
+                DataType::Sized(IntType::I64) => ("%lld", format!("i64 {}", value.value)),
+                DataType::Sized(IntType::U64) => ("%llu", format!("i64 {}", value.value)),
+                DataType::Sized(IntType::U32) => ("%u", format!("i32 {}", value.value)),
+                _ => {
+                    let value = self.convert(value, &DataType::Integer);
+                    ("%d", format!("i32 {}", value.value))
+                }
+            };
+            formats.push(format);
+            args.push(arg);
+        }
+
+        let format = self.string(&(formats.join(", ") + "\n"));
+        self.declare("declare i32 @printf(ptr, ...)");
+        self.instruction(format!(
+            "call i32 (ptr, ...) @printf(ptr {}{})",
+            format,
+            args.iter()
+                .map(|arg| format!(", {}", arg))
+                .collect::<String>()
+        ));
+
+        Operand {
+            value: String::new(),
+            data_type: DataType::Void,
+        }
+    }
+
+    // Converts between scalar types with the semantics of the interpreter, integers wrap and
+    // floats saturate when converted to integers
+    fn convert(&mut self, operand: Operand, to: &DataType) -> Operand {
+        let from = operand.data_type.clone();
+        if from == *to {
+            return operand;
+        }
+
+        let converted = |value: String| Operand {
+            value,
+            data_type: to.clone(),
+        };
+
+        // Literals are converted right away
+        if let (Some(_), Ok(value)) = (from.integer_layout(), operand.value.parse::<i128>()) {
+            match to {
+                DataType::Float => return converted(float_constant(value as f32)),
+                DataType::Boolean => return converted((value != 0).to_string()),
+                _ => return converted(to.wrap_integer(value).to_string()),
+            }
+        }
+
+        let (from_type, to_type) = (llvm_type(&from), llvm_type(to));
+        let value = operand.value;
+        let instruction = match (from.integer_layout(), to.integer_layout()) {
+            (Some((from_bits, signed)), Some((to_bits, _))) => match from_bits.cmp(&to_bits) {
+                std::cmp::Ordering::Greater => "trunc",
+                std::cmp::Ordering::Less if signed => "sext",
+                std::cmp::Ordering::Less => "zext",
+                std::cmp::Ordering::Equal => return converted(value),
+            },
+            (Some(_), None) if *to == DataType::Boolean => {
+                return converted(self.instruction(format!("icmp ne {} {}, 0", from_type, value)));
+            }
+            (Some((_, signed)), None) => match signed {
+                true => "sitofp",
+                false => "uitofp",
+            },
+            (None, Some((bits, signed))) if from == DataType::Float => {
+                let intrinsic = format!(
+                    "llvm.fpto{}i.sat.i{}.f32",
+                    if signed { "s" } else { "u" },
+                    bits
+                );
+                self.declare(&format!("declare {} @{}(float)", to_type, intrinsic));
+                return converted(
+                    self.instruction(format!("call {} @{}(float {})", to_type, intrinsic, value)),
+                );
+            }
+            (None, _) if from == DataType::Boolean => "zext",
+            (None, _) if *to == DataType::Boolean => {
+                return converted(self.instruction(format!(
+                    "fcmp une float {}, {}",
+                    value,
+                    float_constant(0.0)
+                )));
+            }
+            _ => panic!("The LLVM backend can not convert {} to {}", from, to),
+        };
+
+        // Booleans are unsigned, true becomes 1
+        let instruction = match (instruction, to) {
+            ("zext", DataType::Float) => "uitofp",
+            (instruction, _) => instruction,
+        };
+        converted(self.instruction(format!(
+            "{} {} {} to {}",
+            instruction, from_type, value, to_type
+        )))
+    }
+}
+
+// Floats cross the extern boundary as doubles, like in the C backend
+fn extern_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Float => "double".to_string(),
+        data_type => llvm_type(data_type),
+    }
+}
+
+fn llvm_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Integer => "i32".to_string(),
+        DataType::Sized(int_type) => format!("i{}", int_type.bits()),
+        DataType::Boolean => "i1".to_string(),
+        DataType::Float => "float".to_string(),
+        DataType::String => "ptr".to_string(),
+        DataType::Void => "void".to_string(),
+        data_type => panic!(
+            "The LLVM backend does not support values of type {}",
+            data_type
+        ),
+    }
+}
+
+fn integer_predicate(node_type: &NodeType, signed: bool) -> &'static str {
+    match (node_type, signed) {
+        (NodeType::Eq, _) => "eq",
+        (NodeType::NotEq, _) => "ne",
+        (NodeType::LessThan, true) => "slt",
+        (NodeType::LessThan, false) => "ult",
+        (NodeType::GreaterThan, true) => "sgt",
+        (NodeType::GreaterThan, false) => "ugt",
+        (NodeType::Leq, true) => "sle",
+        (NodeType::Leq, false) => "ule",
+        (NodeType::Geq, true) => "sge",
+        (NodeType::Geq, false) => "uge",
+        _ => panic!("Unexpected comparison {:?}", node_type),
+    }
+}
+
+fn float_predicate(node_type: &NodeType) -> &'static str {
+    match node_type {
+        NodeType::Eq => "oeq",
+        NodeType::NotEq => "une",
+        NodeType::LessThan => "olt",
+        NodeType::GreaterThan => "ogt",
+        NodeType::Leq => "ole",
+        NodeType::Geq => "oge",
+        _ => panic!("Unexpected comparison {:?}", node_type),
+    }
+}
+
+// LLVM writes float constants as the hex bits of the same value as a double
+fn float_constant(value: f32) -> String {
+    format!("0x{:016X}", (value as f64).to_bits())
+}
+
+fn escape(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b' '..=b'~' if byte != b'"' && byte != b'\\' => (byte as char).to_string(),
+            byte => format!("\\{:02X}", byte),
+        })
+        .collect()
+}
+
+fn declared_name(node: &AstNode) -> String {
+    let target = &node.children[1];
+    match target.node_type {
+        NodeType::Assign => target.children[0].value.clone().unwrap(),
+        _ => target.value.clone().unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{check_script, test_golden};
+
+    fn compile_script(script: &str) -> String {
+        compile_llvm(&check_script(script))
+    }
+
+    // The golden files were checked with lli
+    test_golden!(
+        llvm_golden_scalars,
+        compile_llvm,
+        "scalars.tan",
+        "llvm/scalars.ll"
+    );
+    test_golden!(
+        llvm_golden_control_flow,
+        compile_llvm,
+        "control_flow.tan",
+        "llvm/control_flow.ll"
+    );
+
+    #[test]
+    fn llvm_uses_allocas_for_locals() {
+        let ir = compile_script("fn twice(int x) int { int y = x * 2; return y; };");
+        assert!(ir.contains("entry:\n  %x.addr = alloca i32\n  %y.addr = alloca i32\n"));
+        assert!(ir.contains("  %1 = mul i32 %0, 2\n  store i32 %1, ptr %y.addr\n"));
+    }
+
+    #[test]
+    fn llvm_names_shadowed_slots_apart() {
+        let ir = compile_script("fn f() int { int x = 1; { int x = x + 1; return x; }; };");
+        assert!(ir.contains("  %x.addr = alloca i32\n  %x.addr1 = alloca i32\n"));
+        assert!(ir.contains("  %0 = load i32, ptr %x.addr\n  %1 = add i32 %0, 1\n"));
+    }
+
+    #[test]
+    fn llvm_opens_block_after_terminator() {
+        let ir = compile_script("fn f() int { return 1; return 2; };");
+        assert!(ir.contains("  ret i32 1\ndead.0:\n  ret i32 2\n"));
+    }
+
+    #[test]
+    fn llvm_passes_floats_to_externs_as_doubles() {
+        let ir = compile_script("extern fn sqrt(float x) float; float r = sqrt(2 as float);");
+        assert!(ir.contains("declare double @sqrt(double)\n"));
+        assert!(ir.contains(
+            "  %0 = fpext float 0x4000000000000000 to double\n  %1 = call double @sqrt(double %0)\n  %2 = fptrunc double %1 to float\n"
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "The LLVM backend does not support")]
+    fn llvm_rejects_unsupported_nodes() {
+        compile_script("struct Point { int x; int y; }; Point p = Point { x: 1, y: 2 };");
+    }
+}
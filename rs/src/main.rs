@@ -1,21 +1,193 @@
 mod ast;
+mod builtins;
+mod checker;
 mod compiler;
+mod driver;
+mod interpreter;
+mod ir;
+mod js;
 mod lexer;
+mod llvm;
+mod modules;
+mod optimize;
 mod parser;
+mod regalloc;
+mod signals;
+mod sourcemap;
+#[cfg(test)]
 mod test;
 mod types;
+mod wat;
+mod x86;
 
 fn main() {
-    let content = std::fs::read_to_string("script.tan").expect("Could not read file");
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let interpret = args.iter().any(|arg| arg == "--interpret");
+
+    // rs build compiles the C output with the system C compiler, rs run also runs the program
+    let command = args
+        .first()
+        .filter(|arg| *arg == "build" || *arg == "run")
+        .cloned();
+    let cc = match args.iter().position(|arg| arg == "--cc") {
+        Some(index) => match args.get(index + 1) {
+            Some(compiler) => Some(compiler.as_str()),
+            None => panic!("Expected a C compiler after --cc"),
+        },
+        None => None,
+    };
+
+    // The backend comes from --emit, the older flags are short forms of it
+    let emit = match args.iter().position(|arg| arg == "--emit") {
+        Some(index) => match args.get(index + 1) {
+            Some(target) => target.clone(),
+            None => panic!("Expected a backend after --emit"),
+        },
+        None => ["llvm", "wat", "asm"]
+            .into_iter()
+            .find(|target| args.contains(&format!("--{}", target)))
+            .unwrap_or("c")
+            .to_string(),
+    };
+    if command.is_some() && emit != "c" {
+        panic!("Only C can be built, got --emit {}", emit);
+    }
+    let path = args
+        .iter()
+        .enumerate()
+        .skip(command.is_some() as usize)
+        .find(|(index, arg)| {
+            !arg.starts_with("--")
+                && (*index == 0 || !["--emit", "--cc"].contains(&args[index - 1].as_str()))
+        })
+        .map_or("script.tan".to_string(), |(_, arg)| arg.clone());
+    let path = std::path::Path::new(&path);
+
+    let content = std::fs::read_to_string(path).expect("Could not read file");
     let tokens: Vec<lexer::LexerToken> = lexer::tokenize(&content);
-    for token in tokens.iter() {
-        println!("{:?}", token);
+    // The output of a program run by rs run is not mixed with the tokens and the C
+    if command.is_none() {
+        for token in tokens.iter() {
+            println!("{:?}", token);
+        }
+    }
+
+    let mut ast = modules::link(path, parser::parse(tokens));
+    checker::check(&mut ast);
+
+    if interpret {
+        let code = interpreter::interpret(
+            &ast,
+            &interpreter::Externs::standard(),
+            &mut std::io::stdin().lock(),
+            &mut std::io::stdout(),
+        );
+        std::process::exit(code);
     }
 
-    let ast = parser::parse(tokens);
-    let result = compiler::compile(&ast);
-    println!("{}", result);
+    // Backends that compile from the IR run the passes asked for, --optimize runs them all.
+    // C compiles from the IR when passes run, and from the AST otherwise or when the program
+    // uses something the IR does not support
+    let mut manager = optimize::PassManager::default();
+    if args.iter().any(|arg| arg == "--optimize") {
+        manager.passes = optimize::Pass::DEFAULT.to_vec();
+    }
+    for arg in &args {
+        if let Some(passes) = arg.strip_prefix("--passes=") {
+            manager.passes = passes
+                .split(',')
+                .filter(|name| !name.is_empty())
+                .map(pass)
+                .collect();
+        } else if let Some(name) = arg.strip_prefix("--print-after=") {
+            manager.print_after = Some(pass(name));
+        }
+    }
+    if let Some(print_after) = manager.print_after {
+        if !manager.passes.contains(&print_after) {
+            panic!(
+                "--print-after={} names a pass that does not run, add it with --passes or --optimize",
+                print_after.name()
+            );
+        }
+    }
+    let lower = || {
+        let mut program = ir::lower(&ast);
+        manager.run(&mut program, &mut std::io::stderr());
+        program
+    };
+
+    let (mut result, extension, map) = match emit.as_str() {
+        "c" => {
+            let result = compiler::compile_optimized(&ast, &manager, &mut std::io::stderr());
+            (result, "c", None)
+        }
+        "llvm" => (llvm::compile_llvm(&ast), "ll", None),
+        "wat" => {
+            let (result, map) = wat::compile_wat(&ast);
+            (result, "wat", Some(map))
+        }
+        "asm" => {
+            // --spill-all keeps every temporary on the stack, to compare with allocation
+            let registers = match args.iter().any(|arg| arg == "--spill-all") {
+                true => regalloc::Registers::NONE,
+                false => x86::REGISTERS,
+            };
+            (x86::compile_asm(&lower(), &registers), "s", None)
+        }
+        "js" => {
+            let (result, map) = js::compile_js(&ast);
+            (result, "mjs", Some(map))
+        }
+        "ir" => (lower().to_string(), "ir", None),
+        target => panic!(
+            "Unknown backend {}, expected c, llvm, wat, asm, js or ir",
+            target
+        ),
+    };
+
+    // Source maps are written next to the output, JS names its map so node and browsers find it
+    let output = path.with_extension(extension);
+    if let Some(map) = map {
+        let map_path = path.with_extension(format!("{}.map", extension));
+        let file_name =
+            |path: &std::path::Path| path.file_name().unwrap().to_string_lossy().to_string();
+        if extension == "mjs" {
+            result += &format!("//# sourceMappingURL={}\n", file_name(&map_path));
+        }
+        let directory = path.parent().unwrap_or(std::path::Path::new(""));
+        std::fs::write(map_path, map.to_json(&file_name(&output), directory))
+            .expect("Could not write file");
+    }
+    if command.is_none() {
+        println!("{}", result);
+    }
 
     // Write to file
-    std::fs::write("script.c", result).expect("Could not write file");
+    std::fs::write(&output, result).expect("Could not write file");
+
+    if let Some(command) = command {
+        let executable = driver::executable(path);
+        if let Err(diagnostics) = driver::build(&driver::compiler(cc), &output, &executable) {
+            eprint!("{}", diagnostics);
+            std::process::exit(1);
+        }
+        if command == "run" {
+            std::process::exit(driver::run(&executable));
+        }
+    }
+}
+
+fn pass(name: &str) -> optimize::Pass {
+    match optimize::Pass::from_name(name) {
+        Some(pass) => pass,
+        None => {
+            let names = optimize::Pass::ALL.map(|pass| pass.name());
+            panic!(
+                "Unknown pass {}, expected one of {}",
+                name,
+                names.join(", ")
+            )
+        }
+    }
 }
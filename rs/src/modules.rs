@@ -0,0 +1,540 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+use crate::ast::*;
+use crate::lexer;
+use crate::parser;
+use crate::types::DataType;
+
+// Links the main script and the modules it imports into one program.
+// The top level names of a module are prefixed with the module name, so math.sqrt becomes
+// math_sqrt, which can not collide with a TanScript name. Dependencies come first, so the
+// top level code of a module runs before the code importing it.
+pub fn link(path: &Path, ast: AstNode) -> AstNode {
+    link_with(path, ast, &|path| std::fs::read_to_string(path).ok())
+}
+
+fn link_with(path: &Path, ast: AstNode, read: &dyn Fn(&Path) -> Option<String>) -> AstNode {
+    let mut linker = Linker {
+        read,
+        stack: vec![],
+        modules: HashMap::new(),
+        names: HashMap::new(),
+        statements: vec![],
+    };
+    linker.link_file(&normalize(path), ast, None);
+
+    AstNode {
+        node_type: NodeType::Block,
+        children: linker.statements,
+        value: None,
+        data_type: None,
+        span: None,
+    }
+}
+
+struct Linker<'a> {
+    read: &'a dyn Fn(&Path) -> Option<String>,
+    // Files being linked, an import of one of them is a cycle
+    stack: Vec<PathBuf>,
+    modules: HashMap<PathBuf, Module>,
+    // Paths of the modules by name, two modules can not share a name
+    names: HashMap<String, PathBuf>,
+    // Top level statements of all linked files
+    statements: Vec<AstNode>,
+}
+
+#[derive(Clone)]
+struct Module {
+    name: String,
+    functions: HashSet<String>,
+    globals: HashSet<String>,
+    private: HashSet<String>,
+}
+
+impl Module {
+    fn qualify(&self, name: &str) -> String {
+        format!("{}_{}", self.name, name)
+    }
+}
+
+impl Linker<'_> {
+    fn link_file(&mut self, path: &Path, ast: AstNode, module: Option<&str>) -> Module {
+        self.stack.push(path.to_path_buf());
+
+        let mut imports = HashMap::new();
+        let mut statements = vec![];
+        for node in ast.children {
+            if node.node_type == NodeType::Import {
+                let import = self.import(path, node.value.as_ref().unwrap());
+                imports.insert(import.name.clone(), import);
+            } else {
+                statements.push(node);
+            }
+        }
+
+        let mut exports = Module {
+            name: module.unwrap_or_default().to_string(),
+            functions: HashSet::new(),
+            globals: HashSet::new(),
+            private: HashSet::new(),
+        };
+        let mut names = HashMap::new();
+        let statements: Vec<AstNode> = statements
+            .into_iter()
+            .map(|node| {
+                let (node, public) = match node.node_type {
+                    NodeType::Pub => {
+                        let span = node.span;
+                        let mut node = node.children.into_iter().next().unwrap();
+                        node.span = span;
+                        (node, true)
+                    }
+                    _ => (node, false),
+                };
+
+                if let (Some(_), Some(name)) = (module, definition_name(&node)) {
+                    names.insert(name.clone(), exports.qualify(&name));
+                    match node.node_type {
+                        NodeType::FunctionDef if public => exports.functions.insert(name),
+                        NodeType::GenericDef
+                            if public && node.children[1].node_type == NodeType::FunctionDef =>
+                        {
+                            exports.functions.insert(name)
+                        }
+                        NodeType::Declare | NodeType::ConstDeclare if public => {
+                            exports.globals.insert(name)
+                        }
+                        _ => exports.private.insert(name),
+                    };
+                }
+
+                node
+            })
+            .collect();
+
+        let mut renamer = Renamer {
+            names: &names,
+            imports: &imports,
+            module: module.is_some(),
+            scopes: vec![HashSet::new()],
+        };
+        let file = path.display().to_string();
+        for mut node in statements {
+            renamer.visit(&mut node, true);
+            set_file(&mut node, &file);
+            self.statements.push(node);
+        }
+
+        self.stack.pop();
+        exports
+    }
+
+    fn import(&mut self, from: &Path, import: &str) -> Module {
+        let path = normalize(&from.parent().unwrap_or(Path::new("")).join(import));
+
+        if let Some(start) = self.stack.iter().position(|file| *file == path) {
+            let cycle = self.stack[start..]
+                .iter()
+                .chain(std::iter::once(&path))
+                .map(|file| file.display().to_string())
+                .collect::<Vec<String>>()
+                .join(" -> ");
+            panic!("Import cycle: {}", cycle);
+        }
+
+        if let Some(module) = self.modules.get(&path) {
+            return module.clone();
+        }
+
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic())
+            || !name.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            panic!("Invalid module name {}", path.display());
+        }
+        if let Some(other) = self.names.insert(name.clone(), path.clone()) {
+            panic!(
+                "Modules {} and {} are both named {}",
+                other.display(),
+                path.display(),
+                name
+            );
+        }
+
+        let content = (self.read)(&path)
+            .unwrap_or_else(|| panic!("Could not read module {}", path.display()));
+        let ast = parser::parse(lexer::tokenize(&content));
+        let module = self.link_file(&path, ast, Some(&name));
+        self.modules.insert(path, module.clone());
+        module
+    }
+}
+
+// Name of a top level definition, which other files can refer to
+fn definition_name(node: &AstNode) -> Option<String> {
+    match node.node_type {
+        NodeType::FunctionDef
+        | NodeType::GenericDef
+        | NodeType::StructDef
+        | NodeType::EnumDef
+        | NodeType::SignalDeclare
+        | NodeType::ComputedDeclare => node.value.clone(),
+        NodeType::Declare | NodeType::ConstDeclare => declared_name(node).value.clone(),
+        _ => None,
+    }
+}
+
+fn declared_name(node: &AstNode) -> &AstNode {
+    match node.children[1].node_type {
+        NodeType::Assign => &node.children[1].children[0],
+        _ => &node.children[1],
+    }
+}
+
+// Statements of a linked file remember which file they come from
+pub fn set_file(node: &mut AstNode, file: &str) {
+    if let Some(span) = &mut node.span {
+        span.file = Some(file.to_string());
+    }
+    for child in &mut node.children {
+        set_file(child, file);
+    }
+}
+
+// Lexically resolves . and .. so that every file has one path
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+// Renames the top level names of a file and resolves accesses to imported modules
+struct Renamer<'a> {
+    names: &'a HashMap<String, String>,
+    imports: &'a HashMap<String, Module>,
+    // Top level declarations of a module are renamed, those of the main script keep their names
+    module: bool,
+    scopes: Vec<HashSet<String>>,
+}
+
+impl Renamer<'_> {
+    fn is_local(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(name))
+    }
+
+    fn rename(&self, node: &mut AstNode) {
+        let name = node.value.as_ref().unwrap();
+        if let Some(renamed) = self.names.get(name) {
+            node.value = Some(renamed.clone());
+        }
+    }
+
+    fn rename_value(&self, node: &mut AstNode) {
+        if !self.is_local(node.value.as_ref().unwrap()) {
+            self.rename(node);
+        }
+    }
+
+    fn rename_type(&self, data_type: &DataType) -> DataType {
+        let rename = |name: &String| self.names.get(name).unwrap_or(name).clone();
+        match data_type {
+            DataType::Struct(name) => DataType::Struct(rename(name)),
+            DataType::Enum(name) => DataType::Enum(rename(name)),
+            DataType::Instance(name, args) => DataType::Instance(
+                rename(name),
+                args.iter().map(|arg| self.rename_type(arg)).collect(),
+            ),
+            DataType::Pointer(pointee) => DataType::Pointer(Box::new(self.rename_type(pointee))),
+            DataType::Object(value) => DataType::Object(Box::new(self.rename_type(value))),
+            DataType::Function(params, return_type) => DataType::Function(
+                params.iter().map(|param| self.rename_type(param)).collect(),
+                Box::new(self.rename_type(return_type)),
+            ),
+            data_type => data_type.clone(),
+        }
+    }
+
+    // The linked name of module.name, and whether it is a function
+    fn module_access(&self, node: &AstNode) -> Option<(String, bool)> {
+        if node.node_type != NodeType::FieldAccess {
+            return None;
+        }
+
+        let base = &node.children[0];
+        let module_name = base.value.as_ref()?;
+        if base.node_type != NodeType::Identifier
+            || self.is_local(module_name)
+            || self.names.contains_key(module_name)
+        {
+            return None;
+        }
+
+        let module = self.imports.get(module_name)?;
+        let name = node.value.as_ref().unwrap();
+        if module.functions.contains(name) {
+            Some((module.qualify(name), true))
+        } else if module.globals.contains(name) {
+            Some((module.qualify(name), false))
+        } else if module.private.contains(name) {
+            panic!("{} is not pub in module {}", name, module_name);
+        } else {
+            panic!("Module {} has no {}", module_name, name);
+        }
+    }
+
+    fn declare(&mut self, name: &AstNode) {
+        let name = name.value.clone().unwrap();
+        self.scopes.last_mut().unwrap().insert(name);
+    }
+
+    fn visit_children(&mut self, node: &mut AstNode) {
+        for child in node.children.iter_mut() {
+            self.visit(child, false);
+        }
+    }
+
+    fn visit(&mut self, node: &mut AstNode, top_level: bool) {
+        match &node.node_type {
+            NodeType::Import => panic!("Imports can only be at the top level"),
+            NodeType::Pub => panic!("Only top level definitions can be pub"),
+            NodeType::Identifier | NodeType::FunctionCall => {
+                self.rename_value(node);
+                self.visit_children(node);
+            }
+            NodeType::Signal | NodeType::Computed | NodeType::EnumDef => self.rename(node),
+            NodeType::Type(data_type) => {
+                node.node_type = NodeType::Type(self.rename_type(data_type))
+            }
+            NodeType::Declare | NodeType::ConstDeclare => {
+                self.visit(&mut node.children[0], false);
+                if node.children[1].node_type == NodeType::Assign {
+                    self.visit(&mut node.children[1].children[1], false);
+                }
+
+                let name = match node.children[1].node_type {
+                    NodeType::Assign => &mut node.children[1].children[0],
+                    _ => &mut node.children[1],
+                };
+                if top_level && self.module {
+                    self.rename(name);
+                } else {
+                    self.declare(name);
+                }
+            }
+            // Extern functions keep their C names
+            NodeType::FunctionDef | NodeType::Lambda | NodeType::ExternDef => {
+                if top_level {
+                    self.rename(node);
+                }
+
+                self.scopes.push(HashSet::new());
+                self.visit_children(node);
+                self.scopes.pop();
+            }
+            NodeType::Parameters => {
+                for param in node.children.iter_mut() {
+                    let (name, types) = param.children.split_last_mut().unwrap();
+                    for data_type in types {
+                        self.visit(data_type, false);
+                    }
+                    self.declare(name);
+                }
+            }
+            NodeType::GenericDef => {
+                self.rename(node);
+                self.visit(&mut node.children[1], true);
+            }
+            NodeType::StructDef => {
+                self.rename(node);
+                for field in node.children.iter_mut() {
+                    self.visit(&mut field.children[0], false);
+                }
+            }
+            NodeType::StructLiteral | NodeType::ObjectLiteral => {
+                if node.node_type == NodeType::StructLiteral {
+                    self.rename(node);
+                }
+
+                // Field names and property keys are not variables
+                for child in node.children.iter_mut() {
+                    match child.node_type {
+                        NodeType::Assign => self.visit(&mut child.children[1], false),
+                        _ => self.visit(child, false),
+                    }
+                }
+            }
+            NodeType::EnumVariant => self.rename(&mut node.children[0]),
+            NodeType::SignalDeclare | NodeType::ComputedDeclare => {
+                self.visit_children(node);
+                self.rename(node);
+            }
+            NodeType::FieldAccess => match self.module_access(node) {
+                Some((name, _)) => {
+                    node.node_type = NodeType::Identifier;
+                    node.value = Some(name);
+                    node.children.clear();
+                }
+                None => self.visit_children(node),
+            },
+            NodeType::Call => match self.module_access(&node.children[0]) {
+                Some((name, true)) => {
+                    node.node_type = NodeType::FunctionCall;
+                    node.value = Some(name);
+                    node.children.remove(0);
+                    self.visit_children(node);
+                }
+                _ => self.visit_children(node),
+            },
+            NodeType::Block => {
+                self.scopes.push(HashSet::new());
+                self.visit_children(node);
+                self.scopes.pop();
+            }
+            _ => self.visit_children(node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link_files(files: &[(&str, &str)]) -> AstNode {
+        let files: HashMap<PathBuf, String> = files
+            .iter()
+            .map(|(path, content)| (PathBuf::from(path), content.to_string()))
+            .collect();
+        let main = &files[Path::new("main.tan")];
+        let ast = parser::parse(lexer::tokenize(main));
+        link_with(Path::new("main.tan"), ast, &|path| files.get(path).cloned())
+    }
+
+    fn names(ast: &AstNode) -> Vec<String> {
+        ast.children.iter().filter_map(definition_name).collect()
+    }
+
+    #[test]
+    fn modules_link_imports() {
+        let ast = link_files(&[
+            (
+                "main.tan",
+                "import math; import \"lib/vec.tan\"; print(math.sqrt(4), math.pi, vec.len());",
+            ),
+            (
+                "math.tan",
+                "fn half(int x) int { return x / 2; }; pub fn sqrt(int x) int { return half(x); }; pub int pi = 3;",
+            ),
+            (
+                "lib/vec.tan",
+                "import \"../math.tan\"; struct Vec { int x; }; pub fn len() int { Vec v = Vec { x: math.sqrt(9) }; return v.x; };",
+            ),
+        ]);
+
+        assert_eq!(
+            names(&ast),
+            vec!["math_half", "math_sqrt", "math_pi", "vec_Vec", "vec_len"]
+        );
+
+        let print = &ast.children[5];
+        assert_eq!(print.children[0].node_type, NodeType::FunctionCall);
+        assert_eq!(print.children[0].value, Some("math_sqrt".to_string()));
+        assert_eq!(print.children[1].node_type, NodeType::Identifier);
+        assert_eq!(print.children[1].value, Some("math_pi".to_string()));
+
+        let half = &ast.children[1].children[2].children[0].children[0];
+        assert_eq!(half.value, Some("math_half".to_string()));
+    }
+
+    #[test]
+    fn modules_record_source_files() {
+        let ast = link_files(&[
+            (
+                "main.tan",
+                "import \"lib/math.tan\";\nprint(math.twice(1));",
+            ),
+            (
+                "lib/math.tan",
+                "pub fn twice(int x) int {\n    return x * 2;\n};",
+            ),
+        ]);
+
+        let span = |node: &AstNode| node.span.clone().unwrap();
+        let twice = &ast.children[0];
+        assert_eq!(
+            span(twice),
+            Span {
+                file: Some("lib/math.tan".to_string()),
+                line: 1
+            }
+        );
+        assert_eq!(span(&twice.children[2].children[0]).line, 2);
+        assert_eq!(
+            span(&ast.children[1]),
+            Span {
+                file: Some("main.tan".to_string()),
+                line: 2
+            }
+        );
+    }
+
+    #[test]
+    fn modules_keep_locals() {
+        let ast = link_files(&[
+            ("main.tan", "import math; int math = 1;"),
+            (
+                "math.tan",
+                "int count = 1; fn get(int count) int { return count; }; count += 1;",
+            ),
+        ]);
+
+        let param = &ast.children[1].children[0].children[0].children[1];
+        assert_eq!(param.value, Some("count".to_string()));
+        let read = &ast.children[1].children[2].children[0].children[0];
+        assert_eq!(read.value, Some("count".to_string()));
+        assert_eq!(
+            ast.children[2].children[0].value,
+            Some("math_count".to_string())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Import cycle: main.tan -> a.tan -> b.tan -> main.tan")]
+    fn modules_reject_cycles() {
+        link_files(&[
+            ("main.tan", "import a;"),
+            ("a.tan", "import b;"),
+            ("b.tan", "import \"main.tan\";"),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "half is not pub in module math")]
+    fn modules_reject_private_access() {
+        link_files(&[
+            ("main.tan", "import math; math.half(2);"),
+            ("math.tan", "fn half(int x) int { return x / 2; };"),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Modules a/util.tan and b/util.tan are both named util")]
+    fn modules_reject_duplicate_names() {
+        link_files(&[
+            ("main.tan", "import \"a/util.tan\"; import \"b/util.tan\";"),
+            ("a/util.tan", ""),
+            ("b/util.tan", ""),
+        ]);
+    }
+}
@@ -0,0 +1,1131 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+use crate::ir::*;
+use crate::types::*;
+
+// Optimizations on the IR. Every pass rewrites the program in place and keeps its meaning,
+// the pass manager runs them in the order they are given
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pass {
+    Inline,
+    ConstantPropagation,
+    CopyPropagation,
+    CommonSubexpressions,
+    LoopInvariants,
+    DeadStores,
+    DeadCode,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 7] = [
+        Pass::Inline,
+        Pass::ConstantPropagation,
+        Pass::CopyPropagation,
+        Pass::CommonSubexpressions,
+        Pass::LoopInvariants,
+        Pass::DeadStores,
+        Pass::DeadCode,
+    ];
+
+    // The passes --optimize runs, copies left by reusing subexpressions are propagated again
+    pub const DEFAULT: [Pass; 8] = [
+        Pass::Inline,
+        Pass::ConstantPropagation,
+        Pass::CopyPropagation,
+        Pass::CommonSubexpressions,
+        Pass::CopyPropagation,
+        Pass::LoopInvariants,
+        Pass::DeadStores,
+        Pass::DeadCode,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Pass> {
+        Pass::ALL.into_iter().find(|pass| pass.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::Inline => "inline",
+            Pass::ConstantPropagation => "constprop",
+            Pass::CopyPropagation => "copyprop",
+            Pass::CommonSubexpressions => "cse",
+            Pass::LoopInvariants => "licm",
+            Pass::DeadStores => "dse",
+            Pass::DeadCode => "dce",
+        }
+    }
+
+    pub fn run(&self, program: &mut Program) {
+        if *self == Pass::Inline {
+            return inline(program);
+        }
+        let constants = program
+            .globals
+            .iter()
+            .filter(|global| global.constant)
+            .map(|global| (global.name.clone(), global.value.clone()))
+            .collect::<HashMap<String, Constant>>();
+        for function in &mut program.functions {
+            match self {
+                Pass::ConstantPropagation => constant_propagation(function, &constants),
+                Pass::CopyPropagation => copy_propagation(function),
+                Pass::CommonSubexpressions => common_subexpressions(function),
+                Pass::LoopInvariants => loop_invariants(function),
+                Pass::DeadStores => dead_stores(function),
+                Pass::DeadCode => dead_code(function),
+                Pass::Inline => unreachable!(),
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PassManager {
+    pub passes: Vec<Pass>,
+    // Dumps the program after this pass has run
+    pub print_after: Option<Pass>,
+}
+
+impl PassManager {
+    pub fn run(&self, program: &mut Program, output: &mut impl Write) {
+        for pass in &self.passes {
+            pass.run(program);
+            if self.print_after == Some(*pass) {
+                write!(output, "; after {}\n{}", pass.name(), program)
+                    .expect("Could not write the program");
+            }
+        }
+    }
+}
+
+// Solves a forward data flow problem, where facts hold at the start of a block when they
+// hold at the end of every predecessor that can be reached. Returns the facts at the start
+// of each block, or None for blocks that can not be reached
+fn forward<T: Clone + PartialEq>(
+    function: &Function,
+    entry: T,
+    meet: impl Fn(&T, &T) -> T,
+    transfer: impl Fn(&Block, T) -> T,
+) -> Vec<Option<T>> {
+    let predecessors = function.predecessors();
+    let mut ins: Vec<Option<T>> = vec![None; function.blocks.len()];
+    let mut outs: Vec<Option<T>> = vec![None; function.blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (index, block) in function.blocks.iter().enumerate() {
+            let mut facts = match index {
+                0 => Some(entry.clone()),
+                _ => None,
+            };
+            for predecessor in &predecessors[index] {
+                if let Some(out) = &outs[*predecessor] {
+                    facts = Some(match facts {
+                        Some(facts) => meet(&facts, out),
+                        None => out.clone(),
+                    });
+                }
+            }
+            let Some(facts) = facts else { continue };
+            let out = transfer(block, facts.clone());
+            ins[index] = Some(facts);
+            if outs[index].as_ref() != Some(&out) {
+                outs[index] = Some(out);
+                changed = true;
+            }
+        }
+    }
+    ins
+}
+
+// Runs a forward pass, the step both rewrites an instruction with the facts known before it
+// and updates them
+fn rewrite<T: Clone + PartialEq>(
+    function: &mut Function,
+    entry: T,
+    meet: impl Fn(&T, &T) -> T,
+    step: impl Fn(&Function, &mut T, &mut Instruction),
+    finish: impl Fn(&T, &mut Terminator),
+) {
+    let ins = forward(function, entry, meet, |block, mut facts| {
+        for instruction in &block.instructions {
+            step(function, &mut facts, &mut instruction.clone());
+        }
+        facts
+    });
+
+    for (index, facts) in ins.into_iter().enumerate() {
+        let Some(mut facts) = facts else { continue };
+        let mut instructions = std::mem::take(&mut function.blocks[index].instructions);
+        for instruction in &mut instructions {
+            step(function, &mut facts, instruction);
+        }
+        function.blocks[index].instructions = instructions;
+        finish(&facts, &mut function.blocks[index].terminator);
+    }
+}
+
+fn intersect<K: Clone + Eq + std::hash::Hash, V: Clone + PartialEq>(
+    left: &HashMap<K, V>,
+    right: &HashMap<K, V>,
+) -> HashMap<K, V> {
+    left.iter()
+        .filter(|(key, value)| right.get(key) == Some(value))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+fn substitute(operand: &mut Operand, values: &HashMap<Temp, Operand>) {
+    if let Operand::Temp(temp) = operand {
+        if let Some(value) = values.get(temp) {
+            *operand = value.clone();
+        }
+    }
+}
+
+// ------------------------------ Propagation ------------------------------
+// Replaces temporaries known to hold a constant and loads of constant globals with the
+// value, folds operations on constants and branches on a constant condition
+fn constant_propagation(function: &mut Function, globals: &HashMap<String, Constant>) {
+    rewrite(
+        function,
+        HashMap::new(),
+        intersect,
+        |function, constants: &mut HashMap<Temp, Operand>, instruction| {
+            for operand in instruction.operands_mut() {
+                substitute(operand, constants);
+            }
+            let value = match instruction {
+                Instruction::LoadGlobal { name, .. } => globals.get(name).cloned(),
+                _ => fold(function, instruction),
+            };
+            if let Some(value) = value {
+                let dest = instruction.dest().unwrap();
+                *instruction = Instruction::Copy {
+                    dest,
+                    src: Operand::Constant(value.clone()),
+                };
+                constants.insert(dest, Operand::Constant(value));
+            } else if let Some(dest) = instruction.dest() {
+                constants.remove(&dest);
+            }
+        },
+        |constants, terminator| {
+            for operand in terminator.operands_mut() {
+                substitute(operand, constants);
+            }
+            if let Terminator::Branch {
+                condition: Operand::Constant(Constant::Boolean(condition)),
+                then,
+                otherwise,
+            } = terminator
+            {
+                *terminator = Terminator::Jump(if *condition { *then } else { *otherwise });
+            }
+        },
+    );
+}
+
+// Value of an instruction whose operands are all constants
+fn fold(function: &Function, instruction: &Instruction) -> Option<Constant> {
+    match instruction {
+        Instruction::Copy {
+            src: Operand::Constant(value),
+            ..
+        } => Some(value.clone()),
+        Instruction::Binary {
+            dest,
+            op,
+            left: Operand::Constant(left),
+            right: Operand::Constant(right),
+        } => fold_binary(*op, left, right, &function.temps[*dest]),
+        Instruction::Unary {
+            op,
+            src: Operand::Constant(value),
+            ..
+        } => match (op, value) {
+            (UnaryOp::Not, Constant::Boolean(value)) => Some(Constant::Boolean(!value)),
+            (UnaryOp::BitwiseNot, Constant::Integer(value, data_type)) => Some(Constant::Integer(
+                data_type.wrap_integer(!value),
+                data_type.clone(),
+            )),
+            _ => None,
+        },
+        Instruction::Compare {
+            op,
+            left: Operand::Constant(left),
+            right: Operand::Constant(right),
+            ..
+        } => {
+            let ordering = match (left, right) {
+                (Constant::Integer(left, _), Constant::Integer(right, _)) => {
+                    left.partial_cmp(right)
+                }
+                (Constant::Float(left), Constant::Float(right)) => left.partial_cmp(right),
+                (Constant::Boolean(left), Constant::Boolean(right)) => left.partial_cmp(right),
+                (Constant::String(left), Constant::String(right)) => left.partial_cmp(right),
+                _ => return None,
+            };
+            let result = match ordering {
+                Some(ordering) => match op {
+                    Comparison::Eq => ordering.is_eq(),
+                    Comparison::NotEq => ordering.is_ne(),
+                    Comparison::LessThan => ordering.is_lt(),
+                    Comparison::GreaterThan => ordering.is_gt(),
+                    Comparison::Leq => ordering.is_le(),
+                    Comparison::Geq => ordering.is_ge(),
+                },
+                // Comparisons with NaN are only unequal
+                None => *op == Comparison::NotEq,
+            };
+            Some(Constant::Boolean(result))
+        }
+        Instruction::Convert {
+            dest,
+            src: Operand::Constant(value),
+        } => value.convert(&function.temps[*dest]),
+        _ => None,
+    }
+}
+
+// Computes like the interpreter, divisions by zero are left to trap when they run
+fn fold_binary(
+    op: BinaryOp,
+    left: &Constant,
+    right: &Constant,
+    data_type: &DataType,
+) -> Option<Constant> {
+    match (left, right) {
+        (Constant::Integer(left, _), Constant::Integer(right, _)) => {
+            let value = match op {
+                BinaryOp::Add => left.wrapping_add(*right),
+                BinaryOp::Subtract => left.wrapping_sub(*right),
+                BinaryOp::Multiply => left.wrapping_mul(*right),
+                BinaryOp::Divide | BinaryOp::Mod if *right == 0 => return None,
+                BinaryOp::Divide => left.wrapping_div(*right),
+                BinaryOp::Mod => left.wrapping_rem(*right),
+                BinaryOp::And => left & right,
+                BinaryOp::Or => left | right,
+                BinaryOp::Xor => left ^ right,
+            };
+            Some(Constant::Integer(
+                data_type.wrap_integer(value),
+                data_type.clone(),
+            ))
+        }
+        (Constant::Float(left), Constant::Float(right)) => Some(Constant::Float(match op {
+            BinaryOp::Add => left + right,
+            BinaryOp::Subtract => left - right,
+            BinaryOp::Multiply => left * right,
+            BinaryOp::Divide => left / right,
+            BinaryOp::Mod => left % right,
+            _ => return None,
+        })),
+        _ => None,
+    }
+}
+
+// Replaces uses of a copy with its source, as long as neither was assigned since
+fn copy_propagation(function: &mut Function) {
+    rewrite(
+        function,
+        HashMap::new(),
+        intersect,
+        |_, copies: &mut HashMap<Temp, Operand>, instruction| {
+            for operand in instruction.operands_mut() {
+                substitute(operand, copies);
+            }
+            if let Some(dest) = instruction.dest() {
+                copies.retain(|copy, source| *copy != dest && *source != Operand::Temp(dest));
+                if let Instruction::Copy {
+                    src: Operand::Temp(source),
+                    ..
+                } = instruction
+                {
+                    if *source != dest {
+                        copies.insert(dest, Operand::Temp(*source));
+                    }
+                }
+            }
+        },
+        |copies, terminator| {
+            for operand in terminator.operands_mut() {
+                substitute(operand, copies);
+            }
+        },
+    );
+}
+
+// ------------------------------ Common subexpressions ------------------------------
+// Reuses the result of an operation computed before on every path, while its operands and
+// the temporary holding it were not assigned. Loads of globals are reused until the global
+// is stored to or a function is called
+fn common_subexpressions(function: &mut Function) {
+    rewrite(
+        function,
+        vec![],
+        |left: &Vec<((Instruction, DataType), Temp)>, right| {
+            left.iter()
+                .filter(|available| right.contains(available))
+                .cloned()
+                .collect()
+        },
+        |function, available, instruction| {
+            if let Some(key) = expression(function, instruction) {
+                if let Some((_, temp)) = available.iter().find(|(other, _)| *other == key) {
+                    *instruction = Instruction::Copy {
+                        dest: instruction.dest().unwrap(),
+                        src: Operand::Temp(*temp),
+                    };
+                }
+            }
+
+            match instruction {
+                Instruction::StoreGlobal { name, .. } => available.retain(|((key, _), _)| {
+                    !matches!(key, Instruction::LoadGlobal { name: other, .. } if other == name)
+                }),
+                Instruction::Call { .. } => available
+                    .retain(|((key, _), _)| !matches!(key, Instruction::LoadGlobal { .. })),
+                _ => {}
+            }
+            if let Some(dest) = instruction.dest() {
+                available.retain(|((key, _), temp)| *temp != dest && !key.uses().contains(&dest));
+                if let Some(key) = expression(function, instruction) {
+                    if !key.0.uses().contains(&dest) {
+                        available.push((key, dest));
+                    }
+                }
+            }
+        },
+        |_, _| {},
+    );
+}
+
+// The operation an instruction computes without its destination, when it can be reused, and
+// the type it computes. Conversions of one operand to different types differ only in it
+fn expression(function: &Function, instruction: &Instruction) -> Option<(Instruction, DataType)> {
+    match instruction {
+        Instruction::Binary { .. }
+        | Instruction::Unary { .. }
+        | Instruction::Compare { .. }
+        | Instruction::Convert { .. }
+        | Instruction::LoadGlobal { .. } => {
+            let mut key = instruction.clone();
+            let dest = key.dest_mut().unwrap();
+            let data_type = function.temps[*dest].clone();
+            *dest = Temp::MAX;
+            Some((key, data_type))
+        }
+        _ => None,
+    }
+}
+
+// ------------------------------ Loop invariants ------------------------------
+// Moves operations whose operands do not change in a loop in front of it. The result has to
+// be assigned only there, must not be used before it in the loop, and is either only used
+// in the loop or computed on every way out of it. Operations that can trap are left in place,
+// the loop might not run at all
+fn loop_invariants(function: &mut Function) {
+    while hoist(function) {}
+}
+
+// Hoists the invariants of one loop, or adds a block in front of one, and tells if it did
+fn hoist(function: &mut Function) -> bool {
+    let dominators = dominators(function);
+    let predecessors = function.predecessors();
+    let (live_in, _) = function.liveness();
+
+    for (header, body) in loops(function, &dominators, &predecessors) {
+        let outside = predecessors[header]
+            .iter()
+            .copied()
+            .filter(|predecessor| !body.contains(predecessor))
+            .collect::<Vec<BlockId>>();
+        let preheader = match outside[..] {
+            [predecessor]
+                if function.blocks[predecessor].terminator.successors() == vec![header] =>
+            {
+                predecessor
+            }
+            _ => {
+                insert_block(function, header, &outside);
+                return true;
+            }
+        };
+
+        let mut assigned = HashMap::new();
+        let mut stored = HashSet::new();
+        let mut calls = false;
+        for block in &body {
+            for instruction in &function.blocks[*block].instructions {
+                if let Some(dest) = instruction.dest() {
+                    *assigned.entry(dest).or_insert(0) += 1;
+                }
+                match instruction {
+                    Instruction::StoreGlobal { name, .. } => {
+                        stored.insert(name.clone());
+                    }
+                    Instruction::Call { .. } => calls = true,
+                    _ => {}
+                }
+            }
+        }
+        let exits = body
+            .iter()
+            .flat_map(|block| {
+                function.blocks[*block]
+                    .terminator
+                    .successors()
+                    .into_iter()
+                    .filter(|successor| !body.contains(successor))
+                    .map(|successor| (*block, successor))
+            })
+            .collect::<Vec<(BlockId, BlockId)>>();
+
+        let mut hoisted = vec![];
+        for block in &body {
+            let mut index = 0;
+            while index < function.blocks[*block].instructions.len() {
+                let instruction = &function.blocks[*block].instructions[index];
+                let invariant = match instruction {
+                    Instruction::LoadGlobal { name, .. } => !calls && !stored.contains(name),
+                    Instruction::Call { .. }
+                    | Instruction::Builtin { .. }
+                    | Instruction::StoreGlobal { .. } => false,
+                    instruction => instruction.is_pure(),
+                } && instruction
+                    .uses()
+                    .iter()
+                    .all(|temp| !assigned.contains_key(temp));
+                let movable = instruction.dest().is_some_and(|dest| {
+                    assigned[&dest] == 1
+                        && !live_in[header].contains(&dest)
+                        && exits.iter().all(|(from, to)| {
+                            !live_in[*to].contains(&dest) || dominators[*from].contains(block)
+                        })
+                });
+
+                if invariant && movable {
+                    let instruction = function.blocks[*block].instructions.remove(index);
+                    assigned.remove(&instruction.dest().unwrap());
+                    hoisted.push(instruction);
+                } else {
+                    index += 1;
+                }
+            }
+        }
+
+        if !hoisted.is_empty() {
+            function.blocks[preheader].instructions.extend(hoisted);
+            return true;
+        }
+    }
+    false
+}
+
+// Blocks that dominate each block, that is every path from the entry to it goes through them
+fn dominators(function: &Function) -> Vec<HashSet<BlockId>> {
+    let predecessors = function.predecessors();
+    let all = (0..function.blocks.len()).collect::<HashSet<BlockId>>();
+    let mut dominators = vec![all; function.blocks.len()];
+    dominators[0] = HashSet::from([0]);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for index in 1..function.blocks.len() {
+            let mut dominated = predecessors[index]
+                .iter()
+                .map(|predecessor| dominators[*predecessor].clone())
+                .reduce(|left, right| &left & &right)
+                .unwrap_or_default();
+            dominated.insert(index);
+            if dominated != dominators[index] {
+                dominators[index] = dominated;
+                changed = true;
+            }
+        }
+    }
+    dominators
+}
+
+// Natural loops by their header, found from the jumps back to a block that dominates them
+fn loops(
+    function: &Function,
+    dominators: &[HashSet<BlockId>],
+    predecessors: &[Vec<BlockId>],
+) -> Vec<(BlockId, Vec<BlockId>)> {
+    let mut loops: Vec<(BlockId, Vec<BlockId>)> = vec![];
+    for (index, block) in function.blocks.iter().enumerate() {
+        for header in block.terminator.successors() {
+            if !dominators[index].contains(&header) {
+                continue;
+            }
+            let mut body = vec![header];
+            let mut pending = vec![index];
+            while let Some(block) = pending.pop() {
+                if !body.contains(&block) {
+                    body.push(block);
+                    pending.extend(&predecessors[block]);
+                }
+            }
+            match loops.iter_mut().find(|(other, _)| *other == header) {
+                Some((_, other)) => {
+                    for block in body {
+                        if !other.contains(&block) {
+                            other.push(block);
+                        }
+                    }
+                }
+                None => loops.push((header, body)),
+            }
+        }
+    }
+    for (_, body) in &mut loops {
+        body.sort();
+    }
+    loops
+}
+
+// Puts an empty block in front of a loop header, the given predecessors jump to it instead
+fn insert_block(function: &mut Function, header: BlockId, predecessors: &[BlockId]) {
+    for block in &mut function.blocks {
+        for target in block.terminator.successors_mut() {
+            if *target >= header {
+                *target += 1;
+            }
+        }
+    }
+    function.blocks.insert(
+        header,
+        Block {
+            instructions: vec![],
+            terminator: Terminator::Jump(header + 1),
+        },
+    );
+    for predecessor in predecessors {
+        let predecessor = if *predecessor >= header {
+            predecessor + 1
+        } else {
+            *predecessor
+        };
+        for target in function.blocks[predecessor].terminator.successors_mut() {
+            if *target == header + 1 {
+                *target = header;
+            }
+        }
+    }
+}
+
+// ------------------------------ Dead code ------------------------------
+// Removes assignments to temporaries that are not used before the next assignment, and
+// stores to globals that are stored again in the same block before they can be read
+fn dead_stores(function: &mut Function) {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        let (_, live_out) = function.liveness();
+        for (index, block) in function.blocks.iter_mut().enumerate() {
+            let mut live = live_out[index].clone();
+            live.extend(block.terminator.uses());
+            let mut overwritten = HashSet::new();
+
+            let mut instructions = vec![];
+            for mut instruction in std::mem::take(&mut block.instructions).into_iter().rev() {
+                if let Some(dest) = instruction.dest() {
+                    if !live.contains(&dest) {
+                        match &mut instruction {
+                            Instruction::Call { dest, .. } | Instruction::Builtin { dest, .. } => {
+                                *dest = None;
+                                changed = true;
+                            }
+                            instruction if instruction.is_pure() => {
+                                changed = true;
+                                continue;
+                            }
+                            _ => {}
+                        }
+                    }
+                    live.remove(&dest);
+                }
+                match &instruction {
+                    Instruction::StoreGlobal { name, .. } if !overwritten.insert(name.clone()) => {
+                        changed = true;
+                        continue;
+                    }
+                    Instruction::LoadGlobal { name, .. } => {
+                        overwritten.remove(name);
+                    }
+                    Instruction::Call { .. } => overwritten.clear(),
+                    _ => {}
+                }
+                live.extend(instruction.uses());
+                instructions.push(instruction);
+            }
+            instructions.reverse();
+            block.instructions = instructions;
+        }
+    }
+}
+
+// Removes blocks that can not be reached, jumps to empty blocks and jumps between blocks
+// that follow each other, pure operations whose result is never used, and temporaries that
+// are no longer used
+fn dead_code(function: &mut Function) {
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for block in &mut function.blocks {
+            if let Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } = &block.terminator
+            {
+                let target = match condition {
+                    _ if then == otherwise => *then,
+                    Operand::Constant(Constant::Boolean(true)) => *then,
+                    Operand::Constant(Constant::Boolean(false)) => *otherwise,
+                    _ => continue,
+                };
+                block.terminator = Terminator::Jump(target);
+                changed = true;
+            }
+        }
+
+        // Jumps to an empty block go where it jumps to, unless the empty blocks loop. A block
+        // with only source lines counts as empty
+        let forward = |mut target: BlockId| {
+            let mut seen = vec![];
+            while let Terminator::Jump(next) = function.blocks[target].terminator {
+                let empty = function.blocks[target]
+                    .instructions
+                    .iter()
+                    .all(|instruction| matches!(instruction, Instruction::Line(_)));
+                if target == 0 || !empty {
+                    break;
+                }
+                if seen.contains(&next) {
+                    return None;
+                }
+                seen.push(target);
+                target = next;
+            }
+            Some(target)
+        };
+        let targets = (0..function.blocks.len())
+            .map(forward)
+            .collect::<Vec<Option<BlockId>>>();
+        for block in &mut function.blocks {
+            for target in block.terminator.successors_mut() {
+                if let Some(next) = targets[*target] {
+                    if next != *target {
+                        *target = next;
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        // A block that is the only way into the next one takes over its code
+        let predecessors = function.predecessors();
+        for index in 0..function.blocks.len() {
+            if let Terminator::Jump(target) = function.blocks[index].terminator {
+                if target != index && target > 0 && predecessors[target] == vec![index] {
+                    let next = std::mem::replace(
+                        &mut function.blocks[target],
+                        Block {
+                            instructions: vec![],
+                            terminator: Terminator::Jump(target),
+                        },
+                    );
+                    let block = &mut function.blocks[index];
+                    block.instructions.extend(next.instructions);
+                    block.terminator = next.terminator;
+                    changed = true;
+                    break;
+                }
+            }
+        }
+
+        changed |= remove_unreachable(function);
+    }
+
+    // Results that are never read anywhere
+    let mut changed = true;
+    while changed {
+        let used = function
+            .blocks
+            .iter()
+            .flat_map(|block| {
+                block
+                    .instructions
+                    .iter()
+                    .flat_map(|instruction| instruction.uses())
+                    .chain(block.terminator.uses())
+            })
+            .collect::<HashSet<Temp>>();
+        changed = false;
+        for block in &mut function.blocks {
+            block.instructions.retain(|instruction| {
+                let unused = instruction.is_pure()
+                    && instruction.dest().is_some_and(|dest| !used.contains(&dest));
+                changed |= unused;
+                !unused
+            });
+        }
+    }
+
+    compact_temps(function);
+}
+
+fn remove_unreachable(function: &mut Function) -> bool {
+    let mut reachable = vec![false; function.blocks.len()];
+    let mut pending = vec![0];
+    while let Some(block) = pending.pop() {
+        if !reachable[block] {
+            reachable[block] = true;
+            pending.extend(function.blocks[block].terminator.successors());
+        }
+    }
+    if reachable.iter().all(|reachable| *reachable) {
+        return false;
+    }
+
+    let mut numbers = vec![0; function.blocks.len()];
+    let mut count = 0;
+    for (index, reachable) in reachable.iter().enumerate() {
+        numbers[index] = count;
+        count += *reachable as usize;
+    }
+    let mut index = 0;
+    function.blocks.retain(|_| {
+        index += 1;
+        reachable[index - 1]
+    });
+    for block in &mut function.blocks {
+        for target in block.terminator.successors_mut() {
+            *target = numbers[*target];
+        }
+    }
+    true
+}
+
+// Numbers the temporaries that are still used from zero
+fn compact_temps(function: &mut Function) {
+    let mut used = function.params.iter().copied().collect::<HashSet<Temp>>();
+    for block in &function.blocks {
+        for instruction in &block.instructions {
+            used.extend(instruction.dest());
+            used.extend(instruction.uses());
+        }
+        used.extend(block.terminator.uses());
+    }
+
+    let mut numbers = HashMap::new();
+    let mut temps = vec![];
+    for (temp, data_type) in function.temps.iter().enumerate() {
+        if used.contains(&temp) {
+            numbers.insert(temp, temps.len());
+            temps.push(data_type.clone());
+        }
+    }
+    rename_temps(function, &numbers);
+    function.temps = temps;
+}
+
+fn rename_temps(function: &mut Function, numbers: &HashMap<Temp, Temp>) {
+    let rename = |operand: &mut Operand| {
+        if let Operand::Temp(temp) = operand {
+            *temp = numbers[temp];
+        }
+    };
+    for block in &mut function.blocks {
+        for instruction in &mut block.instructions {
+            if let Some(dest) = instruction.dest_mut() {
+                *dest = numbers[dest];
+            }
+            instruction.operands_mut().into_iter().for_each(rename);
+        }
+        block.terminator.operands_mut().into_iter().for_each(rename);
+    }
+    for param in &mut function.params {
+        *param = numbers[param];
+    }
+    function.names = std::mem::take(&mut function.names)
+        .into_iter()
+        .filter_map(|(temp, name)| Some((*numbers.get(&temp)?, name)))
+        .collect();
+}
+
+// ------------------------------ Inlining ------------------------------
+// Functions with at most this many instructions are inlined
+const INLINE_SIZE: usize = 16;
+
+// Replaces calls to small functions that do not call themselves with their body. The body
+// gets its own temporaries, the arguments are copied to its parameters and its returns
+// jump to the code after the call. Code that was inlined is not inlined into again
+fn inline(program: &mut Program) {
+    let callees = program
+        .functions
+        .iter()
+        .filter(|function| {
+            let instructions = function
+                .blocks
+                .iter()
+                .flat_map(|block| &block.instructions)
+                .filter(|instruction| !matches!(instruction, Instruction::Line(_)));
+            function.name != "main"
+                && instructions.clone().count() <= INLINE_SIZE
+                && !instructions.clone().any(|instruction| {
+                    matches!(instruction, Instruction::Call { function: name, .. } if *name == function.name)
+                })
+        })
+        .map(|function| (function.name.clone(), function.clone()))
+        .collect::<HashMap<String, Function>>();
+
+    for function in &mut program.functions {
+        let mut index = 0;
+        while index < function.blocks.len() {
+            let call = function.blocks[index]
+                .instructions
+                .iter()
+                .position(|instruction| match instruction {
+                    Instruction::Call { function: name, .. } => callees.contains_key(name),
+                    _ => false,
+                });
+            index = match call {
+                Some(call) => inline_call(function, index, call, &callees),
+                None => index + 1,
+            };
+        }
+    }
+}
+
+// Inlines the call and returns the block with the code after it
+fn inline_call(
+    function: &mut Function,
+    block: BlockId,
+    call: usize,
+    callees: &HashMap<String, Function>,
+) -> BlockId {
+    let mut instructions = std::mem::take(&mut function.blocks[block].instructions);
+    let rest = instructions.split_off(call + 1);
+    let Some(Instruction::Call {
+        dest,
+        function: name,
+        args,
+    }) = instructions.pop()
+    else {
+        unreachable!()
+    };
+    let callee = &callees[&name];
+
+    // Blocks after the call move behind the body and the code after the call
+    let start = block + 1;
+    let after = start + callee.blocks.len();
+    for other in &mut function.blocks {
+        for target in other.terminator.successors_mut() {
+            if *target > block {
+                *target += callee.blocks.len() + 1;
+            }
+        }
+    }
+
+    let offset = function.temps.len();
+    function.temps.extend(callee.temps.iter().cloned());
+    for (temp, name) in &callee.names {
+        function.names.insert(temp + offset, name.clone());
+    }
+    for (param, arg) in callee.params.iter().zip(args) {
+        instructions.push(Instruction::Copy {
+            dest: param + offset,
+            src: arg,
+        });
+    }
+
+    let rename = |operand: &mut Operand| {
+        if let Operand::Temp(temp) = operand {
+            *temp += offset;
+        }
+    };
+    let mut body = vec![];
+    for callee_block in &callee.blocks {
+        let mut instructions = callee_block.instructions.clone();
+        for instruction in &mut instructions {
+            if let Some(dest) = instruction.dest_mut() {
+                *dest += offset;
+            }
+            instruction.operands_mut().into_iter().for_each(rename);
+        }
+        let mut terminator = callee_block.terminator.clone();
+        terminator.operands_mut().into_iter().for_each(rename);
+        for target in terminator.successors_mut() {
+            *target += start;
+        }
+        if let Terminator::Return(value) = terminator {
+            if let (Some(dest), Some(value)) = (dest, value) {
+                instructions.push(Instruction::Copy { dest, src: value });
+            }
+            terminator = Terminator::Jump(after);
+        }
+        body.push(Block {
+            instructions,
+            terminator,
+        });
+    }
+
+    let terminator = std::mem::replace(
+        &mut function.blocks[block].terminator,
+        Terminator::Jump(start),
+    );
+    function.blocks[block].instructions = instructions;
+    body.push(Block {
+        instructions: rest,
+        terminator,
+    });
+    function.blocks.splice(start..start, body);
+    after
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::lower_script;
+
+    fn dump(script: &str, passes: &[Pass]) -> String {
+        lower_script(script, passes).to_string()
+    }
+
+    #[test]
+    fn optimize_golden_scalars() {
+        assert_eq!(
+            dump(include_str!("../golden/scalars.tan"), &Pass::DEFAULT),
+            include_str!("../golden/ir/scalars.opt.ir")
+        );
+    }
+
+    #[test]
+    fn optimize_propagates_and_folds_constants() {
+        let program = lower_script(
+            "fn f() int { int x = 6; int y = x * 7; if y > 40 { return y; }; return 0; };",
+            &[Pass::ConstantPropagation, Pass::DeadStores, Pass::DeadCode],
+        );
+        assert_eq!(
+            program.functions[0].to_string(),
+            "fn f() int {\nbb0:\n  ret int 42\n}\n"
+        );
+    }
+
+    #[test]
+    fn optimize_does_not_fold_division_by_zero() {
+        let dump = dump(
+            "fn f() int { int x = 0; return 5 / x; };",
+            &[Pass::ConstantPropagation],
+        );
+        assert!(dump.contains("  %0 = divide int 5, 0\n"));
+    }
+
+    #[test]
+    fn optimize_propagates_copies() {
+        let dump = dump(
+            "fn f(int a) int { int b = a; int c = b + 1; b = 3; return c + b; };",
+            &[Pass::CopyPropagation],
+        );
+        assert!(dump.contains("  %0 = add int %a, 1\n"));
+        assert!(dump.contains("  %1 = add int %0, %b\n"));
+    }
+
+    #[test]
+    fn optimize_reuses_common_subexpressions() {
+        let dump = dump(
+            "fn f(int a, int b) int { int x = a * b; int y = a * b; a = 1; int z = a * b; return x + y + z; };",
+            &[Pass::CommonSubexpressions],
+        );
+        assert!(dump.contains("  %0 = multiply int %a, %b\n"));
+        assert!(dump.contains("  %1 = copy int %0\n"));
+        assert!(dump.contains("  %2 = multiply int %a, %b\n"));
+    }
+
+    #[test]
+    fn optimize_keeps_conversions_to_different_types() {
+        let dump = dump(
+            "fn f(float x) int { print(x as i64, x as u64); return 0; };",
+            &[Pass::CommonSubexpressions],
+        );
+        assert!(dump.contains("  %0 = convert float %x to i64\n  %1 = convert float %x to u64\n"));
+    }
+
+    #[test]
+    fn optimize_removes_dead_stores() {
+        let dump = dump(
+            "int g = 0; fn f(int a) int { int x = a * 2; x = a + 1; g = 1; g = x; return x; };",
+            &[Pass::DeadStores],
+        );
+        assert!(!dump.contains("multiply"));
+        assert!(!dump.contains("store int 1, @g"));
+        assert!(dump.contains("  store int %x, @g\n"));
+    }
+
+    #[test]
+    fn optimize_removes_unreachable_code() {
+        let program = lower_script(
+            "fn f(int a) int { if true { return a; } else { print(a); }; return 0; };",
+            &[Pass::ConstantPropagation, Pass::DeadCode],
+        );
+        assert_eq!(
+            program.functions[0].to_string(),
+            "fn f(int %a) int {\nbb0:\n  ret int %a\n}\n"
+        );
+    }
+
+    #[test]
+    fn optimize_hoists_loop_invariants() {
+        let program = lower_script(
+            "fn f(int n, int k) int { int i = 0; int total = 0; while i < n { total += k * 3; i += 1; }; return total; };",
+            &[Pass::LoopInvariants],
+        );
+        let dump = program.functions[0].to_string();
+        let (before, after) = dump.split_once("bb1:").unwrap();
+        assert!(before.contains("  %1 = multiply int %k, 3\n"));
+        assert!(!after.contains("multiply"));
+    }
+
+    #[test]
+    fn optimize_keeps_loop_code_that_can_trap() {
+        let dump = dump(
+            "fn f(int n, int k) int { int i = 0; while i < n { i += 10 / k; }; return i; };",
+            &[Pass::LoopInvariants],
+        );
+        assert!(dump.contains("bb2:\n  %1 = divide int 10, %k\n"));
+    }
+
+    #[test]
+    fn optimize_inlines_small_functions() {
+        let program = lower_script(
+            "fn square(int x) int { return x * x; };
+            fn fib(int n) int { if n < 2 { return n; }; return fib(n - 1) + fib(n - 2); };
+            print(square(3), fib(3));",
+            &[Pass::Inline],
+        );
+        let main = program.functions[2].to_string();
+        assert!(main.contains("  %x = copy int 3\n  jmp bb1\nbb1:\n  %2 = multiply int %x, %x\n  %0 = copy int %2\n  jmp bb2\n"));
+        assert!(main.contains("call int fib(3)"));
+        assert!(!program.functions[1].to_string().contains("%n.1"));
+    }
+
+    #[test]
+    fn optimize_prints_after_a_pass() {
+        let mut program = lower_script("int x = 1 + 2; print(x);", &[]);
+        let manager = PassManager {
+            passes: vec![Pass::ConstantPropagation, Pass::DeadCode],
+            print_after: Some(Pass::ConstantPropagation),
+        };
+        let mut output = Vec::new();
+        manager.run(&mut program, &mut output);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("; after constprop\nglobal @x: int = 0\n"));
+        assert!(output.contains("  %0 = copy int 3\n"));
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use crate::ast::*;
 use crate::lexer::LexerToken;
 use crate::types::*;
@@ -5,6 +7,11 @@ use crate::types::*;
 struct Parser {
     tokens: Vec<LexerToken>,
     position: usize,
+    // Names of user defined types, needed to tell declarations from expressions
+    types: HashSet<String>,
+    enums: HashSet<String>,
+    // Type parameters of the generic definition being parsed
+    type_params: Vec<String>,
 }
 
 impl Parser {
@@ -19,10 +26,37 @@ impl Parser {
     fn is_end(&self) -> bool {
         self.position >= self.tokens.len()
     }
+
+    fn is_type_name(&self, name: &str) -> bool {
+        self.types.contains(name) || self.type_params.iter().any(|param| param == name)
+    }
+
+    fn is_enum_name(&self, name: &str) -> bool {
+        self.enums.contains(name)
+    }
+
+    // fn (int x) int { ... } is a lambda and fn(int) int f = ... declares a function value.
+    // The body of a lambda comes before anything that ends a declaration
+    fn is_lambda(&self) -> bool {
+        let end = self.tokens[self.position..].iter().find(|token| {
+            matches!(
+                token.token,
+                Token::LCurly
+                    | Token::Assign
+                    | Token::Semi
+                    | Token::SignalAssign
+                    | Token::ComputeAssign
+            )
+        });
+        matches!(end, Some(token) if token.token == Token::LCurly)
+    }
 }
 
 pub fn parse(tokens: Vec<LexerToken>) -> AstNode {
     let mut parser = Parser {
+        types: collect_type_names(&tokens, &[Token::Struct, Token::Enum]),
+        enums: collect_type_names(&tokens, &[Token::Enum]),
+        type_params: vec![],
         tokens,
         position: 0,
     };
@@ -31,6 +65,8 @@ pub fn parse(tokens: Vec<LexerToken>) -> AstNode {
         node_type: NodeType::Block,
         children: vec![],
         value: None,
+        data_type: None,
+        span: None,
     };
 
     while parser.position < parser.tokens.len() {
@@ -41,28 +77,76 @@ pub fn parse(tokens: Vec<LexerToken>) -> AstNode {
     root
 }
 
+// Types can be used before their definition, so their names are collected up front
+fn collect_type_names(tokens: &[LexerToken], keywords: &[Token]) -> HashSet<String> {
+    tokens
+        .windows(2)
+        .filter_map(|pair| match (&pair[0].token, &pair[1].token) {
+            (keyword, Token::Identifier(name)) if keywords.contains(keyword) => Some(name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
 fn parse_statement(parser: &mut Parser) -> AstNode {
-    let node = parse_next(parser);
+    let node = parse_spanned(parser);
     consume_token(parser, Token::Semi);
     node
 }
 
+// Statements remember the line they start on
+fn parse_spanned(parser: &mut Parser) -> AstNode {
+    let line = parser.get_current_token().line;
+    let mut node = parse_next(parser);
+    node.span = Some(Span { file: None, line });
+    node
+}
+
 fn parse_next(parser: &mut Parser) -> AstNode {
     let token = parser.get_current_token();
     match token.token {
+        // Dereferences and parentheses can start an assignment target
+        Token::Operator(Operator::Multiply) | Token::LParen => {
+            parse_expression_or_assignment(parser)
+        }
         Token::Operator(_)
         | Token::Not
         | Token::BitwiseOp(BitwiseOp::Not)
-        | Token::Number(_)
-        | Token::Boolean(_) => parse_expression(parser),
+        | Token::BitwiseOp(BitwiseOp::And)
+        | Token::BitwiseOp(BitwiseOp::Or)
+        | Token::Or
+        | Token::Number(..)
+        | Token::Boolean(_)
+        | Token::String(_)
+        | Token::Null
+        | Token::New
+        | Token::Signal(_)
+        | Token::Computed(_) => parse_expression(parser),
+        Token::Identifier(ref name) if parser.is_type_name(name) => parse_declare(parser),
         Token::Identifier(_) => parse_expression_or_assignment(parser),
-        Token::Type(_) => parse_declare(parser),
-        Token::LParen => parse_parentheses(parser),
+        Token::Type(_) | Token::Object => parse_declare(parser),
+        Token::Struct => parse_struct(parser),
+        Token::Enum => parse_enum(parser),
+        Token::Match => parse_match(parser),
+        // fn( starts a lambda or a declaration of a function value, fn name( a function
+        // definition
+        Token::Function if matches!(parser.get_next(1), Some(next) if next.token == Token::LParen) => {
+            match parser.is_lambda() {
+                true => parse_expression(parser),
+                false => parse_declare(parser),
+            }
+        }
         Token::Function => parse_function(parser),
+        Token::Extern => parse_extern(parser),
+        Token::Const => parse_const(parser),
+        Token::Import => parse_import(parser),
+        Token::Pub => parse_pub(parser),
         Token::LCurly => parse_block(parser),
         Token::Return => parse_return(parser),
         Token::If => parse_if(parser),
         Token::While => parse_while(parser),
+        Token::Label(_) => parse_labelled_loop(parser),
+        Token::Break | Token::Continue => parse_loop_jump(parser),
         Token::Assign => panic!("Unexpected assign"),
         Token::ShortAssign(_) => panic!("Unexpected short assign"),
         Token::Increment => panic!("Unexpected increment"),
@@ -72,6 +156,8 @@ fn parse_next(parser: &mut Parser) -> AstNode {
         Token::Semi => panic!("Unexpected semicolon"),
         Token::Comma => panic!("Unexpected comma"),
         Token::RCurly => panic!("Unexpected RCurly"),
+        Token::LBracket => panic!("Unexpected LBracket"),
+        Token::RBracket => panic!("Unexpected RBracket"),
         Token::Eq => panic!("Unexpected Eq"),
         Token::NotEq => panic!("Unexpected NotEq"),
         Token::LessThan => panic!("Unexpected LessThan"),
@@ -79,25 +165,63 @@ fn parse_next(parser: &mut Parser) -> AstNode {
         Token::Leq => panic!("Unexpected Leq"),
         Token::Geq => panic!("Unexpected Geq"),
         Token::And => panic!("Unexpected And"),
-        Token::Or => panic!("Unexpected Or"),
         Token::BitwiseOp(_) => panic!("Unexpected BitwiseOp"),
         Token::Colon => panic!("Unexpected colon"),
+        Token::Dot => panic!("Unexpected dot"),
+        Token::Arrow => panic!("Unexpected arrow"),
+        Token::DoubleColon => panic!("Unexpected double colon"),
+        Token::FatArrow => panic!("Unexpected fat arrow"),
+        Token::Underscore => panic!("Unexpected underscore"),
+        Token::SignalAssign => panic!("Unexpected signal assign"),
+        Token::ComputeAssign => panic!("Unexpected compute assign"),
+        Token::As => panic!("Unexpected as"),
     }
 }
 
 fn parse_expression_or_assignment(parser: &mut Parser) -> AstNode {
-    let next_token = parser.get_next(1).unwrap();
+    let expression = parse_expression(parser);
+    if parser.is_end() {
+        return expression;
+    }
 
-    match next_token.token {
-        Token::Assign => parse_assignment(parser),
-        Token::ShortAssign(_) => parse_short_assign(parser),
-        Token::Increment | Token::Decrement => parse_increment_decrement(parser),
-        _ => parse_expression(parser),
+    match parser.get_current_token().token {
+        Token::Assign => parse_assignment_to(parser, expression),
+        Token::ShortAssign(_) => parse_short_assign(parser, expression),
+        Token::Increment | Token::Decrement => parse_increment_decrement(parser, expression),
+        Token::SignalAssign => parse_signal_assign(parser, expression),
+        _ => expression,
     }
 }
 
-fn parse_short_assign(parser: &mut Parser) -> AstNode {
-    let ident_ast = parse_identifier(parser);
+// x #= value
+fn parse_signal_assign(parser: &mut Parser, target: AstNode) -> AstNode {
+    if target.node_type != NodeType::Identifier {
+        panic!("Invalid signal assignment target {:?}", target.node_type);
+    }
+
+    consume_token(parser, Token::SignalAssign);
+    AstNode {
+        node_type: NodeType::SignalAssign,
+        children: vec![parse_expression(parser)],
+        value: target.value,
+        data_type: None,
+        span: None,
+    }
+}
+
+fn expect_assignable(node: &AstNode) {
+    match node.node_type {
+        NodeType::Identifier
+        | NodeType::FieldAccess
+        | NodeType::PointerFieldAccess
+        | NodeType::Index
+        | NodeType::Deref => {}
+        _ => panic!("Invalid assignment target {:?}", node.node_type),
+    }
+}
+
+fn parse_short_assign(parser: &mut Parser, target: AstNode) -> AstNode {
+    expect_assignable(&target);
     let op = consume_token(parser, Token::ShortAssign(Operator::Add));
 
     let expression_ast = parse_expression(parser);
@@ -107,19 +231,23 @@ fn parse_short_assign(parser: &mut Parser) -> AstNode {
             Token::ShortAssign(op) => NodeType::ShortAssign(op),
             _ => panic!("expected short assign, got {:?}", op),
         },
-        children: vec![ident_ast, expression_ast],
+        children: vec![target, expression_ast],
         value: None,
+        data_type: None,
+        span: None,
     }
 }
 
-fn parse_increment_decrement(parser: &mut Parser) -> AstNode {
-    let ident_ast = parse_identifier(parser);
+fn parse_increment_decrement(parser: &mut Parser, target: AstNode) -> AstNode {
+    expect_assignable(&target);
     let op = consume_one_of(parser, vec![Token::Increment, Token::Decrement]);
 
     let one_ast = AstNode {
         node_type: NodeType::Number,
         children: vec![],
         value: Some("1".to_string()),
+        data_type: None,
+        span: None,
     };
 
     AstNode {
@@ -128,8 +256,10 @@ fn parse_increment_decrement(parser: &mut Parser) -> AstNode {
             Token::Decrement => NodeType::ShortAssign(Operator::Subtract),
             _ => panic!("Unexpected increment or decrement"),
         },
-        children: vec![ident_ast, one_ast],
+        children: vec![target, one_ast],
         value: None,
+        data_type: None,
+        span: None,
     }
 }
 
@@ -142,6 +272,48 @@ fn parse_while(parser: &mut Parser) -> AstNode {
         node_type: NodeType::While,
         children: vec![condition, block],
         value: None,
+        data_type: None,
+        span: None,
+    }
+}
+
+fn parse_labelled_loop(parser: &mut Parser) -> AstNode {
+    let token = consume_token(parser, Token::Label(String::new()));
+    consume_token(parser, Token::Colon);
+
+    let mut loop_ast = parse_while(parser);
+    loop_ast.value = match token.token {
+        Token::Label(label) => Some(label),
+        _ => panic!("Expected label"),
+    };
+
+    loop_ast
+}
+
+fn parse_loop_jump(parser: &mut Parser) -> AstNode {
+    let token = consume_one_of(parser, vec![Token::Break, Token::Continue]);
+
+    // The label is optional, without one the innermost loop is targeted
+    let mut label = None;
+    if let Some(LexerToken {
+        token: Token::Label(name),
+        ..
+    }) = parser.get_next(0)
+    {
+        label = Some(name.clone());
+        parser.position += 1;
+    }
+
+    AstNode {
+        node_type: match token.token {
+            Token::Break => NodeType::Break,
+            Token::Continue => NodeType::Continue,
+            _ => panic!("Expected break or continue"),
+        },
+        children: vec![],
+        value: label,
+        data_type: None,
+        span: None,
     }
 }
 
@@ -152,16 +324,152 @@ fn parse_if(parser: &mut Parser) -> AstNode {
 
     let mut children = vec![condition, block];
 
-    if parser.get_current_token().token == Token::Else {
+    if !parser.is_end() && parser.get_current_token().token == Token::Else {
         consume_token(parser, Token::Else);
-        let else_block = parse_block(parser);
-        children.push(else_block);
+
+        // else if chains nest the next if as the else branch
+        let else_ast = match parser.get_current_token().token {
+            Token::If => parse_if(parser),
+            _ => parse_block(parser),
+        };
+        children.push(else_ast);
     }
 
     AstNode {
         node_type: NodeType::If,
         children,
         value: None,
+        data_type: None,
+        span: None,
+    }
+}
+
+// An if used as a value needs an else branch, and every branch has to end in an expression
+fn into_if_expression(mut node: AstNode) -> AstNode {
+    if node.children.len() != 3 {
+        panic!("if expression requires an else branch");
+    }
+
+    let else_ast = node.children.pop().unwrap();
+    let block = node.children.pop().unwrap();
+    node.children
+        .push(into_branch_value(block, "if expression"));
+    node.children
+        .push(into_branch_value(else_ast, "if expression"));
+    node.node_type = NodeType::IfExpression;
+
+    node
+}
+
+// A match used as a value needs every arm to produce one
+fn into_match_expression(mut node: AstNode) -> AstNode {
+    for arm in node.children.iter_mut().skip(1) {
+        let body = arm.children.pop().unwrap();
+        arm.children
+            .push(into_branch_value(body, "match expression"));
+    }
+
+    node.node_type = NodeType::MatchExpression;
+    node
+}
+
+fn into_branch_value(mut node: AstNode, context: &str) -> AstNode {
+    if node.node_type != NodeType::Block {
+        return into_value(node, context);
+    }
+
+    let last = match node.children.pop() {
+        Some(last) => into_value(last, context),
+        None => panic!("Expected a value at the end of {} branch", context),
+    };
+
+    node.children.push(last);
+    node
+}
+
+fn into_value(node: AstNode, context: &str) -> AstNode {
+    match node.node_type {
+        NodeType::If => into_if_expression(node),
+        NodeType::Match => into_match_expression(node),
+        _ if is_value(&node) => node,
+        _ => panic!("Expected a value at the end of {} branch", context),
+    }
+}
+
+fn is_value(node: &AstNode) -> bool {
+    matches!(
+        node.node_type,
+        NodeType::Number
+            | NodeType::Identifier
+            | NodeType::Boolean
+            | NodeType::String
+            | NodeType::FunctionCall
+            | NodeType::IfExpression
+            | NodeType::MatchExpression
+            | NodeType::StructLiteral
+            | NodeType::FieldAccess
+            | NodeType::PointerFieldAccess
+            | NodeType::ObjectLiteral
+            | NodeType::Index
+            | NodeType::EnumVariant
+            | NodeType::Lambda
+            | NodeType::Call
+            | NodeType::Signal
+            | NodeType::Computed
+            | NodeType::AddressOf
+            | NodeType::Deref
+            | NodeType::Null
+            | NodeType::New
+            | NodeType::Operator(_)
+            | NodeType::LParen
+            | NodeType::Eq
+            | NodeType::NotEq
+            | NodeType::LessThan
+            | NodeType::GreaterThan
+            | NodeType::Leq
+            | NodeType::Geq
+            | NodeType::And
+            | NodeType::Or
+            | NodeType::Not
+            | NodeType::BitwiseOp(_)
+            | NodeType::Cast
+    )
+}
+
+// import "path/name.tan" or import name, for name.tan next to the importing file
+fn parse_import(parser: &mut Parser) -> AstNode {
+    consume_token(parser, Token::Import);
+    let path = match parser.get_current_token().token {
+        Token::String(_) => parse_string(parser).value.unwrap(),
+        _ => format!("{}.tan", parse_identifier(parser).value.unwrap()),
+    };
+
+    AstNode {
+        node_type: NodeType::Import,
+        children: vec![],
+        value: Some(path),
+        data_type: None,
+        span: None,
+    }
+}
+
+fn parse_pub(parser: &mut Parser) -> AstNode {
+    let line = consume_token(parser, Token::Pub).line;
+    let definition = parse_next(parser);
+    match definition.node_type {
+        NodeType::FunctionDef
+        | NodeType::GenericDef
+        | NodeType::Declare
+        | NodeType::ConstDeclare => {}
+        _ => panic!("Only functions and globals can be pub on line {}", line),
+    }
+
+    AstNode {
+        node_type: NodeType::Pub,
+        children: vec![definition],
+        value: None,
+        data_type: None,
+        span: None,
     }
 }
 
@@ -173,13 +481,121 @@ fn parse_return(parser: &mut Parser) -> AstNode {
         node_type: NodeType::Return,
         children: vec![expression],
         value: None,
+        data_type: None,
+        span: None,
     }
 }
 
 fn parse_function(parser: &mut Parser) -> AstNode {
     consume_token(parser, Token::Function);
     let name = parse_identifier(parser);
+    let type_params = parse_type_parameters(parser);
+
+    let params = parse_parameters(parser);
+    let type_ast = type_node(parse_type(parser));
+
+    let block_ast = parse_block(parser);
+    let function = AstNode {
+        node_type: NodeType::FunctionDef,
+        children: vec![params, type_ast, block_ast],
+        value: name.value,
+        data_type: None,
+        span: None,
+    };
+
+    into_generic_def(parser, type_params, function)
+}
+
+// extern "math.h" fn sin(float x) float, the header is included instead of a prototype.
+// Without a return type the function returns nothing
+fn parse_extern(parser: &mut Parser) -> AstNode {
+    consume_token(parser, Token::Extern);
+    let header = match parser.get_current_token().token {
+        Token::String(_) => Some(parse_string(parser)),
+        _ => None,
+    };
+
+    consume_token(parser, Token::Function);
+    let name = parse_identifier(parser);
+    let params = parse_parameters(parser);
+    let return_type = match parser.get_current_token().token {
+        Token::Semi => DataType::Void,
+        _ => parse_type(parser),
+    };
+
+    let mut children = vec![params, type_node(return_type)];
+    children.extend(header);
+    AstNode {
+        node_type: NodeType::ExternDef,
+        children,
+        value: name.value,
+        data_type: None,
+        span: None,
+    }
+}
+
+// <T, U> after the name of a generic function or struct, the names are types until the
+// end of the definition
+fn parse_type_parameters(parser: &mut Parser) -> Option<AstNode> {
+    if parser.get_current_token().token != Token::LessThan {
+        return None;
+    }
+
+    consume_token(parser, Token::LessThan);
+    let mut params = vec![];
+    while parser.get_current_token().token != Token::GreaterThan {
+        let param = parse_identifier(parser);
+        if parser.types.contains(param.value.as_ref().unwrap()) {
+            panic!(
+                "Type parameter {} shadows a type",
+                param.value.as_ref().unwrap()
+            );
+        }
+
+        params.push(param);
+        if parser.get_current_token().token == Token::GreaterThan {
+            break;
+        }
+        consume_token(parser, Token::Comma);
+    }
+    consume_token(parser, Token::GreaterThan);
+
+    if params.is_empty() {
+        panic!("Expected type parameters between < and >");
+    }
+
+    parser.type_params = params
+        .iter()
+        .map(|param| param.value.clone().unwrap())
+        .collect();
+
+    Some(AstNode {
+        node_type: NodeType::TypeParameters,
+        children: params,
+        value: None,
+        data_type: None,
+        span: None,
+    })
+}
+
+// Generic definitions keep their type parameters next to the definition they belong to
+fn into_generic_def(parser: &mut Parser, type_params: Option<AstNode>, def: AstNode) -> AstNode {
+    let type_params = match type_params {
+        Some(type_params) => type_params,
+        None => return def,
+    };
+
+    parser.type_params.clear();
+    AstNode {
+        node_type: NodeType::GenericDef,
+        value: def.value.clone(),
+        children: vec![type_params, def],
+        data_type: None,
+        span: None,
+    }
+}
 
+fn parse_parameters(parser: &mut Parser) -> AstNode {
     consume_token(parser, Token::LParen);
     let mut args = vec![];
     while parser.get_current_token().token != Token::RParen {
@@ -194,296 +610,708 @@ fn parse_function(parser: &mut Parser) -> AstNode {
 
     consume_token(parser, Token::RParen);
 
-    let params = AstNode {
+    AstNode {
         node_type: NodeType::Parameters,
         children: args,
         value: None,
-    };
-
-    let token = consume_token(parser, Token::Type(DataType::Integer));
-    let explicit_type = match token.token.clone() {
-        Token::Type(t @ DataType::Integer)
-        | Token::Type(t @ DataType::Boolean)
-        | Token::Type(t @ DataType::Float) => t,
-        _ => panic!("Expected type specifier"),
-    };
+        data_type: None,
+        span: None,
+    }
+}
 
-    let type_ast = AstNode {
-        node_type: NodeType::Type(explicit_type),
-        children: vec![],
-        value: None,
-    };
+// fn (int x) int { ... }
+fn parse_lambda(parser: &mut Parser) -> AstNode {
+    let line = parser.get_current_token().line;
+    consume_token(parser, Token::Function);
+    let params = parse_parameters(parser);
+    let type_ast = type_node(parse_type(parser));
 
     let block_ast = parse_block(parser);
     AstNode {
-        node_type: NodeType::FunctionDef,
+        node_type: NodeType::Lambda,
         children: vec![params, type_ast, block_ast],
-        value: name.value,
+        value: None,
+        data_type: None,
+        span: Some(Span { file: None, line }),
     }
 }
 
-fn parse_parameter(parser: &mut Parser) -> AstNode {
-    let token = consume_token(parser, Token::Type(DataType::Integer));
+// |x, y| x + y, the checker fills in the types from where the lambda is used
+fn parse_pipe_lambda(parser: &mut Parser) -> AstNode {
+    let line = parser.get_current_token().line;
+    let mut params = vec![];
+
+    // || is lexed as a single token
+    if parser.get_current_token().token == Token::Or {
+        parser.position += 1;
+    } else {
+        consume_token(parser, Token::BitwiseOp(BitwiseOp::Or));
+        while parser.get_current_token().token != Token::BitwiseOp(BitwiseOp::Or) {
+            params.push(AstNode {
+                node_type: NodeType::Parameter,
+                children: vec![parse_identifier(parser)],
+                value: None,
+                data_type: None,
+                span: None,
+            });
 
-    let explicit_type = match token.token.clone() {
-        Token::Type(t @ DataType::Integer)
-        | Token::Type(t @ DataType::Boolean)
-        | Token::Type(t @ DataType::Float) => t,
-        _ => panic!("Expected type specifier"),
+            if parser.get_current_token().token == Token::BitwiseOp(BitwiseOp::Or) {
+                break;
+            }
+
+            consume_token(parser, Token::Comma);
+        }
+        parser.position += 1;
+    }
+
+    let return_ast = AstNode {
+        node_type: NodeType::Return,
+        span: Some(Span {
+            file: None,
+            line: parser.get_current_token().line,
+        }),
+        children: vec![parse_expression(parser)],
+        value: None,
+        data_type: None,
     };
 
-    let type_ast = AstNode {
-        node_type: NodeType::Type(explicit_type.clone()),
-        children: vec![],
-        value: Some(explicit_type.to_string()),
+    let params = AstNode {
+        node_type: NodeType::Parameters,
+        children: params,
+        value: None,
+        data_type: None,
+        span: None,
+    };
+
+    let block_ast = AstNode {
+        node_type: NodeType::Block,
+        children: vec![return_ast],
+        value: None,
+        data_type: None,
+        span: None,
     };
 
-    let ident_ast = parse_identifier(parser);
     AstNode {
-        node_type: NodeType::Parameter,
-        children: vec![type_ast, ident_ast],
+        node_type: NodeType::Lambda,
+        children: vec![params, block_ast],
         value: None,
+        data_type: None,
+        span: Some(Span { file: None, line }),
     }
 }
 
-fn parse_block(parser: &mut Parser) -> AstNode {
-    consume_token(parser, Token::LCurly);
-
-    let mut children = vec![];
-    while parser.get_current_token().token != Token::RCurly {
-        children.push(parse_statement(parser));
-    }
-
-    consume_token(parser, Token::RCurly);
+fn parse_parameter(parser: &mut Parser) -> AstNode {
+    let type_ast = type_node(parse_type(parser));
 
+    let ident_ast = parse_identifier(parser);
     AstNode {
-        node_type: NodeType::Block,
-        children,
+        node_type: NodeType::Parameter,
+        children: vec![type_ast, ident_ast],
         value: None,
+        data_type: None,
+        span: None,
     }
 }
 
-fn parse_parentheses(parser: &mut Parser) -> AstNode {
-    consume_token(parser, Token::LParen);
-    let ast = AstNode {
-        node_type: NodeType::LParen,
-        children: vec![parse_expression(parser)],
-        value: None,
-    };
+fn parse_type(parser: &mut Parser) -> DataType {
+    let token = parser.get_current_token().clone();
+    if token.token == Token::Function {
+        return parse_function_type(parser);
+    }
 
-    consume_token(parser, Token::RParen);
-    ast
-}
+    if token.token == Token::Object {
+        return parse_object_type(parser);
+    }
 
-fn parse_declare(parser: &mut Parser) -> AstNode {
-    let token = consume_token(parser, Token::Type(DataType::Integer));
-    let type_ast = match token.token.clone() {
-        Token::Type(t @ DataType::Integer)
-        | Token::Type(t @ DataType::Boolean)
-        | Token::Type(t @ DataType::Float)
-        | Token::Type(t @ DataType::Pointer(_)) => AstNode {
-            node_type: NodeType::Type(t.clone()),
-            children: vec![],
-            value: Some(t.to_string()),
-        },
-        _ => panic!("Expected type specifier"),
+    let mut data_type = match token.token {
+        Token::Type(t) => t,
+        Token::Identifier(name) if parser.type_params.contains(&name) => DataType::Param(name),
+        Token::Identifier(name) if parser.is_enum_name(&name) => DataType::Enum(name),
+        Token::Identifier(name) if parser.is_type_name(&name) => DataType::Struct(name),
+        _ => panic!(
+            "Expected type specifier, got {:?} on line {}",
+            token.token, token.line
+        ),
     };
 
-    let next_token = parser.get_next(1).unwrap();
-    if next_token.token == Token::Assign {
-        return AstNode {
-            node_type: NodeType::Declare,
-            children: vec![type_ast, parse_assignment(parser)],
-            value: None,
-        };
+    parser.position += 1;
+
+    // Pair<int> is an instance of a generic struct
+    if let DataType::Struct(ref name) = data_type {
+        if !parser.is_end() && parser.get_current_token().token == Token::LessThan {
+            data_type = DataType::Instance(name.clone(), parse_type_arguments(parser));
+        }
     }
 
-    // Allow uninitialized variables
-    AstNode {
-        node_type: NodeType::Declare,
-        children: vec![type_ast, parse_identifier(parser)],
-        value: None,
+    // The lexer only folds pointers into builtin types, it does not know user defined ones
+    while !parser.is_end()
+        && parser.get_current_token().token == Token::Operator(Operator::Multiply)
+    {
+        parser.position += 1;
+        data_type = DataType::Pointer(Box::new(data_type));
     }
-}
 
-fn parse_assignment(parser: &mut Parser) -> AstNode {
-    let ident_ast = parse_identifier(parser);
+    data_type
+}
 
-    consume_token(parser, Token::Assign);
-    let expression_ast = parse_expression(parser);
+fn parse_type_arguments(parser: &mut Parser) -> Vec<DataType> {
+    consume_token(parser, Token::LessThan);
 
-    AstNode {
-        node_type: NodeType::Assign,
-        children: vec![ident_ast, expression_ast],
-        value: None,
+    let mut args = vec![parse_type(parser)];
+    while parser.get_current_token().token == Token::Comma {
+        parser.position += 1;
+        args.push(parse_type(parser));
     }
+
+    consume_token(parser, Token::GreaterThan);
+    args
 }
 
-fn parse_expression(parser: &mut Parser) -> AstNode {
-    parse_and_or(parser)
+// object<int>, objects map property names to values of one type
+fn parse_object_type(parser: &mut Parser) -> DataType {
+    consume_token(parser, Token::Object);
+    consume_token(parser, Token::LessThan);
+    let value = parse_type(parser);
+    consume_token(parser, Token::GreaterThan);
+
+    DataType::Object(Box::new(value))
 }
 
-fn parse_prefix_op(parser: &mut Parser) -> AstNode {
-    let token = parser.get_current_token().clone();
-    match token.token {
-        Token::Not | Token::BitwiseOp(BitwiseOp::Not) => {
-            parser.position += 1;
-            AstNode {
-                node_type: match token.token {
-                    Token::Not => NodeType::Not,
-                    Token::BitwiseOp(BitwiseOp::Not) => NodeType::BitwiseOp(BitwiseOp::Not),
-                    _ => panic!("Expected not or bitwise not"),
-                },
-                children: vec![parse_prefix_op(parser)],
-                value: None,
-            }
+// fn(int, float) bool
+fn parse_function_type(parser: &mut Parser) -> DataType {
+    consume_token(parser, Token::Function);
+    consume_token(parser, Token::LParen);
+
+    let mut params = vec![];
+    while parser.get_current_token().token != Token::RParen {
+        params.push(parse_type(parser));
+
+        if parser.get_current_token().token == Token::RParen {
+            break;
         }
-        _ => parse_factor(parser),
-    }
-}
 
-fn parse_mul_div(parser: &mut Parser) -> AstNode {
-    let left = parse_prefix_op(parser);
-    if parser.is_end() {
-        return left;
+        consume_token(parser, Token::Comma);
     }
 
-    match parser.get_current_token().token.clone() {
-        Token::Operator(op @ Operator::Multiply)
-        | Token::Operator(op @ Operator::Divide)
-        | Token::Operator(op @ Operator::Mod) => {
-            parser.position += 1;
-            AstNode {
-                node_type: operator_to_node_type(op),
-                children: vec![left, parse_mul_div(parser)],
-                value: None,
-            }
-        }
-        _ => left,
+    consume_token(parser, Token::RParen);
+    DataType::Function(params, Box::new(parse_type(parser)))
+}
+
+pub fn type_node(data_type: DataType) -> AstNode {
+    AstNode {
+        value: Some(data_type.to_string()),
+        node_type: NodeType::Type(data_type),
+        children: vec![],
+        data_type: None,
+        span: None,
     }
 }
 
-fn parse_add_sub(parser: &mut Parser) -> AstNode {
-    let left = parse_mul_div(parser);
-    if parser.is_end() {
-        return left;
+fn parse_struct(parser: &mut Parser) -> AstNode {
+    consume_token(parser, Token::Struct);
+    let name = parse_identifier(parser);
+    let type_params = parse_type_parameters(parser);
+    consume_token(parser, Token::LCurly);
+
+    let mut fields = vec![];
+    while parser.get_current_token().token != Token::RCurly {
+        let type_ast = type_node(parse_type(parser));
+        let ident_ast = parse_identifier(parser);
+        consume_token(parser, Token::Semi);
+
+        fields.push(AstNode {
+            node_type: NodeType::Field,
+            children: vec![type_ast, ident_ast],
+            value: None,
+            data_type: None,
+            span: None,
+        });
     }
 
-    match parser.get_current_token().token.clone() {
-        Token::Operator(op @ Operator::Add) | Token::Operator(op @ Operator::Subtract) => {
-            parser.position += 1;
-            AstNode {
-                node_type: operator_to_node_type(op),
-                children: vec![left, parse_add_sub(parser)],
-                value: None,
-            }
+    consume_token(parser, Token::RCurly);
+
+    let struct_def = AstNode {
+        node_type: NodeType::StructDef,
+        children: fields,
+        value: name.value,
+        data_type: None,
+        span: None,
+    };
+
+    into_generic_def(parser, type_params, struct_def)
+}
+
+fn parse_enum(parser: &mut Parser) -> AstNode {
+    consume_token(parser, Token::Enum);
+    let name = parse_identifier(parser);
+    consume_token(parser, Token::LCurly);
+
+    let mut variants = vec![];
+    while parser.get_current_token().token != Token::RCurly {
+        variants.push(parse_identifier(parser));
+
+        if parser.get_current_token().token == Token::RCurly {
+            break;
         }
-        _ => left,
+
+        consume_token(parser, Token::Comma);
+    }
+
+    consume_token(parser, Token::RCurly);
+
+    AstNode {
+        node_type: NodeType::EnumDef,
+        children: variants,
+        value: name.value,
+        data_type: None,
+        span: None,
     }
 }
 
-fn parse_relational(parser: &mut Parser) -> AstNode {
-    let left = parse_add_sub(parser);
-    if parser.is_end() {
-        return left;
+fn parse_enum_variant(parser: &mut Parser) -> AstNode {
+    let enum_ast = parse_identifier(parser);
+    consume_token(parser, Token::DoubleColon);
+    let variant = parse_identifier(parser);
+
+    AstNode {
+        node_type: NodeType::EnumVariant,
+        children: vec![enum_ast],
+        value: variant.value,
+        data_type: None,
+        span: None,
     }
+}
 
-    match parser.get_current_token().token {
-        Token::LessThan | Token::GreaterThan | Token::Leq | Token::Geq => {
-            parser.position += 1;
-            AstNode {
-                node_type: match parser.get_current_token().token {
-                    Token::LessThan => NodeType::LessThan,
-                    Token::GreaterThan => NodeType::GreaterThan,
-                    Token::Leq => NodeType::Leq,
-                    Token::Geq => NodeType::Geq,
-                    _ => panic!("Expected relational operator"),
-                },
-                children: vec![left, parse_relational(parser)],
-                value: None,
+fn parse_match(parser: &mut Parser) -> AstNode {
+    consume_token(parser, Token::Match);
+    let mut children = vec![parse_expression(parser)];
+    consume_token(parser, Token::LCurly);
+
+    while parser.get_current_token().token != Token::RCurly {
+        let pattern = parse_pattern(parser);
+        consume_token(parser, Token::FatArrow);
+
+        let body = parse_next(parser);
+        let is_block = body.node_type == NodeType::Block;
+        children.push(AstNode {
+            node_type: NodeType::MatchArm,
+            children: vec![pattern, body],
+            value: None,
+            data_type: None,
+            span: None,
+        });
+
+        // Like in Rust the comma after a block arm is optional
+        match parser.get_current_token().token {
+            Token::RCurly => break,
+            Token::Comma => parser.position += 1,
+            _ if is_block => {}
+            _ => {
+                consume_token(parser, Token::Comma);
             }
         }
-        _ => left,
     }
-}
 
-fn parse_equality(parser: &mut Parser) -> AstNode {
-    let left = parse_relational(parser);
-    if parser.is_end() {
-        return left;
+    consume_token(parser, Token::RCurly);
+
+    AstNode {
+        node_type: NodeType::Match,
+        children,
+        value: None,
+        data_type: None,
+        span: None,
     }
+}
 
-    match parser.get_current_token().token {
-        Token::Eq | Token::NotEq => {
+fn parse_pattern(parser: &mut Parser) -> AstNode {
+    let token = parser.get_current_token().clone();
+    match token.token {
+        Token::Underscore => {
             parser.position += 1;
             AstNode {
-                node_type: match parser.get_current_token().token {
-                    Token::Eq => NodeType::Eq,
-                    Token::NotEq => NodeType::NotEq,
-                    _ => panic!("Expected equality operator"),
-                },
-                children: vec![left, parse_equality(parser)],
+                node_type: NodeType::Wildcard,
+                children: vec![],
                 value: None,
+                data_type: None,
+                span: None,
             }
         }
-        _ => left,
+        Token::Number(..) => parse_number(parser),
+        Token::Operator(Operator::Subtract) => {
+            parser.position += 1;
+            let mut number = parse_number(parser);
+            number.value = Some(format!("-{}", number.value.unwrap()));
+            number
+        }
+        Token::Boolean(_) => parse_boolean(parser),
+        Token::Identifier(ref name) if parser.is_enum_name(name) => parse_enum_variant(parser),
+        _ => panic!(
+            "Expected pattern, got {:?} on line {}",
+            token.token, token.line
+        ),
     }
 }
 
-fn parse_bitwise_op(parser: &mut Parser) -> AstNode {
-    let left = parse_equality(parser);
-    if parser.is_end() {
-        return left;
+fn parse_struct_literal(parser: &mut Parser) -> AstNode {
+    // Type arguments of a generic struct are kept as a leading Type child, without them
+    // the checker infers them from the fields
+    let mut fields = vec![];
+    let name = match parser.get_next(1) {
+        Some(next) if next.token == Token::LessThan => {
+            let struct_type = type_node(parse_type(parser));
+            let name = match struct_type.data_type() {
+                DataType::Instance(name, _) => name.clone(),
+                data_type => panic!("Expected a struct type, got {}", data_type),
+            };
+            fields.push(struct_type);
+            name
+        }
+        _ => parse_identifier(parser).value.unwrap(),
+    };
+    consume_token(parser, Token::LCurly);
+
+    while parser.get_current_token().token != Token::RCurly {
+        let field = parse_identifier(parser);
+        consume_token(parser, Token::Colon);
+
+        fields.push(AstNode {
+            node_type: NodeType::Assign,
+            children: vec![field, parse_expression(parser)],
+            value: None,
+            data_type: None,
+            span: None,
+        });
+
+        if parser.get_current_token().token == Token::RCurly {
+            break;
+        }
+
+        consume_token(parser, Token::Comma);
     }
 
-    match parser.get_current_token().token.clone() {
-        Token::BitwiseOp(op @ BitwiseOp::And)
-        | Token::BitwiseOp(op @ BitwiseOp::Or)
-        | Token::BitwiseOp(op @ BitwiseOp::Xor) => {
-            parser.position += 1;
-            AstNode {
-                node_type: bitwise_op_to_node_type(op),
-                children: vec![left, parse_bitwise_op(parser)],
-                value: None,
-            }
+    consume_token(parser, Token::RCurly);
+
+    AstNode {
+        node_type: NodeType::StructLiteral,
+        children: fields,
+        value: Some(name),
+        data_type: None,
+        span: None,
+    }
+}
+
+// { name: value, "any key": value }, property names are identifiers or strings
+fn parse_object_literal(parser: &mut Parser) -> AstNode {
+    consume_token(parser, Token::LCurly);
+
+    let mut properties = vec![];
+    while parser.get_current_token().token != Token::RCurly {
+        let key = match parser.get_current_token().token {
+            Token::String(_) => parse_string(parser),
+            _ => parse_identifier(parser),
+        };
+        consume_token(parser, Token::Colon);
+
+        properties.push(AstNode {
+            node_type: NodeType::Assign,
+            children: vec![key, parse_expression(parser)],
+            value: None,
+            data_type: None,
+            span: None,
+        });
+
+        if parser.get_current_token().token == Token::RCurly {
+            break;
+        }
+
+        consume_token(parser, Token::Comma);
+    }
+
+    consume_token(parser, Token::RCurly);
+
+    AstNode {
+        node_type: NodeType::ObjectLiteral,
+        children: properties,
+        value: None,
+        data_type: None,
+        span: None,
+    }
+}
+
+fn parse_block(parser: &mut Parser) -> AstNode {
+    consume_token(parser, Token::LCurly);
+
+    let mut children = vec![];
+    while parser.get_current_token().token != Token::RCurly {
+        let node = parse_spanned(parser);
+
+        // The semicolon after the last statement is optional so blocks can end in a value
+        if parser.get_current_token().token != Token::RCurly {
+            consume_token(parser, Token::Semi);
         }
-        _ => left,
+
+        children.push(node);
+    }
+
+    consume_token(parser, Token::RCurly);
+
+    AstNode {
+        node_type: NodeType::Block,
+        children,
+        value: None,
+        data_type: None,
+        span: None,
     }
 }
 
-fn parse_and_or(parser: &mut Parser) -> AstNode {
-    let left = parse_bitwise_op(parser);
-    if parser.is_end() {
-        return left;
+fn parse_parentheses(parser: &mut Parser) -> AstNode {
+    consume_token(parser, Token::LParen);
+    let ast = AstNode {
+        node_type: NodeType::LParen,
+        children: vec![parse_expression(parser)],
+        value: None,
+        data_type: None,
+        span: None,
+    };
+
+    consume_token(parser, Token::RParen);
+    ast
+}
+
+fn parse_const(parser: &mut Parser) -> AstNode {
+    let line = consume_token(parser, Token::Const).line;
+    let mut declare = parse_declare(parser);
+    if declare.node_type != NodeType::Declare || declare.children[1].node_type != NodeType::Assign {
+        panic!("Constant on line {} needs a type, a name and a value", line);
     }
 
-    match parser.get_current_token().token {
-        Token::And | Token::Or => {
+    declare.node_type = NodeType::ConstDeclare;
+    declare
+}
+
+fn parse_declare(parser: &mut Parser) -> AstNode {
+    let type_ast = type_node(parse_type(parser));
+
+    let next_token = parser.get_next(1).unwrap();
+    if let Token::SignalAssign | Token::ComputeAssign = next_token.token {
+        return parse_signal_declare(parser, type_ast);
+    }
+
+    if next_token.token == Token::Assign {
+        return AstNode {
+            node_type: NodeType::Declare,
+            children: vec![type_ast, parse_assignment(parser)],
+            value: None,
+            data_type: None,
+            span: None,
+        };
+    }
+
+    // Allow uninitialized variables
+    AstNode {
+        node_type: NodeType::Declare,
+        children: vec![type_ast, parse_identifier(parser)],
+        value: None,
+        data_type: None,
+        span: None,
+    }
+}
+
+// int x #= 1; declares a signal, int y $= #x + 1; a signal computed from other signals
+fn parse_signal_declare(parser: &mut Parser, type_ast: AstNode) -> AstNode {
+    let name = parse_identifier(parser);
+    let node_type =
+        match consume_one_of(parser, vec![Token::SignalAssign, Token::ComputeAssign]).token {
+            Token::SignalAssign => NodeType::SignalDeclare,
+            _ => NodeType::ComputedDeclare,
+        };
+
+    AstNode {
+        node_type,
+        children: vec![type_ast, parse_expression(parser)],
+        value: name.value,
+        data_type: None,
+        span: None,
+    }
+}
+
+fn parse_assignment(parser: &mut Parser) -> AstNode {
+    let ident_ast = parse_identifier(parser);
+    parse_assignment_to(parser, ident_ast)
+}
+
+fn parse_assignment_to(parser: &mut Parser, target: AstNode) -> AstNode {
+    expect_assignable(&target);
+
+    consume_token(parser, Token::Assign);
+    let expression_ast = parse_expression(parser);
+
+    AstNode {
+        node_type: NodeType::Assign,
+        children: vec![target, expression_ast],
+        value: None,
+        data_type: None,
+        span: None,
+    }
+}
+
+fn parse_expression(parser: &mut Parser) -> AstNode {
+    parse_or(parser)
+}
+
+// Parses a left associative chain of operators that share a precedence level
+fn parse_binary(
+    parser: &mut Parser,
+    parse_operand: fn(&mut Parser) -> AstNode,
+    match_operator: fn(&Token) -> Option<NodeType>,
+) -> AstNode {
+    let mut left = parse_operand(parser);
+
+    while !parser.is_end() {
+        let node_type = match match_operator(&parser.get_current_token().token) {
+            Some(node_type) => node_type,
+            None => break,
+        };
+
+        parser.position += 1;
+        left = AstNode {
+            node_type,
+            children: vec![left, parse_operand(parser)],
+            value: None,
+            data_type: None,
+            span: None,
+        };
+    }
+
+    left
+}
+
+fn parse_prefix_op(parser: &mut Parser) -> AstNode {
+    let token = parser.get_current_token().clone();
+    match token.token {
+        Token::Not
+        | Token::BitwiseOp(BitwiseOp::Not)
+        | Token::BitwiseOp(BitwiseOp::And)
+        | Token::Operator(Operator::Multiply) => {
             parser.position += 1;
             AstNode {
-                node_type: match parser.get_current_token().token {
-                    Token::And => NodeType::And,
-                    Token::Or => NodeType::Or,
-                    _ => panic!("Expected and or or"),
+                node_type: match token.token {
+                    Token::Not => NodeType::Not,
+                    Token::BitwiseOp(BitwiseOp::Not) => NodeType::BitwiseOp(BitwiseOp::Not),
+                    Token::BitwiseOp(BitwiseOp::And) => NodeType::AddressOf,
+                    Token::Operator(Operator::Multiply) => NodeType::Deref,
+                    _ => panic!("Expected prefix operator"),
                 },
-                children: vec![left, parse_and_or(parser)],
+                children: vec![parse_prefix_op(parser)],
                 value: None,
+                data_type: None,
+                span: None,
             }
         }
-        _ => left,
+        _ => parse_factor(parser),
+    }
+}
+
+// Casts bind tighter than arithmetic, so a * b as float converts only b. Only builtin types
+// can be cast to, which keeps the * in x as float * 2 a multiplication
+fn parse_cast(parser: &mut Parser) -> AstNode {
+    let mut node = parse_prefix_op(parser);
+
+    while !parser.is_end() && parser.get_current_token().token == Token::As {
+        parser.position += 1;
+        let token = parser.get_current_token().clone();
+        let data_type = match token.token {
+            Token::Type(data_type) => data_type,
+            _ => panic!(
+                "Expected type to cast to, got {:?} on line {}",
+                token.token, token.line
+            ),
+        };
+        parser.position += 1;
+        node = AstNode {
+            node_type: NodeType::Cast,
+            children: vec![node, type_node(data_type)],
+            value: None,
+            data_type: None,
+            span: None,
+        };
     }
+
+    node
+}
+
+fn parse_mul_div(parser: &mut Parser) -> AstNode {
+    parse_binary(parser, parse_cast, |token| match token {
+        Token::Operator(op @ Operator::Multiply)
+        | Token::Operator(op @ Operator::Divide)
+        | Token::Operator(op @ Operator::Mod) => Some(operator_to_node_type(op.clone())),
+        _ => None,
+    })
+}
+
+fn parse_add_sub(parser: &mut Parser) -> AstNode {
+    parse_binary(parser, parse_mul_div, |token| match token {
+        Token::Operator(op @ Operator::Add) | Token::Operator(op @ Operator::Subtract) => {
+            Some(operator_to_node_type(op.clone()))
+        }
+        _ => None,
+    })
+}
+
+fn parse_relational(parser: &mut Parser) -> AstNode {
+    parse_binary(parser, parse_add_sub, |token| match token {
+        Token::LessThan => Some(NodeType::LessThan),
+        Token::GreaterThan => Some(NodeType::GreaterThan),
+        Token::Leq => Some(NodeType::Leq),
+        Token::Geq => Some(NodeType::Geq),
+        _ => None,
+    })
+}
+
+fn parse_equality(parser: &mut Parser) -> AstNode {
+    parse_binary(parser, parse_relational, |token| match token {
+        Token::Eq => Some(NodeType::Eq),
+        Token::NotEq => Some(NodeType::NotEq),
+        _ => None,
+    })
+}
+
+fn parse_bitwise_op(parser: &mut Parser) -> AstNode {
+    parse_binary(parser, parse_equality, |token| match token {
+        Token::BitwiseOp(op @ BitwiseOp::And)
+        | Token::BitwiseOp(op @ BitwiseOp::Or)
+        | Token::BitwiseOp(op @ BitwiseOp::Xor) => Some(bitwise_op_to_node_type(op.clone())),
+        _ => None,
+    })
+}
+
+fn parse_and(parser: &mut Parser) -> AstNode {
+    parse_binary(parser, parse_bitwise_op, |token| match token {
+        Token::And => Some(NodeType::And),
+        _ => None,
+    })
 }
 
+fn parse_or(parser: &mut Parser) -> AstNode {
+    parse_binary(parser, parse_and, |token| match token {
+        Token::Or => Some(NodeType::Or),
+        _ => None,
+    })
+}
+
+// A type suffix becomes a Type child, literals without one get their type from where they are used
 fn parse_number(parser: &mut Parser) -> AstNode {
-    let token = consume_token(parser, Token::Number(0));
+    let token = consume_token(parser, Token::Number(0, None));
 
-    AstNode {
-        node_type: NodeType::Number,
-        value: match token.token {
-            Token::Number(value) => Some(value.to_string()),
-            _ => panic!("Expected number"),
+    match token.token {
+        Token::Number(value, suffix) => AstNode {
+            node_type: NodeType::Number,
+            value: Some(value.to_string()),
+            children: suffix.into_iter().map(type_node).collect(),
+            data_type: None,
+            span: None,
         },
-        children: vec![],
+        _ => panic!("Expected number"),
     }
 }
 
@@ -497,19 +1325,123 @@ fn parse_identifier(parser: &mut Parser) -> AstNode {
             _ => panic!("Expected identifier"),
         },
         children: vec![],
+        data_type: None,
+        span: None,
     }
 }
 
 fn parse_factor(parser: &mut Parser) -> AstNode {
     let token = &parser.get_current_token();
 
-    match token.token {
-        Token::Number(_) => parse_number(parser),
+    let factor = match token.token {
+        Token::Number(..) => parse_number(parser),
+        Token::Identifier(ref name)
+            if parser.is_type_name(name)
+                && matches!(
+                    parser.get_next(1),
+                    Some(next) if next.token == Token::LCurly || next.token == Token::LessThan
+                ) =>
+        {
+            parse_struct_literal(parser)
+        }
+        Token::Identifier(ref name) if parser.is_enum_name(name) => parse_enum_variant(parser),
         Token::Identifier(_) => parse_identifier_or_function_call(parser),
         Token::LParen => parse_parentheses(parser),
         Token::Boolean(_) => parse_boolean(parser),
+        Token::String(_) => parse_string(parser),
+        // Blocks are statements, so a curly brace in an expression starts an object
+        Token::LCurly => parse_object_literal(parser),
+        Token::Null => {
+            parser.position += 1;
+            AstNode {
+                node_type: NodeType::Null,
+                children: vec![],
+                value: None,
+                data_type: None,
+                span: None,
+            }
+        }
+        Token::New => {
+            parser.position += 1;
+            AstNode {
+                node_type: NodeType::New,
+                children: vec![type_node(parse_type(parser))],
+                value: None,
+                data_type: None,
+                span: None,
+            }
+        }
+        Token::If => into_if_expression(parse_if(parser)),
+        Token::Match => into_match_expression(parse_match(parser)),
+        Token::Function => parse_lambda(parser),
+        Token::Signal(ref name) | Token::Computed(ref name) => {
+            let node_type = match token.token {
+                Token::Signal(_) => NodeType::Signal,
+                _ => NodeType::Computed,
+            };
+            let value = Some(name.clone());
+            parser.position += 1;
+            AstNode {
+                node_type,
+                children: vec![],
+                value,
+                data_type: None,
+                span: None,
+            }
+        }
+        Token::Or | Token::BitwiseOp(BitwiseOp::Or) => parse_pipe_lambda(parser),
         _ => panic!("Expected number or identifier"),
+    };
+
+    parse_postfix(parser, factor)
+}
+
+// Field accesses, indexing and calls of function values, like a.b->c[d](1)
+fn parse_postfix(parser: &mut Parser, mut node: AstNode) -> AstNode {
+    while !parser.is_end() {
+        let node_type = match parser.get_current_token().token {
+            Token::LBracket => {
+                parser.position += 1;
+                let key = parse_expression(parser);
+                consume_token(parser, Token::RBracket);
+                node = AstNode {
+                    node_type: NodeType::Index,
+                    children: vec![node, key],
+                    value: None,
+                    data_type: None,
+                    span: None,
+                };
+                continue;
+            }
+            Token::Dot => NodeType::FieldAccess,
+            Token::Arrow => NodeType::PointerFieldAccess,
+            Token::LParen => {
+                let mut children = vec![node];
+                children.extend(parse_arguments(parser));
+                node = AstNode {
+                    node_type: NodeType::Call,
+                    children,
+                    value: None,
+                    data_type: None,
+                    span: None,
+                };
+                continue;
+            }
+            _ => break,
+        };
+
+        parser.position += 1;
+        let field = parse_identifier(parser);
+        node = AstNode {
+            node_type,
+            children: vec![node],
+            value: field.value,
+            data_type: None,
+            span: None,
+        };
     }
+
+    node
 }
 
 fn parse_boolean(parser: &mut Parser) -> AstNode {
@@ -522,6 +1454,23 @@ fn parse_boolean(parser: &mut Parser) -> AstNode {
             _ => panic!("Expected boolean"),
         },
         children: vec![],
+        data_type: None,
+        span: None,
+    }
+}
+
+fn parse_string(parser: &mut Parser) -> AstNode {
+    let token = consume_token(parser, Token::String(String::new()));
+
+    AstNode {
+        node_type: NodeType::String,
+        value: match token.token {
+            Token::String(value) => Some(value),
+            _ => panic!("Expected string"),
+        },
+        children: vec![],
+        data_type: None,
+        span: None,
     }
 }
 
@@ -537,6 +1486,17 @@ fn parse_identifier_or_function_call(parser: &mut Parser) -> AstNode {
 
 fn parse_function_call(parser: &mut Parser) -> AstNode {
     let ident_ast = parse_identifier(parser);
+
+    AstNode {
+        node_type: NodeType::FunctionCall,
+        children: parse_arguments(parser),
+        value: ident_ast.value,
+        data_type: None,
+        span: None,
+    }
+}
+
+fn parse_arguments(parser: &mut Parser) -> Vec<AstNode> {
     consume_token(parser, Token::LParen);
 
     let mut args = vec![];
@@ -549,12 +1509,7 @@ fn parse_function_call(parser: &mut Parser) -> AstNode {
     }
 
     consume_token(parser, Token::RParen);
-
-    AstNode {
-        node_type: NodeType::FunctionCall,
-        children: args,
-        value: ident_ast.value,
-    }
+    args
 }
 
 fn consume_token(parser: &mut Parser, expected_token: Token) -> LexerToken {
@@ -564,11 +1519,14 @@ fn consume_token(parser: &mut Parser, expected_token: Token) -> LexerToken {
 
     let token = parser.get_current_token().clone();
     if !variant_eq(&expected_token, &token.token) {
-        panic!("Expected token {:?}, got {:?}", expected_token, token.token);
+        panic!(
+            "Expected token {:?}, got {:?} on line {}",
+            expected_token, token.token, token.line
+        );
     }
 
     parser.position += 1;
-    return token.clone();
+    token
 }
 
 fn consume_one_of(parser: &mut Parser, expected_tokens: Vec<Token>) -> LexerToken {
@@ -581,7 +1539,7 @@ fn consume_one_of(parser: &mut Parser, expected_tokens: Vec<Token>) -> LexerToke
     }
 
     parser.position += 1;
-    return token.clone();
+    token
 }
 
 #[cfg(test)]
@@ -613,6 +1571,9 @@ mod tests {
         let assign_ast = parse_assignment(&mut Parser {
             tokens,
             position: 0,
+            types: HashSet::new(),
+            enums: HashSet::new(),
+            type_params: vec![],
         });
 
         assert_eq!(assign_ast.node_type, NodeType::Assign);
@@ -624,4 +1585,470 @@ mod tests {
         assert_eq!(assign_ast.children[1].node_type, NodeType::Number);
         assert_eq!(assign_ast.children[1].value, Some("1".to_string()));
     }
+
+    #[test]
+    fn parser_parse_left_associative() {
+        let ast = parse(tokenize("10 - 2 - 3;"));
+
+        let outer = &ast.children[0];
+        assert_eq!(outer.node_type, NodeType::Operator(Operator::Subtract));
+        assert_eq!(outer.children[1].value, Some("3".to_string()));
+
+        let inner = &outer.children[0];
+        assert_eq!(inner.node_type, NodeType::Operator(Operator::Subtract));
+        assert_eq!(inner.children[0].value, Some("10".to_string()));
+        assert_eq!(inner.children[1].value, Some("2".to_string()));
+    }
+
+    #[test]
+    fn parser_parse_comparison() {
+        let ast = parse(tokenize("a < b == c >= d;"));
+
+        let eq = &ast.children[0];
+        assert_eq!(eq.node_type, NodeType::Eq);
+        assert_eq!(eq.children[0].node_type, NodeType::LessThan);
+        assert_eq!(eq.children[1].node_type, NodeType::Geq);
+    }
+
+    #[test]
+    fn parser_parse_labelled_loop() {
+        let ast = parse(tokenize("'outer: while true { break 'outer; continue; };"));
+
+        let loop_ast = &ast.children[0];
+        assert_eq!(loop_ast.node_type, NodeType::While);
+        assert_eq!(loop_ast.value, Some("outer".to_string()));
+
+        let body = &loop_ast.children[1];
+        assert_eq!(body.children[0].node_type, NodeType::Break);
+        assert_eq!(body.children[0].value, Some("outer".to_string()));
+        assert_eq!(body.children[1].node_type, NodeType::Continue);
+        assert_eq!(body.children[1].value, None);
+    }
+
+    #[test]
+    fn parser_parse_else_if_chain() {
+        let ast = parse(tokenize("if a { } else if b { } else { };"));
+
+        let if_ast = &ast.children[0];
+        assert_eq!(if_ast.node_type, NodeType::If);
+
+        let else_if = &if_ast.children[2];
+        assert_eq!(else_if.node_type, NodeType::If);
+        assert_eq!(else_if.children[0].value, Some("b".to_string()));
+        assert_eq!(else_if.children[2].node_type, NodeType::Block);
+    }
+
+    #[test]
+    fn parser_parse_if_expression() {
+        let ast = parse(tokenize("int x = if c { 1 } else if d { 2 } else { 3 };"));
+
+        let if_ast = &ast.children[0].children[1].children[1];
+        assert_eq!(if_ast.node_type, NodeType::IfExpression);
+        assert_eq!(if_ast.children[2].node_type, NodeType::IfExpression);
+    }
+
+    #[test]
+    fn parser_accepts_casts_as_branch_values() {
+        let ast = parse(tokenize(
+            "float x = if c { 1 } else { 2 as float }; float y = match k { 1 => k as float, _ => 0 as float };",
+        ));
+
+        let if_ast = &ast.children[0].children[1].children[1];
+        assert_eq!(if_ast.children[2].children[0].node_type, NodeType::Cast);
+        let match_ast = &ast.children[1].children[1].children[1];
+        assert_eq!(match_ast.node_type, NodeType::MatchExpression);
+        assert_eq!(match_ast.children[1].children[1].node_type, NodeType::Cast);
+    }
+
+    #[test]
+    fn parser_accepts_lambdas_as_branch_values() {
+        let ast = parse(tokenize(
+            "enum Op { Add, Sub };
+            fn(int) int f = if c { |a| a + 1 } else { fn (int a) int { return a; } };
+            fn(int, int) int g = match op { Op::Add => |a, b| a + b, _ => |a, b| a };
+            fn() int h = if c { || 1 } else { || 2 };
+            fn() int k = fn () int { return 1; };",
+        ));
+
+        let if_ast = &ast.children[1].children[1].children[1];
+        assert_eq!(if_ast.children[1].children[0].node_type, NodeType::Lambda);
+        assert_eq!(if_ast.children[2].children[0].node_type, NodeType::Lambda);
+        let match_ast = &ast.children[2].children[1].children[1];
+        assert_eq!(
+            match_ast.children[1].children[1].node_type,
+            NodeType::Lambda
+        );
+        let if_ast = &ast.children[3].children[1].children[1];
+        assert_eq!(if_ast.children[1].children[0].node_type, NodeType::Lambda);
+        assert_eq!(ast.children[4].node_type, NodeType::Declare);
+    }
+
+    #[test]
+    fn parser_parse_struct() {
+        let ast = parse(tokenize(
+            "struct Point { int x; Point* next; }; Point p = Point { x: 1, next: n };",
+        ));
+
+        let struct_ast = &ast.children[0];
+        assert_eq!(struct_ast.node_type, NodeType::StructDef);
+        assert_eq!(struct_ast.value, Some("Point".to_string()));
+        assert_eq!(struct_ast.children[0].node_type, NodeType::Field);
+        assert_eq!(
+            struct_ast.children[1].children[0].node_type,
+            NodeType::Type(DataType::Pointer(Box::new(DataType::Struct(
+                "Point".to_string()
+            ))))
+        );
+
+        let declare = &ast.children[1];
+        assert_eq!(
+            declare.children[0].node_type,
+            NodeType::Type(DataType::Struct("Point".to_string()))
+        );
+
+        let literal = &declare.children[1].children[1];
+        assert_eq!(literal.node_type, NodeType::StructLiteral);
+        assert_eq!(literal.children.len(), 2);
+        assert_eq!(
+            literal.children[1].children[0].value,
+            Some("next".to_string())
+        );
+    }
+
+    #[test]
+    fn parser_parse_field_assignment() {
+        let ast = parse(tokenize("p.next->x += 1;"));
+
+        let assign = &ast.children[0];
+        assert_eq!(assign.node_type, NodeType::ShortAssign(Operator::Add));
+
+        let target = &assign.children[0];
+        assert_eq!(target.node_type, NodeType::PointerFieldAccess);
+        assert_eq!(target.value, Some("x".to_string()));
+        assert_eq!(target.children[0].node_type, NodeType::FieldAccess);
+        assert_eq!(target.children[0].value, Some("next".to_string()));
+    }
+
+    #[test]
+    fn parser_parse_enum_and_match() {
+        let ast = parse(tokenize(
+            "enum Color { Red, Green }; Color c = Color::Green; match c { Color::Red => { print(1); } _ => print(2), };",
+        ));
+
+        let enum_ast = &ast.children[0];
+        assert_eq!(enum_ast.node_type, NodeType::EnumDef);
+        assert_eq!(enum_ast.children.len(), 2);
+
+        let declare = &ast.children[1];
+        assert_eq!(
+            declare.children[0].node_type,
+            NodeType::Type(DataType::Enum("Color".to_string()))
+        );
+        assert_eq!(
+            declare.children[1].children[1].node_type,
+            NodeType::EnumVariant
+        );
+
+        let match_ast = &ast.children[2];
+        assert_eq!(match_ast.node_type, NodeType::Match);
+        assert_eq!(match_ast.children.len(), 3);
+        assert_eq!(
+            match_ast.children[1].children[0].value,
+            Some("Red".to_string())
+        );
+        assert_eq!(
+            match_ast.children[2].children[0].node_type,
+            NodeType::Wildcard
+        );
+        assert_eq!(
+            match_ast.children[2].children[1].node_type,
+            NodeType::FunctionCall
+        );
+    }
+
+    #[test]
+    fn parser_parse_match_expression() {
+        let ast = parse(tokenize(
+            "int x = match y { -1 => 0, 2 => { int z = 3; z } _ => 1 };",
+        ));
+
+        let match_ast = &ast.children[0].children[1].children[1];
+        assert_eq!(match_ast.node_type, NodeType::MatchExpression);
+        assert_eq!(
+            match_ast.children[1].children[0].value,
+            Some("-1".to_string())
+        );
+        assert_eq!(match_ast.children[2].children[1].node_type, NodeType::Block);
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected a value at the end of match expression branch")]
+    fn parser_rejects_match_expression_without_value() {
+        parse(tokenize("int x = match y { _ => { y = 1; } };"));
+    }
+
+    #[test]
+    fn parser_parse_signals() {
+        let ast = parse(tokenize("int x #= 1; int y $= #x + $z; x #= 2;"));
+
+        let signal = &ast.children[0];
+        assert_eq!(signal.node_type, NodeType::SignalDeclare);
+        assert_eq!(signal.value, Some("x".to_string()));
+        assert_eq!(
+            signal.children[0].node_type,
+            NodeType::Type(DataType::Integer)
+        );
+
+        let computed = &ast.children[1];
+        assert_eq!(computed.node_type, NodeType::ComputedDeclare);
+        assert_eq!(computed.children[1].children[0].node_type, NodeType::Signal);
+        assert_eq!(
+            computed.children[1].children[1].node_type,
+            NodeType::Computed
+        );
+
+        let assign = &ast.children[2];
+        assert_eq!(assign.node_type, NodeType::SignalAssign);
+        assert_eq!(assign.value, Some("x".to_string()));
+        assert_eq!(assign.children[0].value, Some("2".to_string()));
+    }
+
+    #[test]
+    fn parser_parse_imports() {
+        let ast = parse(tokenize(
+            r#"import math; import "lib/vec.tan"; pub fn f() int { return math.pi; }; pub int x = 1;"#,
+        ));
+        assert_eq!(ast.children[0].node_type, NodeType::Import);
+        assert_eq!(ast.children[0].value, Some("math.tan".to_string()));
+        assert_eq!(ast.children[1].value, Some("lib/vec.tan".to_string()));
+        assert_eq!(ast.children[2].node_type, NodeType::Pub);
+        assert_eq!(ast.children[2].children[0].node_type, NodeType::FunctionDef);
+        assert_eq!(ast.children[3].children[0].node_type, NodeType::Declare);
+    }
+
+    #[test]
+    fn parser_parse_externs() {
+        let ast = parse(tokenize(
+            r#"extern "math.h" fn sin(float x) float; extern fn srand(int seed);"#,
+        ));
+        assert_eq!(ast.children[0].node_type, NodeType::ExternDef);
+        assert_eq!(ast.children[0].value, Some("sin".to_string()));
+        assert_eq!(*ast.children[0].children[1].data_type(), DataType::Float);
+        assert_eq!(
+            ast.children[0].children[2].value,
+            Some("math.h".to_string())
+        );
+        assert_eq!(*ast.children[1].children[1].data_type(), DataType::Void);
+        assert_eq!(ast.children[1].children.len(), 2);
+    }
+
+    #[test]
+    fn parser_parse_constants() {
+        let ast = parse(tokenize(
+            "const int size = 4 * 4; pub const bool debug = false;",
+        ));
+        assert_eq!(ast.children[0].node_type, NodeType::ConstDeclare);
+        assert_eq!(ast.children[0].children[1].node_type, NodeType::Assign);
+        assert_eq!(
+            ast.children[1].children[0].node_type,
+            NodeType::ConstDeclare
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Constant on line 1 needs a type, a name and a value")]
+    fn parser_rejects_constant_without_value() {
+        parse(tokenize("const int size;"));
+    }
+
+    #[test]
+    fn parser_parse_literal_suffixes() {
+        let ast = parse(tokenize("u8 x = 0xFFu8 + 1;"));
+        let add = &ast.children[0].children[1].children[1];
+        assert_eq!(add.children[0].value, Some("255".to_string()));
+        assert_eq!(
+            add.children[0].children[0].node_type,
+            NodeType::Type(DataType::Sized(IntType::U8))
+        );
+        assert!(add.children[1].children.is_empty());
+    }
+
+    #[test]
+    fn parser_parse_casts() {
+        let ast = parse(tokenize("a * b as float; x as float * 2;"));
+        let product = &ast.children[0];
+        assert_eq!(product.node_type, NodeType::Operator(Operator::Multiply));
+        assert_eq!(product.children[1].node_type, NodeType::Cast);
+        assert_eq!(
+            *product.children[1].children[1].data_type(),
+            DataType::Float
+        );
+
+        let product = &ast.children[1];
+        assert_eq!(product.node_type, NodeType::Operator(Operator::Multiply));
+        assert_eq!(product.children[0].node_type, NodeType::Cast);
+        assert_eq!(product.children[1].value, Some("2".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected type to cast to, got Identifier(\"Point\") on line 1")]
+    fn parser_rejects_cast_to_struct() {
+        parse(tokenize("x as Point;"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only functions and globals can be pub")]
+    fn parser_rejects_pub_struct() {
+        parse(tokenize("pub struct A { int x; };"));
+    }
+
+    #[test]
+    fn parser_parse_generics() {
+        let ast = parse(tokenize(
+            "struct Pair<T> { T first; T second; };
+            fn first<T>(Pair<T> pair) T { return pair.first; };
+            Pair<int> p = Pair<int> { first: 1, second: 2 };",
+        ));
+
+        let pair = &ast.children[0];
+        assert_eq!(pair.node_type, NodeType::GenericDef);
+        assert_eq!(pair.children[0].children[0].value, Some("T".to_string()));
+        assert_eq!(
+            pair.children[1].children[0].children[0].node_type,
+            NodeType::Type(DataType::Param("T".to_string()))
+        );
+
+        let first = &ast.children[1].children[1];
+        assert_eq!(first.node_type, NodeType::FunctionDef);
+        assert_eq!(
+            first.children[0].children[0].children[0].node_type,
+            NodeType::Type(DataType::Instance(
+                "Pair".to_string(),
+                vec![DataType::Param("T".to_string())]
+            ))
+        );
+
+        let int_pair = DataType::Instance("Pair".to_string(), vec![DataType::Integer]);
+        let declare = &ast.children[2];
+        assert_eq!(
+            declare.children[0].node_type,
+            NodeType::Type(int_pair.clone())
+        );
+
+        let literal = &declare.children[1].children[1];
+        assert_eq!(literal.node_type, NodeType::StructLiteral);
+        assert_eq!(literal.value, Some("Pair".to_string()));
+        assert_eq!(literal.children[0].node_type, NodeType::Type(int_pair));
+    }
+
+    #[test]
+    fn parser_parse_objects() {
+        let ast = parse(tokenize(
+            r#"object<int> o = { a: 1, "b c": 2 }; o["b c"] = o.a; {};"#,
+        ));
+
+        let declare = &ast.children[0];
+        assert_eq!(
+            declare.children[0].node_type,
+            NodeType::Type(DataType::Object(Box::new(DataType::Integer)))
+        );
+
+        let literal = &declare.children[1].children[1];
+        assert_eq!(literal.node_type, NodeType::ObjectLiteral);
+        assert_eq!(
+            literal.children[0].children[0].node_type,
+            NodeType::Identifier
+        );
+        assert_eq!(literal.children[1].children[0].node_type, NodeType::String);
+        assert_eq!(
+            literal.children[1].children[0].value,
+            Some("b c".to_string())
+        );
+
+        let assign = &ast.children[1];
+        assert_eq!(assign.children[0].node_type, NodeType::Index);
+        assert_eq!(assign.children[0].children[1].node_type, NodeType::String);
+        assert_eq!(assign.children[1].node_type, NodeType::FieldAccess);
+
+        // A curly brace starting a statement is still a block
+        assert_eq!(ast.children[2].node_type, NodeType::Block);
+    }
+
+    #[test]
+    fn parser_parse_lambdas() {
+        let ast = parse(tokenize(
+            "fn(int, int) int f = fn (int a, int b) int { return a; }; g = |x| x + 1; h = || 2; f(1, 2)(3);",
+        ));
+
+        let declare = &ast.children[0];
+        assert_eq!(
+            declare.children[0].node_type,
+            NodeType::Type(DataType::Function(
+                vec![DataType::Integer, DataType::Integer],
+                Box::new(DataType::Integer)
+            ))
+        );
+        let lambda = &declare.children[1].children[1];
+        assert_eq!(lambda.node_type, NodeType::Lambda);
+        assert_eq!(lambda.children.len(), 3);
+
+        // Pipe lambdas leave their types to the checker
+        let pipe = &ast.children[1].children[1];
+        assert_eq!(pipe.node_type, NodeType::Lambda);
+        assert_eq!(pipe.children.len(), 2);
+        assert_eq!(pipe.children[0].children[0].children.len(), 1);
+        assert_eq!(pipe.children[1].children[0].node_type, NodeType::Return);
+        assert!(ast.children[2].children[1].children[0].children.is_empty());
+
+        let call = &ast.children[3];
+        assert_eq!(call.node_type, NodeType::Call);
+        assert_eq!(call.children[0].node_type, NodeType::FunctionCall);
+        assert_eq!(call.children[1].value, Some("3".to_string()));
+    }
+
+    #[test]
+    fn parser_parse_pointer_operations() {
+        let ast = parse(tokenize(
+            "int* p = &a; *p = *p * 2; (*q).x = 1; p = new int;",
+        ));
+
+        let address = &ast.children[0].children[1].children[1];
+        assert_eq!(address.node_type, NodeType::AddressOf);
+        assert_eq!(address.children[0].value, Some("a".to_string()));
+
+        let assign = &ast.children[1];
+        assert_eq!(assign.node_type, NodeType::Assign);
+        assert_eq!(assign.children[0].node_type, NodeType::Deref);
+        assert_eq!(
+            assign.children[1].node_type,
+            NodeType::Operator(Operator::Multiply)
+        );
+        assert_eq!(assign.children[1].children[0].node_type, NodeType::Deref);
+
+        let field = &ast.children[2].children[0];
+        assert_eq!(field.node_type, NodeType::FieldAccess);
+        assert_eq!(field.children[0].node_type, NodeType::LParen);
+
+        let new = &ast.children[3].children[1];
+        assert_eq!(new.node_type, NodeType::New);
+        assert_eq!(new.children[0].node_type, NodeType::Type(DataType::Integer));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid assignment target")]
+    fn parser_rejects_invalid_assignment_target() {
+        parse(tokenize("a + b = 1;"));
+    }
+
+    #[test]
+    #[should_panic(expected = "if expression requires an else branch")]
+    fn parser_rejects_if_expression_without_else() {
+        parse(tokenize("int x = if c { 1 };"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected a value at the end of if expression branch")]
+    fn parser_rejects_if_expression_without_value() {
+        parse(tokenize("int x = if c { 1 } else { x = 2; };"));
+    }
 }
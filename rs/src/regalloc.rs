@@ -0,0 +1,273 @@
+use crate::ir::*;
+
+// Linear scan register allocation over live intervals. Positions number the block starts and
+// instructions in block order, and a temporary lives from the first to the last position it
+// is defined, used or live at, so an interval also covers the blocks a loop goes through.
+// Intervals are visited by their start, each takes a register that is free, or the interval
+// that lives the longest is spilled to the stack
+pub struct Registers {
+    // Clobbered by calls, only given to temporaries that are not live across one
+    pub caller_saved: &'static [&'static str],
+    // Kept by calls, a function that uses one saves it on entry and restores it on return
+    pub callee_saved: &'static [&'static str],
+}
+
+impl Registers {
+    // Spills every temporary
+    pub const NONE: Registers = Registers {
+        caller_saved: &[],
+        callee_saved: &[],
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Location {
+    Register(&'static str),
+    // Index of an 8 byte stack slot
+    Stack(usize),
+}
+
+pub struct Allocation {
+    // None for temporaries that are never used
+    pub locations: Vec<Option<Location>>,
+    pub stack_slots: usize,
+    // Callee saved registers the function uses
+    pub saved: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub temp: Temp,
+    pub start: usize,
+    pub end: usize,
+    // Whether the temporary is live while a call clobbers the caller saved registers, or
+    // is read by a call
+    pub crosses_call: bool,
+}
+
+// Live intervals sorted by their start. The target tells which instructions call functions,
+// parameters are defined at the start of the function
+pub fn intervals(function: &Function, is_call: impl Fn(&Instruction) -> bool) -> Vec<Interval> {
+    let (live_in, live_out) = function.liveness();
+    let mut ranges: Vec<Option<(usize, usize)>> = vec![None; function.temps.len()];
+    let mut extend = |temp: Temp, position: usize| {
+        ranges[temp] = Some(match ranges[temp] {
+            Some((start, end)) => (start.min(position), end.max(position)),
+            None => (position, position),
+        });
+    };
+    for param in &function.params {
+        extend(*param, 0);
+    }
+
+    let mut calls = vec![];
+    let mut position = 0;
+    for (index, block) in function.blocks.iter().enumerate() {
+        for temp in &live_in[index] {
+            extend(*temp, position);
+        }
+        for instruction in &block.instructions {
+            position += 1;
+            if is_call(instruction) {
+                calls.push(position);
+            }
+            instruction
+                .uses()
+                .into_iter()
+                .for_each(|temp| extend(temp, position));
+            instruction
+                .dest()
+                .into_iter()
+                .for_each(|temp| extend(temp, position));
+        }
+        position += 1;
+        for temp in block
+            .terminator
+            .uses()
+            .into_iter()
+            .chain(live_out[index].iter().copied())
+        {
+            extend(temp, position);
+        }
+        position += 1;
+    }
+
+    let mut intervals = ranges
+        .into_iter()
+        .enumerate()
+        .filter_map(|(temp, range)| {
+            let (start, end) = range?;
+            Some(Interval {
+                temp,
+                start,
+                end,
+                // Printing reads its operands between calls, so they count as live across
+                crosses_call: calls.iter().any(|call| start < *call && *call <= end),
+            })
+        })
+        .collect::<Vec<Interval>>();
+    intervals.sort_by_key(|interval| (interval.start, interval.temp));
+    intervals
+}
+
+pub fn allocate(
+    function: &Function,
+    registers: &Registers,
+    is_call: impl Fn(&Instruction) -> bool,
+) -> Allocation {
+    let mut locations = vec![None; function.temps.len()];
+    let mut stack_slots = 0;
+    let mut spill = |locations: &mut Vec<Option<Location>>, temp: Temp| {
+        locations[temp] = Some(Location::Stack(stack_slots));
+        stack_slots += 1;
+    };
+    let mut active: Vec<(Interval, &'static str)> = vec![];
+
+    for interval in intervals(function, is_call) {
+        // A temporary used for the last time can give its register to the one defined there,
+        // operands are read before the result is written
+        active.retain(|(other, _)| other.end > interval.start);
+
+        let candidates = match interval.crosses_call {
+            true => registers.callee_saved.to_vec(),
+            false => [registers.caller_saved, registers.callee_saved].concat(),
+        };
+        let free = candidates
+            .iter()
+            .find(|register| active.iter().all(|(_, taken)| taken != *register));
+        if let Some(register) = free {
+            locations[interval.temp] = Some(Location::Register(register));
+            active.push((interval, register));
+            continue;
+        }
+
+        let victim = active
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, register))| candidates.contains(register))
+            .max_by_key(|(_, (other, _))| other.end)
+            .map(|(index, _)| index);
+        match victim {
+            Some(index) if active[index].0.end > interval.end => {
+                let (victim, register) = active[index];
+                spill(&mut locations, victim.temp);
+                locations[interval.temp] = Some(Location::Register(register));
+                active[index] = (interval, register);
+            }
+            _ => spill(&mut locations, interval.temp),
+        }
+    }
+
+    let saved = registers
+        .callee_saved
+        .iter()
+        .copied()
+        .filter(|register| locations.contains(&Some(Location::Register(register))))
+        .collect();
+    Allocation {
+        locations,
+        stack_slots,
+        saved,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::lower_script;
+
+    const REGISTERS: Registers = Registers {
+        caller_saved: &["r1"],
+        callee_saved: &["s1", "s2"],
+    };
+
+    // The last function defined by the script, main comes after it
+    fn function(script: &str) -> Function {
+        let mut functions = lower_script(script, &[]).functions;
+        functions.pop();
+        functions.pop().unwrap()
+    }
+
+    fn is_call(instruction: &Instruction) -> bool {
+        matches!(instruction, Instruction::Call { .. })
+    }
+
+    #[test]
+    fn regalloc_intervals_cover_loops() {
+        let function =
+            function("fn f(int n) int { int i = 0; while i < n { i += 1; }; return i; };");
+        let intervals = intervals(&function, is_call);
+        // n is defined on entry and live until the end of the loop body jumps back
+        assert_eq!(
+            intervals[0],
+            Interval {
+                temp: 0,
+                start: 0,
+                end: 8,
+                crosses_call: false
+            }
+        );
+        assert_eq!(
+            intervals[1],
+            Interval {
+                temp: 1,
+                start: 1,
+                end: 10,
+                crosses_call: false
+            }
+        );
+        assert_eq!(
+            intervals[2],
+            Interval {
+                temp: 2,
+                start: 4,
+                end: 5,
+                crosses_call: false
+            }
+        );
+    }
+
+    #[test]
+    fn regalloc_reuses_registers_of_finished_intervals() {
+        let function = function("fn f(int a) int { int b = a + 1; int c = b * 2; return c; };");
+        let allocation = allocate(&function, &REGISTERS, is_call);
+        assert!(allocation
+            .locations
+            .iter()
+            .all(|location| *location == Some(Location::Register("r1"))));
+        assert_eq!(allocation.stack_slots, 0);
+        assert!(allocation.saved.is_empty());
+    }
+
+    #[test]
+    fn regalloc_keeps_values_across_calls_in_callee_saved_registers() {
+        let function =
+            function("fn g() int { return 1; }; fn f(int a) int { int b = g(); return a + b; };");
+        let allocation = allocate(&function, &REGISTERS, is_call);
+        assert_eq!(allocation.locations[0], Some(Location::Register("s1")));
+        assert_eq!(allocation.saved, vec!["s1"]);
+    }
+
+    #[test]
+    fn regalloc_spills_the_interval_that_ends_last() {
+        let function =
+            function("fn f(int a, int b, int c, int d) int { int x = a + b; return x + c + d; };");
+        let allocation = allocate(&function, &REGISTERS, is_call);
+        // d lives the longest of the parameters and only three registers are free
+        assert_eq!(allocation.locations[3], Some(Location::Stack(0)));
+        assert_eq!(allocation.stack_slots, 1);
+        assert_eq!(allocation.saved, vec!["s1", "s2"]);
+    }
+
+    #[test]
+    fn regalloc_spills_everything_without_registers() {
+        let function = function("fn f(int a) int { int b = a + 1; return b; };");
+        let allocation = allocate(&function, &Registers::NONE, is_call);
+        assert_eq!(
+            allocation.locations,
+            (0..3)
+                .map(|slot| Some(Location::Stack(slot)))
+                .collect::<Vec<_>>()
+        );
+    }
+}
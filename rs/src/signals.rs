@@ -0,0 +1,103 @@
+use crate::ast::*;
+
+// Dependency graph of the signals of a program, a port of the TypeScript engine's Signal.ts.
+// Instead of marking computed signals dirty and recomputing them when they are read,
+// every assignment re-evaluates the signals that depend on the assigned one
+#[derive(Default)]
+pub struct SignalGraph {
+    // In declaration order, a computed signal can only depend on signals declared before it
+    signals: Vec<SignalNode>,
+}
+
+struct SignalNode {
+    name: String,
+    computed: bool,
+    depends_on: Vec<String>,
+}
+
+impl SignalGraph {
+    pub fn new() -> SignalGraph {
+        SignalGraph { signals: vec![] }
+    }
+
+    // Builds the graph from the signal declarations at the top level of a program
+    pub fn from_ast(ast: &AstNode) -> SignalGraph {
+        let mut graph = SignalGraph::new();
+        for child in &ast.children {
+            if let NodeType::SignalDeclare | NodeType::ComputedDeclare = child.node_type {
+                graph.declare(child);
+            }
+        }
+
+        graph
+    }
+
+    pub fn declare(&mut self, node: &AstNode) {
+        let computed = node.node_type == NodeType::ComputedDeclare;
+        let depends_on = match computed {
+            true => find_signals(&node.children[1]),
+            false => vec![],
+        };
+
+        self.signals.push(SignalNode {
+            name: node.value.clone().unwrap(),
+            computed,
+            depends_on,
+        });
+    }
+
+    // The computed signals to re-evaluate after a signal changes, every signal comes after
+    // the signals it depends on
+    pub fn dependents(&self, name: &str) -> Vec<String> {
+        let mut changed = vec![name.to_string()];
+        let mut dependents = vec![];
+
+        for signal in &self.signals {
+            if signal.computed && signal.depends_on.iter().any(|dep| changed.contains(dep)) {
+                changed.push(signal.name.clone());
+                dependents.push(signal.name.clone());
+            }
+        }
+
+        dependents
+    }
+}
+
+// Names of the signals read in an expression, like findSignals in the TypeScript engine
+pub fn find_signals(node: &AstNode) -> Vec<String> {
+    let mut signals = vec![];
+    collect_signals(node, &mut signals);
+    signals
+}
+
+fn collect_signals(node: &AstNode, signals: &mut Vec<String>) {
+    if let NodeType::Signal | NodeType::Computed = node.node_type {
+        let name = node.value.clone().unwrap();
+        if !signals.contains(&name) {
+            signals.push(name);
+        }
+    }
+
+    for child in &node.children {
+        collect_signals(child, signals);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    #[test]
+    fn signals_find_transitive_dependents() {
+        let ast = parse(tokenize(
+            "int x #= 1; int w #= 2; int y $= #x + 1; int z $= $y * #w; int v $= #w;",
+        ));
+        let graph = SignalGraph::from_ast(&ast);
+
+        assert_eq!(graph.dependents("x"), vec!["y", "z"]);
+        assert_eq!(graph.dependents("w"), vec!["z", "v"]);
+        assert!(graph.dependents("v").is_empty());
+    }
+}
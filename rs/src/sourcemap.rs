@@ -0,0 +1,163 @@
+use std::path::Path;
+
+use crate::ast::{AstNode, Span};
+
+// Backends put a marker line before the code of a statement, the control character can not
+// start a line of generated code since string literals are escaped
+const MARKER: char = '\u{1}';
+
+// Source maps in the version 3 format that node, browsers and debuggers read. Each line of
+// generated code maps to the statement of the marker before it
+#[derive(Debug, Default, PartialEq)]
+pub struct SourceMap {
+    pub sources: Vec<String>,
+    // Source index and line, counted from 1, of each generated line
+    pub lines: Vec<Option<(usize, usize)>>,
+}
+
+// Marker line for a statement, empty when the file it comes from is unknown
+pub fn marker(node: &AstNode) -> String {
+    match &node.span {
+        Some(Span {
+            file: Some(file),
+            line,
+        }) => format!("{}{} {}\n", MARKER, line, file),
+        _ => String::new(),
+    }
+}
+
+// Removes the marker lines and maps the lines after them
+pub fn extract(code: &str) -> (String, SourceMap) {
+    let mut map = SourceMap::default();
+    let mut output = String::new();
+    let mut current = None;
+    for line in code.split_inclusive('\n') {
+        if let Some(marker) = line.trim_start().strip_prefix(MARKER) {
+            let (line, file) = marker.trim_end_matches('\n').split_once(' ').unwrap();
+            let source = match map.sources.iter().position(|source| source == file) {
+                Some(index) => index,
+                None => {
+                    map.sources.push(file.to_string());
+                    map.sources.len() - 1
+                }
+            };
+            current = Some((source, line.parse().unwrap()));
+            continue;
+        }
+
+        output += line;
+        map.lines.push(current);
+    }
+    (output, map)
+}
+
+impl SourceMap {
+    // Sources are relative to the directory the map is written to
+    pub fn to_json(&self, file: &str, directory: &Path) -> String {
+        let sources = self
+            .sources
+            .iter()
+            .map(|source| {
+                let source = Path::new(source);
+                let relative = source.strip_prefix(directory).unwrap_or(source);
+                json_string(&relative.display().to_string())
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+
+        format!(
+            "{{\"version\":3,\"file\":{},\"sources\":[{}],\"names\":[],\"mappings\":{}}}",
+            json_string(file),
+            sources,
+            json_string(&self.mappings())
+        )
+    }
+
+    // Every mapped line has one segment at its first column, fields after the column are
+    // relative to the previous segment
+    fn mappings(&self) -> String {
+        let (mut previous_source, mut previous_line) = (0, 0);
+        self.lines
+            .iter()
+            .map(|line| match line {
+                Some((source, line)) => {
+                    let line = line - 1;
+                    let mut segment = String::new();
+                    for value in [
+                        0,
+                        *source as i64 - previous_source as i64,
+                        line as i64 - previous_line as i64,
+                        0,
+                    ] {
+                        vlq(value, &mut segment);
+                    }
+                    (previous_source, previous_line) = (*source, line);
+                    segment
+                }
+                None => String::new(),
+            })
+            .collect::<Vec<String>>()
+            .join(";")
+    }
+}
+
+// Base 64 variable length quantity, the lowest bit of the first digit is the sign
+fn vlq(value: i64, output: &mut String) {
+    const DIGITS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut rest = match value < 0 {
+        true => (-value << 1) | 1,
+        false => value << 1,
+    };
+    loop {
+        let mut digit = rest & 31;
+        rest >>= 5;
+        if rest > 0 {
+            digit |= 32;
+        }
+        output.push(DIGITS[digit as usize] as char);
+        if rest == 0 {
+            break;
+        }
+    }
+}
+
+fn json_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(value: i64) -> String {
+        let mut output = String::new();
+        vlq(value, &mut output);
+        output
+    }
+
+    #[test]
+    fn sourcemap_encodes_vlq() {
+        assert_eq!(encode(0), "A");
+        assert_eq!(encode(1), "C");
+        assert_eq!(encode(-1), "D");
+        assert_eq!(encode(15), "e");
+        assert_eq!(encode(16), "gB");
+        assert_eq!(encode(-123), "3H");
+    }
+
+    #[test]
+    fn sourcemap_maps_lines_after_markers() {
+        let code = "header;\n\u{1}3 lib/a.tan\nf() {\n  \u{1}4 lib/a.tan\n  g();\n}\n\u{1}1 main.tan\nf();\n";
+        let (code, map) = extract(code);
+        assert_eq!(code, "header;\nf() {\n  g();\n}\nf();\n");
+        assert_eq!(map.sources, vec!["lib/a.tan", "main.tan"]);
+        assert_eq!(
+            map.lines,
+            vec![None, Some((0, 3)), Some((0, 4)), Some((0, 4)), Some((1, 1))]
+        );
+        assert_eq!(
+            map.to_json("a.mjs", Path::new("lib")),
+            r#"{"version":3,"file":"a.mjs","sources":["a.tan","main.tan"],"names":[],"mappings":";AAEA;AACA;AAAA;ACHA"}"#
+        );
+    }
+}
@@ -1,7 +1,50 @@
-use crate::ast;
-use crate::compiler::{compile_declare, compile_expression};
+use crate::ast::AstNode;
+use crate::checker::check;
+use crate::compiler::{
+    compile, compile_assign, compile_call, compile_declare, compile_expression,
+    compile_function_call, compile_if, compile_lambda, compile_lambda_def, compile_node,
+    compile_object_literal, compile_print, compile_program, compile_signal_update,
+    compile_struct_def, compile_while,
+};
+use crate::interpreter::{interpret, Externs, Value};
+use crate::ir::{lower, Program};
 use crate::lexer::tokenize;
+use crate::modules::set_file;
+use crate::optimize::Pass;
 use crate::parser::parse;
+use crate::signals::SignalGraph;
+
+// Parses and checks a script, which is what every backend starts from
+pub fn check_script(script: &str) -> AstNode {
+    let mut root = parse(tokenize(script));
+    check(&mut root);
+    root
+}
+
+// Lowers a script to the IR and runs the passes on it
+pub fn lower_script(script: &str, passes: &[Pass]) -> Program {
+    let mut program = lower(&check_script(script));
+    for pass in passes {
+        pass.run(&mut program);
+    }
+    program
+}
+
+// Compares what a backend generates for a script of golden/ with a golden file, the golden
+// files were run and print what the interpreter prints
+macro_rules! test_golden {
+    ($name:ident, $compile:expr, $script:literal, $golden:literal) => {
+        #[test]
+        fn $name() {
+            let root = $crate::test::check_script(include_str!(concat!("../golden/", $script)));
+            assert_eq!(
+                $compile(&root),
+                include_str!(concat!("../golden/", $golden))
+            );
+        }
+    };
+}
+pub(crate) use test_golden;
 
 macro_rules! test {
     ($name:ident, $script:expr, $expected:expr) => {
@@ -81,3 +124,848 @@ test_compile!(
     "int** a;",
     "int** a"
 );
+
+test_compile!(
+    integration_compile_comparison,
+    compile_expression,
+    "a < b == c >= d;",
+    "a < b == c >= d"
+);
+
+test_compile!(
+    integration_compile_loop_jumps,
+    compile_while,
+    "while a < 10 { a += 1; continue; break; };",
+    "while (a < 10) {\n\ta += 1;\n\tcontinue;\n\tbreak;\n}"
+);
+
+test_compile!(
+    integration_compile_labelled_loop,
+    compile_while,
+    "'outer: while true { while true { continue 'outer; break 'outer; }; };",
+    "while (true) {\n\twhile (true) {\n\t\tgoto outer_continue;\n\t\tgoto outer_break;\n\t}\n\touter_continue:;\n}\nouter_break:;"
+);
+
+test_compile!(
+    integration_compile_nested_statements,
+    compile_while,
+    "while a { match b { 1 => { if c { d = 1; }; }, _ => d = 2 }; if e {}; { f = 1; }; };",
+    "while (a) {\n\tif (b == 1) {\n\t\tif (c) {\n\t\t\td = 1;\n\t\t}\n\t} else {\n\t\td = 2;\n\t}\n\tif (e) {}\n\t{\n\t\tf = 1;\n\t}\n}"
+);
+
+test_compile!(
+    integration_compile_unused_label,
+    compile_while,
+    "'outer: while true { break; };",
+    "while (true) {\n\tbreak;\n}"
+);
+
+test_compile!(
+    integration_compile_else_if,
+    compile_if,
+    "if a { b = 1; } else if c { b = 2; } else { b = 3; };",
+    "if (a) {\n\tb = 1;\n} else if (c) {\n\tb = 2;\n} else {\n\tb = 3;\n}"
+);
+
+test_compile!(
+    integration_compile_if_expression,
+    compile_declare,
+    "int x = if a { 1 } else if b { 2 } else { 3 };",
+    "int x = (a ? 1 : (b ? 2 : 3))"
+);
+
+test_compile!(
+    integration_compile_if_expression_statements,
+    compile_declare,
+    "int x = if a { int y = 2; y * 2 } else { 3 };",
+    "int x = (a ? ({ int y = 2; y * 2; }) : 3)"
+);
+
+test_compile!(
+    integration_compile_struct_def,
+    compile_struct_def,
+    "struct Node { int value; Node* next; };",
+    "typedef struct Node {\n\tint value;\n\tNode* next;\n} Node"
+);
+
+test_compile!(
+    integration_compile_pointer_operations,
+    compile_assign,
+    "*p = *(q + 1) + &a->b;",
+    "*p = *(q + 1) + &a->b"
+);
+
+#[test]
+fn integration_compile_allocations() {
+    let tokens =
+        tokenize("int n = 1; int* a = alloc(n + 1); int** b = new int*; a = null; free(a);");
+    let mut root = parse(tokens);
+    check(&mut root);
+
+    assert_eq!(
+        compile_declare(&root.children[1]),
+        "int* a = malloc(sizeof(int) * (n + 1))"
+    );
+    assert_eq!(
+        compile_declare(&root.children[2]),
+        "int** b = malloc(sizeof(int*))"
+    );
+    assert_eq!(compile_assign(&root.children[3]), "a = NULL");
+    assert_eq!(compile_function_call(&root.children[4]), "free(a)");
+}
+
+#[test]
+fn integration_compile_lambda() {
+    // Lambdas refer to globals, so the captured variable is declared in a block
+    let tokens = tokenize("{ int n = 1; fn(int) int f = |x| x + n; f(2); };");
+    let mut root = parse(tokens);
+    check(&mut root);
+    let block = &root.children[0];
+
+    let lambda = &block.children[1].children[1].children[1];
+    assert_eq!(
+        compile_lambda_def(lambda),
+        "int lambda_0(void* lambda_env, int x) {\n\tint n = ((lambda_0_env*)lambda_env)->n;\n\treturn x + n;\n}"
+    );
+    assert_eq!(
+        compile_lambda(lambda),
+        "({ lambda_0_env* lambda_env = malloc(sizeof(lambda_0_env)); *lambda_env = (lambda_0_env){ .n = n }; (tan_closure){ (void (*)(void))lambda_0, lambda_env }; })"
+    );
+    assert_eq!(
+        compile_call(&block.children[2]),
+        "((int (*)(void*, int))f.call)(f.env, 2)"
+    );
+}
+
+#[test]
+fn integration_compile_generic_instances() {
+    let tokens = tokenize(
+        "struct Box<T> { T value; }; fn open<T>(Box<T> box) T { return box.value; }; open(Box { value: 1 });",
+    );
+    let mut root = parse(tokens);
+    check(&mut root);
+
+    assert_eq!(
+        compile_struct_def(&root.children[0]),
+        "typedef struct Box_int {\n\tint value;\n} Box_int"
+    );
+    assert_eq!(
+        compile_node(&root.children[1]),
+        "int open_int(Box_int box) {\n\treturn box.value;\n}"
+    );
+    assert_eq!(
+        compile_node(&root.children[2]),
+        "open_int((Box_int){ .value = 1 })"
+    );
+}
+
+#[test]
+fn integration_compile_objects() {
+    let tokens = tokenize(r#"object<int> o = { a: 1, "b c": 2 }; o["d"] = o.a; o.a += 1;"#);
+    let mut root = parse(tokens);
+    check(&mut root);
+
+    assert_eq!(
+        compile_object_literal(&root.children[0].children[1].children[1]),
+        r#"({ tan_object* object_value = tan_object_new(sizeof(int)); *(int*)tan_object_set(object_value, "a") = 1; *(int*)tan_object_set(object_value, "b c") = 2; object_value; })"#
+    );
+    assert_eq!(
+        compile_node(&root.children[1]),
+        r#"({ int property_value = (*(int*)tan_object_get(o, "a")); *(int*)tan_object_set(o, "d") = property_value; })"#
+    );
+    assert_eq!(
+        compile_node(&root.children[2]),
+        r#"(*(int*)tan_object_get(o, "a")) += 1"#
+    );
+}
+
+#[test]
+fn integration_compile_signal_update() {
+    let tokens = tokenize("int x #= 1; int w #= 2; int y $= #x + 1; int z $= $y * #w; x #= 3;");
+    let mut root = parse(tokens);
+    check(&mut root);
+
+    let signal_defs = root.children[..4].iter().collect::<Vec<&AstNode>>();
+    let graph = SignalGraph::from_ast(&root);
+    assert_eq!(
+        compile_signal_update(&root.children[0], &signal_defs, &graph),
+        "void update_x(int value) {\n\tsignal_x = value;\n\tsignal_y = signal_x + 1;\n\tsignal_z = signal_y * signal_w;\n}"
+    );
+    assert_eq!(compile_node(&root.children[4]), "update_x(3)");
+}
+
+#[test]
+fn integration_compile_struct_literal() {
+    let tokens = tokenize("struct Point { int x; int y; }; Point p = Point { x: 1, y: 2 + 3 };");
+    let root = parse(tokens);
+
+    assert_eq!(
+        compile_declare(&root.children[1]),
+        "Point p = (Point){ .x = 1, .y = 2 + 3 }"
+    );
+}
+
+test_golden!(
+    integration_compile_golden_control_flow,
+    compile,
+    "control_flow.tan",
+    "c/control_flow.c"
+);
+
+// With passes the C is compiled from the optimized IR
+test_golden!(
+    integration_compile_golden_optimized_control_flow,
+    |root| {
+        let mut program = lower(root);
+        for pass in Pass::DEFAULT {
+            pass.run(&mut program);
+        }
+        compile_program(&program)
+    },
+    "control_flow.tan",
+    "c/control_flow.opt.c"
+);
+
+#[test]
+fn integration_compile_ir_names() {
+    let c = compile_program(&lower_script(
+        "int x = 1; fn f(int a) int { int y = a; if a > 0 { int y = 2; a += y; }; return a + y + x; }; print(f(x));",
+        &[],
+    ));
+
+    assert!(c.contains("int f(int a) {\n\tint y_1;\n\tbool t_2;\n\tint y_3;"));
+    assert!(c.contains("\tif (!t_2) goto block_2;\n\ty_3 = 2;\n\ta = a + y_3;\nblock_2:\n"));
+    assert!(c.contains("\tt_0 = x;\n\tt_1 = f(t_0);\n\tprintf(\"%d\\n\", t_1);\n\treturn 0;\n}\n"));
+}
+
+#[test]
+fn integration_compile_line_directives() {
+    let mut root = parse(tokenize(
+        "fn f(int a) int {\n    if a > 0 {\n\n        return 1;\n    };\n    return 0;\n};\nprint(f(2));",
+    ));
+    check(&mut root);
+    set_file(&mut root, "main.tan");
+
+    assert!(compile(&root).contains(
+        "#line 1 \"main.tan\"\nint f(int a) {\n\t#line 2 \"main.tan\"\n\tif (a > 0) {\n\t\t#line 4 \"main.tan\"\n\t\treturn 1;\n\t}\n\t#line 6 \"main.tan\"\n\treturn 0;\n}\n\nint main() {\n\t#line 8 \"main.tan\"\n\tprintf("
+    ));
+}
+
+#[test]
+fn integration_compile_line_directives_of_hoisted_code() {
+    let mut root = parse(tokenize(
+        "int x #= 1;\nint y $= #x + 1;\nfn(int) int f = |a|\n    a + 1;\nx #= 2;",
+    ));
+    check(&mut root);
+    set_file(&mut root, "main.tan");
+
+    let output = compile(&root);
+    assert!(output.contains(
+        "#line 3 \"main.tan\"\nint lambda_0(void* lambda_env, int a) {\n\t#line 4 \"main.tan\"\n\treturn a + 1;\n}"
+    ));
+    assert!(output.contains(
+        "#line 1 \"main.tan\"\nvoid update_x(int value) {\n\tsignal_x = value;\n\t#line 2 \"main.tan\"\n\tsignal_y = signal_x + 1;\n}"
+    ));
+}
+
+test_compile!(
+    integration_compile_field_assignment,
+    compile_assign,
+    "a.b->c = d->e.f;",
+    "a.b->c = d->e.f"
+);
+
+macro_rules! test_interpret {
+    ($name:ident, $script:expr, $expected:expr) => {
+        #[test]
+        fn $name() {
+            let tokens = tokenize($script);
+            let mut root = parse(tokens);
+            check(&mut root);
+
+            let mut output = vec![];
+            interpret(
+                &root,
+                &Externs::default(),
+                &mut std::io::empty(),
+                &mut output,
+            );
+            assert_eq!(String::from_utf8(output).unwrap(), $expected);
+        }
+    };
+}
+
+test_interpret!(
+    integration_interpret_function_call,
+    "fn add(int a, int b) int { return a + b; }; int a = 1; int b = 2; print(add(a, b));",
+    "3\n"
+);
+
+test_interpret!(
+    integration_interpret_break_continue,
+    "int i = 0; while true { i += 1; if i % 2 == 0 { continue; }; if i > 7 { break; }; print(i); };",
+    "1\n3\n5\n7\n"
+);
+
+test_interpret!(
+    integration_interpret_labelled_break,
+    "int i = 0;
+    'outer: while i < 3 {
+        int j = 0;
+        while true {
+            j += 1;
+            if j == 2 { i += 1; continue 'outer; };
+            if i == 2 { break 'outer; };
+            print(i, j);
+        };
+    };
+    print(i);",
+    "0, 1\n1, 1\n2\n"
+);
+
+test_interpret!(
+    integration_interpret_else_if,
+    "int i = 0;
+    while i < 4 {
+        if i == 0 { print(10); } else if i == 1 { print(11); } else if i == 2 { print(12); } else { print(13); };
+        i += 1;
+    };",
+    "10\n11\n12\n13\n"
+);
+
+test_interpret!(
+    integration_interpret_if_expression,
+    "int a = 5;
+    int x = if a > 3 { int y = a * 2; y + 1 } else { 0 };
+    print(x, if a == 1 { 1 } else if a == 5 { 5 } else { 0 });",
+    "11, 5\n"
+);
+
+test_interpret!(
+    integration_interpret_lambdas_as_branch_values,
+    "enum Op { Add, Mul };
+    bool c = false;
+    Op op = Op::Mul;
+    fn(int) int f = if c { |a| a + 1 } else { |a| a };
+    fn(int, int) int g = match op { Op::Add => |a, b| a + b, Op::Mul => |a, b| a * b };
+    fn() int h = if c { || 1 } else { fn () int { return 2; } };
+    print(f(5), g(3, 4), h());",
+    "5, 12, 2\n"
+);
+
+test_interpret!(
+    integration_interpret_structs,
+    "struct Point { int x; int y; };
+    struct Rect { Point min; Point max; };
+
+    fn area(Rect r) int {
+        return (r.max.x - r.min.x) * (r.max.y - r.min.y);
+    };
+
+    fn shift(Point p, int by) Point {
+        p.x += by;
+        return p;
+    };
+
+    Rect r = Rect { min: Point { x: 1, y: 2 }, max: Point { x: 4, y: 6 } };
+    Point copy = r.max;
+    r.max = shift(r.max, 2);
+    r.min.y++;
+    print(area(r), copy.x, r.max);",
+    "15, 4, Point { x: 6, y: 6 }\n"
+);
+
+test_interpret!(
+    integration_interpret_match,
+    "enum Color { Red, Green, Blue };
+
+    fn describe(Color c) int {
+        return match c { Color::Red => 1, Color::Green => { int g = 20; g + 2 } Color::Blue => 3 };
+    };
+
+    int i = 0;
+    while true {
+        i += 1;
+        match i {
+            1 => print(describe(Color::Red)),
+            2 => { print(describe(Color::Green)); continue; }
+            5 => break,
+            _ => print(match i % 2 == 0 { true => 0, false => i }),
+        };
+    };
+    Color c;
+    print(c, c == Color::Red);",
+    "1\n22\n3\n0\nColor::Red, true\n"
+);
+
+test_interpret!(
+    integration_interpret_pointers,
+    "struct Node { int value; Node* next; };
+
+    fn push(Node* head, int value) Node* {
+        Node* node = new Node;
+        node->value = value;
+        node->next = head;
+        return node;
+    };
+
+    fn swap(int* a, int* b) int {
+        int t = *a;
+        *a = *b;
+        *b = t;
+        return 0;
+    };
+
+    int x = 1;
+    int y = 2;
+    swap(&x, &y);
+
+    Node* list = null;
+    int i = 0;
+    while i < 4 {
+        list = push(list, i);
+        i++;
+    };
+
+    int total = 0;
+    Node* node = list;
+    while node != null {
+        total += node->value;
+        node = node->next;
+    };
+
+    int* squares = alloc(3);
+    int* last = squares + 2;
+    *last = 9;
+    (*list).value = 10;
+    print(x, y, total, *last, last - squares, list->value, node == null);
+    free(squares);",
+    "2, 1, 6, 9, 2, 10, true\n"
+);
+
+test_interpret!(
+    integration_interpret_closures,
+    "fn apply(fn(int) int f, int x) int { return f(x); };
+    fn dbl(int x) int { return x * 2; };
+
+    fn adder(int n) fn(int) int {
+        return |x| x + n;
+    };
+
+    fn compose(fn(int) int f, fn(int) int g) fn(int) int {
+        return fn (int x) int { return g(f(x)); };
+    };
+
+    int base = 10;
+    fn(int) int addBase = |x| x + base;
+    base = 100;
+    fn() int seven = || 7;
+    print(addBase(1), apply(dbl, 4), apply(|x| x * x, 5), adder(5)(5), compose(adder(3), dbl)(1), seven());",
+    "101, 8, 25, 10, 8, 7\n"
+);
+
+test_interpret!(
+    integration_interpret_lambdas_use_globals,
+    "int g = 1;
+    fn() int read = || g;
+    g = 5;
+    fn() int inc = fn () int { g++; return g; };
+    inc();
+    print(read(), inc(), g);",
+    "6, 7, 7\n"
+);
+
+test_interpret!(
+    integration_interpret_signals,
+    "fn square(int x) int { return x * x; };
+
+    int x #= 1;
+    int w #= 2;
+    int y $= #x + 1;
+    int z $= square($y) * #w;
+    print(#x, $y, $z);
+
+    x #= 4;
+    print($y, $z);
+
+    fn bump(int by) int {
+        w #= #w + by;
+        return $z;
+    };
+    print(bump(1), bump(1));",
+    "1, 2, 8\n5, 50\n75, 100\n"
+);
+
+test_interpret!(
+    integration_interpret_generics,
+    "struct Pair<T> { T first; T second; };
+    struct Node<T> { T value; Node<T>* next; };
+
+    fn max<T>(T a, T b) T {
+        if a > b { return a; };
+        return b;
+    };
+
+    fn larger<T>(Pair<T> pair) T {
+        return max(pair.first, pair.second);
+    };
+
+    fn push<T>(Node<T>* head, T value) Node<T>* {
+        Node<T>* node = new Node<T>;
+        node->value = value;
+        node->next = head;
+        return node;
+    };
+
+    fn sum(Node<int>* list) int {
+        int total = 0;
+        while list != null {
+            total += list->value;
+            list = list->next;
+        };
+        return total;
+    };
+
+    print(max(3, 7), max(true, false), larger(Pair { first: 4, second: 2 }));
+    Pair<Pair<int>> nested = Pair<Pair<int>> {
+        first: Pair { first: 1, second: 2 },
+        second: Pair { first: 3, second: 4 }
+    };
+    print(larger(nested.second), sum(push(push(null, 5), 6)));",
+    "7, true, 4\n4, 11\n"
+);
+
+test_interpret!(
+    integration_interpret_objects,
+    r#"fn area(object<int> shape) int {
+        return shape.width * shape["height"];
+    };
+
+    object<int> box = { width: 3, "height": 4 };
+    object<int> alias = box;
+    alias.width = 10;
+    print(area(box), box == alias);
+
+    object<float> counts = {};
+    string key = "seen";
+    counts[key] = 1;
+    counts.seen += 2;
+    print(counts["seen"], key == "seen");
+
+    object<object<int>> nested = { inner: { a: 1 } };
+    nested.inner.b = nested.inner.a + 1;
+    print(nested.inner.b);"#,
+    "40, true\n3, true\n2\n"
+);
+
+test_interpret!(
+    integration_interpret_builtins,
+    r#"seed(42);
+    int a = random(100);
+    seed(42);
+    assert(a == random(100));
+    string s = concat("tan", str(pow(2, 10)));
+    print(abs(0 - 3), min(4, 2), max(4, 2), sqrt(17), s, len(s), substr(s, 1, 4));
+    exit(3);
+    print(0);"#,
+    "3, 2, 4, 4, tan1024, 7, an10\n"
+);
+
+test_interpret!(
+    integration_interpret_float_math,
+    "float f = 0 as float - 9 as float / 4;
+    float g = 3 as float / 2;
+    print(abs(f), min(f, g), max(f, 2), pow(g, 3), sqrt(2 as float), sqrt(f), pow(2, g));
+    print(abs(0 - 3), sqrt(17), min(sqrt(f), 1));",
+    "2.25, -2.25, 2, 3.375, 1.4142135, NaN, 2.828427\n3, 4, 1\n"
+);
+
+test_interpret!(
+    integration_interpret_globals,
+    r#"fn bump() int { count += step; return count; };
+    const int step = 2 * 3;
+    const string label = "count";
+    int count = 1;
+    fn(int) int plus = |x| x + count;
+    bump();
+    print(label, bump(), plus(0));"#,
+    "count, 13, 13\n"
+);
+
+test_interpret!(
+    integration_interpret_casts,
+    "float f = 79 as float / 10;
+    float big = 2147483647 as float * 4;
+    const bool on = 2 as bool;
+    print(f as int, ((0 - 25) as float / 10) as int, big as int, (0 - big) as int);
+    print(on, 0 as bool, true as int + 1);",
+    "7, -2, 2147483647, -2147483648\ntrue, false, 2\n"
+);
+
+#[test]
+fn integration_compile_casts() {
+    let mut root = parse(tokenize(
+        "float f = 3; int n = f as int; bool b = n as bool;",
+    ));
+    check(&mut root);
+
+    assert_eq!(
+        compile_declare(&root.children[1]),
+        "int n = tan_float_to_int(f)"
+    );
+    assert_eq!(compile_declare(&root.children[2]), "bool b = (bool)(n)");
+    assert!(compile(&root).contains("int tan_float_to_int(float x) {"));
+
+    let mut root = parse(tokenize(
+        "float f = 3; u8 n = f as u8; i64 wide = f as i64;",
+    ));
+    check(&mut root);
+    assert_eq!(
+        compile_declare(&root.children[1]),
+        "uint8_t n = tan_float_to_u8(f)"
+    );
+    let c = compile(&root);
+    assert!(c.contains("uint8_t tan_float_to_u8(float x) {"));
+    assert!(c.contains("\tif (x >= 9223372036854775808.0f) {\n\t\treturn 9223372036854775807LL;"));
+}
+
+test_interpret!(
+    integration_interpret_sized_integers,
+    "u8 a = 200;
+    i8 b = 0x7f;
+    b += 1;
+    u32 big = 4_000_000_000;
+    u64 huge = 0xFFFF_FFFF_FFFF_FFFF;
+    i64 neg = 0 - 3000000000;
+    print(a + 100, b, big + 1, huge + 1, neg, ~0u16, 0b1010 * 0o17);
+    print(300 as u8, (0 - 1) as u32, big as i8, huge as i64, a as int + 100);",
+    "44, -128, 4000000001, 0, -3000000000, 65535, 150\n44, 4294967295, 0, -1, 300\n"
+);
+
+#[test]
+fn integration_compile_sized_integers() {
+    let mut root = parse(tokenize(
+        "u8 a = 200; u8 b = a + 100; u64 huge = 0xFFFF_FFFF_FFFF_FFFF; print(a, huge);",
+    ));
+    check(&mut root);
+
+    assert_eq!(
+        compile_declare(&root.children[1]),
+        "uint8_t b = (uint8_t)(a + 100)"
+    );
+    assert_eq!(
+        compile_declare(&root.children[2]),
+        "uint64_t huge = 18446744073709551615ULL"
+    );
+    assert_eq!(
+        compile_print(&root.children[3]),
+        "printf(\"%d, %llu\\n\", a, (unsigned long long)(huge))"
+    );
+    assert!(compile(&root).contains("#include <stdint.h>\n"));
+}
+
+#[test]
+fn integration_compile_globals() {
+    let mut root = parse(tokenize(
+        r#"fn read() int { return count; }; const string label = "n"; int count = 1; int unset;"#,
+    ));
+    check(&mut root);
+
+    let program = compile(&root);
+    assert!(program.contains("const char* const label = \"n\";\nint count;\nint unset;\n"));
+    assert!(program.contains("int main() {\n\tcount = 1;\n\treturn 0;\n}"));
+}
+
+#[test]
+fn integration_interpret_input() {
+    let mut root = parse(tokenize(
+        "int n = readInt(); string name = readLine(); print(n * 2, name); print(readLine() == \"\");",
+    ));
+    check(&mut root);
+
+    let mut output = vec![];
+    let code = interpret(
+        &root,
+        &Externs::default(),
+        &mut " 21 \nworld\r\n".as_bytes(),
+        &mut output,
+    );
+    assert_eq!(code, 0);
+    assert_eq!(String::from_utf8(output).unwrap(), "42, world\ntrue\n");
+}
+
+#[test]
+fn integration_compile_arguments_in_order() {
+    let mut root = parse(tokenize(
+        "fn(int) int f = |a| a; int n = 1; print(readInt(), readInt()); print(n, len(\"ab\")); n = max(f(n), random(n));",
+    ));
+    check(&mut root);
+
+    assert_eq!(
+        compile_print(&root.children[2]),
+        "({ __auto_type arg_0 = tan_read_int(); __auto_type arg_1 = tan_read_int(); printf(\"%d, %d\\n\", arg_0, arg_1); })"
+    );
+    assert_eq!(
+        compile_print(&root.children[3]),
+        "printf(\"%d, %d\\n\", n, (int)strlen(\"ab\"))"
+    );
+    assert_eq!(
+        compile_assign(&root.children[4]),
+        "n = ({ __auto_type arg_0 = ((int (*)(void*, int))f.call)(f.env, n); __auto_type arg_1 = tan_random(n); tan_max(arg_0, arg_1); })"
+    );
+}
+
+#[test]
+fn integration_compile_struct_print() {
+    let mut root = parse(tokenize(
+        "struct Point { int x; bool up; }; Point p = Point { x: 1, up: false }; print(2, p);",
+    ));
+    check(&mut root);
+
+    let program = compile(&root);
+    assert!(program.contains(
+        "void tan_print_Point(Point value) {\n\tprintf(\"Point { x: %d, up: %s }\", value.x, (value.up) ? \"true\" : \"false\");\n}"
+    ));
+    assert!(program.contains("({ printf(\"%d, \", 2); tan_print_Point(p); printf(\"\\n\"); });"));
+}
+
+#[test]
+fn integration_compile_enum_print() {
+    let mut root = parse(tokenize(
+        "enum Shape { Circle, Square }; print(Shape::Square);",
+    ));
+    check(&mut root);
+
+    let program = compile(&root);
+    assert!(program.contains(
+        "const char* const tan_Shape_names[] = {\n\t\"Shape::Circle\",\n\t\"Shape::Square\",\n};"
+    ));
+    assert!(program.contains("printf(\"%s\\n\", tan_Shape_names[Shape_Square]);"));
+}
+
+#[test]
+fn integration_compile_builtins() {
+    let mut root = parse(tokenize(
+        "int n = max(readInt(), len(\"ab\")); free(null); assert(n > 0); seed(n);",
+    ));
+    check(&mut root);
+
+    assert_eq!(
+        compile_declare(&root.children[0]),
+        "int n = tan_max(tan_read_int(), (int)strlen(\"ab\"))"
+    );
+    assert_eq!(compile_function_call(&root.children[1]), "free(NULL)");
+
+    let program = compile(&root);
+    assert!(program.contains("#include <time.h>\n"));
+    assert!(program.contains("int tan_read_int(void) {"));
+    assert!(program.contains("void tan_assert(bool condition) {"));
+    assert!(!program.contains("tan_concat"));
+}
+
+#[test]
+fn integration_compile_externs() {
+    let mut root = parse(tokenize(
+        r#"extern "math.h" fn sin(float x) float; extern "math.h" fn cos(float x) float;
+        extern fn scale(int x, float factor) int; extern fn reset();
+        reset(); print(scale(2, sin(0)));"#,
+    ));
+    check(&mut root);
+
+    let program = compile(&root);
+    assert_eq!(program.matches("#include <math.h>\n").count(), 1);
+    assert!(program.contains("int scale(int x, double factor);\nvoid reset();\n"));
+    assert!(!program.contains("float sin("));
+    assert!(program.contains("printf(\"%d\\n\", scale(2, sin(0)))"));
+}
+
+#[test]
+fn integration_interpret_externs() {
+    let mut root = parse(tokenize(
+        "extern fn twice(int x) int; extern fn log(int x); log(twice(21)); print(floor(2));
+        extern fn floor(float x) float;",
+    ));
+    check(&mut root);
+
+    let logged = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+    let mut externs = Externs::standard();
+    externs.register("twice", |args| match args[0] {
+        Value::Integer(x) => Value::Integer(x * 2),
+        _ => panic!("Expected an integer"),
+    });
+    let log = logged.clone();
+    externs.register("log", move |args| {
+        log.borrow_mut().push(args[0].clone());
+        Value::Void
+    });
+
+    let mut output = vec![];
+    interpret(&root, &externs, &mut std::io::empty(), &mut output);
+    assert_eq!(*logged.borrow(), vec![Value::Integer(42)]);
+    assert_eq!(String::from_utf8(output).unwrap(), "2\n");
+}
+
+macro_rules! test_interpret_panics {
+    ($name:ident, $script:expr, $expected:expr) => {
+        #[test]
+        #[should_panic(expected = $expected)]
+        fn $name() {
+            let mut root = parse(tokenize($script));
+            check(&mut root);
+            interpret(
+                &root,
+                &Externs::default(),
+                &mut std::io::empty(),
+                &mut vec![],
+            );
+        }
+    };
+}
+
+test_interpret_panics!(
+    integration_interpret_null_dereference,
+    "int* p = null; print(*p);",
+    "Null pointer dereference"
+);
+
+test_interpret_panics!(
+    integration_interpret_use_after_free,
+    "int* p = new int; free(p); *p = 1;",
+    "Use of freed memory"
+);
+
+test_interpret_panics!(
+    integration_interpret_double_free,
+    "int* p = alloc(2); free(p); free(p);",
+    "Double free"
+);
+
+test_interpret_panics!(
+    integration_interpret_out_of_bounds,
+    "int* p = alloc(2); print(*(p + 2));",
+    "Pointer out of bounds"
+);
+
+test_interpret_panics!(
+    integration_interpret_dangling_pointer,
+    "int* p = null; { int a = 1; p = &a; }; print(*p);",
+    "Use of a pointer to a variable that went out of scope"
+);
+
+test_interpret_panics!(
+    integration_interpret_failed_assertion,
+    "assert(1 > 2);",
+    "Assertion failed"
+);
+
+test_interpret_panics!(
+    integration_interpret_missing_property,
+    "object<int> o = { a: 1 }; print(o.b);",
+    "Object has no property b"
+);
+
+test_interpret_panics!(
+    integration_interpret_unregistered_extern,
+    "extern fn puts(string s) int; puts(\"hi\");",
+    "Extern function puts has no implementation in the interpreter"
+);
@@ -1,8 +1,10 @@
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Identifier(String),
-    Number(i32),
+    // Value and type suffix of an integer literal, literals are never negative
+    Number(u64, Option<DataType>),
     Boolean(bool),
+    String(String),
     Operator(Operator),
     BitwiseOp(BitwiseOp),
     Type(DataType),
@@ -27,6 +29,8 @@ pub enum Token {
 
     LCurly,
     RCurly,
+    LBracket,
+    RBracket,
 
     Function,
     Comma,
@@ -34,6 +38,29 @@ pub enum Token {
     If,
     Else,
     While,
+    Break,
+    Continue,
+    Label(String),
+    Struct,
+    Dot,
+    Arrow,
+    Enum,
+    Match,
+    DoubleColon,
+    FatArrow,
+    Underscore,
+    Null,
+    New,
+    Signal(String),
+    Computed(String),
+    SignalAssign,
+    ComputeAssign,
+    Object,
+    Import,
+    Pub,
+    Extern,
+    Const,
+    As,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -56,18 +83,139 @@ pub enum BitwiseOp {
 #[derive(Debug, Clone, PartialEq)]
 pub enum DataType {
     Pointer(Box<DataType>),
+    // 32 bit signed integer, also called i32
     Integer,
+    // Integer of any other width and signedness
+    Sized(IntType),
     Float,
     Boolean,
+    String,
+    Struct(String),
+    Enum(String),
+    // Only used behind a pointer, for null and allocations before their type is known,
+    // and as the return type of extern functions that return nothing
+    Void,
+    // Parameter types and return type of a function value
+    Function(Vec<DataType>, Box<DataType>),
+    // Map from property names to values of the given type, shared by reference
+    Object(Box<DataType>),
+    // Type parameter of a generic definition
+    Param(String),
+    // Generic struct with its type arguments, the checker replaces it with a struct instance
+    Instance(String, Vec<DataType>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IntType {
+    I8,
+    I16,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl IntType {
+    pub fn from_name(name: &str) -> Option<IntType> {
+        match name {
+            "i8" => Some(IntType::I8),
+            "i16" => Some(IntType::I16),
+            "i64" => Some(IntType::I64),
+            "u8" => Some(IntType::U8),
+            "u16" => Some(IntType::U16),
+            "u32" => Some(IntType::U32),
+            "u64" => Some(IntType::U64),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            IntType::I8 => "i8",
+            IntType::I16 => "i16",
+            IntType::I64 => "i64",
+            IntType::U8 => "u8",
+            IntType::U16 => "u16",
+            IntType::U32 => "u32",
+            IntType::U64 => "u64",
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        match self {
+            IntType::I8 | IntType::U8 => 8,
+            IntType::I16 | IntType::U16 => 16,
+            IntType::U32 => 32,
+            IntType::I64 | IntType::U64 => 64,
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(self, IntType::I8 | IntType::I16 | IntType::I64)
+    }
 }
 
 impl DataType {
-    pub fn to_string(&self) -> String {
+    // Width in bits and signedness of an integer type, int is i32
+    pub fn integer_layout(&self) -> Option<(u32, bool)> {
+        match self {
+            DataType::Integer => Some((32, true)),
+            DataType::Sized(int_type) => Some((int_type.bits(), int_type.is_signed())),
+            _ => None,
+        }
+    }
+
+    // Smallest and largest value of an integer type
+    pub fn integer_range(&self) -> Option<(i128, i128)> {
+        let (bits, signed) = self.integer_layout()?;
+        Some(match signed {
+            true => (-(1 << (bits - 1)), (1 << (bits - 1)) - 1),
+            false => (0, (1 << bits) - 1),
+        })
+    }
+
+    // Wraps a value around into the range of an integer type, like a C cast does
+    pub fn wrap_integer(&self, value: i128) -> i128 {
+        let (bits, signed) = self.integer_layout().unwrap();
+        let wrapped = value.rem_euclid(1 << bits);
+        if signed && wrapped >= 1 << (bits - 1) {
+            wrapped - (1 << bits)
+        } else {
+            wrapped
+        }
+    }
+}
+
+impl std::fmt::Display for DataType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            DataType::Integer => "int".to_string(),
-            DataType::Float => "float".to_string(),
-            DataType::Boolean => "bool".to_string(),
-            DataType::Pointer(t) => format!("{}*", t.to_string()),
+            DataType::Integer => write!(f, "int"),
+            DataType::Sized(int_type) => write!(f, "{}", int_type.name()),
+            DataType::Float => write!(f, "float"),
+            DataType::Boolean => write!(f, "bool"),
+            DataType::String => write!(f, "string"),
+            DataType::Pointer(t) => write!(f, "{}*", t),
+            DataType::Struct(name) | DataType::Enum(name) => write!(f, "{}", name),
+            DataType::Void => write!(f, "void"),
+            DataType::Function(params, return_type) => {
+                let params = params
+                    .iter()
+                    .map(|param| param.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "fn({}) {}", params, return_type)
+            }
+            DataType::Object(value) => write!(f, "object<{}>", value),
+            DataType::Param(name) => write!(f, "{}", name),
+            DataType::Instance(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "{}<{}>", name, args)
+            }
         }
     }
 }
@@ -77,12 +225,27 @@ pub enum Keywords {
     If,
     Else,
     While,
+    Break,
+    Continue,
+    Struct,
+    Enum,
+    Match,
+    Null,
+    New,
     Function,
+    Object,
+    Import,
+    Pub,
+    Extern,
+    Const,
+    As,
     True,
     False,
     Integer,
     Float,
     Boolean,
+    String,
+    Sized(IntType),
 }
 
 impl Keywords {
@@ -92,13 +255,28 @@ impl Keywords {
             "if" => Some(Keywords::If),
             "else" => Some(Keywords::Else),
             "while" => Some(Keywords::While),
+            "break" => Some(Keywords::Break),
+            "continue" => Some(Keywords::Continue),
+            "struct" => Some(Keywords::Struct),
+            "enum" => Some(Keywords::Enum),
+            "match" => Some(Keywords::Match),
+            "null" => Some(Keywords::Null),
+            "new" => Some(Keywords::New),
             "fn" => Some(Keywords::Function),
+            "object" => Some(Keywords::Object),
+            "import" => Some(Keywords::Import),
+            "pub" => Some(Keywords::Pub),
+            "extern" => Some(Keywords::Extern),
+            "const" => Some(Keywords::Const),
+            "as" => Some(Keywords::As),
             "true" => Some(Keywords::True),
             "false" => Some(Keywords::False),
             "int" => Some(Keywords::Integer),
             "float" => Some(Keywords::Float),
             "bool" => Some(Keywords::Boolean),
-            _ => None,
+            "string" => Some(Keywords::String),
+            "i32" => Some(Keywords::Integer),
+            name => IntType::from_name(name).map(Keywords::Sized),
         }
     }
 
@@ -108,13 +286,27 @@ impl Keywords {
             Keywords::If => Token::If,
             Keywords::Else => Token::Else,
             Keywords::While => Token::While,
+            Keywords::Break => Token::Break,
+            Keywords::Continue => Token::Continue,
+            Keywords::Struct => Token::Struct,
+            Keywords::Enum => Token::Enum,
+            Keywords::Match => Token::Match,
+            Keywords::Null => Token::Null,
+            Keywords::New => Token::New,
             Keywords::Function => Token::Function,
+            Keywords::Object => Token::Object,
+            Keywords::Import => Token::Import,
+            Keywords::Pub => Token::Pub,
+            Keywords::Extern => Token::Extern,
+            Keywords::Const => Token::Const,
+            Keywords::As => Token::As,
             Keywords::True => Token::Boolean(true),
             Keywords::False => Token::Boolean(false),
             Keywords::Integer => Token::Type(DataType::Integer),
             Keywords::Float => Token::Type(DataType::Float),
             Keywords::Boolean => Token::Type(DataType::Boolean),
-            _ => panic!("Keyword not implemented"),
+            Keywords::String => Token::Type(DataType::String),
+            Keywords::Sized(int_type) => Token::Type(DataType::Sized(*int_type)),
         }
     }
 }
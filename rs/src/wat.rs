@@ -0,0 +1,833 @@
+use std::collections::HashMap;
+
+use crate::ast::*;
+use crate::builtins::Builtin;
+use crate::checker::common_type;
+use crate::sourcemap::{extract, marker, SourceMap};
+use crate::types::*;
+
+// Compares two null terminated strings, emitted when strings are compared
+const STREQ_RUNTIME: &str = "  (func $tan_streq (param $a i32) (param $b i32) (result i32)
+    (local $c i32)
+    loop $next
+      local.get $a
+      i32.load8_u
+      local.tee $c
+      local.get $b
+      i32.load8_u
+      i32.ne
+      if
+        i32.const 0
+        return
+      end
+      local.get $c
+      i32.eqz
+      if
+        i32.const 1
+        return
+      end
+      local.get $a
+      i32.const 1
+      i32.add
+      local.set $a
+      local.get $b
+      i32.const 1
+      i32.add
+      local.set $b
+      br $next
+    end
+    i32.const 0)
+";
+
+// Strings are null terminated in the exported memory, address 0 is left unused
+const STRINGS_START: usize = 8;
+
+// Emits a WebAssembly text module. The top level code becomes the exported main function,
+// print calls the host functions print_i32, print_u32, print_i64, print_u64, print_f32,
+// print_bool and print_string imported from "host", extern functions are imported from "env".
+// The source map points the lines of the text module back to the statements
+pub fn compile_wat(ast: &AstNode) -> (String, SourceMap) {
+    let mut generator = Generator::default();
+    let mut globals = String::new();
+    let mut functions = vec![];
+    let mut main = vec![];
+
+    (generator.signatures, generator.globals) = top_level_types(ast);
+
+    for node in &ast.children {
+        match node.node_type {
+            NodeType::FunctionDef => functions.push(marker(node) + &generator.function_def(node)),
+            NodeType::ExternDef => {
+                let name = node.value.as_ref().unwrap();
+                let (params, return_type) = generator.signatures[name].clone();
+                generator.import("env", name, &params, &return_type);
+            }
+            // Constants are folded to literals by the checker
+            NodeType::ConstDeclare => {
+                let data_type = node.children[0].data_type();
+                let value = constant(&mut generator, &node.children[1].children[1], data_type);
+                globals += &format!(
+                    "  (global ${} {} ({}))\n",
+                    declared_name(node),
+                    wasm_type(data_type),
+                    value
+                );
+            }
+            // Globals start out zeroed, main runs their initializers in order
+            NodeType::Declare => {
+                let data_type = node.children[0].data_type();
+                let value = generator.default_value(data_type);
+                globals += &format!(
+                    "  (global ${} (mut {}) ({}))\n",
+                    declared_name(node),
+                    wasm_type(data_type),
+                    value
+                );
+                if node.children[1].node_type == NodeType::Assign {
+                    main.push((marker(node), &node.children[1]));
+                }
+            }
+            _ => main.push((String::new(), node)),
+        }
+    }
+
+    functions.push(
+        generator.function("main", &[], &DataType::Integer, |generator| {
+            for (statement_marker, statement) in main {
+                generator.body += &statement_marker;
+                generator.statement(statement);
+            }
+        }),
+    );
+
+    let mut module = String::from("(module\n");
+    module += &generator.imports.concat();
+
+    let mut offset = STRINGS_START;
+    let mut data = String::new();
+    for value in &generator.strings {
+        data += &format!(
+            "  (data (i32.const {}) \"{}\\00\")\n",
+            offset,
+            escape(value)
+        );
+        offset += value.len() + 1;
+    }
+    module += &format!("  (memory (export \"memory\") {})\n", offset / 65536 + 1);
+    module += &data;
+    module += &globals;
+    if generator.uses_streq {
+        module += STREQ_RUNTIME;
+    }
+    module += &functions.concat();
+    extract(&(module + ")\n"))
+}
+
+fn constant(generator: &mut Generator, node: &AstNode, data_type: &DataType) -> String {
+    match node.node_type {
+        NodeType::String => {
+            let address = generator.string(node.value.as_ref().unwrap());
+            format!("i32.const {}", address)
+        }
+        NodeType::Boolean => format!("i32.const {}", (node.value == Some("true".into())) as i32),
+        _ => format!(
+            "{}.const {}",
+            wasm_type(data_type),
+            node.value.as_ref().unwrap()
+        ),
+    }
+}
+
+struct Loop {
+    label: Option<String>,
+    next: String,
+    end: String,
+}
+
+#[derive(Default)]
+struct Generator {
+    imports: Vec<String>,
+    strings: Vec<String>,
+    uses_streq: bool,
+    globals: HashMap<String, DataType>,
+    signatures: HashMap<String, Signature>,
+
+    // State of the function being generated, wasm locals are declared up front so they are
+    // collected apart from the body
+    return_type: Option<DataType>,
+    locals: String,
+    body: String,
+    depth: usize,
+    labels: usize,
+    slots: HashMap<String, usize>,
+    scopes: Vec<HashMap<String, (String, DataType)>>,
+    loops: Vec<Loop>,
+}
+
+impl Generator {
+    fn function_def(&mut self, node: &AstNode) -> String {
+        let params = node.children[0]
+            .children
+            .iter()
+            .map(|param| {
+                (
+                    param.children[1].value.clone().unwrap(),
+                    param.children[0].data_type().clone(),
+                )
+            })
+            .collect::<Vec<(String, DataType)>>();
+
+        self.function(
+            node.value.as_ref().unwrap(),
+            &params,
+            node.children[1].data_type(),
+            |generator| generator.statement(&node.children[2]),
+        )
+    }
+
+    fn function(
+        &mut self,
+        name: &str,
+        params: &[(String, DataType)],
+        return_type: &DataType,
+        body: impl FnOnce(&mut Generator),
+    ) -> String {
+        self.return_type = Some(return_type.clone());
+        self.locals = String::new();
+        self.body = String::new();
+        self.depth = 2;
+        self.labels = 0;
+        self.slots = HashMap::new();
+        self.scopes = vec![HashMap::new()];
+
+        let mut signature = format!("  (func ${}", name);
+        if name == "main" {
+            signature += " (export \"main\")";
+        }
+        for (param, data_type) in params {
+            self.slots.insert(param.clone(), 1);
+            self.scopes
+                .last_mut()
+                .unwrap()
+                .insert(param.clone(), (format!("${}", param), data_type.clone()));
+            signature += &format!(" (param ${} {})", param, wasm_type(data_type));
+        }
+        signature += &format!(" (result {})\n", wasm_type(return_type));
+
+        body(self);
+
+        // Falling off the end returns a zero value, after a return this is unreachable
+        let value = self.default_value(return_type);
+        self.emit(&value);
+
+        format!("{}{}{})\n", signature, self.locals, self.body.trim_end())
+    }
+
+    fn emit(&mut self, instruction: &str) {
+        self.body += &format!("{}{}\n", "  ".repeat(self.depth), instruction);
+    }
+
+    // Opens a structured block, the instructions up to its end are indented
+    fn open(&mut self, instruction: &str) {
+        self.emit(instruction);
+        self.depth += 1;
+    }
+
+    fn close(&mut self) {
+        self.depth -= 1;
+        self.emit("end");
+    }
+
+    fn label(&mut self, name: &str) -> String {
+        self.labels += 1;
+        format!("${}.{}", name, self.labels - 1)
+    }
+
+    fn import(&mut self, module: &str, name: &str, params: &[DataType], result: &DataType) {
+        let mut import = format!("  (import \"{}\" \"{}\" (func ${}", module, name, name);
+        for param in params {
+            import += &format!(" (param {})", wasm_type(param));
+        }
+        if *result != DataType::Void {
+            import += &format!(" (result {})", wasm_type(result));
+        }
+        import += "))\n";
+
+        if !self.imports.contains(&import) {
+            self.imports.push(import);
+        }
+    }
+
+    // Address of a string constant in memory
+    fn string(&mut self, value: &str) -> usize {
+        if !self.strings.iter().any(|string| string == value) {
+            self.strings.push(value.to_string());
+        }
+        let mut address = STRINGS_START;
+        for string in &self.strings {
+            if string == value {
+                break;
+            }
+            address += string.len() + 1;
+        }
+        address
+    }
+
+    fn default_value(&mut self, data_type: &DataType) -> String {
+        match data_type {
+            DataType::String => format!("i32.const {}", self.string("")),
+            data_type => format!("{}.const 0", wasm_type(data_type)),
+        }
+    }
+
+    fn local(&mut self, name: &str, data_type: &DataType) -> String {
+        let count = self.slots.entry(name.to_string()).or_insert(0);
+        let local = match *count {
+            0 => format!("${}", name),
+            count => format!("${}.{}", name, count),
+        };
+        *count += 1;
+
+        self.locals += &format!("    (local {} {})\n", local, wasm_type(data_type));
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), (local.clone(), data_type.clone()));
+        local
+    }
+
+    // Instructions to read and write a variable, locals shadow globals
+    fn variable(&self, name: &str) -> (String, String, DataType) {
+        if let Some((local, data_type)) = self.scopes.iter().rev().find_map(|scope| scope.get(name))
+        {
+            return (
+                format!("local.get {}", local),
+                format!("local.set {}", local),
+                data_type.clone(),
+            );
+        }
+
+        match self.globals.get(name) {
+            Some(data_type) => (
+                format!("global.get ${}", name),
+                format!("global.set ${}", name),
+                data_type.clone(),
+            ),
+            None => panic!("Use of undeclared variable {}", name),
+        }
+    }
+
+    fn target(&self, node: &AstNode) -> (String, String, DataType) {
+        match node.node_type {
+            NodeType::Identifier => self.variable(node.value.as_ref().unwrap()),
+            _ => panic!("The WAT backend can only assign to variables"),
+        }
+    }
+
+    // ------------------------------ Statements ------------------------------
+    fn statement(&mut self, node: &AstNode) {
+        self.body += &marker(node);
+        match &node.node_type {
+            NodeType::Declare => {
+                let data_type = node.children[0].data_type();
+                let target = &node.children[1];
+
+                // A shadowed variable keeps its local until the value is on the stack
+                let name = match target.node_type {
+                    NodeType::Assign => {
+                        self.value_as(&target.children[1], data_type);
+                        target.children[0].value.clone().unwrap()
+                    }
+                    _ => {
+                        let value = self.default_value(data_type);
+                        self.emit(&value);
+                        target.value.clone().unwrap()
+                    }
+                };
+                let local = self.local(&name, data_type);
+                self.emit(&format!("local.set {}", local));
+            }
+            NodeType::Assign => {
+                let (_, set, data_type) = self.target(&node.children[0]);
+                self.value_as(&node.children[1], &data_type);
+                self.emit(&set);
+            }
+            NodeType::ShortAssign(op) => {
+                let (get, set, data_type) = self.target(&node.children[0]);
+                self.emit(&get);
+                self.value_as(&node.children[1], &data_type);
+                self.arithmetic(op, &data_type);
+                self.emit(&set);
+            }
+            NodeType::Block => {
+                self.scopes.push(HashMap::new());
+                for child in &node.children {
+                    self.statement(child);
+                }
+                self.scopes.pop();
+            }
+            NodeType::If => {
+                self.value_as(&node.children[0], &DataType::Boolean);
+                self.open("if");
+                self.statement(&node.children[1]);
+                if let Some(else_ast) = node.children.get(2) {
+                    self.depth -= 1;
+                    self.emit("else");
+                    self.depth += 1;
+                    self.statement(else_ast);
+                }
+                self.close();
+            }
+            NodeType::While => self.while_loop(node),
+            NodeType::Break | NodeType::Continue => {
+                let target = match &node.value {
+                    Some(label) => self
+                        .loops
+                        .iter()
+                        .rev()
+                        .find(|target| target.label.as_ref() == Some(label)),
+                    None => self.loops.last(),
+                };
+                let target = match target {
+                    Some(target) if node.node_type == NodeType::Break => target.end.clone(),
+                    Some(target) => target.next.clone(),
+                    None => panic!("Unexpected {:?} outside of a loop", node.node_type),
+                };
+                self.emit(&format!("br {}", target));
+            }
+            NodeType::Return => {
+                let return_type = self.return_type.clone().unwrap();
+                self.value_as(&node.children[0], &return_type);
+                self.emit("return");
+            }
+            _ => {
+                // Values of expression statements are dropped
+                if self.value(node) != DataType::Void {
+                    self.emit("drop");
+                }
+            }
+        }
+    }
+
+    // The loop is wrapped in a block, branching to the block leaves the loop and branching
+    // to the loop starts the next iteration
+    fn while_loop(&mut self, node: &AstNode) {
+        let end = self.label("while.end");
+        let next = self.label("while.cond");
+
+        self.open(&format!("block {}", end));
+        self.open(&format!("loop {}", next));
+        self.value_as(&node.children[0], &DataType::Boolean);
+        self.emit("i32.eqz");
+        self.emit(&format!("br_if {}", end));
+
+        self.loops.push(Loop {
+            label: node.value.clone(),
+            next: next.clone(),
+            end: end.clone(),
+        });
+        self.statement(&node.children[1]);
+        self.loops.pop();
+
+        self.emit(&format!("br {}", next));
+        self.close();
+        self.close();
+    }
+
+    // ------------------------------ Expressions ------------------------------
+    // Pushes the value of the expression and returns its type
+    fn value(&mut self, node: &AstNode) -> DataType {
+        let data_type = node.data_type.clone().unwrap_or(DataType::Void);
+        match &node.node_type {
+            NodeType::Number | NodeType::Boolean | NodeType::String => {
+                let value = constant(self, node, &data_type);
+                self.emit(&value);
+            }
+            NodeType::Identifier => {
+                let (get, _, data_type) = self.variable(node.value.as_ref().unwrap());
+                self.emit(&get);
+                return data_type;
+            }
+            NodeType::LParen => return self.value(&node.children[0]),
+            NodeType::Operator(op) => {
+                self.value_as(&node.children[0], &data_type);
+                self.value_as(&node.children[1], &data_type);
+                self.arithmetic(op, &data_type);
+            }
+            NodeType::Eq
+            | NodeType::NotEq
+            | NodeType::LessThan
+            | NodeType::GreaterThan
+            | NodeType::Leq
+            | NodeType::Geq => self.comparison(node),
+            NodeType::And => {
+                self.value_as(&node.children[0], &DataType::Boolean);
+                self.open("if (result i32)");
+                self.value_as(&node.children[1], &DataType::Boolean);
+                self.otherwise();
+                self.emit("i32.const 0");
+                self.close();
+            }
+            NodeType::Or => {
+                self.value_as(&node.children[0], &DataType::Boolean);
+                self.open("if (result i32)");
+                self.emit("i32.const 1");
+                self.otherwise();
+                self.value_as(&node.children[1], &DataType::Boolean);
+                self.close();
+            }
+            NodeType::Not => {
+                self.value_as(&node.children[0], &DataType::Boolean);
+                self.emit("i32.eqz");
+            }
+            NodeType::BitwiseOp(BitwiseOp::Not) => {
+                self.value_as(&node.children[0], &data_type);
+                self.emit(&format!("{}.const -1", wasm_type(&data_type)));
+                self.emit(&format!("{}.xor", wasm_type(&data_type)));
+                self.wrap(&data_type);
+            }
+            NodeType::BitwiseOp(op) => {
+                self.value_as(&node.children[0], &data_type);
+                self.value_as(&node.children[1], &data_type);
+                let instruction = match op {
+                    BitwiseOp::And => "and",
+                    BitwiseOp::Or => "or",
+                    BitwiseOp::Xor => "xor",
+                    BitwiseOp::Not => unreachable!(),
+                };
+                self.emit(&format!("{}.{}", wasm_type(&data_type), instruction));
+            }
+            NodeType::Cast => {
+                let target = node.children[1].data_type();
+                self.value_as(&node.children[0], target);
+                return target.clone();
+            }
+            NodeType::IfExpression => {
+                self.value_as(&node.children[0], &DataType::Boolean);
+                self.open(&format!("if (result {})", wasm_type(&data_type)));
+                self.branch_value(&node.children[1], &data_type);
+                self.otherwise();
+                self.branch_value(&node.children[2], &data_type);
+                self.close();
+            }
+            NodeType::FunctionCall => return self.call(node),
+            NodeType::BuiltinCall => self.builtin_call(node),
+            node_type => panic!("The WAT backend does not support {:?}", node_type),
+        }
+        data_type
+    }
+
+    fn value_as(&mut self, node: &AstNode, data_type: &DataType) {
+        let from = self.value(node);
+        self.convert(&from, data_type);
+    }
+
+    fn otherwise(&mut self) {
+        self.depth -= 1;
+        self.emit("else");
+        self.depth += 1;
+    }
+
+    // Blocks produce the value of their last child
+    fn branch_value(&mut self, node: &AstNode, data_type: &DataType) {
+        if node.node_type != NodeType::Block {
+            return self.value_as(node, data_type);
+        }
+
+        self.scopes.push(HashMap::new());
+        let (value, statements) = node.children.split_last().unwrap();
+        for statement in statements {
+            self.statement(statement);
+        }
+        self.value_as(value, data_type);
+        self.scopes.pop();
+    }
+
+    // Applies the operator to the two values on the stack
+    fn arithmetic(&mut self, op: &Operator, data_type: &DataType) {
+        let signed = data_type.integer_layout().map(|(_, signed)| signed);
+        let instruction = match (signed, op) {
+            (_, Operator::Add) => "add",
+            (_, Operator::Subtract) => "sub",
+            (_, Operator::Multiply) => "mul",
+            (None, Operator::Divide) => "div",
+            (Some(true), Operator::Divide) => "div_s",
+            (Some(false), Operator::Divide) => "div_u",
+            (None, Operator::Mod) => panic!("The WAT backend does not support % on floats"),
+            (Some(true), Operator::Mod) => "rem_s",
+            (Some(false), Operator::Mod) => "rem_u",
+        };
+        self.emit(&format!("{}.{}", wasm_type(data_type), instruction));
+        self.wrap(data_type);
+    }
+
+    fn comparison(&mut self, node: &AstNode) {
+        let left = node.children[0].data_type.clone().unwrap();
+        let right = node.children[1].data_type.clone().unwrap();
+
+        if left == DataType::String {
+            self.uses_streq = true;
+            self.value(&node.children[0]);
+            self.value(&node.children[1]);
+            self.emit("call $tan_streq");
+            if node.node_type == NodeType::NotEq {
+                self.emit("i32.eqz");
+            }
+            return;
+        }
+
+        let data_type = match common_type(&left, &right) {
+            Some(data_type) => data_type,
+            None => panic!("The WAT backend can not compare {} and {}", left, right),
+        };
+        self.value_as(&node.children[0], &data_type);
+        self.value_as(&node.children[1], &data_type);
+
+        let signed = data_type.integer_layout().map(|(_, signed)| signed);
+        let suffix = match signed {
+            Some(true) => "_s",
+            Some(false) => "_u",
+            None => "",
+        };
+        let instruction = match node.node_type {
+            NodeType::Eq => "eq".to_string(),
+            NodeType::NotEq => "ne".to_string(),
+            NodeType::LessThan => format!("lt{}", suffix),
+            NodeType::GreaterThan => format!("gt{}", suffix),
+            NodeType::Leq => format!("le{}", suffix),
+            NodeType::Geq => format!("ge{}", suffix),
+            _ => panic!("Unexpected comparison {:?}", node.node_type),
+        };
+        self.emit(&format!("{}.{}", wasm_type(&data_type), instruction));
+    }
+
+    fn call(&mut self, node: &AstNode) -> DataType {
+        let name = node.value.clone().unwrap();
+        let (params, return_type) = match self.signatures.get(&name) {
+            Some(signature) => signature.clone(),
+            None => panic!("The WAT backend does not support calling {}", name),
+        };
+
+        for (arg, param) in node.children.iter().zip(&params) {
+            self.value_as(arg, param);
+        }
+        self.emit(&format!("call ${}", name));
+        return_type
+    }
+
+    // Each value is printed by the host function for its type
+    fn builtin_call(&mut self, node: &AstNode) {
+        let name = node.value.as_ref().unwrap();
+        if Builtin::from_name(name) != Some(Builtin::Print) {
+            panic!("The WAT backend does not support the builtin {}", name);
+        }
+
+        for (index, arg) in node.children.iter().enumerate() {
+            if index > 0 {
+                self.print_string(", ");
+            }
+            let data_type = self.value(arg);
+            let (host, param) = match &data_type {
+                DataType::String => ("print_string", DataType::String),
+                DataType::Boolean => ("print_bool", DataType::Boolean),
+                DataType::Float => ("print_f32", DataType::Float),
+                DataType::Sized(IntType::I64) => ("print_i64", data_type.clone()),
+                DataType::Sized(IntType::U64) => ("print_u64", data_type.clone()),
+                DataType::Sized(IntType::U32) => ("print_u32", data_type.clone()),
+                _ => ("print_i32", DataType::Integer),
+            };
+            self.convert(&data_type, &param);
+            self.import("host", host, &[param], &DataType::Void);
+            self.emit(&format!("call ${}", host));
+        }
+        self.print_string("\n");
+    }
+
+    fn print_string(&mut self, value: &str) {
+        let address = self.string(value);
+        self.emit(&format!("i32.const {}", address));
+        self.import("host", "print_string", &[DataType::String], &DataType::Void);
+        self.emit("call $print_string");
+    }
+
+    // Integers narrower than 32 bits are kept in i32 values and wrapped after every operation
+    fn wrap(&mut self, data_type: &DataType) {
+        match data_type {
+            DataType::Sized(IntType::I8) => self.emit("i32.extend8_s"),
+            DataType::Sized(IntType::I16) => self.emit("i32.extend16_s"),
+            DataType::Sized(IntType::U8) => {
+                self.emit("i32.const 255");
+                self.emit("i32.and");
+            }
+            DataType::Sized(IntType::U16) => {
+                self.emit("i32.const 65535");
+                self.emit("i32.and");
+            }
+            _ => {}
+        }
+    }
+
+    // Converts the value on the stack with the semantics of the interpreter, integers wrap
+    // and floats saturate when converted to integers
+    fn convert(&mut self, from: &DataType, to: &DataType) {
+        if from == to {
+            return;
+        }
+
+        match (from.integer_layout(), to.integer_layout()) {
+            (Some((from_bits, signed)), Some((to_bits, _))) => {
+                if from_bits == 64 && to_bits < 64 {
+                    self.emit("i32.wrap_i64");
+                }
+                if from_bits < 64 && to_bits == 64 {
+                    let sign = if signed { "s" } else { "u" };
+                    self.emit(&format!("i64.extend_i32_{}", sign));
+                }
+                self.wrap(to);
+            }
+            (Some((bits, signed)), None) if *to == DataType::Float => {
+                let sign = if signed { "s" } else { "u" };
+                self.emit(&format!("f32.convert_i{}_{}", bits.max(32), sign));
+            }
+            (Some((bits, _)), None) if *to == DataType::Boolean => {
+                let integer = if bits == 64 { "i64" } else { "i32" };
+                self.emit(&format!("{}.const 0", integer));
+                self.emit(&format!("{}.ne", integer));
+            }
+            (None, Some((bits, signed))) if *from == DataType::Float => {
+                let sign = if signed { "s" } else { "u" };
+                // Narrow types are clamped to their range first
+                if bits < 32 {
+                    let (min, max) = to.integer_range().unwrap();
+                    self.emit(&format!("f32.const {}", min));
+                    self.emit("f32.max");
+                    self.emit(&format!("f32.const {}", max));
+                    self.emit("f32.min");
+                }
+                self.emit(&format!(
+                    "{}.trunc_sat_f32_{}",
+                    if bits == 64 { "i64" } else { "i32" },
+                    sign
+                ));
+            }
+            (None, Some((bits, _))) if *from == DataType::Boolean => {
+                if bits == 64 {
+                    self.emit("i64.extend_i32_u");
+                }
+            }
+            (None, None) if *from == DataType::Boolean && *to == DataType::Float => {
+                self.emit("f32.convert_i32_u");
+            }
+            (None, None) if *from == DataType::Float && *to == DataType::Boolean => {
+                self.emit("f32.const 0");
+                self.emit("f32.ne");
+            }
+            _ => panic!("The WAT backend can not convert {} to {}", from, to),
+        }
+    }
+}
+
+fn wasm_type(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::Integer | DataType::Boolean | DataType::String => "i32",
+        DataType::Sized(IntType::I64 | IntType::U64) => "i64",
+        DataType::Sized(_) => "i32",
+        DataType::Float => "f32",
+        data_type => panic!(
+            "The WAT backend does not support values of type {}",
+            data_type
+        ),
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b' '..=b'~' if byte != b'"' && byte != b'\\' => (byte as char).to_string(),
+            byte => format!("\\{:02x}", byte),
+        })
+        .collect()
+}
+
+fn declared_name(node: &AstNode) -> String {
+    let target = &node.children[1];
+    match target.node_type {
+        NodeType::Assign => target.children[0].value.clone().unwrap(),
+        _ => target.value.clone().unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::set_file;
+    use crate::test::{check_script, test_golden};
+
+    fn compile_script(script: &str) -> String {
+        compile_wat(&check_script(script)).0
+    }
+
+    // The golden files were run with a host printing like the interpreter
+    test_golden!(
+        wat_golden_scalars,
+        |root| compile_wat(root).0,
+        "scalars.tan",
+        "wat/scalars.wat"
+    );
+    test_golden!(
+        wat_golden_control_flow,
+        |root| compile_wat(root).0,
+        "control_flow.tan",
+        "wat/control_flow.wat"
+    );
+
+    #[test]
+    fn wat_maps_instructions_to_source_lines() {
+        let mut root = check_script("int a = 1;\nif a > 0 {\n    a = 2;\n};");
+        set_file(&mut root, "main.tan");
+
+        let (wat, map) = compile_wat(&root);
+        let lines = wat.lines().zip(map.lines).collect::<Vec<_>>();
+        assert_eq!(
+            lines[3],
+            ("  (func $main (export \"main\") (result i32)", None)
+        );
+        assert_eq!(lines[4], ("    i32.const 1", Some((0, 1))));
+        assert_eq!(lines[6], ("    global.get $a", Some((0, 2))));
+        assert_eq!(lines[10], ("      i32.const 2", Some((0, 3))));
+    }
+
+    #[test]
+    fn wat_maps_locals_and_params() {
+        let wat =
+            compile_script("fn twice(int x) int { int y = x * 2; { int y = y; return y; }; };");
+        assert!(wat.contains("(func $twice (param $x i32) (result i32)\n"));
+        assert!(wat.contains("    (local $y i32)\n    (local $y.1 i32)\n"));
+        assert!(wat.contains("    local.get $y\n    local.set $y.1\n"));
+    }
+
+    #[test]
+    fn wat_lowers_while_to_block_and_loop() {
+        let wat = compile_script("int i = 0; while i < 3 { i += 1; };");
+        assert!(wat
+            .contains("    block $while.end.0\n      loop $while.cond.1\n        global.get $i\n"));
+        assert!(wat.contains("        i32.eqz\n        br_if $while.end.0\n"));
+        assert!(wat.contains("        br $while.cond.1\n      end\n    end\n"));
+    }
+
+    #[test]
+    fn wat_imports_print_per_type() {
+        let wat = compile_script("print(1, true);");
+        assert!(wat.contains("(import \"host\" \"print_i32\" (func $print_i32 (param i32)))"));
+        assert!(wat.contains("(import \"host\" \"print_bool\" (func $print_bool (param i32)))"));
+        assert!(wat.contains("(data (i32.const 8) \", \\00\")\n"));
+        assert!(!wat.contains("$tan_streq"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The WAT backend does not support")]
+    fn wat_rejects_unsupported_nodes() {
+        compile_script("struct Point { int x; int y; }; Point p = Point { x: 1, y: 2 };");
+    }
+}
@@ -0,0 +1,681 @@
+use std::collections::HashSet;
+
+use crate::builtins::Builtin;
+use crate::ir::*;
+use crate::regalloc::{self, Allocation, Location, Registers};
+use crate::types::*;
+
+// Prints one value with printf, emitted when the program prints. Bools pick their text and
+// fall through to printing it as a string
+const PRINT_RUNTIME: &str = "tan_print_int:
+\tpushq %rbp
+\tmovq %rsp, %rbp
+\tmovq %rdi, %rsi
+\tleaq .Ltan_int(%rip), %rdi
+\txorl %eax, %eax
+\tcall printf@PLT
+\tpopq %rbp
+\tret
+tan_print_uint:
+\tpushq %rbp
+\tmovq %rsp, %rbp
+\tmovq %rdi, %rsi
+\tleaq .Ltan_uint(%rip), %rdi
+\txorl %eax, %eax
+\tcall printf@PLT
+\tpopq %rbp
+\tret
+tan_print_bool:
+\tleaq .Ltan_true(%rip), %rax
+\tleaq .Ltan_false(%rip), %rsi
+\ttestq %rdi, %rdi
+\tcmovneq %rax, %rsi
+\tmovq %rsi, %rdi
+tan_print_string:
+\tpushq %rbp
+\tmovq %rsp, %rbp
+\tmovq %rdi, %rsi
+\tleaq .Ltan_string(%rip), %rdi
+\txorl %eax, %eax
+\tcall printf@PLT
+\tpopq %rbp
+\tret
+";
+
+const PRINT_FORMATS: &str = ".Ltan_int:
+\t.string \"%lld\"
+.Ltan_uint:
+\t.string \"%llu\"
+.Ltan_string:
+\t.string \"%s\"
+.Ltan_true:
+\t.string \"true\"
+.Ltan_false:
+\t.string \"false\"
+";
+
+// System V registers for the first integer arguments, the rest go on the stack
+const ARGUMENT_REGISTERS: [&str; 6] = ["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+
+// Emits x86-64 assembly in GAS syntax for the System V ABI from the IR, linked with the C
+// library for printf and extern functions. Temporaries live in the registers they are
+// allocated or in 8 byte slots in the frame, instructions load their operands into scratch
+// registers and store the result back. Integers are kept sign or zero extended to 64 bits.
+// Integers, bools and strings are supported, the rest panics
+pub fn compile_asm(program: &Program, registers: &Registers) -> String {
+    let mut generator = Generator {
+        registers,
+        strings: vec![],
+        uses_print: false,
+        externs: program.externs.iter().map(|def| def.name.clone()).collect(),
+        name: String::new(),
+        body: String::new(),
+        allocation: None,
+    };
+    let mut data = String::new();
+    let mut constants = String::new();
+
+    // Globals start out zeroed, main runs their initializers in order
+    for global in &program.globals {
+        supported(&global.data_type);
+        let value = generator.constant(&global.value);
+        let definition = format!("{}:\n\t.quad {}\n", global.name, value);
+        match global.constant {
+            true => constants += &definition,
+            false => data += &definition,
+        }
+    }
+
+    let functions = program
+        .functions
+        .iter()
+        .map(|function| generator.function(function))
+        .collect::<Vec<String>>();
+
+    let mut program = String::from("\t.section .rodata\n");
+    for (index, value) in generator.strings.iter().enumerate() {
+        program += &format!(".Lstr.{}:\n\t.string \"{}\"\n", index, escape(value));
+    }
+    if generator.uses_print {
+        program += PRINT_FORMATS;
+    }
+    // Constants may hold addresses of strings, which are relocated when the program loads
+    if !constants.is_empty() {
+        program += "\t.section .data.rel.ro\n\t.balign 8\n";
+        program += &constants;
+    }
+    if !data.is_empty() {
+        program += "\t.data\n\t.balign 8\n";
+        program += &data;
+    }
+    program += "\t.text\n\t.globl main\n";
+    if generator.uses_print {
+        program += PRINT_RUNTIME;
+    }
+    program += &functions.concat();
+    program + "\t.section .note.GNU-stack,\"\",@progbits\n"
+}
+
+// Registers left to the allocator. The generated code uses %rax, %rcx and %rdx as scratch
+// registers and the argument registers for calls, so none of them are allocated
+pub const REGISTERS: Registers = Registers {
+    caller_saved: &["%r10", "%r11"],
+    callee_saved: &["%rbx", "%r12", "%r13", "%r14", "%r15"],
+};
+
+struct Generator<'a> {
+    registers: &'a Registers,
+    strings: Vec<String>,
+    uses_print: bool,
+    externs: HashSet<String>,
+
+    // State of the function being generated
+    name: String,
+    body: String,
+    allocation: Option<Allocation>,
+}
+
+impl Generator<'_> {
+    fn function(&mut self, function: &Function) -> String {
+        supported(&function.return_type);
+        function.temps.iter().for_each(supported);
+        self.name = function.name.clone();
+        self.body = String::new();
+
+        // Calls clobber the caller saved registers, printing and comparing strings call too
+        let allocation =
+            regalloc::allocate(function, self.registers, |instruction| match instruction {
+                Instruction::Call { .. } | Instruction::Builtin { .. } => true,
+                Instruction::Compare { left, .. } => {
+                    function.operand_type(left) == DataType::String
+                }
+                _ => false,
+            });
+        let saved = allocation.saved.clone();
+        let frame = 8 * (saved.len() + allocation.stack_slots);
+        self.allocation = Some(allocation);
+
+        // Parameters are moved to their place, from registers or above the return address
+        for (index, param) in function.params.iter().enumerate() {
+            let place = self.place(*param);
+            match ARGUMENT_REGISTERS.get(index) {
+                Some(register) => self.emit(&format!("movq {}, {}", register, place)),
+                None => {
+                    let offset = 16 + 8 * (index - ARGUMENT_REGISTERS.len());
+                    self.emit(&format!("movq {}(%rbp), %rax", offset));
+                    self.emit(&format!("movq %rax, {}", place));
+                }
+            }
+        }
+
+        for (index, block) in function.blocks.iter().enumerate() {
+            if index > 0 {
+                self.body += &format!("{}:\n", self.label(index));
+            }
+            for instruction in &block.instructions {
+                self.instruction(function, instruction);
+            }
+            self.terminator(function, &block.terminator, index + 1);
+        }
+
+        // The frame keeps the stack 16 byte aligned for calls, callee saved registers are
+        // saved at its top
+        let mut prologue = format!("{}:\n\tpushq %rbp\n\tmovq %rsp, %rbp\n", function.name);
+        if frame > 0 {
+            prologue += &format!("\tsubq ${}, %rsp\n", frame.div_ceil(16) * 16);
+        }
+        for (index, register) in saved.iter().enumerate() {
+            prologue += &format!("\tmovq {}, -{}(%rbp)\n", register, 8 * (index + 1));
+        }
+        prologue + &self.body
+    }
+
+    // The register or stack slot of a temporary
+    fn place(&self, temp: Temp) -> String {
+        let allocation = self.allocation.as_ref().unwrap();
+        match allocation.locations[temp] {
+            Some(Location::Register(register)) => register.to_string(),
+            Some(Location::Stack(slot)) => {
+                format!("-{}(%rbp)", 8 * (allocation.saved.len() + slot + 1))
+            }
+            None => panic!("Unexpected temporary {} without a place", temp),
+        }
+    }
+
+    fn emit(&mut self, instruction: &str) {
+        self.body += &format!("\t{}\n", instruction);
+    }
+
+    // Labels are local to the file, so they are prefixed with the function
+    fn label(&self, block: BlockId) -> String {
+        format!(".L{}.{}", self.name, block)
+    }
+
+    fn string(&mut self, value: &str) -> String {
+        let index = match self.strings.iter().position(|string| string == value) {
+            Some(index) => index,
+            None => {
+                self.strings.push(value.to_string());
+                self.strings.len() - 1
+            }
+        };
+        format!(".Lstr.{}", index)
+    }
+
+    // Value of a constant as the 64 bit pattern it is stored as
+    fn constant(&mut self, value: &Constant) -> String {
+        match value {
+            Constant::String(value) => self.string(value),
+            Constant::Boolean(value) => (*value as i32).to_string(),
+            Constant::Integer(value, _) => (*value as i64).to_string(),
+            Constant::Float(_) => {
+                panic!("The x86-64 backend does not support values of type float")
+            }
+        }
+    }
+
+    fn load(&mut self, operand: &Operand, register: &str) {
+        match operand {
+            Operand::Temp(temp) => self.emit(&format!("movq {}, {}", self.place(*temp), register)),
+            Operand::Constant(Constant::String(value)) => {
+                let label = self.string(value);
+                self.emit(&format!("leaq {}(%rip), {}", label, register));
+            }
+            Operand::Constant(value) => {
+                let value = self.constant(value);
+                match value.parse::<i32>() {
+                    Ok(_) => self.emit(&format!("movq ${}, {}", value, register)),
+                    Err(_) => self.emit(&format!("movabsq ${}, {}", value, register)),
+                }
+            }
+        }
+    }
+
+    fn store(&mut self, dest: Temp) {
+        self.emit(&format!("movq %rax, {}", self.place(dest)));
+    }
+
+    fn instruction(&mut self, function: &Function, instruction: &Instruction) {
+        match instruction {
+            // Copies into or out of a register need no scratch register
+            Instruction::Copy { dest, src } => {
+                let place = self.place(*dest);
+                match src {
+                    Operand::Temp(src) if self.place(*src) == place => {}
+                    Operand::Temp(src)
+                        if place.starts_with('%') || self.place(*src).starts_with('%') =>
+                    {
+                        self.emit(&format!("movq {}, {}", self.place(*src), place))
+                    }
+                    src if place.starts_with('%') => self.load(src, &place),
+                    src => {
+                        self.load(src, "%rax");
+                        self.store(*dest);
+                    }
+                }
+            }
+            Instruction::Binary {
+                dest,
+                op,
+                left,
+                right,
+            } => {
+                self.load(left, "%rax");
+                self.load(right, "%rcx");
+                self.arithmetic(*op, &function.temps[*dest]);
+                self.store(*dest);
+            }
+            Instruction::Unary { dest, op, src } => {
+                self.load(src, "%rax");
+                match op {
+                    UnaryOp::Not => self.emit("xorq $1, %rax"),
+                    UnaryOp::BitwiseNot => {
+                        self.emit("notq %rax");
+                        self.extend(&function.temps[*dest]);
+                    }
+                }
+                self.store(*dest);
+            }
+            Instruction::Compare {
+                dest,
+                op,
+                left,
+                right,
+            } => {
+                self.comparison(*op, &function.operand_type(left), left, right);
+                self.store(*dest);
+            }
+            Instruction::Convert { dest, src } => {
+                self.load(src, "%rax");
+                self.convert(&function.operand_type(src), &function.temps[*dest]);
+                self.store(*dest);
+            }
+            Instruction::Call {
+                dest,
+                function: name,
+                args,
+            } => {
+                self.call(name, args, dest.map(|dest| &function.temps[dest]));
+                if let Some(dest) = dest {
+                    self.store(*dest);
+                }
+            }
+            Instruction::Builtin { builtin, args, .. } => {
+                if *builtin != Builtin::Print {
+                    panic!(
+                        "The x86-64 backend does not support the builtin {}",
+                        builtin.name()
+                    );
+                }
+                self.print(function, args);
+            }
+            Instruction::LoadGlobal { dest, name } => {
+                self.emit(&format!("movq {}(%rip), %rax", name));
+                self.store(*dest);
+            }
+            Instruction::StoreGlobal { name, src } => {
+                self.load(src, "%rax");
+                self.emit(&format!("movq %rax, {}(%rip)", name));
+            }
+            // The assembly has no debug info
+            Instruction::Line(_) => {}
+        }
+    }
+
+    // Blocks are placed in order, so jumps to the next one fall through
+    fn terminator(&mut self, function: &Function, terminator: &Terminator, next: BlockId) {
+        match terminator {
+            Terminator::Jump(target) => {
+                if *target != next {
+                    self.emit(&format!("jmp {}", self.label(*target)));
+                }
+            }
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                self.load(condition, "%rax");
+                self.emit("testq %rax, %rax");
+                self.emit(&format!("je {}", self.label(*otherwise)));
+                if *then != next {
+                    self.emit(&format!("jmp {}", self.label(*then)));
+                }
+            }
+            Terminator::Return(value) => {
+                match value {
+                    Some(value) => {
+                        supported(&function.operand_type(value));
+                        self.load(value, "%rax");
+                    }
+                    None => self.emit("xorl %eax, %eax"),
+                }
+                let saved = self.allocation.as_ref().unwrap().saved.clone();
+                for (index, register) in saved.iter().enumerate() {
+                    self.emit(&format!("movq -{}(%rbp), {}", 8 * (index + 1), register));
+                }
+                self.emit("leave");
+                self.emit("ret");
+            }
+        }
+    }
+
+    // Applies the operation to %rax and %rcx, the result wraps to the type
+    fn arithmetic(&mut self, op: BinaryOp, data_type: &DataType) {
+        let signed = match data_type.integer_layout() {
+            Some((_, signed)) => signed,
+            None => panic!(
+                "The x86-64 backend does not support arithmetic on {}",
+                data_type
+            ),
+        };
+        match op {
+            BinaryOp::Add => self.emit("addq %rcx, %rax"),
+            BinaryOp::Subtract => self.emit("subq %rcx, %rax"),
+            BinaryOp::Multiply => self.emit("imulq %rcx, %rax"),
+            BinaryOp::Divide | BinaryOp::Mod => {
+                if signed {
+                    self.emit("cqto");
+                    self.emit("idivq %rcx");
+                } else {
+                    self.emit("xorl %edx, %edx");
+                    self.emit("divq %rcx");
+                }
+                if op == BinaryOp::Mod {
+                    self.emit("movq %rdx, %rax");
+                }
+            }
+            // Bitwise operations keep extended values extended
+            BinaryOp::And => return self.emit("andq %rcx, %rax"),
+            BinaryOp::Or => return self.emit("orq %rcx, %rax"),
+            BinaryOp::Xor => return self.emit("xorq %rcx, %rax"),
+        }
+        self.extend(data_type);
+    }
+
+    fn comparison(
+        &mut self,
+        op: Comparison,
+        data_type: &DataType,
+        left: &Operand,
+        right: &Operand,
+    ) {
+        let condition = if *data_type == DataType::String {
+            self.load(left, "%rdi");
+            self.load(right, "%rsi");
+            self.emit("call strcmp@PLT");
+            self.emit("testl %eax, %eax");
+            match op {
+                Comparison::Eq => "e",
+                _ => "ne",
+            }
+        } else {
+            self.load(left, "%rax");
+            self.load(right, "%rcx");
+            self.emit("cmpq %rcx, %rax");
+
+            let signed = data_type.integer_layout().is_some_and(|(_, signed)| signed);
+            match (op, signed) {
+                (Comparison::Eq, _) => "e",
+                (Comparison::NotEq, _) => "ne",
+                (Comparison::LessThan, true) => "l",
+                (Comparison::LessThan, false) => "b",
+                (Comparison::GreaterThan, true) => "g",
+                (Comparison::GreaterThan, false) => "a",
+                (Comparison::Leq, true) => "le",
+                (Comparison::Leq, false) => "be",
+                (Comparison::Geq, true) => "ge",
+                (Comparison::Geq, false) => "ae",
+            }
+        };
+
+        self.emit(&format!("set{} %al", condition));
+        self.emit("movzbq %al, %rax");
+    }
+
+    // Arguments past the registers go in an area at the bottom of the stack, padded so
+    // the stack stays 16 byte aligned
+    fn call(&mut self, name: &str, args: &[Operand], return_type: Option<&DataType>) {
+        let stack_arguments = args.len().saturating_sub(ARGUMENT_REGISTERS.len());
+        let area = 8 * (stack_arguments + stack_arguments % 2);
+        if area > 0 {
+            self.emit(&format!("subq ${}, %rsp", area));
+        }
+        for (index, arg) in args.iter().enumerate().skip(ARGUMENT_REGISTERS.len()) {
+            self.load(arg, "%rax");
+            let offset = 8 * (index - ARGUMENT_REGISTERS.len());
+            self.emit(&format!("movq %rax, {}(%rsp)", offset));
+        }
+        for (arg, register) in args.iter().zip(ARGUMENT_REGISTERS) {
+            self.load(arg, register);
+        }
+
+        // Functions of C only set the low bits of narrow return values
+        match self.externs.contains(name) {
+            true => {
+                self.emit("xorl %eax, %eax");
+                self.emit(&format!("call {}@PLT", name));
+                if let Some(return_type) = return_type {
+                    self.extend(return_type);
+                }
+            }
+            false => self.emit(&format!("call {}", name)),
+        }
+        if area > 0 {
+            self.emit(&format!("addq ${}, %rsp", area));
+        }
+    }
+
+    // Each value is printed by the runtime function for its type
+    fn print(&mut self, function: &Function, args: &[Operand]) {
+        self.uses_print = true;
+
+        for (index, arg) in args.iter().enumerate() {
+            if index > 0 {
+                self.print_string(", ");
+            }
+            let data_type = function.operand_type(arg);
+            let runtime = match &data_type {
+                DataType::String => "tan_print_string",
+                DataType::Boolean => "tan_print_bool",
+                data_type => match data_type.integer_layout() {
+                    Some((_, false)) => "tan_print_uint",
+                    _ => "tan_print_int",
+                },
+            };
+            self.load(arg, "%rdi");
+            self.emit(&format!("call {}", runtime));
+        }
+        self.print_string("\n");
+    }
+
+    fn print_string(&mut self, value: &str) {
+        let label = self.string(value);
+        self.emit(&format!("leaq {}(%rip), %rdi", label));
+        self.emit("call tan_print_string");
+    }
+
+    // Sign or zero extends the low bits of %rax that hold a value of the type
+    fn extend(&mut self, data_type: &DataType) {
+        let instruction = match data_type.integer_layout() {
+            Some((8, true)) => "movsbq %al, %rax",
+            Some((8, false)) => "movzbq %al, %rax",
+            Some((16, true)) => "movswq %ax, %rax",
+            Some((16, false)) => "movzwq %ax, %rax",
+            Some((32, true)) => "movslq %eax, %rax",
+            Some((32, false)) => "movl %eax, %eax",
+            Some(_) => return,
+            None if *data_type == DataType::Boolean => "movzbq %al, %rax",
+            None => return,
+        };
+        self.emit(instruction);
+    }
+
+    // Converts %rax with the semantics of the interpreter, integers wrap to the new type
+    fn convert(&mut self, from: &DataType, to: &DataType) {
+        if from == to {
+            return;
+        }
+
+        let to_integer = to.integer_layout().is_some();
+        match from.integer_layout() {
+            Some(_) if *to == DataType::Boolean => {
+                self.emit("testq %rax, %rax");
+                self.emit("setne %al");
+                self.emit("movzbq %al, %rax");
+            }
+            Some(_) if to_integer => self.extend(to),
+            None if *from == DataType::Boolean && to_integer => {}
+            _ => panic!("The x86-64 backend can not convert {} to {}", from, to),
+        }
+    }
+}
+
+fn supported(data_type: &DataType) {
+    match data_type {
+        DataType::Integer | DataType::Sized(_) | DataType::Boolean | DataType::String => {}
+        data_type => panic!(
+            "The x86-64 backend does not support values of type {}",
+            data_type
+        ),
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b' '..=b'~' if byte != b'"' && byte != b'\\' => (byte as char).to_string(),
+            byte => format!("\\{:03o}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimize::Pass;
+    use crate::test::{lower_script, test_golden};
+
+    fn compile_script(script: &str) -> String {
+        compile_asm(&lower_script(script, &[]), &REGISTERS)
+    }
+
+    fn compile_spilled(script: &str) -> String {
+        compile_asm(&lower_script(script, &[]), &Registers::NONE)
+    }
+
+    // Assembles and runs a program with the C compiler, None when there is none
+    fn run(asm: &str, name: &str) -> Option<String> {
+        let base = std::env::temp_dir().join(format!("tan_{}_{}", name, std::process::id()));
+        let source = base.with_extension("s");
+        std::fs::write(&source, asm).unwrap();
+        let status = std::process::Command::new("cc")
+            .arg("-o")
+            .arg(&base)
+            .arg(&source)
+            .status()
+            .ok()?;
+        assert!(status.success(), "Could not assemble {}", name);
+        let output = std::process::Command::new(&base).output().unwrap();
+        std::fs::remove_file(&source).unwrap();
+        std::fs::remove_file(&base).unwrap();
+        Some(String::from_utf8(output.stdout).unwrap())
+    }
+
+    // The golden file was assembled with gcc
+    test_golden!(
+        asm_golden_integers,
+        |root| compile_asm(&lower(root), &REGISTERS),
+        "integers.tan",
+        "x86/integers.s"
+    );
+
+    #[test]
+    fn asm_allocated_code_prints_the_same_as_spilled_code() {
+        let scripts = [
+            ("integers", include_str!("../golden/integers.tan")),
+            ("pressure", include_str!("../golden/pressure.tan")),
+        ];
+        for (name, script) in scripts {
+            for passes in [&[][..], &Pass::DEFAULT[..]] {
+                let program = lower_script(script, passes);
+                let Some(spilled) = run(&compile_asm(&program, &Registers::NONE), name) else {
+                    return;
+                };
+                let allocated = run(&compile_asm(&program, &REGISTERS), name).unwrap();
+                assert_eq!(allocated, spilled, "{} printed differently", name);
+            }
+        }
+    }
+
+    #[test]
+    fn asm_keeps_temporaries_in_registers() {
+        let asm = compile_script("fn twice(int x) int { int y = x * 2; return y; };");
+        assert!(asm.contains("twice:\n\tpushq %rbp\n\tmovq %rsp, %rbp\n\tmovq %rdi, %r10\n"));
+        assert!(asm.contains("\timulq %rcx, %rax\n\tmovslq %eax, %rax\n\tmovq %rax, %r10\n"));
+    }
+
+    #[test]
+    fn asm_saves_callee_saved_registers_across_calls() {
+        let asm =
+            compile_script("fn one() int { return 1; }; fn f(int a) int { return a + one(); };");
+        assert!(asm.contains("f:\n\tpushq %rbp\n\tmovq %rsp, %rbp\n\tsubq $16, %rsp\n\tmovq %rbx, -8(%rbp)\n\tmovq %rdi, %rbx\n"));
+        assert!(asm.contains("\tmovq -8(%rbp), %rbx\n\tleave\n\tret\n"));
+        // Values that are not live across a call can use caller saved registers
+        assert!(asm.contains("\tcall one\n\tmovq %rax, %r10\n"));
+    }
+
+    #[test]
+    fn asm_keeps_temporaries_on_the_stack_when_spilling() {
+        let asm = compile_spilled("fn twice(int x) int { int y = x * 2; return y; };");
+        assert!(asm.contains("twice:\n\tpushq %rbp\n\tmovq %rsp, %rbp\n\tsubq $32, %rsp\n"));
+        assert!(asm.contains("\tmovq %rdi, -8(%rbp)\n"));
+        assert!(asm.contains("\timulq %rcx, %rax\n\tmovslq %eax, %rax\n\tmovq %rax, -16(%rbp)\n"));
+    }
+
+    #[test]
+    fn asm_passes_extra_arguments_on_the_stack() {
+        let asm = compile_spilled(
+            "fn last(int a, int b, int c, int d, int e, int f, int g) int { return g; };
+            last(1, 2, 3, 4, 5, 6, 7);",
+        );
+        assert!(asm.contains("\tmovq 16(%rbp), %rax\n\tmovq %rax, -56(%rbp)\n"));
+        assert!(asm.contains("\tsubq $16, %rsp\n\tmovq $7, %rax\n\tmovq %rax, 0(%rsp)\n"));
+        assert!(asm.contains("\tmovq $6, %r9\n\tcall last\n\taddq $16, %rsp\n"));
+    }
+
+    #[test]
+    fn asm_calls_with_the_frame_aligned() {
+        let asm = compile_spilled("fn one() int { return 1; }; int x = 2 + one();");
+        assert!(asm.contains("\tsubq $16, %rsp\n\tcall one\n\tmovq %rax, -8(%rbp)\n"));
+        let asm = compile_spilled("int x = 2; print(x);");
+        assert!(asm.contains("\tmovq -8(%rbp), %rdi\n\tcall tan_print_int\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The x86-64 backend does not support values of type float")]
+    fn asm_rejects_floats() {
+        compile_script("float f = 1;");
+    }
+}